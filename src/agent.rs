@@ -8,6 +8,8 @@
 //! Since request executions are driven through futures, the agent also acts as
 //! a specialized task executor for tasks related to requests.
 
+use crate::clock::{Clock, SystemClock};
+use crate::config::{DnsCache, Priority, SetOpt};
 use crate::handler::RequestHandler;
 use crate::task::{UdpWaker, WakerExt};
 use crate::Error;
@@ -15,8 +17,12 @@ use crossbeam_channel::{Receiver, Sender};
 use crossbeam_utils::sync::WaitGroup;
 use curl::multi::WaitFd;
 use slab::Slab;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::mem;
 use std::net::UdpSocket;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
 use std::task::Waker;
 use std::thread;
 use std::time::{Duration, Instant};
@@ -32,6 +38,11 @@ type MultiMessage = (usize, Result<(), curl::Error>);
 pub(crate) struct AgentBuilder {
     max_connections: usize,
     max_connections_per_host: usize,
+    max_idle_connections: usize,
+    max_requests: usize,
+    connection_idle_timeout: Option<Duration>,
+    share_ssl_sessions: bool,
+    clock: Option<Arc<dyn Clock>>,
 }
 
 impl AgentBuilder {
@@ -45,6 +56,31 @@ impl AgentBuilder {
         self
     }
 
+    pub(crate) fn max_idle_connections(mut self, max: usize) -> Self {
+        self.max_idle_connections = max;
+        self
+    }
+
+    pub(crate) fn max_requests(mut self, max: usize) -> Self {
+        self.max_requests = max;
+        self
+    }
+
+    pub(crate) fn connection_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.connection_idle_timeout = Some(timeout);
+        self
+    }
+
+    pub(crate) fn share_ssl_sessions(mut self, enable: bool) -> Self {
+        self.share_ssl_sessions = enable;
+        self
+    }
+
+    pub(crate) fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
     /// Spawn a new agent using the configuration in this builder and return a
     /// handle for communicating with the agent.
     pub(crate) fn spawn(&self) -> Result<Handle, Error> {
@@ -58,45 +94,69 @@ impl AgentBuilder {
         log::debug!("agent waker listening on {}", wake_addr);
 
         let (message_tx, message_rx) = crossbeam_channel::unbounded();
+        let (finished_tx, finished_rx) = crossbeam_channel::bounded::<()>(0);
 
         let wait_group = WaitGroup::new();
         let wait_group_thread = wait_group.clone();
 
         let max_connections = self.max_connections;
         let max_connections_per_host = self.max_connections_per_host;
+        let max_idle_connections = self.max_idle_connections;
+        let max_requests = self.max_requests;
+        let connection_idle_timeout = self.connection_idle_timeout;
+        let share_ssl_sessions = self.share_ssl_sessions;
+        let clock = self.clock.clone().unwrap_or_else(|| Arc::new(SystemClock));
 
         let handle = Handle {
             message_tx: message_tx.clone(),
             waker: waker.clone(),
-            join_handle: Some(thread::Builder::new()
+            shut_down: AtomicBool::new(false),
+            finished_rx,
+            join_handle: Mutex::new(Some(thread::Builder::new()
                 .name(AGENT_THREAD_NAME.into())
                 .spawn(move || {
-                    let mut multi = curl::multi::Multi::new();
-
-                    if max_connections > 0 {
-                        multi.set_max_total_connections(max_connections)?;
-                    }
+                    // Dropped when the thread exits, however it does so.
+                    let _finished = finished_tx;
 
-                    if max_connections_per_host > 0 {
-                        multi.set_max_host_connections(max_connections_per_host)?;
-                    }
+                    let ssl_session_share = if share_ssl_sessions {
+                        Some(SslSessionShare::new()?)
+                    } else {
+                        None
+                    };
 
                     let agent = AgentContext {
-                        multi,
+                        multi: create_multi(
+                            max_connections,
+                            max_connections_per_host,
+                            max_idle_connections,
+                        )?,
+                        max_connections,
+                        max_connections_per_host,
+                        max_idle_connections,
                         multi_messages: crossbeam_channel::unbounded(),
                         message_tx,
                         message_rx,
                         wake_socket,
                         requests: Slab::new(),
+                        max_requests,
+                        queue: BinaryHeap::new(),
+                        queue_sequence: 0,
+                        connection_idle_timeout,
+                        idle_since: None,
+                        prune_requested: false,
+                        invalidate_requested: false,
+                        clock,
                         close_requested: false,
+                        draining: false,
                         waker,
+                        ssl_session_share,
                     };
 
                     drop(wait_group_thread);
                     log::debug!("agent took {:?} to start up", create_start.elapsed());
 
                     agent.run()
-                })?),
+                })?)),
         };
 
         // Block until the agent thread responds.
@@ -118,8 +178,15 @@ pub(crate) struct Handle {
     /// A waker that can wake up the agent thread while it is polling.
     waker: Waker,
 
-    /// A join handle for the agent thread.
-    join_handle: Option<thread::JoinHandle<Result<(), Error>>>,
+    /// Set once the agent has been asked to shut down, after which no more
+    /// messages are accepted.
+    shut_down: AtomicBool,
+
+    /// Disconnected once the agent thread has exited.
+    finished_rx: Receiver<()>,
+
+    /// A join handle for the agent thread, taken once it has been joined.
+    join_handle: Mutex<Option<thread::JoinHandle<Result<(), Error>>>>,
 }
 
 /// Internal state of an agent thread.
@@ -131,6 +198,15 @@ struct AgentContext {
     /// A curl multi handle, of course.
     multi: curl::multi::Multi,
 
+    /// Maximum number of connections the multi handle may keep open.
+    max_connections: usize,
+
+    /// Maximum number of connections per host the multi handle may keep open.
+    max_connections_per_host: usize,
+
+    /// Maximum number of idle connections the multi handle may keep pooled.
+    max_idle_connections: usize,
+
     /// Queue of messages from the multi handle.
     multi_messages: (Sender<MultiMessage>, Receiver<MultiMessage>),
 
@@ -146,11 +222,48 @@ struct AgentContext {
     /// Contains all of the active requests.
     requests: Slab<curl::multi::Easy2Handle<RequestHandler>>,
 
+    /// Maximum number of requests that may be active at once, or zero for no
+    /// limit.
+    max_requests: usize,
+
+    /// Requests waiting for a free slot before they can be started.
+    queue: BinaryHeap<QueuedRequest>,
+
+    /// Sequence number to give to the next queued request.
+    queue_sequence: u64,
+
+    /// Close all pooled connections once the agent has been idle for this
+    /// long.
+    connection_idle_timeout: Option<Duration>,
+
+    /// When the last active request completed, if the agent has been idle
+    /// ever since and there might be connections left in the pool.
+    idle_since: Option<Instant>,
+
+    /// Indicates if pooled connections should be closed as soon as the agent
+    /// is idle.
+    prune_requested: bool,
+
+    /// Indicates if pooled connections and cached DNS entries are no longer
+    /// trustworthy, and should not be used by new requests.
+    invalidate_requested: bool,
+
+    /// Source of the current time for queue and idle deadlines.
+    clock: Arc<dyn Clock>,
+
     /// Indicates if the thread has been requested to stop.
     close_requested: bool,
 
+    /// Indicates if the thread should stop once all active and queued
+    /// requests have completed.
+    draining: bool,
+
     /// A waker that can wake up the agent thread while it is polling.
     waker: Waker,
+
+    /// TLS session cache shared by all requests, if enabled. Declared last so
+    /// that it outlives every request attached to it.
+    ssl_session_share: Option<SslSessionShare>,
 }
 
 /// A message sent from the main thread to the agent thread.
@@ -160,7 +273,16 @@ enum Message {
     Close,
 
     /// Begin executing a new request.
-    Execute(EasyHandle),
+    Execute(EasyHandle, Priority, Option<Duration>),
+
+    /// Requests the agent to close once all requests have completed.
+    Drain,
+
+    /// Close all idle pooled connections.
+    PruneIdle,
+
+    /// Stop using all pooled connections and cached DNS entries.
+    InvalidateConnections,
 
     /// Request to resume reading the request body for the request with the
     /// given ID.
@@ -169,18 +291,79 @@ enum Message {
     /// Request to resume writing the response body for the request with the
     /// given ID.
     UnpauseWrite(usize),
+
+    /// Abort the request with the given ID, which has been canceled with its
+    /// cancellation token.
+    Cancel(usize),
 }
 
 impl Handle {
     /// Begin executing a request with this agent.
-    pub(crate) fn submit_request(&self, request: EasyHandle) -> Result<(), Error> {
-        self.send_message(Message::Execute(request))
+    ///
+    /// If the agent is already executing as many requests as it is allowed to,
+    /// the request will wait in a queue ordered by the given priority. If an
+    /// acquire timeout is given, the request fails if it waits for longer than
+    /// that.
+    pub(crate) fn submit_request(
+        &self,
+        request: EasyHandle,
+        priority: Priority,
+        acquire_timeout: Option<Duration>,
+    ) -> Result<(), Error> {
+        self.send_message(Message::Execute(request, priority, acquire_timeout))
+    }
+
+    /// Close any pooled connections that are not in use.
+    pub(crate) fn prune_idle(&self) -> Result<(), Error> {
+        self.send_message(Message::PruneIdle)
+    }
+
+    /// Discard all pooled connections and cached DNS entries.
+    pub(crate) fn invalidate_connections(&self) -> Result<(), Error> {
+        self.send_message(Message::InvalidateConnections)
+    }
+
+    /// Shut down the agent, waiting up to the given timeout for active and
+    /// queued requests to complete first. Any requests still active after
+    /// that are aborted, and a timeout error is returned.
+    ///
+    /// Once this is called, no more requests are accepted.
+    pub(crate) fn shutdown(&self, timeout: Duration) -> Result<(), Error> {
+        let join_handle = match self.join_handle.lock().unwrap().take() {
+            Some(join_handle) => join_handle,
+            // Already shut down.
+            None => return Ok(()),
+        };
+
+        self.shut_down.store(true, AtomicOrdering::SeqCst);
+        self.send_raw(Message::Drain);
+
+        let drained = match self.finished_rx.recv_timeout(timeout) {
+            Err(crossbeam_channel::RecvTimeoutError::Timeout) => {
+                log::warn!("requests still active after {:?}, aborting them", timeout);
+                self.send_raw(Message::Close);
+                false
+            }
+            _ => true,
+        };
+
+        join(join_handle);
+
+        if drained {
+            Ok(())
+        } else {
+            Err(Error::Timeout(None))
+        }
     }
 
     /// Send a message to the agent thread.
     ///
-    /// If the agent is not connected, an error is returned.
+    /// If the agent has been shut down, an error is returned.
     fn send_message(&self, message: Message) -> Result<(), Error> {
+        if self.shut_down.load(AtomicOrdering::SeqCst) {
+            return Err(Error::ClientShutdown);
+        }
+
         match self.message_tx.send(message) {
             Ok(()) => {
                 // Wake the agent thread up so it will check its messages soon.
@@ -192,28 +375,210 @@ impl Handle {
             }
         }
     }
+
+    /// Send a message to the agent thread while shutting it down, when it may
+    /// already have exited.
+    fn send_raw(&self, message: Message) {
+        if self.message_tx.send(message).is_ok() {
+            self.waker.wake_by_ref();
+        }
+    }
+}
+
+/// A request waiting in the agent's queue for a free slot.
+struct QueuedRequest {
+    request: EasyHandle,
+    priority: Priority,
+
+    /// Increases with every queued request, so that requests with the same
+    /// priority are started in the order they were submitted.
+    sequence: u64,
+
+    /// When the request was added to the queue.
+    queued_at: Instant,
+
+    /// If the request is still queued at this time, it is failed instead.
+    deadline: Option<Instant>,
+}
+
+impl Ord for QueuedRequest {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Since the queue is a max-heap, higher priorities and lower sequence
+        // numbers must compare as greater.
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialOrd for QueuedRequest {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for QueuedRequest {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
 }
 
+impl Eq for QueuedRequest {}
+
 impl Drop for Handle {
     fn drop(&mut self) {
-        // Request the agent thread to shut down.
-        if self.send_message(Message::Close).is_err() {
-            log::error!("agent thread terminated prematurely");
-        }
-
-        // Wait for the agent thread to shut down before continuing.
-        if let Some(join_handle) = self.join_handle.take() {
-            match join_handle.join() {
-                Ok(Ok(())) => {}
-                Ok(Err(e)) => log::error!("agent thread terminated with error: {}", e),
-                Err(_) => log::error!("agent thread panicked"),
+        // Nothing to do if the agent has already been shut down.
+        if let Some(join_handle) = self.join_handle.get_mut().unwrap().take() {
+            // Request the agent thread to shut down.
+            if self.send_message(Message::Close).is_err() {
+                log::error!("agent thread terminated prematurely");
             }
+
+            // Wait for the agent thread to shut down before continuing.
+            join(join_handle);
         }
     }
 }
 
+/// Wait for an agent thread to exit, logging how it went.
+fn join(join_handle: thread::JoinHandle<Result<(), Error>>) {
+    match join_handle.join() {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => log::error!("agent thread terminated with error: {}", e),
+        Err(_) => log::error!("agent thread panicked"),
+    }
+}
+
 impl AgentContext {
+    /// Check if another request may be started right now.
+    fn has_free_slot(&self) -> bool {
+        self.max_requests == 0 || self.requests.len() < self.max_requests
+    }
+
+    /// Start a newly submitted request, or queue it if too many requests are
+    /// already active.
+    fn submit_request(
+        &mut self,
+        request: EasyHandle,
+        priority: Priority,
+        acquire_timeout: Option<Duration>,
+    ) -> Result<(), Error> {
+        if self.queue.is_empty() && self.has_free_slot() {
+            return self.begin_request(request);
+        }
+
+        log::debug!(
+            "request limit of {} reached, queueing request with {:?} priority",
+            self.max_requests,
+            priority
+        );
+
+        let now = self.clock.now();
+
+        self.queue.push(QueuedRequest {
+            request,
+            priority,
+            sequence: self.queue_sequence,
+            queued_at: now,
+            deadline: acquire_timeout.map(|timeout| now + timeout),
+        });
+        self.queue_sequence += 1;
+
+        Ok(())
+    }
+
+    /// Fail any queued requests that have been waiting for longer than they
+    /// are allowed to.
+    fn expire_queued_requests(&mut self) {
+        if self.queue.is_empty() {
+            return;
+        }
+
+        let now = self.clock.now();
+        let queue = mem::replace(&mut self.queue, BinaryHeap::new());
+
+        for mut queued in queue.into_vec() {
+            if queued.request.get_ref().is_canceled() {
+                queued.request.get_mut().on_canceled();
+                continue;
+            }
+
+            match queued.deadline {
+                Some(deadline) if deadline <= now => {
+                    log::debug!(
+                        "request waited {:?} in queue, giving up",
+                        now - queued.queued_at
+                    );
+                    queued.request.get_mut().fail(Error::AcquireTimeout);
+                }
+                _ => self.queue.push(queued),
+            }
+        }
+    }
+
+    /// Start as many queued requests as there are free slots available.
+    fn begin_queued_requests(&mut self) -> Result<(), Error> {
+        while self.has_free_slot() {
+            match self.queue.pop() {
+                Some(mut queued) => {
+                    let queue_time = self.clock.now() - queued.queued_at;
+                    log::debug!("starting request after waiting {:?} in queue", queue_time);
+                    queued.request.get_mut().set_queue_time(queue_time);
+                    self.begin_request(queued.request)?;
+                }
+                None => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Close all connections kept in the connection pool.
+    ///
+    /// Curl does not offer a way to close individual pooled connections, so
+    /// this is done by replacing the multi handle with a fresh one. This must
+    /// only be done while no requests are active.
+    fn prune_connections(&mut self) -> Result<(), Error> {
+        debug_assert!(self.requests.is_empty());
+        log::debug!("closing idle connections");
+
+        let multi = create_multi(
+            self.max_connections,
+            self.max_connections_per_host,
+            self.max_idle_connections,
+        )?;
+        mem::replace(&mut self.multi, multi).close()?;
+
+        self.idle_since = None;
+        self.prune_requested = false;
+        self.invalidate_requested = false;
+
+        Ok(())
+    }
+
+    /// Get the time at which pooled connections should be closed if the agent
+    /// stays idle until then.
+    fn prune_deadline(&self) -> Option<Instant> {
+        match (self.idle_since, self.connection_idle_timeout) {
+            (Some(idle_since), Some(timeout)) => Some(idle_since + timeout),
+            _ => None,
+        }
+    }
+
     fn begin_request(&mut self, mut request: EasyHandle) -> Result<(), Error> {
+        self.idle_since = None;
+
+        // The connection pool and DNS cache can't be replaced while other
+        // requests are still using them, so bypass them in the meantime.
+        if self.invalidate_requested {
+            request.fresh_connect(true)?;
+            DnsCache::Disable.set_opt(&mut request)?;
+        }
+
+        if let Some(share) = self.ssl_session_share.as_ref() {
+            share.attach(&mut request)?;
+        }
+
         // Prepare an entry for storing this request while it executes.
         let entry = self.requests.vacant_entry();
         let id = entry.key();
@@ -249,6 +614,20 @@ impl AgentContext {
             },
         );
 
+        // Abort the request as soon as its token is canceled. If it already
+        // has been, this sends the message right away.
+        if let Some(token) = request.get_ref().cancellation_token() {
+            let tx = self.message_tx.clone();
+
+            token.register(
+                self.waker
+                    .chain(move |inner| match tx.send(Message::Cancel(id)) {
+                        Ok(()) => inner.wake_by_ref(),
+                        Err(_) => log::warn!("agent went away while canceling request [id={}]", id),
+                    }),
+            );
+        }
+
         // Register the request with curl.
         let mut handle = self.multi.add2(request)?;
         handle.set_token(id)?;
@@ -286,9 +665,35 @@ impl AgentContext {
     fn poll_messages(&mut self) -> Result<(), Error> {
         while !self.close_requested {
             if self.requests.is_empty() {
-                match self.message_rx.recv() {
-                    Ok(message) => self.handle_message(message)?,
-                    _ => {
+                let message = match self.prune_deadline() {
+                    // Wait for a message, but not beyond the point where idle
+                    // connections should be closed. The deadline is measured
+                    // by the client's clock, which may not move in step with
+                    // the time spent waiting, so wake up regularly to check
+                    // it again.
+                    Some(deadline) => {
+                        let now = self.clock.now();
+
+                        if deadline <= now {
+                            self.prune_connections()?;
+                            continue;
+                        }
+
+                        match self
+                            .message_rx
+                            .recv_timeout((deadline - now).min(WAIT_TIMEOUT))
+                        {
+                            Ok(message) => Some(message),
+                            Err(crossbeam_channel::RecvTimeoutError::Timeout) => continue,
+                            Err(crossbeam_channel::RecvTimeoutError::Disconnected) => None,
+                        }
+                    }
+                    None => self.message_rx.recv().ok(),
+                };
+
+                match message {
+                    Some(message) => self.handle_message(message)?,
+                    None => {
                         log::warn!("agent handle disconnected without close message");
                         self.close_requested = true;
                         break;
@@ -315,7 +720,34 @@ impl AgentContext {
 
         match message {
             Message::Close => self.close_requested = true,
-            Message::Execute(request) => self.begin_request(request)?,
+            Message::Drain => {
+                log::debug!("agent draining before shutting down");
+                self.draining = true;
+
+                if self.requests.is_empty() && self.queue.is_empty() {
+                    self.close_requested = true;
+                }
+            }
+            Message::Execute(request, priority, acquire_timeout) => {
+                self.submit_request(request, priority, acquire_timeout)?
+            }
+            Message::PruneIdle => {
+                if self.requests.is_empty() {
+                    self.prune_connections()?;
+                } else {
+                    // Connections are still in use, so wait until the current
+                    // requests are done.
+                    self.prune_requested = true;
+                }
+            }
+            Message::InvalidateConnections => {
+                if self.requests.is_empty() {
+                    self.prune_connections()?;
+                } else {
+                    self.prune_requested = true;
+                    self.invalidate_requested = true;
+                }
+            }
             Message::UnpauseRead(token) => {
                 if let Some(request) = self.requests.get(token) {
                     request.unpause_read()?;
@@ -336,6 +768,21 @@ impl AgentContext {
                     );
                 }
             }
+            Message::Cancel(token) => {
+                // The request may have completed already and its token been
+                // reused by another request, which must be left alone.
+                if self
+                    .requests
+                    .get(token)
+                    .map_or(false, |request| request.get_ref().is_canceled())
+                {
+                    let handle = self.requests.remove(token);
+                    let mut handle = self.multi.remove2(handle)?;
+                    handle.get_mut().on_canceled();
+
+                    self.requests_finished()?;
+                }
+            }
         }
 
         Ok(())
@@ -354,10 +801,13 @@ impl AgentContext {
             }
         });
 
+        let mut completed = false;
+
         loop {
             match self.multi_messages.1.try_recv() {
                 // A request completed.
                 Ok((token, result)) => {
+                    completed = true;
                     let handle = self.requests.remove(token);
                     let mut handle = self.multi.remove2(handle)?;
                     handle.get_mut().on_result(result);
@@ -367,6 +817,34 @@ impl AgentContext {
             }
         }
 
+        // Completed requests may have made room for queued ones.
+        self.expire_queued_requests();
+        self.begin_queued_requests()?;
+
+        if self.draining && self.requests.is_empty() && self.queue.is_empty() {
+            log::debug!("all requests completed, agent drained");
+            self.close_requested = true;
+            return Ok(());
+        }
+
+        if completed {
+            self.requests_finished()?;
+        }
+
+        Ok(())
+    }
+
+    /// Update the connection pool after active requests have completed or
+    /// been canceled.
+    fn requests_finished(&mut self) -> Result<(), Error> {
+        if self.requests.is_empty() {
+            if self.prune_requested {
+                self.prune_connections()?;
+            } else {
+                self.idle_since = Some(self.clock.now());
+            }
+        }
+
         Ok(())
     }
 
@@ -408,6 +886,7 @@ impl AgentContext {
 
         log::debug!("agent shutting down");
 
+        self.queue.clear();
         self.requests.clear();
         self.multi.close()?;
 
@@ -415,6 +894,84 @@ impl AgentContext {
     }
 }
 
+/// A curl share handle for sharing TLS sessions between all requests made by
+/// an agent, so that connections to a server can resume an earlier session
+/// instead of performing a full handshake.
+///
+/// The share handle is only ever used from the agent thread, so no locking
+/// callbacks are needed.
+struct SslSessionShare(*mut curl_sys::CURLSH);
+
+#[allow(unsafe_code)]
+impl SslSessionShare {
+    fn new() -> Result<Self, Error> {
+        unsafe {
+            let share = curl_sys::curl_share_init();
+
+            if share.is_null() {
+                return Err(curl::Error::new(curl_sys::CURLE_OUT_OF_MEMORY).into());
+            }
+
+            // Take ownership right away so the handle is cleaned up on error.
+            let share = SslSessionShare(share);
+
+            match curl_sys::curl_share_setopt(
+                share.0,
+                curl_sys::CURLSHOPT_SHARE,
+                curl_sys::CURL_LOCK_DATA_SSL_SESSION,
+            ) {
+                0 => Ok(share),
+                code => {
+                    log::error!("failed to enable TLS session sharing: code {}", code);
+                    Err(curl::Error::new(curl_sys::CURLE_FAILED_INIT).into())
+                }
+            }
+        }
+    }
+
+    /// Make the given request use this shared TLS session cache.
+    fn attach(&self, request: &mut EasyHandle) -> Result<(), curl::Error> {
+        unsafe {
+            match curl_sys::curl_easy_setopt(request.raw(), curl_sys::CURLOPT_SHARE, self.0) {
+                curl_sys::CURLE_OK => Ok(()),
+                code => Err(curl::Error::new(code)),
+            }
+        }
+    }
+}
+
+#[allow(unsafe_code)]
+impl Drop for SslSessionShare {
+    fn drop(&mut self) {
+        unsafe {
+            curl_sys::curl_share_cleanup(self.0);
+        }
+    }
+}
+
+/// Create a new multi handle with the given connection limits applied.
+fn create_multi(
+    max_connections: usize,
+    max_connections_per_host: usize,
+    max_idle_connections: usize,
+) -> Result<curl::multi::Multi, Error> {
+    let mut multi = curl::multi::Multi::new();
+
+    if max_connections > 0 {
+        multi.set_max_total_connections(max_connections)?;
+    }
+
+    if max_connections_per_host > 0 {
+        multi.set_max_host_connections(max_connections_per_host)?;
+    }
+
+    if max_idle_connections > 0 {
+        multi.set_max_connects(max_idle_connections)?;
+    }
+
+    Ok(multi)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -429,4 +986,34 @@ mod tests {
 
         is_send::<Message>();
     }
+
+    #[test]
+    fn queued_requests_are_ordered_by_priority_then_submission() {
+        let mut queue = BinaryHeap::new();
+
+        for (sequence, &priority) in [
+            Priority::Low,
+            Priority::Normal,
+            Priority::High,
+            Priority::Normal,
+            Priority::High,
+        ]
+        .iter()
+        .enumerate()
+        {
+            queue.push(QueuedRequest {
+                request: curl::easy::Easy2::new(RequestHandler::new(crate::Body::empty()).0),
+                priority,
+                sequence: sequence as u64,
+                queued_at: Instant::now(),
+                deadline: None,
+            });
+        }
+
+        let order = std::iter::from_fn(|| queue.pop())
+            .map(|queued| queued.sequence)
+            .collect::<Vec<_>>();
+
+        assert_eq!(order, vec![2, 4, 1, 3, 0]);
+    }
 }
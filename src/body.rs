@@ -1,12 +1,19 @@
 //! Provides types for working with request and response bodies.
 
-use crate::io::Text;
+#[cfg(feature = "compression")]
+use crate::config::Compression;
+use crate::io::{CopyTo, Text};
 use crate::task::Join;
-use bytes::Bytes;
-use futures_io::AsyncRead;
+use bytes::{Bytes, BytesMut};
+use futures_io::{AsyncRead, AsyncWrite};
 use futures_util::io::AsyncReadExt;
+use futures_util::stream::Stream;
 use std::fmt;
-use std::io::{self, Cursor, Read};
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+#[cfg(feature = "compression")]
+use std::mem;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::str;
 use std::task::{Context, Poll};
@@ -21,7 +28,23 @@ use std::task::{Context, Poll};
 /// Since the entire request life-cycle in Isahc is asynchronous, bodies must
 /// also be asynchronous. You can create a body from anything that implements
 /// [`AsyncRead`], which [`Body`] itself also implements.
-pub struct Body(Inner);
+pub struct Body {
+    inner: Inner,
+
+    /// Content type to send with this body if the request does not specify
+    /// one, for bodies that know their own format.
+    content_type: Option<String>,
+
+    /// Number of bytes read so far from a body not stored in memory.
+    position: u64,
+
+    /// Unused part of the buffer that chunks of a streaming body are read
+    /// into, shared with the chunks that were already returned.
+    chunk_buffer: Option<BytesMut>,
+}
+
+/// Size of the buffers that chunks of a streaming body are read into.
+const CHUNK_BUFFER_SIZE: usize = 8192;
 
 /// All possible body implementations.
 enum Inner {
@@ -33,6 +56,9 @@ enum Inner {
 
     /// An asynchronous reader.
     AsyncRead(Pin<Box<dyn AsyncRead + Send>>, Option<u64>),
+
+    /// A file on disk, along with its path and length.
+    File(File, PathBuf, u64),
 }
 
 impl Body {
@@ -40,14 +66,19 @@ impl Body {
     ///
     /// An empty body will have a known length of 0 bytes.
     pub const fn empty() -> Self {
-        Body(Inner::Empty)
+        Body {
+            inner: Inner::Empty,
+            content_type: None,
+            position: 0,
+            chunk_buffer: None,
+        }
     }
 
     /// Create a new body from bytes stored in memory.
     ///
     /// The body will have a known length equal to the number of bytes given.
     pub fn bytes(bytes: impl Into<Bytes>) -> Self {
-        Body(Inner::Bytes(Cursor::new(bytes.into())))
+        Self::from_inner(Inner::Bytes(Cursor::new(bytes.into())))
     }
 
     /// Create a streaming body that reads from the given reader.
@@ -55,7 +86,7 @@ impl Body {
     /// The body will have an unknown length. When used as a request body,
     /// chunked transfer encoding might be used to send the request.
     pub fn reader(read: impl AsyncRead + Send + 'static) -> Self {
-        Body(Inner::AsyncRead(Box::pin(read), None))
+        Self::from_inner(Inner::AsyncRead(Box::pin(read), None))
     }
 
     /// Create a streaming body with a known length.
@@ -68,7 +99,113 @@ impl Body {
     /// the reader will produce may result in errors when sending the body in a
     /// request.
     pub fn reader_sized(read: impl AsyncRead + Send + 'static, length: u64) -> Self {
-        Body(Inner::AsyncRead(Box::pin(read), Some(length)))
+        Self::from_inner(Inner::AsyncRead(Box::pin(read), Some(length)))
+    }
+
+    /// Create a streaming body from a stream of chunks of bytes.
+    ///
+    /// The body will have an unknown length. This is convenient for producers
+    /// that naturally yield data in chunks, such as channels or encoders. An
+    /// error produced by the stream aborts sending the request.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use bytes::Bytes;
+    /// use futures::stream;
+    /// use isahc::Body;
+    ///
+    /// let chunks = stream::iter(vec![
+    ///     Ok(Bytes::from("hello ")),
+    ///     Ok(Bytes::from("world")),
+    /// ]);
+    ///
+    /// let response = isahc::post("https://httpbin.org/post", Body::from_stream(chunks))?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn from_stream<S>(stream: S) -> Self
+    where
+        S: Stream<Item = io::Result<Bytes>> + Send + 'static,
+    {
+        Self::reader(StreamReader {
+            stream: Box::pin(stream),
+            chunk: Cursor::new(Bytes::new()),
+        })
+    }
+
+    /// Create a streaming body from any implementation of
+    /// [`http_body::Body`], such as a body from another HTTP library.
+    ///
+    /// Trailers of the given body are not sent. The body will have a known
+    /// length if its size hint is exact.
+    ///
+    /// This method requires the `http-body` feature to be enabled.
+    #[cfg(feature = "http-body")]
+    pub fn from_http_body<B>(body: B) -> Self
+    where
+        B: http_body::Body + Send + 'static,
+        B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let length = body.size_hint().exact();
+        let reader = StreamReader {
+            stream: Box::pin(HttpBodyStream(Box::pin(body))),
+            chunk: Cursor::new(Bytes::new()),
+        };
+
+        match length {
+            Some(length) => Self::reader_sized(reader, length),
+            None => Self::reader(reader),
+        }
+    }
+
+    /// Create a streaming body that reads the contents of a file.
+    ///
+    /// The file is opened immediately and its current size is used as the
+    /// length of the body, but its contents are only read while the request is
+    /// being sent, without buffering the whole file in memory. Unlike other
+    /// streaming bodies, a file body can be [reset](Body::reset), so it can be
+    /// sent again if a redirect or retry requires it.
+    ///
+    /// Reading from the file blocks the thread driving the request. This is
+    /// usually not noticeable for local files, but may be for files on slow
+    /// network mounts.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::Body;
+    ///
+    /// let response = isahc::put("https://httpbin.org/put", Body::from_file("video.mp4")?)?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let length = file.metadata()?.len();
+
+        Ok(Self::from_inner(Inner::File(file, path.to_owned(), length)))
+    }
+
+    fn from_inner(inner: Inner) -> Self {
+        Body {
+            inner,
+            content_type: None,
+            position: 0,
+            chunk_buffer: None,
+        }
+    }
+
+    /// Set the content type to send with this body if the request does not
+    /// specify one.
+    pub(crate) fn with_content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    /// Get the content type this body should be sent with, if it knows its own
+    /// format.
+    pub(crate) fn content_type(&self) -> Option<&str> {
+        self.content_type.as_ref().map(String::as_str)
     }
 
     /// Report if this body is empty.
@@ -90,24 +227,92 @@ impl Body {
     /// bytes, even if a value is returned it should not be relied on as always
     /// being accurate, and should be treated as a "hint".
     pub fn len(&self) -> Option<u64> {
-        match &self.0 {
+        match &self.inner {
             Inner::Empty => Some(0),
             Inner::Bytes(bytes) => Some(bytes.get_ref().len() as u64),
             Inner::AsyncRead(_, len) => *len,
+            Inner::File(_, _, len) => Some(*len),
+        }
+    }
+
+    /// Get the number of bytes left to read from the body, if known.
+    fn remaining(&self) -> Option<u64> {
+        match &self.inner {
+            Inner::Bytes(cursor) => {
+                Some((cursor.get_ref().len() as u64).saturating_sub(cursor.position()))
+            }
+            _ => self.len().map(|len| len.saturating_sub(self.position)),
+        }
+    }
+
+    /// Get the contents of this body if it is stored entirely in memory.
+    pub(crate) fn as_bytes(&self) -> Option<&[u8]> {
+        match &self.inner {
+            Inner::Bytes(cursor) => Some(cursor.get_ref().as_ref()),
+            _ => None,
         }
     }
 
+    /// Create a copy of this body that can be sent separately, if the body is
+    /// stored in memory or in a file.
+    pub(crate) fn try_clone(&self) -> Option<Self> {
+        let mut body = match &self.inner {
+            Inner::Empty => Self::empty(),
+            Inner::Bytes(cursor) => Self::bytes(cursor.get_ref().clone()),
+            Inner::AsyncRead(_, _) => return None,
+            Inner::File(_, path, _) => Self::from_file(path).ok()?,
+        };
+
+        body.content_type = self.content_type.clone();
+
+        Some(body)
+    }
+
     /// If this body is repeatable, reset the body stream back to the start of
     /// the content. Returns `false` if the body cannot be reset.
     pub fn reset(&mut self) -> bool {
-        match &mut self.0 {
+        let reset = match &mut self.inner {
             Inner::Empty => true,
             Inner::Bytes(cursor) => {
                 cursor.set_position(0);
                 true
             }
             Inner::AsyncRead(_, _) => false,
+            Inner::File(file, _, _) => file.seek(SeekFrom::Start(0)).is_ok(),
+        };
+
+        if reset {
+            self.position = 0;
         }
+
+        reset
+    }
+
+    /// Compress this body with the given encoding.
+    ///
+    /// Bodies stored in memory are compressed immediately, so their compressed
+    /// length is known. Other bodies are compressed on the fly while they are
+    /// read, and have an unknown length.
+    #[cfg(feature = "compression")]
+    pub(crate) fn compress(self, compression: Compression) -> io::Result<Self> {
+        let content_type = self.content_type.clone();
+
+        let mut body = if let Inner::Bytes(cursor) = &self.inner {
+            let mut encoder = Encoder::new(compression);
+            encoder.write_all(cursor.get_ref())?;
+            Self::bytes(encoder.finish()?)
+        } else {
+            Self::reader(Compressor {
+                inner: self,
+                encoder: Encoder::new(compression),
+                output: Cursor::new(Vec::new()),
+                finished: false,
+            })
+        };
+
+        body.content_type = content_type;
+
+        Ok(body)
     }
 
     /// Get the response body as a string.
@@ -132,6 +337,24 @@ impl Body {
         Text::new(self)
     }
 
+    /// Copy the body into a writer.
+    ///
+    /// Returns the number of bytes that were written.
+    pub fn copy_to(&mut self, mut writer: impl Write) -> io::Result<u64> {
+        io::copy(self, &mut writer)
+    }
+
+    /// Copy the body into an asynchronous writer.
+    ///
+    /// The writer is flushed once the body has been copied completely. The
+    /// returned future produces the number of bytes that were written.
+    pub fn copy_to_async<'a, W>(&'a mut self, writer: &'a mut W) -> CopyTo<'a, Body, W>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        CopyTo::new(self, writer)
+    }
+
     /// Deserialize the response body as JSON into a given type.
     ///
     /// This method requires the `json` feature to be enabled.
@@ -153,10 +376,251 @@ impl AsyncRead for Body {
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        match &mut self.0 {
+        let poll = match &mut self.inner {
             Inner::Empty => Poll::Ready(Ok(0)),
             Inner::Bytes(cursor) => AsyncRead::poll_read(Pin::new(cursor), cx, buf),
             Inner::AsyncRead(read, _) => AsyncRead::poll_read(read.as_mut(), cx, buf),
+            Inner::File(file, _, _) => Poll::Ready(file.read(buf)),
+        };
+
+        if let Poll::Ready(Ok(len)) = poll {
+            self.position += len as u64;
+        }
+
+        poll
+    }
+}
+
+/// Allows bodies to be consumed as a stream of byte chunks, such as for use
+/// with stream combinators.
+///
+/// In-memory bodies are produced as a single chunk without copying. Other
+/// bodies are read into a shared buffer, so that chunks do not need an
+/// allocation of their own.
+impl Stream for Body {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Inner::Bytes(cursor) = &mut self.inner {
+            let position = cursor.position() as usize;
+            let bytes = cursor.get_ref().slice_from(position);
+            cursor.set_position(cursor.get_ref().len() as u64);
+
+            return Poll::Ready(if bytes.is_empty() {
+                None
+            } else {
+                Some(Ok(bytes))
+            });
+        }
+
+        // Start a new buffer once the current one is mostly used up.
+        let mut buffer = match self.chunk_buffer.take() {
+            Some(buffer) if buffer.len() >= CHUNK_BUFFER_SIZE / 8 => buffer,
+            _ => BytesMut::from(vec![0; CHUNK_BUFFER_SIZE]),
+        };
+
+        let poll = match AsyncRead::poll_read(self.as_mut(), cx, &mut buffer) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Ok(0)) => Poll::Ready(None),
+            Poll::Ready(Ok(len)) => Poll::Ready(Some(Ok(buffer.split_to(len).freeze()))),
+            Poll::Ready(Err(e)) => Poll::Ready(Some(Err(e))),
+        };
+
+        self.chunk_buffer = Some(buffer);
+
+        poll
+    }
+}
+
+/// Adapts a stream of byte chunks into a reader.
+struct StreamReader {
+    stream: Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send>>,
+    chunk: Cursor<Bytes>,
+}
+
+impl AsyncRead for StreamReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        loop {
+            // Copy from the current chunk until it is exhausted.
+            let len = Read::read(&mut self.chunk, buf)?;
+
+            if len > 0 || buf.is_empty() {
+                return Poll::Ready(Ok(len));
+            }
+
+            match self.stream.as_mut().poll_next(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(None) => return Poll::Ready(Ok(0)),
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(e)),
+                Poll::Ready(Some(Ok(chunk))) => self.chunk = Cursor::new(chunk),
+            }
+        }
+    }
+}
+
+/// Compresses the contents of a body while it is being read.
+#[cfg(feature = "compression")]
+struct Compressor {
+    inner: Body,
+    encoder: Encoder,
+
+    /// Compressed output not yet returned to the reader.
+    output: Cursor<Vec<u8>>,
+
+    /// Whether the inner body has been read completely.
+    finished: bool,
+}
+
+#[cfg(feature = "compression")]
+impl AsyncRead for Compressor {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let mut chunk = [0; 8192];
+
+        loop {
+            let len = Read::read(&mut this.output, buf)?;
+
+            if len > 0 || buf.is_empty() || this.finished {
+                return Poll::Ready(Ok(len));
+            }
+
+            // Feed more input into the encoder until it produces some output.
+            match AsyncRead::poll_read(Pin::new(&mut this.inner), cx, &mut chunk) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Ready(Ok(0)) => {
+                    this.encoder.try_finish()?;
+                    this.finished = true;
+                }
+                Poll::Ready(Ok(len)) => this.encoder.write_all(&chunk[..len])?,
+            }
+
+            let mut output = mem::replace(&mut this.output, Cursor::new(Vec::new())).into_inner();
+            output.clear();
+            this.encoder.swap_output(&mut output);
+            this.output = Cursor::new(output);
+        }
+    }
+}
+
+/// An encoder for one of the supported compression formats, writing to a
+/// buffer in memory.
+#[cfg(feature = "compression")]
+enum Encoder {
+    Gzip(flate2::write::GzEncoder<Vec<u8>>),
+    Deflate(flate2::write::ZlibEncoder<Vec<u8>>),
+}
+
+#[cfg(feature = "compression")]
+impl Encoder {
+    fn new(compression: Compression) -> Self {
+        let level = flate2::Compression::default();
+
+        match compression {
+            Compression::Gzip => Encoder::Gzip(flate2::write::GzEncoder::new(Vec::new(), level)),
+            Compression::Deflate => {
+                Encoder::Deflate(flate2::write::ZlibEncoder::new(Vec::new(), level))
+            }
+        }
+    }
+
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.write_all(data),
+            Encoder::Deflate(encoder) => encoder.write_all(data),
+        }
+    }
+
+    /// Write out all remaining compressed data.
+    fn try_finish(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.try_finish(),
+            Encoder::Deflate(encoder) => encoder.try_finish(),
+        }
+    }
+
+    /// Exchange the compressed output produced so far with the given buffer.
+    fn swap_output(&mut self, output: &mut Vec<u8>) {
+        match self {
+            Encoder::Gzip(encoder) => mem::swap(encoder.get_mut(), output),
+            Encoder::Deflate(encoder) => mem::swap(encoder.get_mut(), output),
+        }
+    }
+
+    /// Write out all remaining compressed data and return the output.
+    fn finish(self) -> io::Result<Vec<u8>> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.finish(),
+            Encoder::Deflate(encoder) => encoder.finish(),
+        }
+    }
+}
+
+/// Adapts an [`http_body::Body`] into a stream of byte chunks.
+#[cfg(feature = "http-body")]
+struct HttpBodyStream<B>(Pin<Box<B>>);
+
+#[cfg(feature = "http-body")]
+impl<B> Stream for HttpBodyStream<B>
+where
+    B: http_body::Body,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        use bytes::Buf;
+
+        match self.0.as_mut().poll_data(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Ready(Some(Ok(data))) => Poll::Ready(Some(Ok(data.collect()))),
+            Poll::Ready(Some(Err(e))) => {
+                Poll::Ready(Some(Err(io::Error::new(io::ErrorKind::Other, e))))
+            }
+        }
+    }
+}
+
+/// Allows isahc bodies, such as response bodies, to be used with other HTTP
+/// libraries.
+///
+/// This implementation requires the `http-body` feature to be enabled.
+#[cfg(feature = "http-body")]
+impl http_body::Body for Body {
+    type Data = Cursor<Bytes>;
+    type Error = io::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        Stream::poll_next(self, cx).map(|chunk| chunk.map(|result| result.map(Cursor::new)))
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(None))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.remaining() == Some(0)
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        match self.remaining() {
+            Some(len) => http_body::SizeHint::with_exact(len),
+            None => http_body::SizeHint::default(),
         }
     }
 }
@@ -231,4 +695,170 @@ mod tests {
     fn traits() {
         is_send::<Body>();
     }
+
+    #[test]
+    fn stream_body_reads_all_chunks() {
+        let chunks = futures_util::stream::iter(vec![
+            Ok(Bytes::from("hello")),
+            Ok(Bytes::new()),
+            Ok(Bytes::from(" world")),
+        ]);
+        let mut body = Body::from_stream(chunks);
+
+        assert_eq!(body.len(), None);
+        assert_eq!(body.text().unwrap(), "hello world");
+    }
+
+    #[test]
+    fn bytes_body_is_streamed_in_one_chunk() {
+        use futures_util::stream::StreamExt;
+
+        let mut body = Body::from("hello world");
+
+        assert_eq!(
+            body.next().join().unwrap().unwrap(),
+            Bytes::from("hello world")
+        );
+        assert!(body.next().join().is_none());
+    }
+
+    #[test]
+    fn reader_body_is_streamed_in_chunks() {
+        use futures_util::stream::StreamExt;
+
+        let reader = futures_util::io::AllowStdIo::new(Cursor::new(vec![1; 10000]));
+        let chunks = Body::reader(reader).collect::<Vec<_>>().join();
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(
+            chunks.into_iter().map(|c| c.unwrap().len()).sum::<usize>(),
+            10000
+        );
+    }
+
+    #[test]
+    fn file_body_can_be_reset() {
+        let path = std::env::temp_dir().join("isahc-body-from-file-test.txt");
+        std::fs::write(&path, "file contents").unwrap();
+
+        let mut body = Body::from_file(&path).unwrap();
+        assert_eq!(body.len(), Some(13));
+        assert_eq!(body.text().unwrap(), "file contents");
+
+        assert!(body.reset());
+        assert_eq!(body.text().unwrap(), "file contents");
+        assert_eq!(body.try_clone().unwrap().text().unwrap(), "file contents");
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[cfg(feature = "http-body")]
+    #[test]
+    fn http_body_is_polled_in_chunks() {
+        use http_body::Body as _;
+
+        let reader = futures_util::io::AllowStdIo::new(Cursor::new(vec![1; 10000]));
+        let mut body = Body::reader_sized(reader, 10000);
+        assert_eq!(body.size_hint().exact(), Some(10000));
+
+        let mut poll_data = || {
+            futures_util::future::poll_fn(|cx| Pin::new(&mut body).poll_data(cx))
+                .join()
+                .map(|chunk| chunk.unwrap().into_inner())
+        };
+
+        assert_eq!(poll_data().unwrap().len(), 8192);
+        assert_eq!(poll_data().unwrap().len(), 1808);
+        assert!(poll_data().is_none());
+
+        assert_eq!(body.size_hint().exact(), Some(0));
+        assert!(body.is_end_stream());
+    }
+
+    #[cfg(feature = "http-body")]
+    #[test]
+    fn http_body_size_hint_tracks_remaining_length() {
+        use http_body::Body as _;
+
+        let path = std::env::temp_dir().join("isahc-body-size-hint-test.txt");
+        std::fs::write(&path, "file contents").unwrap();
+
+        let mut body = Body::from_file(&path).unwrap();
+        assert_eq!(body.size_hint().exact(), Some(13));
+        assert!(!body.is_end_stream());
+
+        Read::read_exact(&mut body, &mut [0; 5]).unwrap();
+        assert_eq!(body.size_hint().exact(), Some(8));
+        assert_eq!(body.len(), Some(13));
+
+        assert_eq!(body.text().unwrap(), "contents");
+        assert_eq!(body.size_hint().exact(), Some(0));
+        assert!(body.is_end_stream());
+
+        assert!(body.reset());
+        assert_eq!(body.size_hint().exact(), Some(13));
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[cfg(feature = "http-body")]
+    #[test]
+    fn http_body_of_bytes_is_one_chunk() {
+        use http_body::Body as _;
+
+        let mut body = Body::from("hello world");
+        assert_eq!(body.size_hint().exact(), Some(11));
+
+        let chunk = futures_util::future::poll_fn(|cx| Pin::new(&mut body).poll_data(cx))
+            .join()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(chunk.into_inner(), Bytes::from("hello world"));
+        assert_eq!(body.size_hint().exact(), Some(0));
+        assert!(body.is_end_stream());
+    }
+
+    #[cfg(feature = "http-body")]
+    #[test]
+    fn http_body_round_trip() {
+        let mut body = Body::from_http_body(Body::from("hello world"));
+
+        assert_eq!(body.len(), Some(11));
+        assert_eq!(body.text().unwrap(), "hello world");
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn bytes_body_is_compressed_eagerly() {
+        let mut body = Body::from("hello world")
+            .compress(Compression::Gzip)
+            .unwrap();
+        assert!(body.len().is_some());
+
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(&mut body)
+            .read_to_string(&mut decoded)
+            .unwrap();
+
+        assert_eq!(decoded, "hello world");
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn reader_body_is_compressed_while_read() {
+        let chunks =
+            futures_util::stream::iter(vec![Ok(Bytes::from("hello")), Ok(Bytes::from(" world"))]);
+        let mut body = Body::from_stream(chunks)
+            .compress(Compression::Deflate)
+            .unwrap();
+        assert_eq!(body.len(), None);
+
+        let mut decoded = String::new();
+        flate2::read::ZlibDecoder::new(&mut body)
+            .read_to_string(&mut decoded)
+            .unwrap();
+
+        assert_eq!(decoded, "hello world");
+    }
 }
@@ -0,0 +1,1027 @@
+//! In-memory HTTP response caching.
+//!
+//! A client with a response cache remembers cacheable responses to `GET`
+//! requests, following the rules of RFC 7234 for a private cache. Responses
+//! that are still fresh according to their `Cache-Control` or `Expires`
+//! headers are served from memory without contacting the server. Stale
+//! responses with an `ETag` or `Last-Modified` header are revalidated with a
+//! conditional request, and served from memory if the server confirms that
+//! they did not change. Responses with a `stale-while-revalidate` directive
+//! are served stale while they are revalidated in the background, and
+//! responses with a `stale-if-error` directive are served stale when the
+//! server cannot be reached or fails, as described in RFC 5861.
+//!
+//! Responses are only reused for requests with the same values for any
+//! headers listed in their `Vary` header, ignoring differences in whitespace
+//! around commas and any headers configured with
+//! [`ResponseCache::ignore_vary`]. Individual requests may bypass the cache,
+//! or use stored responses regardless of their freshness, by setting a
+//! [`CacheMode`].
+//!
+//! See
+//! [`HttpClientBuilder::response_cache`](crate::HttpClientBuilder::response_cache)
+//! for enabling the cache, and
+//! [`ResponseExt::cache_status`](crate::ResponseExt::cache_status) for how a
+//! response was obtained.
+
+use crate::interceptor::{Interceptor, InterceptorFuture, Next};
+use crate::response::{EffectiveUri, ResponseExt};
+use crate::{parse, validators, Body};
+use bytes::Bytes;
+use futures_io::AsyncRead;
+use futures_util::future::{self, FutureExt};
+use http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use http::{Request, Response, StatusCode, Uri, Version};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::mem;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How a response was obtained by a client with a response cache.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CacheStatus {
+    /// The response was served from the cache without contacting the server.
+    Hit,
+    /// The response was received from the server.
+    Miss,
+    /// A stale response was confirmed to still be valid by the server, and
+    /// was served from the cache.
+    Revalidated,
+    /// A stale response was served from the cache without confirming that
+    /// it is still valid. This happens for requests with a
+    /// [`CacheMode`] allowing it, for responses with a
+    /// `stale-while-revalidate` directive while they are revalidated in the
+    /// background, and for responses with a `stale-if-error` directive when
+    /// the server could not be reached or failed with a server error.
+    Stale,
+}
+
+/// How a request uses the client's response cache, similar to the `cache`
+/// option of the Fetch API.
+///
+/// Set the mode of a request with
+/// [`RequestBuilderExt::cache_mode`](crate::RequestBuilderExt::cache_mode).
+/// The default mode is [`CacheMode::Default`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CacheMode {
+    /// Serve fresh responses from the cache, revalidate stale ones, and store
+    /// cacheable responses, following HTTP caching rules.
+    Default,
+    /// Send the request to the server without consulting the cache, and do
+    /// not store the response. Useful when the user forces a refresh.
+    NoStore,
+    /// Serve a stored response even if it is stale, without contacting the
+    /// server. Only requests without a stored response are sent to the
+    /// server.
+    ForceCache,
+    /// Serve a stored response even if it is stale, and never contact the
+    /// server. Requests without a stored response get a synthesized
+    /// `504 Gateway Timeout` response. Useful for showing cached data while
+    /// offline.
+    OnlyIfCached,
+}
+
+impl Default for CacheMode {
+    fn default() -> Self {
+        CacheMode::Default
+    }
+}
+
+/// An in-memory response cache, which stores responses and serves requests
+/// from them where possible.
+///
+/// Enable a cache for a client with
+/// [`HttpClientBuilder::response_cache`](crate::HttpClientBuilder::response_cache).
+/// A cache is also created from its maximum size in bytes.
+#[derive(Debug)]
+pub struct ResponseCache {
+    store: Arc<Mutex<Store>>,
+}
+
+impl ResponseCache {
+    /// Create a cache holding response bodies of up to `max_size` bytes in
+    /// total.
+    pub fn new(max_size: u64) -> Self {
+        Self {
+            store: Arc::new(Mutex::new(Store {
+                entries: HashMap::new(),
+                size: 0,
+                max_size,
+                ignored_vary: Vec::new(),
+                revalidating: HashSet::new(),
+            })),
+        }
+    }
+
+    /// Ignore the given request header when a response lists it in its `Vary`
+    /// header.
+    ///
+    /// Servers commonly vary on headers such as `User-Agent` that differ
+    /// between clients without changing the response, which would otherwise
+    /// make a response only reusable for requests with the exact same value.
+    pub fn ignore_vary(self, name: HeaderName) -> Self {
+        self.store.lock().unwrap().ignored_vary.push(name);
+        self
+    }
+}
+
+impl From<u64> for ResponseCache {
+    fn from(max_size: u64) -> Self {
+        Self::new(max_size)
+    }
+}
+
+impl Interceptor for ResponseCache {
+    fn intercept<'a>(
+        &'a self,
+        mut request: Request<Body>,
+        next: Next<'a>,
+    ) -> InterceptorFuture<'a> {
+        let key = request.uri().to_string();
+
+        if request.method() != http::Method::GET {
+            if request.method().is_safe() {
+                return next.run(request);
+            }
+
+            // A successful unsafe request likely changed the resource, so any
+            // stored response for it is outdated.
+            // https://tools.ietf.org/html/rfc7234#section-4.4
+            let store = self.store.clone();
+
+            return Box::pin(next.run(request).map(move |result| {
+                if let Ok(response) = result.as_ref() {
+                    if response.status().is_success() || response.status().is_redirection() {
+                        store.lock().unwrap().remove(&key);
+                    }
+                }
+
+                result
+            }));
+        }
+
+        let request_directives = directives(request.headers());
+        let mode = request
+            .extensions()
+            .get::<CacheMode>()
+            .cloned()
+            .unwrap_or_default();
+
+        // Leave requests alone that must not be cached, or that the user has
+        // made conditional explicitly, unless they must not reach the server.
+        if mode == CacheMode::NoStore
+            || (mode != CacheMode::OnlyIfCached
+                && (has_directive(&request_directives, "no-store")
+                    || request.headers().contains_key(header::IF_NONE_MATCH)
+                    || request.headers().contains_key(header::IF_MODIFIED_SINCE)))
+        {
+            return next.run(request);
+        }
+
+        let request_headers = request.headers().clone();
+
+        // A copy of the stored response, in case it is evicted while the
+        // request is in flight.
+        let stored = self
+            .store
+            .lock()
+            .unwrap()
+            .get(&key, &request_headers)
+            .cloned();
+
+        let stored = match (stored, mode) {
+            (Some(entry), CacheMode::ForceCache) | (Some(entry), CacheMode::OnlyIfCached) => {
+                let status = if entry.is_fresh() {
+                    CacheStatus::Hit
+                } else {
+                    CacheStatus::Stale
+                };
+
+                return Box::pin(future::ready(Ok(entry.to_response(status))));
+            }
+            (None, CacheMode::OnlyIfCached) => {
+                // https://tools.ietf.org/html/rfc7234#section-5.2.1.7
+                let mut response = Response::new(Body::empty());
+                *response.status_mut() = StatusCode::GATEWAY_TIMEOUT;
+                response.extensions_mut().insert(CacheStatus::Miss);
+
+                return Box::pin(future::ready(Ok(response)));
+            }
+            (Some(entry), _) => {
+                let max_age = directive_seconds(&request_directives, "max-age");
+                let no_cache = has_directive(&request_directives, "no-cache");
+
+                if entry.is_fresh()
+                    && !no_cache
+                    && max_age.map_or(true, |max_age| entry.age() <= max_age)
+                {
+                    return Box::pin(future::ready(Ok(entry.to_response(CacheStatus::Hit))));
+                }
+
+                // Serve the stale response right away, and revalidate it in
+                // the background unless that is already happening.
+                // https://tools.ietf.org/html/rfc5861#section-3
+                if !no_cache
+                    && max_age.is_none()
+                    && entry.is_stale_within(entry.stale_while_revalidate)
+                {
+                    if self.store.lock().unwrap().revalidating.insert(key.clone()) {
+                        entry.add_validators(&mut request);
+                        self.revalidate(key, request_headers, entry.clone(), request, &next);
+                    }
+
+                    return Box::pin(future::ready(Ok(entry.to_response(CacheStatus::Stale))));
+                }
+
+                Some(entry)
+            }
+            (None, _) => None,
+        };
+
+        let conditional = stored
+            .as_ref()
+            .map_or(false, |entry| entry.add_validators(&mut request));
+        let store = self.store.clone();
+
+        Box::pin(next.run(request).map(move |result| {
+            // Serve the stale response instead of an error if it allows that.
+            // https://tools.ietf.org/html/rfc5861#section-4
+            if let Some(entry) = stored.as_ref() {
+                let failed = match result.as_ref() {
+                    Ok(response) => match response.status().as_u16() {
+                        500 | 502 | 503 | 504 => true,
+                        _ => false,
+                    },
+                    Err(e) => {
+                        log::debug!("failed to revalidate cached response: {}", e);
+                        true
+                    }
+                };
+
+                if failed && entry.is_stale_within(entry.stale_if_error) {
+                    return Ok(entry.to_response(CacheStatus::Stale));
+                }
+            }
+
+            let mut response = result?;
+
+            if let Some(mut entry) = stored {
+                if conditional && response.status() == StatusCode::NOT_MODIFIED {
+                    entry.update(response.headers());
+                    let response = entry.to_response(CacheStatus::Revalidated);
+                    store.lock().unwrap().insert(key, entry);
+                    return Ok(response);
+                }
+            }
+
+            response.extensions_mut().insert(CacheStatus::Miss);
+
+            Ok(store_response(store, key, &request_headers, response))
+        }))
+    }
+}
+
+impl ResponseCache {
+    /// Send a conditional request for a stored response in the background,
+    /// and update the cache with its response.
+    ///
+    /// Background requests skip any interceptors after the cache, since they
+    /// outlive the request that started them.
+    fn revalidate(
+        &self,
+        key: String,
+        request_headers: HeaderMap,
+        mut entry: Entry,
+        request: Request<Body>,
+        next: &Next<'_>,
+    ) {
+        let store = self.store.clone();
+        let finish_key = key.clone();
+
+        let result = next.send_detached(request, move |result| {
+            match result {
+                Ok(response) => {
+                    if response.status() == StatusCode::NOT_MODIFIED {
+                        entry.update(response.headers());
+                        store.lock().unwrap().insert(key, entry);
+                    } else {
+                        // The stored response is outdated, whether or not the
+                        // new one can be stored. Store it by reading its body.
+                        store
+                            .lock()
+                            .unwrap()
+                            .remove_where(&key, |stored| stored.vary == entry.vary);
+
+                        let mut body =
+                            store_response(store.clone(), key, &request_headers, response)
+                                .into_body();
+
+                        if let Err(e) = io::copy(&mut body, &mut io::sink()) {
+                            log::debug!("failed to revalidate cached response: {}", e);
+                        }
+                    }
+                }
+                Err(e) => log::debug!("failed to revalidate cached response: {}", e),
+            }
+
+            store.lock().unwrap().revalidating.remove(&finish_key);
+        });
+
+        if let Err(e) = result {
+            log::debug!("failed to revalidate cached response: {}", e);
+            self.store.lock().unwrap().revalidating.remove(&finish_key);
+        }
+    }
+}
+
+/// Arrange for a response to be stored once its body has been read, if it is
+/// cacheable.
+fn store_response(
+    store: Arc<Mutex<Store>>,
+    key: String,
+    request_headers: &HeaderMap,
+    response: Response<Body>,
+) -> Response<Body> {
+    let (entry, max_size) = {
+        let store = store.lock().unwrap();
+
+        match Entry::new(request_headers, &response, &store.ignored_vary) {
+            Some(entry) => (entry, store.max_size),
+            None => return response,
+        }
+    };
+
+    let (parts, body) = response.into_parts();
+    let len = body.len();
+    let reader = CachingReader {
+        inner: body,
+        buffer: Vec::new(),
+        max_size,
+        pending: Some((store, key, entry)),
+    };
+
+    Response::from_parts(
+        parts,
+        match len {
+            Some(len) => Body::reader_sized(reader, len),
+            None => Body::reader(reader),
+        },
+    )
+}
+
+#[derive(Debug)]
+struct Store {
+    /// Stored responses by URI. Responses with a `Vary` header may be stored
+    /// once for each combination of request header values they vary on.
+    entries: HashMap<String, Vec<Entry>>,
+    /// Total size of all stored bodies.
+    size: u64,
+    max_size: u64,
+    /// Request headers not to vary on.
+    ignored_vary: Vec<HeaderName>,
+    /// URIs of stored responses being revalidated in the background.
+    revalidating: HashSet<String>,
+}
+
+impl Store {
+    /// Get the stored response for a request with the given headers, if any.
+    fn get(&self, key: &str, request_headers: &HeaderMap) -> Option<&Entry> {
+        self.entries
+            .get(key)?
+            .iter()
+            .find(|entry| entry.matches(request_headers))
+    }
+
+    fn insert(&mut self, key: String, entry: Entry) {
+        // Replace the variant stored for the same request header values.
+        self.remove_where(&key, |stored| stored.vary == entry.vary);
+
+        let len = entry.body.len() as u64;
+
+        if len > self.max_size {
+            return;
+        }
+
+        // Make room by evicting the oldest responses first.
+        while self.size + len > self.max_size {
+            let oldest = self
+                .entries
+                .iter()
+                .flat_map(|(key, entries)| entries.iter().map(move |entry| (key, entry)))
+                .min_by_key(|(_, entry)| entry.received_at)
+                .map(|(key, entry)| (key.clone(), entry.received_at));
+
+            match oldest {
+                Some((oldest_key, received_at)) => {
+                    self.remove_where(&oldest_key, |stored| stored.received_at == received_at)
+                }
+                None => break,
+            }
+        }
+
+        self.size += len;
+        self.entries.entry(key).or_insert_with(Vec::new).push(entry);
+    }
+
+    /// Remove all stored variants of a response.
+    fn remove(&mut self, key: &str) {
+        self.remove_where(key, |_| true);
+    }
+
+    fn remove_where(&mut self, key: &str, f: impl Fn(&Entry) -> bool) {
+        if let Some(entries) = self.entries.get_mut(key) {
+            let size = &mut self.size;
+
+            entries.retain(|entry| {
+                if f(entry) {
+                    *size -= entry.body.len() as u64;
+                    false
+                } else {
+                    true
+                }
+            });
+
+            if entries.is_empty() {
+                self.entries.remove(key);
+            }
+        }
+    }
+}
+
+/// A stored response.
+#[derive(Clone, Debug)]
+struct Entry {
+    status: StatusCode,
+    version: Version,
+    headers: HeaderMap,
+    body: Bytes,
+    uri: Option<Uri>,
+    /// Normalized values of the request headers that the response varies on.
+    vary: Vec<(HeaderName, Option<String>)>,
+    /// Whether the response must be revalidated before every use.
+    no_cache: bool,
+    /// How long the response stays fresh after being generated.
+    freshness_lifetime: Duration,
+    /// How long after becoming stale the response may still be served while
+    /// it is revalidated in the background.
+    stale_while_revalidate: Option<Duration>,
+    /// How long after becoming stale the response may still be served if
+    /// revalidating it fails.
+    stale_if_error: Option<Duration>,
+    /// How old the response already was when it was received.
+    initial_age: Duration,
+    received_at: Instant,
+}
+
+impl Entry {
+    /// Create an entry without a body for a response, if the response may be
+    /// stored.
+    fn new(
+        request_headers: &HeaderMap,
+        response: &Response<Body>,
+        ignored_vary: &[HeaderName],
+    ) -> Option<Self> {
+        // Only responses cacheable by default are stored.
+        // https://tools.ietf.org/html/rfc7231#section-6.1
+        match response.status().as_u16() {
+            200 | 203 | 204 | 300 | 301 | 404 | 405 | 410 | 414 | 501 => {}
+            _ => return None,
+        }
+
+        let response_directives = directives(response.headers());
+
+        if has_directive(&response_directives, "no-store") {
+            return None;
+        }
+
+        let mut vary = Vec::new();
+
+        for value in response.headers().get_all(header::VARY) {
+            for name in value.to_str().ok()?.split(',').map(str::trim) {
+                // A response that varies on `*` can never be reused, and `*`
+                // conveniently fails to parse as a header name.
+                let name: HeaderName = name.parse().ok()?;
+
+                if !ignored_vary.contains(&name) {
+                    let value = normalized_value(request_headers, &name);
+                    vary.push((name, value));
+                }
+            }
+        }
+
+        let has_explicit_freshness = has_directive(&response_directives, "max-age")
+            || response.headers().contains_key(header::EXPIRES);
+        let has_validators = response.headers().contains_key(header::ETAG)
+            || response.headers().contains_key(header::LAST_MODIFIED);
+
+        if !has_explicit_freshness && !has_validators {
+            return None;
+        }
+
+        let (freshness_lifetime, initial_age) = freshness(response.headers());
+        let (stale_while_revalidate, stale_if_error) = stale_windows(&response_directives);
+
+        Some(Self {
+            status: response.status(),
+            version: response.version(),
+            headers: response.headers().clone(),
+            body: Bytes::new(),
+            uri: response.effective_uri().cloned(),
+            vary,
+            no_cache: has_directive(&response_directives, "no-cache"),
+            freshness_lifetime,
+            stale_while_revalidate,
+            stale_if_error,
+            initial_age,
+            received_at: Instant::now(),
+        })
+    }
+
+    fn age(&self) -> Duration {
+        self.initial_age + self.received_at.elapsed()
+    }
+
+    fn is_fresh(&self) -> bool {
+        !self.no_cache && self.age() < self.freshness_lifetime
+    }
+
+    /// Check whether this response has been stale for no longer than the
+    /// given window.
+    fn is_stale_within(&self, window: Option<Duration>) -> bool {
+        window.map_or(false, |window| {
+            self.age() < self.freshness_lifetime + window
+        })
+    }
+
+    /// Make a request conditional on this response having changed. Returns
+    /// false if the response has no validators.
+    fn add_validators(&self, request: &mut Request<Body>) -> bool {
+        if let Some(etag) = self.headers.get(header::ETAG) {
+            request
+                .headers_mut()
+                .insert(header::IF_NONE_MATCH, etag.clone());
+            true
+        } else if let Some(date) = self.headers.get(header::LAST_MODIFIED) {
+            request
+                .headers_mut()
+                .insert(header::IF_MODIFIED_SINCE, date.clone());
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Check whether this response may be used for a request with the given
+    /// headers.
+    fn matches(&self, request_headers: &HeaderMap) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| normalized_value(request_headers, name) == *value)
+    }
+
+    /// Update the stored headers with those of a `304 Not Modified` response
+    /// confirming that this response is still valid.
+    ///
+    /// https://tools.ietf.org/html/rfc7234#section-4.3.4
+    fn update(&mut self, headers: &HeaderMap) {
+        validators::update_headers(&mut self.headers, headers);
+
+        let (freshness_lifetime, initial_age) = freshness(&self.headers);
+        let directives = directives(&self.headers);
+        let (stale_while_revalidate, stale_if_error) = stale_windows(&directives);
+        self.no_cache = has_directive(&directives, "no-cache");
+        self.freshness_lifetime = freshness_lifetime;
+        self.stale_while_revalidate = stale_while_revalidate;
+        self.stale_if_error = stale_if_error;
+        self.initial_age = initial_age;
+        self.received_at = Instant::now();
+    }
+
+    fn to_response(&self, status: CacheStatus) -> Response<Body> {
+        let mut response = Response::new(Body::from(self.body.clone()));
+
+        *response.status_mut() = self.status;
+        *response.version_mut() = self.version;
+        *response.headers_mut() = self.headers.clone();
+        response
+            .headers_mut()
+            .insert(header::AGE, HeaderValue::from(self.age().as_secs()));
+        response.extensions_mut().insert(status);
+
+        if let Some(uri) = self.uri.as_ref() {
+            response.extensions_mut().insert(EffectiveUri(uri.clone()));
+        }
+
+        response
+    }
+}
+
+/// Get how long a response is fresh for, and how old it was on arrival.
+///
+/// https://tools.ietf.org/html/rfc7234#section-4.2
+fn freshness(headers: &HeaderMap) -> (Duration, Duration) {
+    let now = SystemTime::now();
+    let date = |name: HeaderName| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse::parse_http_date)
+    };
+    let generated_at = date(header::DATE).unwrap_or(now);
+
+    let lifetime = match directive_seconds(&directives(headers), "max-age") {
+        Some(max_age) => max_age,
+        // An invalid Expires header means the response is already expired.
+        None => date(header::EXPIRES)
+            .and_then(|expires| expires.duration_since(generated_at).ok())
+            .unwrap_or_default(),
+    };
+
+    let age_header = headers
+        .get(header::AGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or_default();
+    let apparent_age = now.duration_since(generated_at).unwrap_or_default();
+
+    (lifetime, age_header.max(apparent_age))
+}
+
+/// Get how long a response may be served stale while being revalidated, and
+/// when revalidating fails, according to its `Cache-Control` directives.
+///
+/// https://tools.ietf.org/html/rfc5861
+fn stale_windows(directives: &[(String, Option<String>)]) -> (Option<Duration>, Option<Duration>) {
+    // Responses that must be revalidated are never served stale.
+    // https://tools.ietf.org/html/rfc7234#section-4.2.4
+    if has_directive(directives, "no-cache") || has_directive(directives, "must-revalidate") {
+        return (None, None);
+    }
+
+    (
+        directive_seconds(directives, "stale-while-revalidate"),
+        directive_seconds(directives, "stale-if-error"),
+    )
+}
+
+/// Get the value of a request header for comparing it against a stored
+/// response's `Vary` header.
+///
+/// Multiple fields are combined into one, and whitespace around list items is
+/// removed, so that "gzip,br" and "gzip, br" select the same response.
+fn normalized_value(headers: &HeaderMap, name: &HeaderName) -> Option<String> {
+    let mut values = headers.get_all(name).iter().peekable();
+
+    values.peek()?;
+
+    Some(
+        values
+            .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+            .collect::<Vec<_>>()
+            .join(",")
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .collect::<Vec<_>>()
+            .join(","),
+    )
+}
+
+/// Parse the `Cache-Control` directives in the given headers.
+fn directives(headers: &HeaderMap) -> Vec<(String, Option<String>)> {
+    headers
+        .get_all(header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|directive| {
+            let mut pair = directive.splitn(2, '=');
+            let name = pair.next()?.trim().to_ascii_lowercase();
+            let value = pair
+                .next()
+                .map(|value| value.trim().trim_matches('"').to_owned());
+
+            if name.is_empty() {
+                None
+            } else {
+                Some((name, value))
+            }
+        })
+        .collect()
+}
+
+fn has_directive(directives: &[(String, Option<String>)], name: &str) -> bool {
+    directives.iter().any(|(n, _)| n == name)
+}
+
+fn directive_seconds(directives: &[(String, Option<String>)], name: &str) -> Option<Duration> {
+    directives
+        .iter()
+        .find(|(n, _)| n == name)
+        .and_then(|(_, value)| value.as_ref())
+        .and_then(|value| value.parse().ok())
+        .map(Duration::from_secs)
+}
+
+/// A response body reader that stores the response in the cache once the body
+/// has been read completely.
+struct CachingReader {
+    inner: Body,
+    buffer: Vec<u8>,
+    max_size: u64,
+    pending: Option<(Arc<Mutex<Store>>, String, Entry)>,
+}
+
+impl AsyncRead for CachingReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let len = match Pin::new(&mut this.inner).poll_read(cx, buf) {
+            Poll::Ready(Ok(len)) => len,
+            other => return other,
+        };
+
+        // Reads into an empty buffer also return zero, without meaning that
+        // the body has ended.
+        if len == 0 && !buf.is_empty() {
+            if let Some((store, key, mut entry)) = this.pending.take() {
+                entry.body = Bytes::from(mem::replace(&mut this.buffer, Vec::new()));
+                store.lock().unwrap().insert(key, entry);
+            }
+        } else if len > 0 && this.pending.is_some() {
+            // Give up on bodies too large to ever fit into the cache.
+            if (this.buffer.len() + len) as u64 > this.max_size {
+                this.pending = None;
+                this.buffer = Vec::new();
+            } else {
+                this.buffer.extend_from_slice(&buf[..len]);
+            }
+        }
+
+        Poll::Ready(Ok(len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        for (name, value) in pairs {
+            headers.append(name.parse::<HeaderName>().unwrap(), value.parse().unwrap());
+        }
+
+        headers
+    }
+
+    #[test]
+    fn parse_directives() {
+        let directives = directives(&headers(&[
+            ("Cache-Control", "public, MAX-AGE=\"60\""),
+            ("Cache-Control", "no-cache"),
+        ]));
+
+        assert!(has_directive(&directives, "public"));
+        assert!(has_directive(&directives, "no-cache"));
+        assert_eq!(
+            directive_seconds(&directives, "max-age"),
+            Some(Duration::from_secs(60))
+        );
+    }
+
+    #[test]
+    fn max_age_takes_precedence_over_expires() {
+        let (lifetime, _) = freshness(&headers(&[
+            ("Cache-Control", "max-age=30"),
+            ("Date", "Sun, 06 Nov 1994 08:49:37 GMT"),
+            ("Expires", "Sun, 06 Nov 1994 09:49:37 GMT"),
+        ]));
+
+        assert_eq!(lifetime, Duration::from_secs(30));
+    }
+
+    #[test]
+    fn expires_is_relative_to_date() {
+        let (lifetime, age) = freshness(&headers(&[
+            ("Date", "Sun, 06 Nov 1994 08:49:37 GMT"),
+            ("Expires", "Sun, 06 Nov 1994 09:49:37 GMT"),
+        ]));
+
+        assert_eq!(lifetime, Duration::from_secs(3600));
+        assert!(age > Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn invalid_expires_is_already_expired() {
+        let (lifetime, _) = freshness(&headers(&[("Expires", "0")]));
+
+        assert_eq!(lifetime, Duration::from_secs(0));
+    }
+
+    #[test]
+    fn age_header_is_counted() {
+        let (_, age) = freshness(&headers(&[("Age", "100")]));
+
+        assert_eq!(age, Duration::from_secs(100));
+    }
+
+    #[test]
+    fn oldest_entries_are_evicted() {
+        let response = Response::builder()
+            .header("Cache-Control", "max-age=60")
+            .body(Body::empty())
+            .unwrap();
+        let now = Instant::now();
+        let entry = |body: &'static [u8], seconds_ago| {
+            let mut entry = Entry::new(&HeaderMap::new(), &response, &[]).unwrap();
+            entry.body = Bytes::from_static(body);
+            entry.received_at = now - Duration::from_secs(seconds_ago);
+            entry
+        };
+
+        let mut store = Store {
+            entries: HashMap::new(),
+            size: 0,
+            max_size: 10,
+            ignored_vary: Vec::new(),
+            revalidating: HashSet::new(),
+        };
+
+        store.insert("a".into(), entry(b"12345", 3));
+        store.insert("b".into(), entry(b"12345", 2));
+        store.insert("c".into(), entry(b"123", 1));
+        store.insert("d".into(), entry(b"12345678901", 0));
+
+        assert!(!store.entries.contains_key("a"));
+        assert!(store.entries.contains_key("b"));
+        assert!(store.entries.contains_key("c"));
+        assert!(!store.entries.contains_key("d"));
+        assert_eq!(store.size, 8);
+    }
+
+    #[test]
+    fn empty_reads_do_not_end_the_body() {
+        use futures::task::noop_waker_ref;
+        use std::io::Read;
+
+        let store = Arc::new(Mutex::new(Store {
+            entries: HashMap::new(),
+            size: 0,
+            max_size: 100,
+            ignored_vary: Vec::new(),
+            revalidating: HashSet::new(),
+        }));
+        let response = Response::builder()
+            .header("Cache-Control", "max-age=60")
+            .body(Body::from("hello"))
+            .unwrap();
+        let mut body =
+            store_response(store.clone(), "a".into(), &HeaderMap::new(), response).into_body();
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+        match Pin::new(&mut body).poll_read(&mut cx, &mut []) {
+            Poll::Ready(Ok(0)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(store.lock().unwrap().entries.is_empty());
+
+        let mut buf = Vec::new();
+        body.read_to_end(&mut buf).unwrap();
+
+        let store = store.lock().unwrap();
+        assert_eq!(store.get("a", &HeaderMap::new()).unwrap().body, "hello");
+    }
+
+    #[test]
+    fn variants_are_stored_separately() {
+        let response = Response::builder()
+            .header("Cache-Control", "max-age=60")
+            .header("Vary", "Accept-Language")
+            .body(Body::empty())
+            .unwrap();
+        let english = headers(&[("Accept-Language", "en")]);
+        let german = headers(&[("Accept-Language", "de")]);
+        let entry = |request_headers: &HeaderMap, body: &'static [u8]| {
+            let mut entry = Entry::new(request_headers, &response, &[]).unwrap();
+            entry.body = Bytes::from_static(body);
+            entry
+        };
+
+        let mut store = Store {
+            entries: HashMap::new(),
+            size: 0,
+            max_size: 100,
+            ignored_vary: Vec::new(),
+            revalidating: HashSet::new(),
+        };
+
+        store.insert("a".into(), entry(&english, b"hello"));
+        store.insert("a".into(), entry(&german, b"hallo"));
+        store.insert("a".into(), entry(&german, b"guten tag"));
+
+        assert_eq!(store.get("a", &english).unwrap().body, "hello");
+        assert_eq!(store.get("a", &german).unwrap().body, "guten tag");
+        assert!(store.get("a", &HeaderMap::new()).is_none());
+        assert_eq!(store.size, 14);
+
+        store.remove("a");
+
+        assert!(store.entries.is_empty());
+        assert_eq!(store.size, 0);
+    }
+
+    #[test]
+    fn vary_values_are_normalized() {
+        let response = Response::builder()
+            .header("Cache-Control", "max-age=60")
+            .header("Vary", "Accept-Encoding, Accept-Language")
+            .body(Body::empty())
+            .unwrap();
+        let entry =
+            Entry::new(&headers(&[("Accept-Encoding", "gzip,br")]), &response, &[]).unwrap();
+
+        assert!(entry.matches(&headers(&[("Accept-Encoding", "gzip, br")])));
+        assert!(entry.matches(&headers(&[
+            ("Accept-Encoding", "gzip"),
+            ("Accept-Encoding", " br "),
+        ])));
+        assert!(!entry.matches(&headers(&[("Accept-Encoding", "br, gzip")])));
+        assert!(!entry.matches(&headers(&[
+            ("Accept-Encoding", "gzip,br"),
+            ("Accept-Language", "en"),
+        ])));
+        assert!(!entry.matches(&HeaderMap::new()));
+    }
+
+    #[test]
+    fn ignored_vary_headers_are_not_compared() {
+        let response = Response::builder()
+            .header("Cache-Control", "max-age=60")
+            .header("Vary", "User-Agent, Accept-Language")
+            .body(Body::empty())
+            .unwrap();
+        let entry = Entry::new(
+            &headers(&[("User-Agent", "a"), ("Accept-Language", "en")]),
+            &response,
+            &[header::USER_AGENT],
+        )
+        .unwrap();
+
+        assert!(entry.matches(&headers(&[("User-Agent", "b"), ("Accept-Language", "en")])));
+        assert!(!entry.matches(&headers(&[("User-Agent", "a"), ("Accept-Language", "de")])));
+    }
+
+    #[test]
+    fn vary_star_is_never_stored() {
+        let response = Response::builder()
+            .header("Cache-Control", "max-age=60")
+            .header("Vary", "*")
+            .body(Body::empty())
+            .unwrap();
+
+        assert!(Entry::new(&HeaderMap::new(), &response, &[header::USER_AGENT]).is_none());
+    }
+
+    #[test]
+    fn stale_windows_are_parsed() {
+        let windows = |value| stale_windows(&directives(&headers(&[("Cache-Control", value)])));
+
+        assert_eq!(
+            windows("max-age=60, stale-while-revalidate=30, stale-if-error=600"),
+            (
+                Some(Duration::from_secs(30)),
+                Some(Duration::from_secs(600))
+            )
+        );
+        assert_eq!(windows("max-age=60"), (None, None));
+        assert_eq!(windows("stale-if-error=600, must-revalidate"), (None, None));
+        assert_eq!(windows("stale-while-revalidate=30, no-cache"), (None, None));
+    }
+
+    #[test]
+    fn stale_window_starts_when_freshness_ends() {
+        let response = Response::builder()
+            .header("Cache-Control", "max-age=60, stale-if-error=30")
+            .body(Body::empty())
+            .unwrap();
+        let mut entry = Entry::new(&HeaderMap::new(), &response, &[]).unwrap();
+        let now = Instant::now();
+
+        entry.received_at = now - Duration::from_secs(70);
+        assert!(!entry.is_fresh());
+        assert!(entry.is_stale_within(entry.stale_if_error));
+        assert!(!entry.is_stale_within(entry.stale_while_revalidate));
+
+        entry.received_at = now - Duration::from_secs(100);
+        assert!(!entry.is_stale_within(entry.stale_if_error));
+    }
+}
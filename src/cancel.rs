@@ -0,0 +1,102 @@
+//! Canceling requests while they are in progress.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::Waker;
+
+/// A token for canceling requests from another thread or task.
+///
+/// Attach a token to one or more requests with
+/// [`RequestBuilderExt::cancellation_token`](crate::RequestBuilderExt::cancellation_token),
+/// and call [`cancel`](CancellationToken::cancel) on any clone of it to abort
+/// them. Requests that are still waiting for a response fail with
+/// [`Error::Canceled`](crate::Error::Canceled), and reading the body of a
+/// response that is still being received fails with an I/O error that
+/// converts into the same error.
+///
+/// Unlike dropping the response future, canceling takes effect right away
+/// even if the transfer is currently idle, and also works once the response
+/// has been returned. A token is meant for a single request or a group of
+/// related requests, rather than for reuse over the lifetime of a program.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::prelude::*;
+/// use isahc::CancellationToken;
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// let token = CancellationToken::new();
+///
+/// thread::spawn({
+///     let token = token.clone();
+///     move || {
+///         thread::sleep(Duration::from_secs(5));
+///         token.cancel();
+///     }
+/// });
+///
+/// let result = Request::get("https://example.org/slow")
+///     .cancellation_token(token)
+///     .body(())?
+///     .send();
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    canceled: AtomicBool,
+
+    /// Wakers to notify agents of the requests using this token.
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl CancellationToken {
+    /// Create a new token that has not been canceled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel all requests using this token.
+    ///
+    /// Requests that are sent with this token afterwards fail right away.
+    pub fn cancel(&self) {
+        if !self.inner.canceled.swap(true, Ordering::SeqCst) {
+            for waker in self.inner.wakers.lock().unwrap().drain(..) {
+                waker.wake();
+            }
+        }
+    }
+
+    /// Check whether this token has been canceled.
+    pub fn is_canceled(&self) -> bool {
+        self.inner.canceled.load(Ordering::SeqCst)
+    }
+
+    /// Register a waker to be woken when this token is canceled. If it has
+    /// already been canceled, the waker is woken immediately.
+    pub(crate) fn register(&self, waker: Waker) {
+        let mut wakers = self.inner.wakers.lock().unwrap();
+
+        if self.is_canceled() {
+            drop(wakers);
+            waker.wake();
+        } else {
+            wakers.push(waker);
+        }
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("canceled", &self.is_canceled())
+            .finish()
+    }
+}
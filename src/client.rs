@@ -1,25 +1,50 @@
 //! The HTTP client implementation.
 
+#[cfg(feature = "upgrade")]
+use crate::upgrade::{self, Upgraded};
 use crate::{
     agent::{self, AgentBuilder},
+    clock::{Clock, SystemClock},
     config::*,
-    handler::{RequestHandler, RequestHandlerFuture, ResponseBodyReader},
+    failover::{self, Endpoints, Plan},
+    handler::{PhaseTimeouts, RequestHandler, RequestHandlerFuture, ResponseBodyReader},
+    interceptor::{Interceptor, InterceptorFuture, Next},
+    latency::LatencyTracker,
+    metrics::Metrics,
     middleware::Middleware,
-    Body, Error,
+    parse,
+    proxy::{EnvProxies, ProxyBlacklist},
+    redirect::{self, RedirectState},
+    resolver::Resolver,
+    response::{RedirectHistory, RequestUri, ResponseExt},
+    retry::{Delay, RetryState},
+    sse::EventSource,
+    stats::{HostStats, StatsRegistry},
+    task::Join,
+    uri::IntoUri,
+    validators::Validators,
+    Body, CancellationToken, Error,
 };
 use futures_io::AsyncRead;
-use futures_util::pin_mut;
+use futures_util::{future, pin_mut};
 use http::{Request, Response};
 use lazy_static::lazy_static;
 use std::{
-    fmt,
+    collections::HashMap,
+    ffi::OsString,
+    fmt, fs,
     future::Future,
     io,
     iter::FromIterator,
+    mem,
     net::SocketAddr,
+    ops::RangeInclusive,
+    os::raw::c_long,
+    path::{Path, PathBuf},
     pin::Pin,
     sync::Arc,
     task::{Context, Poll},
+    thread,
     time::Duration,
 };
 
@@ -31,6 +56,11 @@ lazy_static! {
     );
 }
 
+/// POST bodies stored in memory up to this size are handed to curl directly
+/// instead of being streamed through the read callback. This allows curl to
+/// send small bodies in the same write as the request header.
+const MAX_INLINE_BODY_SIZE: usize = 64 * 1024;
+
 /// An HTTP client builder, capable of creating custom [`HttpClient`] instances
 /// with customized behavior.
 ///
@@ -54,6 +84,12 @@ pub struct HttpClientBuilder {
     agent_builder: AgentBuilder,
     defaults: http::Extensions,
     middleware: Vec<Box<dyn Middleware>>,
+    interceptors: Vec<Box<dyn Interceptor>>,
+    adaptive_timeout: Option<AdaptiveTimeout>,
+    endpoints: Option<Vec<http::Uri>>,
+    clock: Option<Arc<dyn Clock>>,
+    tls_backend: Option<TlsBackend>,
+    resolver: Option<Arc<dyn Resolver>>,
 }
 
 impl HttpClientBuilder {
@@ -73,6 +109,93 @@ impl HttpClientBuilder {
         self.middleware_impl(crate::cookies::CookieJar::default())
     }
 
+    /// Enable persistent cookie handling using the given cookie jar.
+    ///
+    /// Since cookie jars share their cookies with their clones, keeping a clone
+    /// of the jar makes it possible to inspect and change the cookies used by
+    /// the client.
+    ///
+    /// This method requires the `cookies` feature to be enabled.
+    #[cfg(feature = "cookies")]
+    pub fn cookie_jar(self, jar: crate::cookies::CookieJar) -> Self {
+        self.middleware_impl(jar)
+    }
+
+    /// Enable persistent cookie handling using a cookie jar stored in a file.
+    ///
+    /// Cookies are loaded from the given file in the Netscape cookie file
+    /// format if it exists, and saved back to it when the client is dropped,
+    /// so that cookies survive process restarts. See
+    /// [`CookieJar::load`](crate::cookies::CookieJar::load) for details.
+    ///
+    /// This method requires the `cookies` feature to be enabled.
+    #[cfg(feature = "cookies")]
+    pub fn cookie_file(self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.middleware_impl(crate::cookies::PersistentCookieJar::open(path.into()))
+    }
+
+    /// Make requests conditional automatically, based on the validators of
+    /// earlier responses.
+    ///
+    /// The client remembers the `ETag` and `Last-Modified` headers of
+    /// successful responses, and adds matching `If-None-Match` or
+    /// `If-Modified-Since` headers when the same URI is requested again with
+    /// `GET` or `HEAD`. If the resource did not change, the server can answer
+    /// with a `304 Not Modified` response without a body, which can be
+    /// recognized using
+    /// [`ResponseExt::not_modified`](crate::ResponseExt::not_modified).
+    ///
+    /// This is useful for polling a resource for changes. Conditions set
+    /// explicitly on a request are left untouched. Validators are remembered
+    /// by the URI that was requested, even if the response came from
+    /// elsewhere after following redirects, and for up to 1024 URIs at a
+    /// time.
+    pub fn conditional_requests(self) -> Self {
+        self.middleware_impl(crate::validators::ValidatorStore::default())
+    }
+
+    /// Cache responses in memory, and serve requests from the cache where
+    /// allowed by HTTP caching rules.
+    ///
+    /// Takes either a [`ResponseCache`](crate::cache::ResponseCache), or the
+    /// maximum size in bytes of a cache with the default configuration.
+    /// Cached response bodies take up to that many bytes of memory in total,
+    /// with the least recently received responses evicted first. A response is
+    /// only stored once its body has been read to the end. See the
+    /// [`cache`](crate::cache) module for details.
+    ///
+    /// The cache is an interceptor, and sees requests and responses in the
+    /// same order as interceptors added with [`HttpClientBuilder::interceptor`].
+    pub fn response_cache(self, cache: impl Into<crate::cache::ResponseCache>) -> Self {
+        self.interceptor(cache.into())
+    }
+
+    /// Resolve host names using the given resolver, instead of letting curl
+    /// use the system resolver. See the [`resolver`](crate::resolver) module
+    /// for details.
+    ///
+    /// The resolver is an interceptor, and sees requests in the same order as
+    /// interceptors added with [`HttpClientBuilder::interceptor`].
+    pub fn resolver(mut self, resolver: impl Resolver) -> Self {
+        let resolver: Arc<dyn Resolver> = Arc::new(resolver);
+
+        // Only the first resolver is ever consulted for a request.
+        if self.resolver.is_none() {
+            self.resolver = Some(resolver.clone());
+        }
+
+        self.interceptor(crate::resolver::ResolverInterceptor(resolver))
+    }
+
+    /// Inject faults into the responses received by the client, for testing
+    /// how an application copes with unreliable servers.
+    ///
+    /// This method requires the `test-utils` feature to be enabled.
+    #[cfg(feature = "test-utils")]
+    pub fn fault_injector(self, injector: crate::testing::FaultInjector) -> Self {
+        self.middleware_impl(injector)
+    }
+
     /// Add a middleware layer to the client.
     ///
     /// This method requires the `middleware-api` feature to be enabled.
@@ -81,6 +204,16 @@ impl HttpClientBuilder {
         self.middleware_impl(middleware)
     }
 
+    /// Add an interceptor to the client.
+    ///
+    /// Interceptors are invoked in the order they are added, so the first
+    /// interceptor added sees each request first and each response last. See
+    /// the [`interceptor`](crate::interceptor) module for details.
+    pub fn interceptor(mut self, interceptor: impl Interceptor) -> Self {
+        self.interceptors.push(Box::new(interceptor));
+        self
+    }
+
     #[allow(unused)]
     fn middleware_impl(mut self, middleware: impl Middleware) -> Self {
         self.middleware.push(Box::new(middleware));
@@ -130,6 +263,99 @@ impl HttpClientBuilder {
         self
     }
 
+    /// Set a maximum number of requests that this client is allowed to execute
+    /// at one time.
+    ///
+    /// If set to a value greater than zero, no more than `max` requests will be
+    /// in flight at once, regardless of how many connections they use. Any
+    /// further requests wait in a queue until an active request completes.
+    /// Waiting requests are started in order of their
+    /// [`Priority`](crate::config::Priority), and then in the order they were
+    /// sent.
+    ///
+    /// Time spent waiting in the queue does not count towards the request's
+    /// timeout.
+    ///
+    /// Setting this value to `0` disables the limit entirely. By default this
+    /// value is `0` and no limit is enforced.
+    pub fn max_concurrent_requests(mut self, max: usize) -> Self {
+        self.agent_builder = self.agent_builder.max_requests(max);
+        self
+    }
+
+    /// Set a maximum amount of time that a request is allowed to wait for the
+    /// client to have room to send it.
+    ///
+    /// Requests only have to wait if the number of concurrent requests is
+    /// limited using [`HttpClientBuilder::max_concurrent_requests`]. A request
+    /// that is still waiting once this timeout is reached fails with
+    /// [`Error::AcquireTimeout`](crate::Error::AcquireTimeout) without ever
+    /// being sent. Since waiting does not count towards the regular request
+    /// timeout, this makes it possible to tell a saturated client apart from
+    /// a slow server.
+    ///
+    /// If not set, requests wait for as long as necessary. This can be
+    /// overridden for individual requests using
+    /// [`RequestBuilderExt::acquire_timeout`](crate::RequestBuilderExt::acquire_timeout).
+    pub fn acquire_timeout(mut self, timeout: Duration) -> Self {
+        self.defaults.insert(AcquireTimeout(timeout));
+        self
+    }
+
+    /// Set how long a connection may sit idle in the connection pool before it
+    /// is closed.
+    ///
+    /// Idle connections older than this are never reused for new requests.
+    /// Additionally, once the client has had no active requests for this long,
+    /// all pooled connections are closed in the background, so that an unused
+    /// client does not hold on to sockets indefinitely. Connections can also
+    /// be closed on demand using [`HttpClient::prune_idle`].
+    ///
+    /// Curl only supports whole seconds for reuse, so the timeout is rounded up
+    /// to at least one second there. Reuse is only limited with libcurl 7.65
+    /// or newer, and older versions log a warning instead. If not set, curl's
+    /// default of 118 seconds is used for reuse, and pooled connections are
+    /// kept open for as long as the client exists.
+    pub fn connection_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.agent_builder = self.agent_builder.connection_idle_timeout(timeout);
+        self.defaults.insert(ConnectionIdleTimeout(timeout));
+        self
+    }
+
+    /// Set a maximum number of idle connections that this client keeps open in
+    /// its connection pool for reuse.
+    ///
+    /// Once the pool is full, the connection that has been idle the longest is
+    /// closed to make room for another one. This is independent of
+    /// [`HttpClientBuilder::max_connections`], which limits connections that
+    /// are in use.
+    ///
+    /// Setting this value to `0` uses curl's default, which is four times the
+    /// number of requests in flight, at least. By default this value is `0`.
+    pub fn max_idle_connections(mut self, max: usize) -> Self {
+        self.agent_builder = self.agent_builder.max_idle_connections(max);
+        self
+    }
+
+    /// Set a maximum amount of time a connection may be reused for after it
+    /// was first opened.
+    ///
+    /// Connections older than this are not used for new requests and are
+    /// closed instead, even if they are otherwise healthy. Rotating
+    /// connections this way makes sure that requests are spread over new
+    /// backends when a load balancer or DNS record changes behind a
+    /// long-lived client. Requests already using a connection are not
+    /// interrupted.
+    ///
+    /// Curl only supports whole seconds, so the lifetime is rounded up to at
+    /// least one second. This option requires libcurl 7.80 or newer, and is
+    /// ignored with a warning otherwise. If not set, connections are reused
+    /// for as long as they remain open.
+    pub fn connection_max_lifetime(mut self, lifetime: Duration) -> Self {
+        self.defaults.insert(ConnectionMaxLifetime(lifetime));
+        self
+    }
+
     /// Set a timeout for the maximum time allowed for a request-response cycle.
     ///
     /// If not set, no timeout will be enforced.
@@ -138,6 +364,20 @@ impl HttpClientBuilder {
         self
     }
 
+    /// Derive request timeouts from the response times previously observed for
+    /// each host.
+    ///
+    /// Until enough requests to a host have completed to derive a timeout,
+    /// requests to that host use the timeout set with
+    /// [`HttpClientBuilder::timeout`], if any. A timeout set on an individual
+    /// request always takes precedence. See [`AdaptiveTimeout`] for details.
+    ///
+    /// By default, adaptive timeouts are disabled.
+    pub fn adaptive_timeout(mut self, config: AdaptiveTimeout) -> Self {
+        self.adaptive_timeout = Some(config);
+        self
+    }
+
     /// Set a timeout for the initial connection phase.
     ///
     /// If not set, a connect timeout of 300 seconds will be used.
@@ -146,6 +386,203 @@ impl HttpClientBuilder {
         self
     }
 
+    /// Set a timeout for resolving the host name of the server.
+    ///
+    /// This caps a single phase of the request, while the overall
+    /// [`timeout`](HttpClientBuilder::timeout) remains a budget for the whole
+    /// request across all phases. Exceeding it fails the request with
+    /// [`Error::Timeout`](crate::Error::Timeout) for
+    /// [`Phase::Resolve`](crate::Phase::Resolve).
+    ///
+    /// Phase timeouts are checked periodically while the request is in
+    /// progress, so they may be exceeded by up to about a second.
+    pub fn dns_timeout(mut self, timeout: Duration) -> Self {
+        self.defaults.insert(DnsTimeout(timeout));
+        self
+    }
+
+    /// Set a timeout for the TLS handshake with the server, measured from when
+    /// the connection is established.
+    ///
+    /// Exceeding it fails the request with
+    /// [`Error::Timeout`](crate::Error::Timeout) for
+    /// [`Phase::TlsHandshake`](crate::Phase::TlsHandshake). See
+    /// [`HttpClientBuilder::dns_timeout`] for how phase timeouts relate to the
+    /// overall timeout.
+    pub fn tls_timeout(mut self, timeout: Duration) -> Self {
+        self.defaults.insert(TlsTimeout(timeout));
+        self
+    }
+
+    /// Set a timeout for the server to begin responding, measured from when
+    /// the connection is ready to send the request.
+    ///
+    /// This includes the time taken to send the request body. Exceeding it
+    /// fails the request with [`Error::Timeout`](crate::Error::Timeout) for
+    /// [`Phase::WaitingForResponse`](crate::Phase::WaitingForResponse). See
+    /// [`HttpClientBuilder::dns_timeout`] for how phase timeouts relate to the
+    /// overall timeout.
+    pub fn first_byte_timeout(mut self, timeout: Duration) -> Self {
+        self.defaults.insert(FirstByteTimeout(timeout));
+        self
+    }
+
+    /// Set how long receiving a response may go without any data arriving
+    /// before it is aborted.
+    ///
+    /// Unlike the overall [`timeout`](HttpClientBuilder::timeout), this allows
+    /// long-lived streaming responses while still detecting a server that has
+    /// stopped sending. Time spent waiting for the response body to be read
+    /// by you does not count. Exceeding it fails the request, or reading the
+    /// response body, with a timeout for
+    /// [`Phase::Transfer`](crate::Phase::Transfer).
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.defaults.insert(ReadTimeout(timeout));
+        self
+    }
+
+    /// Set an ordered list of equivalent base URIs that requests can fail over
+    /// between.
+    ///
+    /// Requests to a URI beginning with any of the given base URIs are sent to
+    /// the first endpoint in the list that is considered healthy, with the
+    /// rest of the URI kept as-is. If the request fails because the endpoint
+    /// could not be connected to, the endpoint is considered unhealthy for a
+    /// while, and the request is sent to the next endpoint in the list
+    /// instead. Requests to other URIs are not affected.
+    ///
+    /// Only requests that never reached the server are sent again, so this is
+    /// safe for requests that are not idempotent. Requests with a streaming
+    /// body cannot be sent more than once and are not failed over.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::prelude::*;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .failover_endpoints(vec![
+    ///         "https://primary.example.org".parse()?,
+    ///         "https://backup.example.org".parse()?,
+    ///     ])
+    ///     .build()?;
+    ///
+    /// // Sent to backup.example.org if primary.example.org is unreachable.
+    /// let response = client.get("https://primary.example.org/status")?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn failover_endpoints(mut self, endpoints: impl IntoIterator<Item = http::Uri>) -> Self {
+        self.endpoints = Some(endpoints.into_iter().collect());
+        self
+    }
+
+    /// Set a policy for automatically retrying failed requests.
+    ///
+    /// Requests with a body that can be sent more than once are retried
+    /// according to the given policy, transparently to the caller. Only the
+    /// result of the final attempt is returned. See [`RetryPolicy`] for which
+    /// failures are retried.
+    ///
+    /// This can be overridden for individual requests using
+    /// [`RequestBuilderExt::retry_policy`](crate::RequestBuilderExt::retry_policy).
+    /// By default, requests are not retried.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::config::RetryPolicy;
+    /// use isahc::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .retry_policy(RetryPolicy::exponential(Duration::from_millis(100)).max_attempts(3))
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.defaults.insert(policy);
+        self
+    }
+
+    /// Set a function to call with the progress of each request.
+    ///
+    /// The function is called from the client's background thread, about once
+    /// a second and whenever data is sent or received, with the number of
+    /// bytes of the request and response bodies transferred so far. It should
+    /// return quickly, since no other requests make progress while it runs.
+    /// This makes it suitable for updating progress bars for large transfers.
+    ///
+    /// This can be overridden for individual requests using
+    /// [`RequestBuilderExt::progress`](crate::RequestBuilderExt::progress).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::prelude::*;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .progress(|progress| {
+    ///         if progress.download_total() > 0 {
+    ///             println!("{}/{} bytes", progress.downloaded(), progress.download_total());
+    ///         }
+    ///     })
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn progress(mut self, f: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.defaults.insert(ProgressCallback(Arc::new(f)));
+        self
+    }
+
+    /// Set a function to call whenever an interim `1xx` response is received,
+    /// such as `100 Continue` or `103 Early Hints`.
+    ///
+    /// The function is called from the client's background thread with the
+    /// status code and headers of each interim response, before the final
+    /// response is available. Early hints can be used this way to start
+    /// preloading resources the server expects to be needed. The function
+    /// should return quickly, since no other requests make progress while it
+    /// runs.
+    ///
+    /// This can be overridden for individual requests using
+    /// [`RequestBuilderExt::on_informational`](crate::RequestBuilderExt::on_informational).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::prelude::*;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .on_informational(|status, headers| {
+    ///         if status == 103 {
+    ///             for link in headers.get_all("link") {
+    ///                 println!("preload {:?}", link);
+    ///             }
+    ///         }
+    ///     })
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn on_informational(
+        mut self,
+        f: impl Fn(http::StatusCode, &http::HeaderMap) + Send + Sync + 'static,
+    ) -> Self {
+        self.defaults.insert(InformationalCallback(Arc::new(f)));
+        self
+    }
+
+    /// Set the source of time used for the client's own deadlines.
+    ///
+    /// This is mostly useful for testing time-dependent behavior using a
+    /// [`MockClock`](crate::clock::MockClock). See the [`clock`](crate::clock)
+    /// module for which deadlines are affected.
+    ///
+    /// By default, the system clock is used.
+    pub fn clock(mut self, clock: impl Clock) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
     /// Set a policy for automatically following server redirects.
     ///
     /// The default is to not follow redirects.
@@ -154,19 +591,289 @@ impl HttpClientBuilder {
         self
     }
 
+    /// Decide whether to follow each redirect by calling the given function.
+    ///
+    /// The function is called for every redirect response received, and can
+    /// inspect the status code, the URI being redirected to, and how many
+    /// redirects have been followed so far. It may follow the redirect, stop
+    /// and return the redirect response as-is, or fail the request with
+    /// [`Error::RedirectRejected`](crate::Error::RedirectRejected).
+    ///
+    /// As curl does with redirect policies, `301` and `302` redirects of
+    /// `POST` requests and `303` redirects of requests other than `HEAD` are
+    /// followed with a `GET` request without a body. All other redirects
+    /// repeat the original request, which requires the request body to be
+    /// sent more than once. The `Authorization`, `Cookie` and
+    /// `Proxy-Authorization` headers are not sent on to other origins, where
+    /// an origin is a combination of scheme, host and port.
+    ///
+    /// Requests following a redirect pass through the client's interceptors
+    /// and middleware just like the original request, so that for example
+    /// the cookie jar applies to them. At most 50 redirects are followed
+    /// for a single request, after which it fails with
+    /// [`Error::TooManyRedirects`](crate::Error::TooManyRedirects).
+    ///
+    /// A redirect handler takes precedence over the client's
+    /// [redirect policy](HttpClientBuilder::redirect_policy). Both can be
+    /// overridden for individual requests using
+    /// [`RequestBuilderExt::redirect_handler`](crate::RequestBuilderExt::redirect_handler)
+    /// or
+    /// [`RequestBuilderExt::redirect_policy`](crate::RequestBuilderExt::redirect_policy).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::config::RedirectAction;
+    /// use isahc::prelude::*;
+    ///
+    /// // Follow up to 10 redirects, but never from HTTPS to HTTP.
+    /// let client = HttpClient::builder()
+    ///     .redirect_handler(|attempt| {
+    ///         if attempt.previous_uri().scheme_str() == Some("https")
+    ///             && attempt.uri().scheme_str() != Some("https")
+    ///         {
+    ///             RedirectAction::Reject("refusing to downgrade to HTTP".into())
+    ///         } else if attempt.hops() > 10 {
+    ///             RedirectAction::Stop
+    ///         } else {
+    ///             RedirectAction::Follow
+    ///         }
+    ///     })
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn redirect_handler(
+        mut self,
+        f: impl Fn(&RedirectAttempt) -> RedirectAction + Send + Sync + 'static,
+    ) -> Self {
+        self.defaults.insert(RedirectHandler(Arc::new(f)));
+        self
+    }
+
     /// Update the `Referer` header automatically when following redirects.
     pub fn auto_referer(mut self) -> Self {
         self.defaults.insert(AutoReferer);
         self
     }
 
+    /// Set whether the client adds the `Host`, `Content-Length`,
+    /// `Transfer-Encoding`, and `Accept-Encoding` request headers
+    /// automatically.
+    ///
+    /// This is enabled by default. When disabled, exactly those of these
+    /// headers that are present in the request are sent, which is needed for
+    /// signing schemes that cover the full header set or for tools testing
+    /// how servers handle unusual requests. The caller is then responsible for
+    /// framing the request body correctly, and response bodies are no longer
+    /// decoded automatically.
+    pub fn automatic_headers(mut self, enable: bool) -> Self {
+        self.defaults.insert(AutomaticHeaders(enable));
+        self
+    }
+
+    /// Compress request bodies with the given encoding before sending them,
+    /// and set the `Content-Encoding` header accordingly.
+    ///
+    /// Bodies stored in memory are compressed up front and keep a known
+    /// length. Streaming bodies are compressed on the fly and sent with
+    /// chunked transfer encoding, since their compressed size is not known in
+    /// advance. Requests that already have a `Content-Encoding` header, or
+    /// have no body, are sent unchanged.
+    ///
+    /// Only use this with servers known to accept compressed requests.
+    ///
+    /// This method requires the `compression` feature to be enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::config::Compression;
+    /// use isahc::prelude::*;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .request_compression(Compression::Gzip)
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    #[cfg(feature = "compression")]
+    pub fn request_compression(mut self, compression: Compression) -> Self {
+        self.defaults.insert(compression);
+        self
+    }
+
+    /// Set exactly which encodings to advertise in the `Accept-Encoding`
+    /// header of requests.
+    ///
+    /// By default all encodings supported by curl are advertised. Responses
+    /// are still decoded automatically as long as curl supports their
+    /// encoding, so listing an encoding that curl was built without, such as
+    /// [`ContentEncoding::Zstd`], results in an
+    /// [`Error::InvalidContentEncoding`] error if the server uses it. An
+    /// explicit `Accept-Encoding` header set on a request takes precedence.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::config::ContentEncoding;
+    /// use isahc::prelude::*;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .accept_encodings(vec![ContentEncoding::Gzip])
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn accept_encodings(
+        mut self,
+        encodings: impl IntoIterator<Item = ContentEncoding>,
+    ) -> Self {
+        self.defaults.insert(AcceptEncodings::from_iter(encodings));
+        self
+    }
+
+    /// Set whether compressed response bodies are decoded automatically.
+    ///
+    /// When enabled, which is the default, an `Accept-Encoding` header listing
+    /// all encodings supported by curl is sent, unless the request sets one
+    /// itself, and bodies encoded with `gzip`, `deflate` or, if curl supports
+    /// it, `br` are decoded transparently. The `Content-Encoding` header of
+    /// the response is left as sent by the server, so it still reports the
+    /// original encoding.
+    ///
+    /// When disabled, no `Accept-Encoding` header is added and response
+    /// bodies are returned exactly as received, which is useful for proxies
+    /// or for storing compressed content as-is.
+    pub fn automatic_decompression(mut self, enable: bool) -> Self {
+        self.defaults.insert(AutomaticDecompression(enable));
+        self
+    }
+
+    /// Enable or disable collecting [`Metrics`](crate::Metrics) for requests.
+    ///
+    /// Metrics are retrieved from responses with
+    /// [`ResponseExt::metrics`](crate::ResponseExt::metrics). Collecting them
+    /// has a small overhead, so it is disabled by default.
+    pub fn metrics(mut self, enable: bool) -> Self {
+        self.defaults.insert(EnableMetrics(enable));
+        self
+    }
+
+    /// Enable or disable collecting [`TlsInfo`](crate::TlsInfo) about the
+    /// secure connection of requests, such as the negotiated TLS version,
+    /// cipher and ALPN protocol.
+    ///
+    /// TLS details are retrieved from responses with
+    /// [`ResponseExt::tls_info`](crate::ResponseExt::tls_info). Collecting them
+    /// is best effort, since most of them are parsed from curl's informational
+    /// output, which also requires enabling curl's verbose mode for each
+    /// request. It is therefore disabled by default. See
+    /// [`TlsInfo`](crate::TlsInfo) for details.
+    pub fn tls_info(mut self, enable: bool) -> Self {
+        self.defaults.insert(EnableTlsInfo(enable));
+        self
+    }
+
+    /// Enable or disable including the certificate chain presented by the
+    /// server in the [`TlsInfo`](crate::TlsInfo) of responses, in DER form.
+    ///
+    /// Enabling this also enables [`HttpClientBuilder::tls_info`]. Disabled by
+    /// default.
+    pub fn tls_peer_certificates(mut self, enable: bool) -> Self {
+        self.defaults.insert(EnablePeerCertificates(enable));
+        self
+    }
+
+    /// Set whether malformed response headers are rejected.
+    ///
+    /// In strict mode, which is the default, a response fails if any of its
+    /// header lines are not terminated by CRLF, use obsolete line folding, or
+    /// contain bytes that are not allowed in a header. Disabling strict mode
+    /// tolerates these instead: bare LF line endings are accepted, folded
+    /// lines are joined onto the previous header, and invalid header lines are
+    /// skipped. This can be useful for talking to embedded devices or other
+    /// servers with broken HTTP implementations.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.defaults.insert(Strict(strict));
+        self
+    }
+
+    /// Set the maximum total size of the header of responses, in bytes,
+    /// including the status line.
+    ///
+    /// Responses with a larger header fail with
+    /// [`Error::ResponseHeadersTooLarge`] as soon as the limit is exceeded,
+    /// without waiting for the rest of the header. This guards against
+    /// untrusted servers exhausting memory by sending an endless header.
+    /// Every line received counts towards the limit, including malformed
+    /// lines skipped when parsing is not [strict](HttpClientBuilder::strict).
+    /// Trailer fields sent after the body count towards the same limit as the
+    /// header. Limited only by curl's own per-line limit by default.
+    pub fn max_response_header_size(mut self, bytes: usize) -> Self {
+        self.defaults.insert(MaxResponseHeaderSize(bytes));
+        self
+    }
+
+    /// Set the maximum number of header fields a response may have.
+    ///
+    /// Responses with more header fields fail with
+    /// [`Error::ResponseHeadersTooLarge`] as soon as the limit is exceeded.
+    /// Lines of a header folded across multiple lines count as a single field,
+    /// while malformed lines and trailer fields count as fields of their own.
+    /// Unlimited by default.
+    pub fn max_response_headers(mut self, count: usize) -> Self {
+        self.defaults.insert(MaxResponseHeaders(count));
+        self
+    }
+
+    /// Set the maximum size of response bodies, in bytes.
+    ///
+    /// If a response announces a larger body in its `Content-Length` header,
+    /// the request fails with [`Error::ResponseTooLarge`] before any of the
+    /// body is received. Otherwise the transfer is aborted as soon as more
+    /// bytes than the limit have been received, and reading the response body
+    /// fails with an I/O error that converts into
+    /// [`Error::ResponseTooLarge`].
+    ///
+    /// This protects against malicious or misconfigured servers sending
+    /// responses too large to handle. With automatic decompression, the
+    /// `Content-Length` header is compared against the limit as sent, so it
+    /// limits the compressed size, while the bytes counted during the
+    /// transfer are those of the decompressed body. Unlimited by default.
+    pub fn max_response_body_size(mut self, bytes: u64) -> Self {
+        self.defaults.insert(MaxResponseBodySize(bytes));
+        self
+    }
+
     /// Set a preferred HTTP version the client should attempt to use to
     /// communicate to the server with.
     ///
     /// This is treated as a suggestion. A different version may be used if the
     /// server does not support it or negotiates a different version.
-    pub fn preferred_http_version(mut self, version: http::Version) -> Self {
-        self.defaults.insert(PreferredHttpVersion(version));
+    ///
+    /// This is a shorthand for
+    /// [`version_negotiation`](HttpClientBuilder::version_negotiation).
+    pub fn preferred_http_version(self, version: http::Version) -> Self {
+        self.version_negotiation(version.into())
+    }
+
+    /// Set which HTTP versions may be used to communicate with servers, and
+    /// how the version is negotiated.
+    ///
+    /// The version actually used for a response is available from
+    /// [`Response::version`](http::Response::version).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::config::VersionNegotiation;
+    /// use isahc::prelude::*;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .version_negotiation(VersionNegotiation::http11())
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn version_negotiation(mut self, negotiation: VersionNegotiation) -> Self {
+        self.defaults.insert(negotiation);
         self
     }
 
@@ -176,9 +883,98 @@ impl HttpClientBuilder {
         self
     }
 
-    /// Enables the `TCP_NODELAY` option on connect.
-    pub fn tcp_nodelay(mut self) -> Self {
-        self.defaults.insert(TcpNoDelay);
+    /// Enable or disable the `TCP_NODELAY` option on connect, which turns off
+    /// Nagle's algorithm.
+    ///
+    /// With the option enabled, small writes are sent immediately instead of
+    /// being buffered until earlier data is acknowledged. This reduces latency
+    /// for small request and response exchanges, at the cost of sending more
+    /// packets. The option is enabled by default.
+    pub fn tcp_nodelay(mut self, enable: bool) -> Self {
+        self.defaults.insert(TcpNoDelay(enable));
+        self
+    }
+
+    /// Restrict which IP versions may be used to connect to servers.
+    ///
+    /// Forcing a single version avoids connection stalls on networks where
+    /// one of the versions is configured but broken. By default both are
+    /// used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::config::IpVersion;
+    /// use isahc::prelude::*;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .ip_version(IpVersion::V4)
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn ip_version(mut self, version: IpVersion) -> Self {
+        self.defaults.insert(version);
+        self
+    }
+
+    /// Bind outgoing connections to the given local network interface or
+    /// address.
+    ///
+    /// This is useful on hosts with more than one network connection, for
+    /// example to keep traffic on or off a VPN.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::config::NetworkInterface;
+    /// use isahc::prelude::*;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .interface(NetworkInterface::name("eth1"))
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn interface(mut self, interface: impl Into<NetworkInterface>) -> Self {
+        self.defaults.insert(interface.into());
+        self
+    }
+
+    /// Restrict the local ports outgoing connections are made from to the
+    /// given range.
+    ///
+    /// Connections fail if no port in the range is available. This is needed
+    /// for some firewall and NAT setups that only allow certain source ports.
+    /// By default, the operating system picks any available port.
+    ///
+    /// Requests fail with an error if the range is empty, such as a range
+    /// whose start is greater than its end.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::prelude::*;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .local_ports(40000..=40100)
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn local_ports(mut self, ports: RangeInclusive<u16>) -> Self {
+        self.defaults.insert(LocalPorts(ports));
+        self
+    }
+
+    /// Set how long to wait for a connection attempt before racing it with an
+    /// attempt over the other IP version, for hosts with addresses of both
+    /// versions.
+    ///
+    /// The first attempt uses the version of the first address returned by
+    /// the resolver, which is usually IPv6 if the system has IPv6
+    /// connectivity, but depends on the system's address selection policy. A
+    /// shorter delay falls back to the other version faster on networks where
+    /// one of them is broken. The default is 200 milliseconds.
+    pub fn happy_eyeballs_timeout(mut self, timeout: Duration) -> Self {
+        self.defaults.insert(HappyEyeballsTimeout(timeout));
         self
     }
 
@@ -187,7 +983,10 @@ impl HttpClientBuilder {
     /// The proxy protocol is specified by the URI scheme.
     ///
     /// - **`http`**: Proxy. Default when no scheme is specified.
-    /// - **`https`**: HTTPS Proxy. (Added in 7.52.0 for OpenSSL, GnuTLS and
+    /// - **`https`**: HTTPS Proxy, where the connection to the proxy itself is
+    ///   encrypted. See
+    ///   [`HttpClientBuilder::proxy_ssl_client_certificate`] and related
+    ///   methods for configuring it. (Added in 7.52.0 for OpenSSL, GnuTLS and
     ///   NSS)
     /// - **`socks4`**: SOCKS4 Proxy.
     /// - **`socks4a`**: SOCKS4a Proxy. Proxy resolves URL hostname.
@@ -196,11 +995,166 @@ impl HttpClientBuilder {
     ///
     /// By default no proxy will be used, unless one is specified in either the
     /// `http_proxy` or `https_proxy` environment variables.
+    ///
+    /// # Examples
+    ///
+    /// Sending requests through a local Tor daemon, letting it resolve host
+    /// names as well:
+    ///
+    /// ```
+    /// use isahc::prelude::*;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .proxy("socks5h://127.0.0.1:9050".parse()?)
+    ///     .build()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
     pub fn proxy(mut self, proxy: http::Uri) -> Self {
         self.defaults.insert(Proxy(proxy));
         self
     }
 
+    /// Set a client certificate to present to HTTPS proxies.
+    ///
+    /// This is separate from
+    /// [`HttpClientBuilder::ssl_client_certificate`], which applies to the
+    /// servers requests are sent to.
+    pub fn proxy_ssl_client_certificate(mut self, certificate: ClientCertificate) -> Self {
+        self.defaults.insert(ProxyClientCertificate(certificate));
+        self
+    }
+
+    /// Set the CA certificates to verify HTTPS proxies against, instead of
+    /// the default certificate store.
+    pub fn proxy_ssl_ca_certificate(mut self, certificate: CaCertificate) -> Self {
+        self.defaults.insert(ProxyCaCertificate(certificate));
+        self
+    }
+
+    /// Controls the use of certificate validation for HTTPS proxies.
+    ///
+    /// Defaults to `false`, so proxy certificates are validated.
+    ///
+    /// # Warning
+    ///
+    /// Like [`HttpClientBuilder::danger_accept_invalid_certs`], this allows
+    /// anyone able to intercept the connection to the proxy to impersonate it,
+    /// and should only be used as a last resort.
+    pub fn danger_accept_invalid_proxy_certs(mut self, accept_invalid: bool) -> Self {
+        self.defaults.insert(AllowUnsafeProxySsl(accept_invalid));
+        self
+    }
+
+    /// Configure proxies from the environment variables of the process, as
+    /// read when the client is built.
+    ///
+    /// The `http_proxy`, `https_proxy` and `all_proxy` variables select the
+    /// proxy for each URI scheme, and `no_proxy` lists hosts to contact
+    /// directly, supporting the same entries as
+    /// [`HttpClientBuilder::proxy_blacklist`]. Uppercase names are recognized
+    /// too, except for `HTTP_PROXY`, which CGI servers may set from a request
+    /// header. A proxy set with [`HttpClientBuilder::proxy`] takes precedence.
+    ///
+    /// Without this option, curl reads the same variables by itself whenever
+    /// a request is sent, but without CIDR support in `no_proxy`.
+    pub fn proxy_from_env(mut self) -> Self {
+        self.defaults.insert(EnvProxies::from_env());
+        self
+    }
+
+    /// Set a list of hosts that requests should be sent to directly, without
+    /// going through a proxy.
+    ///
+    /// Each entry may be one of:
+    ///
+    /// - `*`, which matches all hosts.
+    /// - A domain name, which matches that domain and all of its subdomains.
+    /// - An IP address.
+    /// - A network in CIDR notation, such as `10.0.0.0/8` or `fd00::/8`, which
+    ///   matches IP addresses in that network.
+    ///
+    /// Invalid entries are ignored. Host names are matched as they appear in
+    /// the request URI, without resolving them. This applies to proxies set
+    /// using [`HttpClientBuilder::proxy`] as well as to proxies taken from
+    /// environment variables.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::prelude::*;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .proxy("http://proxy.example.org:3128".parse()?)
+    ///     .proxy_blacklist(vec!["localhost", "corp.example.org", "10.0.0.0/8"])
+    ///     .build()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn proxy_blacklist<I, T>(mut self, hosts: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        self.defaults.insert(ProxyBlacklist::new(hosts));
+        self
+    }
+
+    /// Send requests over the Unix domain socket at the given path, instead
+    /// of connecting to the host named in the request URI.
+    ///
+    /// The request URI is still used for everything else, such as the `Host`
+    /// header and the path of the request. This is useful for talking to local
+    /// daemons that expose an HTTP API over a socket, such as Docker.
+    ///
+    /// This can be overridden for individual requests using
+    /// [`RequestBuilderExt::unix_socket`](crate::RequestBuilderExt::unix_socket).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::prelude::*;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .unix_socket("/var/run/docker.sock")
+    ///     .build()?;
+    ///
+    /// let mut response = client.get("http://localhost/version")?;
+    /// println!("{}", response.text()?);
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn unix_socket(mut self, path: impl Into<PathBuf>) -> Self {
+        self.defaults.insert(UnixSocket(path.into()));
+        self
+    }
+
+    /// Abort requests whose transfer speed stays below a given number of bytes
+    /// per second for a given amount of time.
+    ///
+    /// This catches half-dead connections that still trickle in enough data
+    /// to never reach a timeout. Aborted requests fail with
+    /// [`Error::TransferTooSlow`], as does reading the response body if the
+    /// response has already been returned. Time spent waiting for the
+    /// response body to be read is not counted.
+    ///
+    /// The speed is checked periodically once the request has been sent, so
+    /// requests may be aborted up to about a second later than `time`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// // Give up if less than 1 KiB per second is received for 30 seconds.
+    /// let client = HttpClient::builder()
+    ///     .low_speed_limit(1024, Duration::from_secs(30))
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn low_speed_limit(mut self, bytes_per_second: u64, time: Duration) -> Self {
+        self.defaults.insert(LowSpeedLimit(bytes_per_second, time));
+        self
+    }
+
     /// Set a maximum upload speed for the request body, in bytes per second.
     ///
     /// The default is unlimited.
@@ -256,11 +1210,58 @@ impl HttpClientBuilder {
 
     /// Set a list of specific DNS servers to be used for DNS resolution.
     ///
-    /// By default this option is not set and the system's built-in DNS resolver
-    /// is used. This option can only be used if libcurl is compiled with
-    /// [c-ares](https://c-ares.haxx.se), otherwise this option has no effect.
-    pub fn dns_servers(mut self, servers: impl IntoIterator<Item = SocketAddr>) -> Self {
-        self.defaults.insert(DnsServers::from_iter(servers));
+    /// By default this option is not set and the system's built-in DNS resolver
+    /// is used. This option can only be used if libcurl is compiled with
+    /// [c-ares](https://c-ares.haxx.se), otherwise this option has no effect.
+    pub fn dns_servers(mut self, servers: impl IntoIterator<Item = SocketAddr>) -> Self {
+        self.defaults.insert(DnsServers::from_iter(servers));
+        self
+    }
+
+    /// Resolve host names using DNS over HTTPS (DoH), instead of the system
+    /// resolver.
+    ///
+    /// Accepts either the URI of a DoH server, or a
+    /// [`DnsOverHttps`](crate::config::DnsOverHttps) configuration that also
+    /// controls whether to fall back to the system resolver if DoH fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::config::DnsOverHttps;
+    /// use isahc::prelude::*;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .dns_over_https(
+    ///         DnsOverHttps::new("https://cloudflare-dns.com/dns-query")
+    ///             .fallback_to_system(true),
+    ///     )
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn dns_over_https(mut self, doh: impl Into<DnsOverHttps>) -> Self {
+        self.defaults.insert(doh.into());
+        self
+    }
+
+    /// Set a mapping of host names to fixed addresses, used instead of
+    /// resolving those names through DNS.
+    ///
+    /// See [`ResolveMap`](crate::config::ResolveMap) for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::config::ResolveMap;
+    /// use isahc::prelude::*;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .dns_resolve(ResolveMap::new().add("example.org", 443, [10, 0, 0, 7]))
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn dns_resolve(mut self, map: ResolveMap) -> Self {
+        self.defaults.insert(map);
         self
     }
 
@@ -306,9 +1307,146 @@ impl HttpClientBuilder {
         self.defaults.insert(certificate);
         self
     }
-    /// Controls the use of certificate validation.
+
+    /// Set the CA certificates to verify servers against, instead of the
+    /// system certificate store. This is useful when talking to servers
+    /// issued certificates by a private certificate authority.
+    ///
+    /// The default value is none, which uses the system store.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use isahc::config::*;
+    /// # use isahc::prelude::*;
+    /// #
+    /// let client = HttpClient::builder()
+    ///     .ssl_ca_certificate(CaCertificate::File("ca-bundle.pem".into()))
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn ssl_ca_certificate(mut self, certificate: CaCertificate) -> Self {
+        self.defaults.insert(certificate);
+        self
+    }
+
+    /// Pin the public key that servers are expected to present.
+    ///
+    /// The key can be given as a path to a PEM or DER encoded public key file,
+    /// or as the base64-encoded SHA-256 hash of the server's public key
+    /// prefixed with `sha256//`. Several hashes can be given by separating
+    /// them with `;`. If the server presents any other key, the connection is
+    /// closed before the request is sent and an
+    /// [`Error::BadServerCertificate`] error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use isahc::prelude::*;
+    /// #
+    /// let client = HttpClient::builder()
+    ///     .pinned_public_key("sha256//YhKJKSzoTt2b5FP18fvpHo7fJYqQCjAa3HWY3tvRMwE=")
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn pinned_public_key(mut self, key: impl Into<String>) -> Self {
+        self.defaults.insert(PinnedPublicKey(key.into()));
+        self
+    }
+
+    /// Select the TLS engine to use, when libcurl is built with more than
+    /// one. Use [`TlsBackend::active`] to find out which one is in use.
+    ///
+    /// The TLS engine is a process-wide setting of libcurl that can only be
+    /// chosen before libcurl is first used, so this must be set on the first
+    /// client created by the process. Building a client fails with
+    /// [`Error::SSLEngineError`] if the backend is not available, or if a
+    /// different one has already been put in use.
+    pub fn tls_backend(mut self, backend: TlsBackend) -> Self {
+        self.tls_backend = Some(backend);
+        self
+    }
+
+    /// Set the oldest version of TLS to allow when connecting to servers.
+    ///
+    /// The default is chosen by the underlying TLS engine, and is usually TLS
+    /// 1.0. Setting this to [`TlsVersion::V1_2`] is a common way to enforce
+    /// modern TLS.
+    pub fn tls_min_version(mut self, version: TlsVersion) -> Self {
+        self.defaults.insert(TlsMinVersion(version));
+        self
+    }
+
+    /// Set the newest version of TLS to allow when connecting to servers.
+    ///
+    /// By default the newest version supported by the underlying TLS engine is
+    /// allowed. Limiting this is only useful for talking to legacy servers that
+    /// mishandle newer versions.
+    pub fn tls_max_version(mut self, version: TlsVersion) -> Self {
+        self.defaults.insert(TlsMaxVersion(version));
+        self
+    }
+
+    /// Check server certificates against a certificate revocation list (CRL)
+    /// read from a file in PEM format.
+    ///
+    /// Connections to servers whose certificate, or any certificate in its
+    /// chain, appears in the list fail with [`Error::BadServerCertificate`].
+    /// Not all TLS engines support this option.
+    ///
+    /// The default value is none.
+    pub fn crl_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.defaults.insert(CrlFile(path.into()));
+        self
+    }
+
+    /// Enable or disable resuming earlier TLS sessions using session IDs or
+    /// session tickets.
     ///
-    /// Defaults to `false` as per libcurl's default
+    /// Resuming a session skips part of the TLS handshake, but lets a server
+    /// link the new connection to the earlier one. Privacy-sensitive
+    /// applications may want to disable it.
+    ///
+    /// Defaults to `true`.
+    pub fn ssl_session_cache(mut self, enable: bool) -> Self {
+        self.defaults.insert(SslSessionCache(enable));
+        self
+    }
+
+    /// Share TLS sessions between all requests made by this client.
+    ///
+    /// By default each request keeps its own TLS session cache, so a new
+    /// connection to a server always performs a full handshake. When sharing
+    /// is enabled, new connections resume a session negotiated by an earlier
+    /// request where possible, which saves round trips when connections are
+    /// not reused. Has no effect on requests that disable the session cache
+    /// with [`HttpClientBuilder::ssl_session_cache`].
+    ///
+    /// Defaults to `false`.
+    pub fn share_ssl_sessions(mut self, enable: bool) -> Self {
+        self.agent_builder = self.agent_builder.share_ssl_sessions(enable);
+        self
+    }
+
+    /// Require servers to staple a valid OCSP response to the TLS handshake,
+    /// proving that their certificate has not been revoked.
+    ///
+    /// If the server does not staple a response, or the response is invalid,
+    /// the connection fails with [`Error::BadServerCertificate`]. Only
+    /// supported by some TLS engines, including OpenSSL and GnuTLS; others will
+    /// return an error when sending a request.
+    ///
+    /// Defaults to `false`.
+    pub fn ssl_verify_status(mut self, enable: bool) -> Self {
+        self.defaults.insert(VerifyStatus(enable));
+        self
+    }
+
+    /// Accept invalid server certificates, including self-signed, expired and
+    /// mismatched ones, and skip hostname verification.
+    ///
+    /// Defaults to `false`, so certificates are validated as per libcurl's
+    /// default.
     ///
     /// # Warning
     ///
@@ -316,20 +1454,62 @@ impl HttpClientBuilder {
     /// invalid certificates are trusted, *any* certificate for *any* site
     /// will be trusted for use. This includes expired certificates. This
     /// introduces significant vulnerabilities, and should only be used
-    /// as a last resort.
-    pub fn danger_allow_unsafe_ssl(mut self, allow_unsafe: bool) -> Self {
-        self.defaults.insert(AllowUnsafeSsl(allow_unsafe));
+    /// as a last resort, such as when testing against a self-signed
+    /// development server.
+    pub fn danger_accept_invalid_certs(mut self, accept_invalid: bool) -> Self {
+        self.defaults.insert(AllowUnsafeSsl(accept_invalid));
+        self
+    }
+
+    /// Accept server certificates that do not match the hostname being
+    /// connected to, while still validating them otherwise.
+    ///
+    /// This takes precedence over the hostname check implied by
+    /// [`HttpClientBuilder::danger_accept_invalid_certs`].
+    ///
+    /// When connecting to a server by IP address whose certificate only
+    /// names its DNS names, prefer [`HttpClientBuilder::dns_resolve`] to map
+    /// the name to the address instead, which keeps hostname verification
+    /// intact.
+    ///
+    /// # Warning
+    ///
+    /// Without hostname verification, any server holding a valid certificate
+    /// for *any* site can impersonate the server being connected to.
+    pub fn danger_accept_invalid_hostnames(mut self, accept_invalid: bool) -> Self {
+        self.defaults.insert(AcceptInvalidHostnames(accept_invalid));
         self
     }
 
+    /// Controls the use of certificate validation.
+    #[deprecated(note = "renamed to `danger_accept_invalid_certs`")]
+    pub fn danger_allow_unsafe_ssl(self, allow_unsafe: bool) -> Self {
+        self.danger_accept_invalid_certs(allow_unsafe)
+    }
+
     /// Build an [`HttpClient`] using the configured options.
     ///
     /// If the client fails to initialize, an error will be returned.
     pub fn build(self) -> Result<HttpClient, Error> {
+        // Must happen before the agent initializes curl.
+        if let Some(backend) = self.tls_backend {
+            backend.select()?;
+        }
+
+        let clock = self.clock.unwrap_or_else(|| Arc::new(SystemClock));
+
         Ok(HttpClient {
-            agent: Arc::new(self.agent_builder.spawn()?),
+            agent: Arc::new(self.agent_builder.clock(clock.clone()).spawn()?),
             defaults: self.defaults,
             middleware: self.middleware,
+            interceptors: self.interceptors,
+            latency_tracker: self.adaptive_timeout.map(LatencyTracker::new).map(Arc::new),
+            stats: Arc::default(),
+            endpoints: self
+                .endpoints
+                .map(|endpoints| Endpoints::new(endpoints, clock.clone())),
+            clock,
+            resolver: self.resolver,
         })
     }
 }
@@ -413,6 +1593,18 @@ pub struct HttpClient {
     defaults: http::Extensions,
     /// Any middleware implementations that requests should pass through.
     middleware: Vec<Box<dyn Middleware>>,
+    /// Any interceptors that requests should pass through.
+    interceptors: Vec<Box<dyn Interceptor>>,
+    /// Response times per host, if adaptive timeouts are enabled.
+    latency_tracker: Option<Arc<LatencyTracker>>,
+    /// Requests and transferred bytes per host.
+    stats: Arc<StatsRegistry>,
+    /// Endpoints to fail over between, if configured.
+    endpoints: Option<Endpoints>,
+    /// Source of time for deadlines the client keeps track of itself.
+    clock: Arc<dyn Clock>,
+    /// Custom resolver consulted before each request, if any.
+    resolver: Option<Arc<dyn Resolver>>,
 }
 
 impl HttpClient {
@@ -434,11 +1626,215 @@ impl HttpClient {
         &SHARED
     }
 
+    /// Get the clock this client measures its own deadlines with.
+    pub(crate) fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
     /// Create a new [`HttpClientBuilder`] for building a custom client.
     pub fn builder() -> HttpClientBuilder {
         HttpClientBuilder::default()
     }
 
+    /// Get statistics about the requests this client has sent so far, for
+    /// each host it has sent requests to.
+    ///
+    /// Hosts are identified by the authority of the request URI, such as
+    /// `example.org` or `example.org:8080`. Requests are accounted for once
+    /// they complete, so requests still in flight are not included.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::prelude::*;
+    ///
+    /// let client = HttpClient::new()?;
+    /// client.get("https://example.org")?.text()?;
+    ///
+    /// for (host, stats) in client.host_stats() {
+    ///     println!("{}: {} bytes received", host, stats.bytes_received());
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn host_stats(&self) -> HashMap<String, HostStats> {
+        self.stats.snapshot()
+    }
+
+    /// Close any idle connections kept open by this client.
+    ///
+    /// Connections in use by active requests are not affected. Since
+    /// connections are pooled per client rather than per request, idle
+    /// connections are only closed once the client has no active requests at
+    /// all; if any are in flight, idle connections are closed as soon as they
+    /// complete.
+    ///
+    /// This is useful to release sockets after a burst of requests without
+    /// having to drop the client. To do this automatically, see
+    /// [`HttpClientBuilder::connection_idle_timeout`].
+    pub fn prune_idle(&self) -> Result<(), Error> {
+        self.agent.prune_idle()
+    }
+
+    /// Shut down this client gracefully.
+    ///
+    /// New requests are refused with [`Error::ClientShutdown`] right away,
+    /// while requests that are already in flight or waiting to be sent are
+    /// allowed to complete, for up to the given timeout. Any requests still
+    /// active after that are aborted and a timeout error is returned. Once
+    /// this returns, the client's background thread has exited.
+    ///
+    /// Note that requests are only complete once their response body has
+    /// been read or dropped, so bodies still being consumed elsewhere keep
+    /// the client busy.
+    ///
+    /// Dropping a client instead aborts all of its active requests right away
+    /// once the last response body referring to it is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// let client = HttpClient::new()?;
+    ///
+    /// // Serve requests...
+    ///
+    /// client.shutdown(Duration::from_secs(30))?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn shutdown(&self, timeout: Duration) -> Result<(), Error> {
+        self.agent.shutdown(timeout)
+    }
+
+    /// Discard all connections and cached DNS entries of this client, so that
+    /// subsequent requests resolve names and connect again from scratch.
+    ///
+    /// Call this when the network environment changes, such as when switching
+    /// between Wi-Fi networks or connecting to a VPN. Pooled connections
+    /// usually become unusable when that happens, and cached addresses may
+    /// now be unreachable, which would otherwise cause requests to fail until
+    /// the connections and cache entries time out.
+    ///
+    /// Requests that are already in flight are not interrupted. Until they
+    /// complete, new requests open fresh connections and bypass the DNS
+    /// cache.
+    pub fn invalidate_connections(&self) -> Result<(), Error> {
+        self.agent.invalidate_connections()
+    }
+
+    /// Resolve the host names of the given URIs ahead of time and store the
+    /// results in this client's DNS cache.
+    ///
+    /// This is useful before sending a burst of requests to many different
+    /// hosts, so that the requests don't have to wait for name resolution
+    /// one at a time. All names are resolved concurrently, and this method
+    /// blocks until they are all done.
+    ///
+    /// Only the scheme, host, and port of each URI are used. Cache entries are
+    /// kept according to the client's [`DnsCache`] configuration, and are
+    /// discarded along with any idle connections when
+    /// [`HttpClient::prune_idle`] is called.
+    ///
+    /// Since curl only resolves names as part of connecting to a host, a plain
+    /// TCP connection is briefly opened to each host and closed again right
+    /// away. No request is sent, and no TLS handshake is performed. Names are
+    /// resolved the same way as for requests sent by this client, including
+    /// its DNS over HTTPS server and fixed addresses. If the client has a
+    /// custom [`Resolver`](crate::resolver::Resolver), it is asked for each
+    /// name instead, and no connections are opened.
+    ///
+    /// If any name could not be resolved, the first such error is returned
+    /// after all names have been tried.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::prelude::*;
+    ///
+    /// let client = HttpClient::new()?;
+    ///
+    /// client.prefetch_dns(vec!["https://example.org", "https://example.com"])?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn prefetch_dns<I, U>(&self, uris: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = U>,
+        U: IntoUri,
+    {
+        let uris = uris
+            .into_iter()
+            .map(IntoUri::into_uri)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // A custom resolver takes the place of curl's own lookups and DNS
+        // cache, so warm up the resolver instead.
+        if let Some(resolver) = self.resolver.as_ref() {
+            return self.prefetch_with_resolver(&**resolver, &uris);
+        }
+
+        let mut futures = Vec::new();
+
+        for uri in uris {
+            let host = uri.host().ok_or(Error::CouldntResolveHost)?;
+            let port = uri
+                .port_part()
+                .map(|port| port.as_u16())
+                .unwrap_or_else(|| match uri.scheme_str() {
+                    Some("https") => 443,
+                    _ => 80,
+                });
+
+            let (easy, future) = self.create_connect_only_handle(host, port)?;
+            self.agent.submit_request(easy, Priority::Low, None)?;
+            futures.push(future);
+        }
+
+        let mut result = Ok(());
+
+        for future in futures {
+            if let Err(e) = future.join() {
+                log::debug!("failed to prefetch DNS entry: {}", e);
+
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Resolve the host names of the given URIs concurrently using a custom
+    /// resolver.
+    fn prefetch_with_resolver(
+        &self,
+        resolver: &dyn Resolver,
+        uris: &[http::Uri],
+    ) -> Result<(), Error> {
+        let targets = uris
+            .iter()
+            .filter_map(crate::resolver::target)
+            .collect::<Vec<_>>();
+        let lookups = targets
+            .iter()
+            .map(|(host, port)| resolver.resolve(host, *port));
+
+        let mut result = Ok(());
+
+        for ((host, _), lookup) in targets.iter().zip(future::join_all(lookups).join()) {
+            match lookup {
+                Ok(ref addrs) if !addrs.is_empty() => continue,
+                Ok(_) => log::debug!("resolver returned no addresses for {}", host),
+                Err(e) => log::debug!("failed to prefetch DNS entry for {}: {}", host, e),
+            }
+
+            result = Err(Error::CouldntResolveHost);
+        }
+
+        result
+    }
+
     /// Send a GET request to the given URI.
     ///
     /// To customize the request further, see [`HttpClient::send`]. To execute
@@ -457,7 +1853,7 @@ impl HttpClient {
     #[inline]
     pub fn get<U>(&self, uri: U) -> Result<Response<Body>, Error>
     where
-        http::Uri: http::HttpTryFrom<U>,
+        U: IntoUri,
     {
         self.get_async(uri).join()
     }
@@ -468,9 +1864,9 @@ impl HttpClient {
     /// execute the request synchronously, see [`HttpClient::get`].
     pub fn get_async<U>(&self, uri: U) -> ResponseFuture<'_>
     where
-        http::Uri: http::HttpTryFrom<U>,
+        U: IntoUri,
     {
-        self.send_builder_async(http::Request::get(uri), Body::empty())
+        self.send_uri_async(http::Method::GET, uri, Body::empty())
     }
 
     /// Send a HEAD request to the given URI.
@@ -490,7 +1886,7 @@ impl HttpClient {
     #[inline]
     pub fn head<U>(&self, uri: U) -> Result<Response<Body>, Error>
     where
-        http::Uri: http::HttpTryFrom<U>,
+        U: IntoUri,
     {
         self.head_async(uri).join()
     }
@@ -501,9 +1897,9 @@ impl HttpClient {
     /// execute the request synchronously, see [`HttpClient::head`].
     pub fn head_async<U>(&self, uri: U) -> ResponseFuture<'_>
     where
-        http::Uri: http::HttpTryFrom<U>,
+        U: IntoUri,
     {
-        self.send_builder_async(http::Request::head(uri), Body::empty())
+        self.send_uri_async(http::Method::HEAD, uri, Body::empty())
     }
 
     /// Send a POST request to the given URI with a given request body.
@@ -526,7 +1922,7 @@ impl HttpClient {
     #[inline]
     pub fn post<U>(&self, uri: U, body: impl Into<Body>) -> Result<Response<Body>, Error>
     where
-        http::Uri: http::HttpTryFrom<U>,
+        U: IntoUri,
     {
         self.post_async(uri, body).join()
     }
@@ -538,9 +1934,9 @@ impl HttpClient {
     /// execute the request synchronously, see [`HttpClient::post`].
     pub fn post_async<U>(&self, uri: U, body: impl Into<Body>) -> ResponseFuture<'_>
     where
-        http::Uri: http::HttpTryFrom<U>,
+        U: IntoUri,
     {
-        self.send_builder_async(http::Request::post(uri), body)
+        self.send_uri_async(http::Method::POST, uri, body)
     }
 
     /// Send a PUT request to the given URI with a given request body.
@@ -564,7 +1960,7 @@ impl HttpClient {
     #[inline]
     pub fn put<U>(&self, uri: U, body: impl Into<Body>) -> Result<Response<Body>, Error>
     where
-        http::Uri: http::HttpTryFrom<U>,
+        U: IntoUri,
     {
         self.put_async(uri, body).join()
     }
@@ -576,9 +1972,9 @@ impl HttpClient {
     /// execute the request synchronously, see [`HttpClient::put`].
     pub fn put_async<U>(&self, uri: U, body: impl Into<Body>) -> ResponseFuture<'_>
     where
-        http::Uri: http::HttpTryFrom<U>,
+        U: IntoUri,
     {
-        self.send_builder_async(http::Request::put(uri), body)
+        self.send_uri_async(http::Method::PUT, uri, body)
     }
 
     /// Send a DELETE request to the given URI.
@@ -588,20 +1984,268 @@ impl HttpClient {
     #[inline]
     pub fn delete<U>(&self, uri: U) -> Result<Response<Body>, Error>
     where
-        http::Uri: http::HttpTryFrom<U>,
+        U: IntoUri,
     {
         self.delete_async(uri).join()
     }
 
-    /// Send a DELETE request to the given URI asynchronously.
-    ///
-    /// To customize the request further, see [`HttpClient::send_async`]. To
-    /// execute the request synchronously, see [`HttpClient::delete`].
-    pub fn delete_async<U>(&self, uri: U) -> ResponseFuture<'_>
-    where
-        http::Uri: http::HttpTryFrom<U>,
-    {
-        self.send_builder_async(http::Request::delete(uri), Body::empty())
+    /// Send a DELETE request to the given URI asynchronously.
+    ///
+    /// To customize the request further, see [`HttpClient::send_async`]. To
+    /// execute the request synchronously, see [`HttpClient::delete`].
+    pub fn delete_async<U>(&self, uri: U) -> ResponseFuture<'_>
+    where
+        U: IntoUri,
+    {
+        self.send_uri_async(http::Method::DELETE, uri, Body::empty())
+    }
+
+    /// Download the resource at the given URI into a file.
+    ///
+    /// While the download is in progress, the validator of the resource (its
+    /// `ETag` or `Last-Modified` header) is recorded next to the file, in a
+    /// file with `.validator` appended to its name. If the download is
+    /// interrupted, calling this method again resumes it by requesting only
+    /// the rest of the resource with a `Range` header, conditional on the
+    /// resource still matching the recorded validator with an `If-Range`
+    /// header, and appending it to the file. If the resource changed in the
+    /// meantime, the server does not support range requests, or no validator
+    /// was recorded for an existing file, the file is overwritten with the
+    /// complete resource instead.
+    ///
+    /// Returns the length of the file once the download is complete. A `4xx`
+    /// or `5xx` response is returned as an [`Error::BadStatus`] error, leaving
+    /// the file as it was.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::prelude::*;
+    ///
+    /// let client = HttpClient::new()?;
+    /// let length = client.download("https://example.org/big.iso", "big.iso")?;
+    /// println!("downloaded {} bytes", length);
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn download<U>(&self, uri: U, path: impl AsRef<Path>) -> Result<u64, Error>
+    where
+        U: IntoUri,
+    {
+        let uri = uri.into_uri()?;
+        let path = path.as_ref();
+        let validator_path = download_validator_path(path);
+
+        let mut offset = match fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e.into()),
+        };
+
+        // A partial file can only be resumed if it is known which version of
+        // the resource it holds the beginning of.
+        let validator = if offset > 0 {
+            fs::read_to_string(&validator_path)
+                .ok()
+                .map(|validator| validator.trim().to_owned())
+        } else {
+            None
+        };
+
+        loop {
+            let mut request = http::Request::get(uri.clone());
+
+            if let (true, Some(validator)) = (offset > 0, validator.as_ref()) {
+                request.header(http::header::RANGE, format!("bytes={}-", offset));
+                request.header(http::header::IF_RANGE, validator.as_str());
+            } else {
+                offset = 0;
+            }
+
+            let mut response = self.send(request.body(())?)?;
+
+            if offset > 0 {
+                let content_range = response
+                    .headers()
+                    .get(http::header::CONTENT_RANGE)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(parse::parse_content_range);
+
+                match (response.status(), content_range) {
+                    // The rest of the resource.
+                    (http::StatusCode::PARTIAL_CONTENT, Some((Some((first, _)), _)))
+                        if first == offset =>
+                    {
+                        let mut file = fs::OpenOptions::new().append(true).open(path)?;
+                        let length = offset + response.copy_to(&mut file)?;
+                        remove_file_if_exists(&validator_path)?;
+
+                        return Ok(length);
+                    }
+                    // The file is already complete.
+                    (http::StatusCode::RANGE_NOT_SATISFIABLE, Some((_, Some(length))))
+                        if length == offset =>
+                    {
+                        remove_file_if_exists(&validator_path)?;
+
+                        return Ok(offset);
+                    }
+                    // The partial file does not match the resource, so start
+                    // over.
+                    (http::StatusCode::PARTIAL_CONTENT, _)
+                    | (http::StatusCode::RANGE_NOT_SATISFIABLE, _) => {
+                        log::debug!("cannot resume download of {}, starting over", uri);
+                        offset = 0;
+                        continue;
+                    }
+                    // Anything else is the complete resource, which replaces
+                    // the partial file.
+                    _ => {}
+                }
+            }
+
+            response.error_for_status(0)?;
+
+            // Record which version of the resource is being downloaded, so
+            // that the download can be resumed if it is interrupted.
+            match download_validator(&response) {
+                Some(validator) => fs::write(&validator_path, validator)?,
+                None => remove_file_if_exists(&validator_path)?,
+            }
+
+            let length = response.copy_to_file(path)?;
+            remove_file_if_exists(&validator_path)?;
+
+            return Ok(length);
+        }
+    }
+
+    /// Subscribe to [Server-Sent Events](crate::sse) from the given URI.
+    ///
+    /// The returned event source connects to the URI, and reconnects whenever
+    /// the connection is closed or fails, resuming from the last event
+    /// received. See [`EventSource`](crate::sse::EventSource) for details.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::prelude::*;
+    ///
+    /// let client = HttpClient::new()?;
+    ///
+    /// for event in client.event_source("https://example.org/updates")? {
+    ///     println!("{}", event?.data());
+    /// }
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn event_source<U>(&self, uri: U) -> Result<EventSource<'_>, Error>
+    where
+        U: IntoUri,
+    {
+        let uri = uri.into_uri()?;
+
+        Ok(EventSource::new(self, uri))
+    }
+
+    /// Send an HTTP/1.1 request asking to switch protocols, and take over the
+    /// connection if the server agrees.
+    ///
+    /// The request should include an `Upgrade` header naming the protocol to
+    /// switch to, along with any headers the protocol requires for its
+    /// handshake. A `Connection: upgrade` header is added if not present.
+    ///
+    /// If the server responds with `101 Switching Protocols`, the connection
+    /// is returned as an [`Upgraded`](crate::upgrade::Upgraded) stream.
+    /// Otherwise [`Error::BadStatus`] is returned.
+    ///
+    /// The request bypasses the agent and is not subject to this client's
+    /// interceptors, middleware, redirect or retry policies. Only the connect
+    /// timeout and DNS settings of this client are applied.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::prelude::*;
+    /// use std::io::Write;
+    ///
+    /// let client = HttpClient::new()?;
+    ///
+    /// let request = Request::get("http://example.org/chat")
+    ///     .header("Upgrade", "websocket")
+    ///     .header("Sec-WebSocket-Key", "dGhlIHNhbXBsZSBub25jZQ==")
+    ///     .header("Sec-WebSocket-Version", "13")
+    ///     .body(())?;
+    ///
+    /// let mut connection = client.upgrade(request)?;
+    /// connection.write_all(b"...")?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "upgrade")]
+    pub fn upgrade(&self, request: Request<()>) -> Result<Upgraded, Error> {
+        let (mut parts, _) = request.into_parts();
+
+        let host = match (parts.uri.host(), parts.uri.port_part()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_owned(),
+            (None, _) => return Err(Error::CouldntResolveHost),
+        };
+        let host =
+            http::HeaderValue::from_str(&host).map_err(|e| Error::InvalidHttpFormat(e.into()))?;
+
+        let mut easy = curl::easy::Easy2::new(upgrade::Connection);
+
+        easy.verbose(log::log_enabled!(log::Level::Debug))?;
+        easy.signal(false)?;
+        easy.connect_only(true)?;
+        easy.url(&parts.uri.to_string())?;
+
+        if let Some(timeout) = self.defaults.get::<ConnectTimeout>() {
+            timeout.set_opt(&mut easy)?;
+        }
+
+        if let Some(cache) = self.defaults.get::<DnsCache>() {
+            cache.set_opt(&mut easy)?;
+        }
+
+        if let Some(servers) = self.defaults.get::<DnsServers>() {
+            servers.set_opt(&mut easy)?;
+        }
+
+        easy.perform()?;
+
+        parts
+            .headers
+            .entry(http::header::HOST)
+            .unwrap()
+            .or_insert(host);
+        parts
+            .headers
+            .entry(http::header::CONNECTION)
+            .unwrap()
+            .or_insert(http::HeaderValue::from_static("upgrade"));
+        parts
+            .headers
+            .entry(http::header::USER_AGENT)
+            .unwrap()
+            .or_insert(USER_AGENT.parse().unwrap());
+
+        let path = parts
+            .uri
+            .path_and_query()
+            .map(|p| p.as_str())
+            .unwrap_or("/");
+
+        let mut head = format!("{} {} HTTP/1.1\r\n", parts.method, path).into_bytes();
+
+        for (name, value) in parts.headers.iter() {
+            head.extend_from_slice(name.as_str().as_bytes());
+            head.extend_from_slice(b": ");
+            head.extend_from_slice(value.as_bytes());
+            head.extend_from_slice(b"\r\n");
+        }
+
+        head.extend_from_slice(b"\r\n");
+
+        Upgraded::handshake(easy, &head)
     }
 
     /// Send an HTTP request and return the HTTP response.
@@ -628,6 +2272,12 @@ impl HttpClient {
     /// consumed once. If you need to inspect the response body more than once,
     /// you will have to either read it into memory or write it to a file.
     ///
+    /// Only a small, fixed amount of the response body is buffered in memory at
+    /// a time. If you stop reading from the response body, the transfer is
+    /// paused once this buffer is full and is resumed as soon as you continue
+    /// reading. A paused transfer does not hold up any other requests being
+    /// executed by the client.
+    ///
     /// To execute the request asynchronously, see [`HttpClient::send_async`].
     ///
     /// # Examples
@@ -675,8 +2325,32 @@ impl HttpClient {
     /// assert!(response.status().is_success());
     /// ```
     pub fn send_async<B: Into<Body>>(&self, request: Request<B>) -> ResponseFuture<'_> {
-        let mut request = request.map(Into::into);
+        let request = request.map(Into::into);
+
+        if self.interceptors.is_empty() {
+            return self.send_async_without_interceptors(request);
+        }
+
+        ResponseFuture {
+            intercepted: Some(Next::new(self, &self.interceptors).run(request)),
+            ..ResponseFuture::new(self)
+        }
+    }
+
+    /// Send a request at the end of the interceptor chain.
+    pub(crate) fn send_async_without_interceptors(
+        &self,
+        request: Request<Body>,
+    ) -> ResponseFuture<'_> {
+        ResponseFuture {
+            request: Some(self.prepare_request(request)),
+            ..ResponseFuture::new(self)
+        }
+    }
 
+    /// Fill in defaults and apply request middleware to a request about to be
+    /// sent.
+    fn prepare_request(&self, mut request: Request<Body>) -> Request<Body> {
         // Set default user agent if not specified.
         request
             .headers_mut()
@@ -689,44 +2363,206 @@ impl HttpClient {
             request = middleware.filter_request(request);
         }
 
-        ResponseFuture {
-            client: self,
-            error: None,
-            request: Some(request),
-            inner: None,
+        request
+    }
+
+    /// Send a request in the background without interceptors, and pass the
+    /// response to the given function on another thread once it is received.
+    ///
+    /// Since the request outlives the borrow of the client, it is sent only
+    /// once, without retries, failover or redirect handlers, and
+    /// response middleware does not see the response.
+    pub(crate) fn send_detached(
+        &self,
+        request: Request<Body>,
+        f: impl FnOnce(Result<Response<Body>, Error>) + Send + 'static,
+    ) -> Result<(), Error> {
+        let (mut parts, body) = self.prepare_request(request).into_parts();
+
+        if let Some(endpoints) = self.endpoints.as_ref() {
+            if let Some(plan) = endpoints.plan(&parts.uri) {
+                parts.uri = endpoints.uri(&plan)?;
+            }
         }
+
+        let future = self.submit(&parts, body)?;
+        let agent = self.agent.clone();
+
+        thread::Builder::new()
+            .name(String::from("isahc-background-request"))
+            .spawn(move || {
+                f(future
+                    .join()
+                    .map(|response| response.map(|reader| response_body(reader, agent))))
+            })?;
+
+        Ok(())
     }
 
-    fn send_builder_async(
+    fn send_uri_async(
         &self,
-        mut builder: http::request::Builder,
+        method: http::Method,
+        uri: impl IntoUri,
         body: impl Into<Body>,
     ) -> ResponseFuture<'_> {
-        match builder.body(body.into()) {
+        let request = uri.into_uri().and_then(|uri| {
+            http::Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(body.into())
+                .map_err(Error::from)
+        });
+
+        match request {
             Ok(request) => self.send_async(request),
             Err(e) => ResponseFuture {
-                client: self,
-                error: Some(e.into()),
-                request: None,
-                inner: None,
+                error: Some(e),
+                ..ResponseFuture::new(self)
             },
         }
     }
 
+    /// Create a curl easy handle that only connects to the given host and port
+    /// without sending a request.
+    fn create_connect_only_handle(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> Result<(curl::easy::Easy2<RequestHandler>, RequestHandlerFuture), Error> {
+        let (handler, future) = RequestHandler::new(Body::empty());
+
+        let mut easy = curl::easy::Easy2::new(handler);
+
+        easy.verbose(log::log_enabled!(log::Level::Debug))?;
+        easy.signal(false)?;
+        easy.connect_only(true)?;
+
+        // Always connect using plain HTTP, even for HTTPS hosts, since we only
+        // care about the name being resolved.
+        easy.url(&format!("http://{}:{}/", host, port))?;
+
+        if let Some(timeout) = self.defaults.get::<ConnectTimeout>() {
+            timeout.set_opt(&mut easy)?;
+        }
+
+        // Names must be resolved the same way as for requests, or the shared
+        // cache would be filled with results that requests would not get,
+        // such as from the system resolver when DoH is configured.
+        if let Some(version) = self.defaults.get::<IpVersion>() {
+            version.set_opt(&mut easy)?;
+        }
+
+        if let Some(cache) = self.defaults.get::<DnsCache>() {
+            cache.set_opt(&mut easy)?;
+        }
+
+        if let Some(map) = self.defaults.get::<ResolveMap>() {
+            map.set_opt(&mut easy)?;
+        }
+
+        if let Some(servers) = self.defaults.get::<DnsServers>() {
+            servers.set_opt(&mut easy)?;
+        }
+
+        if let Some(doh) = self.defaults.get::<DnsOverHttps>() {
+            doh.set_opt(&mut easy)?;
+        }
+
+        Ok((easy, future))
+    }
+
+    /// Hand a request over to the agent to be executed, returning a future for
+    /// the response.
+    fn submit(
+        &self,
+        parts: &http::request::Parts,
+        body: Body,
+    ) -> Result<RequestHandlerFuture, Error> {
+        let priority = parts
+            .extensions
+            .get::<Priority>()
+            .cloned()
+            .unwrap_or_default();
+        let mut acquire_timeout = parts
+            .extensions
+            .get::<AcquireTimeout>()
+            .or_else(|| self.defaults.get())
+            .map(|timeout| timeout.0);
+
+        // Waiting to be sent also uses up the time left until the deadline.
+        if let Some(deadline) = parts.extensions.get::<Deadline>() {
+            let remaining = deadline.remaining(&*self.clock)?;
+            acquire_timeout = Some(acquire_timeout.map_or(remaining, |t| t.min(remaining)));
+        }
+
+        // Create and configure a curl easy handle to fulfil the request.
+        let (easy, future) = self.create_easy_handle(parts, body)?;
+
+        // Send the request to the agent to be executed.
+        self.agent.submit_request(easy, priority, acquire_timeout)?;
+
+        Ok(future)
+    }
+
     fn create_easy_handle(
         &self,
-        request: Request<Body>,
+        parts: &http::request::Parts,
+        body: Body,
     ) -> Result<(curl::easy::Easy2<RequestHandler>, RequestHandlerFuture), Error> {
+        // Compress the request body if configured, unless the caller already
+        // encoded it.
+        #[cfg(feature = "compression")]
+        let (body, body_content_encoding) = match parts
+            .extensions
+            .get::<Compression>()
+            .or_else(|| self.defaults.get())
+        {
+            Some(compression)
+                if !body.is_empty()
+                    && !parts.headers.contains_key(http::header::CONTENT_ENCODING) =>
+            {
+                (body.compress(*compression)?, Some(compression.name()))
+            }
+            _ => (body, None),
+        };
+        #[cfg(not(feature = "compression"))]
+        let body_content_encoding: Option<&str> = None;
+
         // Prepare the request plumbing.
-        let (mut parts, body) = request.into_parts();
         let has_body = !body.is_empty();
         let body_length = body.len();
+        let body_content_type = body.content_type().map(str::to_owned);
+        // An explicit Content-Length header wins over the length curl would
+        // send for an inline body.
+        let inline_body = if parts.method == http::Method::POST
+            && !parts.headers.contains_key(http::header::CONTENT_LENGTH)
+        {
+            if has_body {
+                body.as_bytes()
+                    .filter(|bytes| bytes.len() <= MAX_INLINE_BODY_SIZE)
+                    .map(<[u8]>::to_vec)
+            } else {
+                Some(Vec::new())
+            }
+        } else {
+            None
+        };
+        let automatic_headers = parts
+            .extensions
+            .get::<AutomaticHeaders>()
+            .or_else(|| self.defaults.get())
+            .map_or(true, |enable| enable.0);
         let (handler, future) = RequestHandler::new(body);
 
         let mut easy = curl::easy::Easy2::new(handler);
 
         easy.verbose(log::log_enabled!(log::Level::Debug))?;
         easy.signal(false)?;
+        set_protocols(&mut easy)?;
+
+        let scheme = parts.uri.scheme_str();
+        easy.get_mut()
+            .set_is_http(scheme == Some("http") || scheme == Some("https"));
 
         // Macro to apply all config values given in the request or in defaults.
         macro_rules! set_opts {
@@ -746,32 +2582,271 @@ impl HttpClient {
             [
                 Timeout,
                 ConnectTimeout,
+                ConnectionIdleTimeout,
+                ConnectionMaxLifetime,
                 TcpKeepAlive,
                 TcpNoDelay,
+                IpVersion,
+                HappyEyeballsTimeout,
+                NetworkInterface,
+                LocalPorts,
                 RedirectPolicy,
                 AutoReferer,
                 MaxUploadSpeed,
                 MaxDownloadSpeed,
-                PreferredHttpVersion,
+                VersionNegotiation,
                 Proxy,
+                UnixSocket,
                 DnsCache,
+                ResolveMap,
                 DnsServers,
                 SslCiphers,
                 ClientCertificate,
+                CaCertificate,
+                PinnedPublicKey,
+                CrlFile,
+                SslSessionCache,
+                VerifyStatus,
                 AllowUnsafeSsl,
+                AcceptInvalidHostnames,
+                ProxyClientCertificate,
+                ProxyCaCertificate,
+                AllowUnsafeProxySsl,
+                MaxResponseBodySize,
             ]
         );
 
+        TlsVersions {
+            min: parts
+                .extensions
+                .get::<TlsMinVersion>()
+                .or_else(|| self.defaults.get())
+                .map(|version| version.0),
+            max: parts
+                .extensions
+                .get::<TlsMaxVersion>()
+                .or_else(|| self.defaults.get())
+                .map(|version| version.0),
+        }
+        .set_opt(&mut easy)?;
+
+        // Apply proxies read from the environment, unless one was configured
+        // explicitly. An empty proxy keeps curl from reading the environment
+        // again by itself.
+        if parts
+            .extensions
+            .get::<Proxy>()
+            .or_else(|| self.defaults.get())
+            .is_none()
+        {
+            if let Some(proxies) = self.defaults.get::<EnvProxies>() {
+                easy.proxy(proxies.proxy_for(&parts.uri).unwrap_or(""))?;
+            }
+        }
+
+        // Requests to blacklisted hosts skip any proxy, including one set
+        // through environment variables.
+        if let Some(blacklist) = parts
+            .extensions
+            .get::<ProxyBlacklist>()
+            .or_else(|| self.defaults.get())
+        {
+            if parts
+                .uri
+                .host()
+                .map_or(false, |host| blacklist.contains(host))
+            {
+                easy.proxy("")?;
+            }
+        }
+
+        // Requests falling back to the system resolver skip DNS over HTTPS.
+        if parts.extensions.get::<SystemDns>().is_none() {
+            if let Some(doh) = self.defaults.get::<DnsOverHttps>() {
+                doh.set_opt(&mut easy)?;
+            }
+        }
+
+        let mut timeout = parts
+            .extensions
+            .get::<Timeout>()
+            .or_else(|| self.defaults.get())
+            .map(|timeout| timeout.0);
+
+        if let Some(host) = parts.uri.authority_part().map(|a| a.as_str().to_owned()) {
+            // Derive a timeout from previous response times of this host,
+            // unless one was set explicitly for this request.
+            if let Some(tracker) = self.latency_tracker.as_ref() {
+                if parts.extensions.get::<Timeout>().is_none() {
+                    if let Some(adaptive) = tracker.timeout(&host) {
+                        log::trace!("using adaptive timeout of {:?} for {}", adaptive, host);
+                        easy.timeout(adaptive)?;
+                        timeout = Some(adaptive);
+                    }
+                }
+
+                easy.get_mut().set_latency_tracker(tracker.clone());
+            }
+
+            easy.get_mut().set_stats(self.stats.clone());
+            easy.get_mut().set_host(host);
+        }
+
+        // Only the time left until the deadline is available to this attempt,
+        // whichever other timeout applies. This must come after any other
+        // timeout is set so that none of them can outlast the deadline.
+        if let Some(deadline) = parts.extensions.get::<Deadline>() {
+            let remaining = deadline.remaining(&*self.clock)?;
+            let timeout = timeout.map_or(remaining, |timeout| timeout.min(remaining));
+
+            // A zero timeout would mean no timeout at all to curl.
+            easy.timeout(timeout.max(Duration::from_millis(1)))?;
+        }
+
+        if let Some(strict) = parts
+            .extensions
+            .get::<Strict>()
+            .or_else(|| self.defaults.get())
+        {
+            easy.get_mut().set_strict(strict.0);
+        }
+
+        if let Some(token) = parts.extensions.get::<CancellationToken>() {
+            easy.get_mut().set_cancellation_token(token.clone());
+        }
+
+        let max_header_size = parts
+            .extensions
+            .get::<MaxResponseHeaderSize>()
+            .or_else(|| self.defaults.get())
+            .map(|size| size.0);
+        let max_headers = parts
+            .extensions
+            .get::<MaxResponseHeaders>()
+            .or_else(|| self.defaults.get())
+            .map(|count| count.0);
+
+        easy.get_mut()
+            .set_header_limits(max_header_size, max_headers);
+
+        if let Some(size) = parts
+            .extensions
+            .get::<MaxResponseBodySize>()
+            .or_else(|| self.defaults.get())
+        {
+            easy.get_mut().set_max_response_body_size(size.0);
+        }
+
+        // Per-phase timeouts are checked from the progress callback.
+        let phase_timeouts = PhaseTimeouts {
+            dns: parts
+                .extensions
+                .get::<DnsTimeout>()
+                .or_else(|| self.defaults.get())
+                .map(|timeout| timeout.0),
+            tls: parts
+                .extensions
+                .get::<TlsTimeout>()
+                .or_else(|| self.defaults.get())
+                .map(|timeout| timeout.0),
+            first_byte: parts
+                .extensions
+                .get::<FirstByteTimeout>()
+                .or_else(|| self.defaults.get())
+                .map(|timeout| timeout.0),
+            read: parts
+                .extensions
+                .get::<ReadTimeout>()
+                .or_else(|| self.defaults.get())
+                .map(|timeout| timeout.0),
+        };
+
+        if !phase_timeouts.is_empty() {
+            easy.get_mut().set_phase_timeouts(phase_timeouts);
+            easy.progress(true)?;
+        }
+
+        if let Some(limit) = parts
+            .extensions
+            .get::<LowSpeedLimit>()
+            .or_else(|| self.defaults.get())
+        {
+            easy.get_mut().set_low_speed_limit(limit.0, limit.1);
+            easy.progress(true)?;
+        }
+
+        if parts
+            .extensions
+            .get::<EnableMetrics>()
+            .or_else(|| self.defaults.get())
+            .map_or(false, |enable| enable.0)
+        {
+            easy.get_mut().set_metrics(Metrics::default());
+            easy.progress(true)?;
+        }
+
+        if let Some(callback) = parts
+            .extensions
+            .get::<ProgressCallback>()
+            .or_else(|| self.defaults.get())
+        {
+            easy.get_mut().set_progress_callback(callback.clone());
+            easy.progress(true)?;
+        }
+
+        let peer_certificates = parts
+            .extensions
+            .get::<EnablePeerCertificates>()
+            .or_else(|| self.defaults.get())
+            .map_or(false, |enable| enable.0);
+
+        // TLS details other than certificates are only reported in curl's
+        // informational output.
+        if peer_certificates
+            || parts
+                .extensions
+                .get::<EnableTlsInfo>()
+                .or_else(|| self.defaults.get())
+                .map_or(false, |enable| enable.0)
+        {
+            easy.verbose(true)?;
+            easy.certinfo(peer_certificates)?;
+            easy.get_mut().set_tls_info(peer_certificates);
+        }
+
+        if let Some(callback) = parts
+            .extensions
+            .get::<InformationalCallback>()
+            .or_else(|| self.defaults.get())
+        {
+            easy.get_mut().set_informational_callback(callback.clone());
+        }
+
         // Enable automatic response decoding, unless overridden by the user via
         // a custom Accept-Encoding value.
-        easy.accept_encoding(
-            parts
-                .headers
-                .get("Accept-Encoding")
-                .and_then(|value| value.to_str().ok())
-                // Empty string tells curl to fill in all supported encodings.
-                .unwrap_or(""),
-        )?;
+        if automatic_headers
+            && parts
+                .extensions
+                .get::<AutomaticDecompression>()
+                .or_else(|| self.defaults.get())
+                .map_or(true, |enable| enable.0)
+        {
+            let accept_encodings = parts
+                .extensions
+                .get::<AcceptEncodings>()
+                .or_else(|| self.defaults.get())
+                .map(AcceptEncodings::header_value);
+
+            easy.accept_encoding(
+                parts
+                    .headers
+                    .get("Accept-Encoding")
+                    .and_then(|value| value.to_str().ok())
+                    .or_else(|| accept_encodings.as_ref().map(String::as_str))
+                    // Empty string tells curl to fill in all supported encodings.
+                    .unwrap_or(""),
+            )?;
+        }
 
         // Set the HTTP method to use. Curl ties in behavior with the request
         // method, so we need to configure this carefully.
@@ -807,7 +2882,14 @@ impl HttpClient {
         // If the request has a body, then we either need to tell curl how large
         // the body is if we know it, or tell curl to use chunked encoding. If
         // we do neither, curl will simply not send the body without warning.
-        if has_body {
+        //
+        // Small in-memory and empty bodies of POST requests are copied into
+        // curl instead, which sets the body length for us.
+        let mut chunked = false;
+
+        if let Some(bytes) = inline_body {
+            easy.post_fields_copy(&bytes)?;
+        } else if has_body {
             // Use length given in Content-Length header, or the size defined by
             // the body itself.
             let body_length = parts
@@ -827,25 +2909,102 @@ impl HttpClient {
                 // Set the Transfer-Encoding header to instruct curl to use
                 // chunked encoding. Replaces any existing values that may be
                 // incorrect.
-                parts.headers.insert(
-                    "Transfer-Encoding",
-                    http::header::HeaderValue::from_static("chunked"),
-                );
+                chunked = automatic_headers;
             }
         }
 
         // Prepare header list to give to curl.
         let mut headers = curl::easy::List::new();
         for (name, value) in parts.headers.iter() {
+            if chunked && name == http::header::TRANSFER_ENCODING {
+                continue;
+            }
+
             let header = format!("{}: {}", name.as_str(), value.to_str().unwrap());
             headers.append(&header)?;
         }
+        if chunked {
+            headers.append("transfer-encoding: chunked")?;
+        }
+
+        // Describe bodies that know their own format, unless the user already
+        // did.
+        if let Some(content_type) = body_content_type {
+            if !parts.headers.contains_key(http::header::CONTENT_TYPE) {
+                headers.append(&format!("content-type: {}", content_type))?;
+            }
+        }
+
+        if let Some(content_encoding) = body_content_encoding {
+            headers.append(&format!("content-encoding: {}", content_encoding))?;
+        }
+
+        // An empty value tells curl not to send a header it would otherwise
+        // add by itself.
+        if !automatic_headers {
+            for name in &[
+                http::header::HOST,
+                http::header::CONTENT_LENGTH,
+                http::header::TRANSFER_ENCODING,
+                http::header::ACCEPT_ENCODING,
+            ] {
+                if !parts.headers.contains_key(name) {
+                    headers.append(&format!("{}:", name.as_str()))?;
+                }
+            }
+        }
         easy.http_headers(headers)?;
 
         Ok((easy, future))
     }
 }
 
+/// Restrict the protocols curl may use to HTTP and any protocols enabled by
+/// crate features. Redirects are only ever followed to HTTP URLs.
+#[allow(unsafe_code)]
+fn set_protocols<H>(easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+    // Not yet exposed by curl-sys.
+    const CURLOPT_PROTOCOLS: curl_sys::CURLoption = curl_sys::CURLOPTTYPE_LONG + 181;
+    const CURLOPT_REDIR_PROTOCOLS: curl_sys::CURLoption = curl_sys::CURLOPTTYPE_LONG + 182;
+    const CURLPROTO_HTTP: c_long = 1 << 0;
+    const CURLPROTO_HTTPS: c_long = 1 << 1;
+    #[cfg(feature = "ftp")]
+    const CURLPROTO_FTP: c_long = 1 << 2;
+    #[cfg(feature = "ftp")]
+    const CURLPROTO_FTPS: c_long = 1 << 3;
+    #[cfg(feature = "file")]
+    const CURLPROTO_FILE: c_long = 1 << 10;
+
+    let redirect_protocols = CURLPROTO_HTTP | CURLPROTO_HTTPS;
+
+    #[allow(unused_mut)]
+    let mut protocols = redirect_protocols;
+
+    #[cfg(feature = "ftp")]
+    {
+        protocols |= CURLPROTO_FTP | CURLPROTO_FTPS;
+    }
+
+    #[cfg(feature = "file")]
+    {
+        protocols |= CURLPROTO_FILE;
+    }
+
+    unsafe {
+        for &(option, value) in &[
+            (CURLOPT_PROTOCOLS, protocols),
+            (CURLOPT_REDIR_PROTOCOLS, redirect_protocols),
+        ] {
+            match curl_sys::curl_easy_setopt(easy.raw(), option, value) {
+                curl_sys::CURLE_OK => {}
+                code => return Err(curl::Error::new(code)),
+            }
+        }
+    }
+
+    Ok(())
+}
+
 impl fmt::Debug for HttpClient {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("HttpClient").finish()
@@ -853,19 +3012,61 @@ impl fmt::Debug for HttpClient {
 }
 
 /// A future for a request being executed.
-#[derive(Debug)]
 pub struct ResponseFuture<'c> {
     /// The client this future is associated with.
     client: &'c HttpClient,
+    /// The interceptor chain handling the request, if the client has any
+    /// interceptors.
+    intercepted: Option<InterceptorFuture<'c>>,
     /// A pre-filled error to return.
     error: Option<Error>,
     /// The request to send.
     request: Option<Request<Body>>,
     /// The inner future for actual execution.
     inner: Option<RequestHandlerFuture>,
+    /// A copy of the request for sending it again, if failover, retrying,
+    /// falling back to the system resolver or a redirect handler applies
+    /// to this request.
+    replay: Option<Replay>,
+    /// The plan for sending the request to another endpoint, if failover
+    /// applies to this request.
+    failover: Option<Plan>,
+    /// Attempts made so far, if retrying applies to this request.
+    retry: Option<RetryState>,
+    /// Time to wait before the next attempt, if one is scheduled.
+    delay: Option<Delay>,
+    /// Redirects followed so far, if a redirect handler applies to this
+    /// request.
+    redirect: Option<RedirectState>,
+    /// The URI the request was sent to, for attaching to the response.
+    request_uri: Option<http::Uri>,
+}
+
+/// A request that may be sent more than once.
+#[derive(Debug)]
+struct Replay {
+    parts: http::request::Parts,
+    /// A copy of the request body, if it can be sent more than once.
+    body: Option<Body>,
 }
 
 impl<'c> ResponseFuture<'c> {
+    fn new(client: &'c HttpClient) -> Self {
+        Self {
+            client,
+            intercepted: None,
+            error: None,
+            request: None,
+            inner: None,
+            replay: None,
+            failover: None,
+            retry: None,
+            delay: None,
+            redirect: None,
+            request_uri: None,
+        }
+    }
+
     fn maybe_initialize(&mut self) -> Result<(), Error> {
         // If the future has a pre-filled error, return that.
         if let Some(e) = self.error.take() {
@@ -874,37 +3075,318 @@ impl<'c> ResponseFuture<'c> {
 
         // Request has not been sent yet.
         if let Some(request) = self.request.take() {
-            // Create and configure a curl easy handle to fulfil the request.
-            let (easy, future) = self.client.create_easy_handle(request)?;
+            let client = self.client;
+            let (mut parts, body) = request.into_parts();
+
+            self.request_uri = Some(parts.uri.clone());
+
+            let plan = match client.endpoints.as_ref() {
+                Some(endpoints) => match endpoints.plan(&parts.uri) {
+                    Some(plan) => {
+                        parts.uri = endpoints.uri(&plan)?;
+                        Some(plan)
+                    }
+                    None => None,
+                },
+                None => None,
+            };
+
+            let retry_policy = parts
+                .extensions
+                .get::<RetryPolicy>()
+                .or_else(|| client.defaults.get())
+                .cloned();
+
+            // Requests sent to follow a redirect carry on with the state of
+            // the original request.
+            let redirect = match parts.extensions.remove::<RedirectState>() {
+                Some(state) => Some(state),
+                None => redirect::handler(&parts.extensions, &client.defaults).map(|handler| {
+                    // Redirects are followed by the client instead of curl.
+                    parts.extensions.insert(RedirectPolicy::None);
+                    RedirectState::new(handler)
+                }),
+            };
+
+            let dns_fallback = client
+                .defaults
+                .get::<DnsOverHttps>()
+                .map_or(false, |doh| doh.fallback);
+
+            if plan.is_some() || retry_policy.is_some() || redirect.is_some() || dns_fallback {
+                let body_copy = body.try_clone();
+
+                // Only bodies that can be sent more than once allow the
+                // request to be sent again later.
+                if body_copy.is_some() {
+                    self.failover = plan;
+                    self.retry = retry_policy.map(RetryState::new);
+                }
+
+                self.inner = Some(client.submit(&parts, body)?);
+                self.redirect = redirect;
+                self.replay = Some(Replay {
+                    parts,
+                    body: body_copy,
+                });
 
-            // Send the request to the agent to be executed.
-            self.client.agent.submit_request(easy)?;
+                return Ok(());
+            }
+
+            self.inner = Some(client.submit(&parts, body)?);
+        }
+
+        Ok(())
+    }
+
+    /// Handle a failed attempt to send the request by sending it to the next
+    /// endpoint, if possible. Otherwise the error is returned as-is.
+    fn fail_over(&mut self, error: Error) -> Result<(), Error> {
+        let client = self.client;
+
+        let (endpoints, plan, replay) = match (
+            client.endpoints.as_ref(),
+            self.failover.as_mut(),
+            self.replay.as_mut(),
+        ) {
+            (Some(endpoints), Some(plan), Some(replay)) => (endpoints, plan, replay),
+            _ => return Err(error),
+        };
+
+        if !failover::is_connect_error(&error) {
+            return Err(error);
+        }
+
+        endpoints.mark_failed(plan.current());
+
+        if !plan.advance() {
+            return Err(error);
+        }
+
+        let body = match replay.body.as_ref().and_then(Body::try_clone) {
+            Some(body) => body,
+            None => return Err(error),
+        };
+
+        replay.parts.uri = endpoints.uri(plan)?;
+        log::debug!("{}, failing over to {}", error, replay.parts.uri);
+
+        self.inner = Some(client.submit(&replay.parts, body)?);
+
+        Ok(())
+    }
+
+    /// Handle a failure to resolve the host name using DNS over HTTPS by
+    /// sending the request again using the system resolver, if allowed.
+    /// Otherwise the error is returned as-is.
+    fn fall_back_to_system_dns(&mut self, error: Error) -> Result<(), Error> {
+        let client = self.client;
+
+        let fallback = client
+            .defaults
+            .get::<DnsOverHttps>()
+            .map_or(false, |doh| doh.fallback);
+
+        let resolve_failed = match error {
+            Error::CouldntResolveHost => true,
+            _ => false,
+        };
+
+        let replay = match self.replay.as_mut() {
+            Some(replay) if fallback && resolve_failed => replay,
+            _ => return Err(error),
+        };
+
+        // Only fall back once.
+        if replay.parts.extensions.get::<SystemDns>().is_some() {
+            return Err(error);
+        }
+
+        let body = match replay.body.as_ref().and_then(Body::try_clone) {
+            Some(body) => body,
+            None => return Err(error),
+        };
+
+        replay.parts.extensions.insert(SystemDns);
+        log::debug!("{}, falling back to the system resolver", error);
+
+        self.inner = Some(client.submit(&replay.parts, body)?);
+
+        Ok(())
+    }
+
+    /// Decide whether to retry the request after an attempt with the given
+    /// result, returning how long to wait before doing so.
+    fn retry_delay(
+        &mut self,
+        result: &Result<Response<ResponseBodyReader>, Error>,
+    ) -> Option<Duration> {
+        let replay = self.replay.as_ref()?;
+        let delay = self
+            .retry
+            .as_mut()?
+            .next_delay(&replay.parts.method, result)?;
+
+        // Don't bother waiting for an attempt that could not finish in time.
+        if let Some(deadline) = replay.parts.extensions.get::<Deadline>() {
+            match deadline.remaining(&*self.client.clock) {
+                Ok(remaining) if remaining > delay => {}
+                _ => {
+                    log::debug!("not retrying, deadline would pass in the meantime");
+                    return None;
+                }
+            }
+        }
 
-            self.inner = Some(future);
+        match result {
+            Ok(response) => log::debug!(
+                "received status {}, retrying in {:?}",
+                response.status(),
+                delay
+            ),
+            Err(e) => log::debug!("{}, retrying in {:?}", e, delay),
         }
 
+        Some(delay)
+    }
+
+    /// Send the request again.
+    fn resend(&mut self) -> Result<(), Error> {
+        let client = self.client;
+        let replay = self.replay.as_ref().expect("resend without a request copy");
+        let body = replay
+            .body
+            .as_ref()
+            .and_then(Body::try_clone)
+            .expect("request copy must have a cloneable body");
+
+        self.inner = Some(client.submit(&replay.parts, body)?);
+
         Ok(())
     }
 
+    /// Handle a redirect response according to the redirect handler, if any.
+    ///
+    /// If the redirect is followed, the request for the new location is sent
+    /// through the client again, including its interceptors and middleware,
+    /// and `None` is returned. Otherwise the response is handed back as-is.
+    fn follow_redirect(
+        &mut self,
+        response: Response<ResponseBodyReader>,
+    ) -> Result<Option<Response<ResponseBodyReader>>, Error> {
+        let client = self.client;
+
+        let (state, replay) = match (self.redirect.as_mut(), self.replay.as_mut()) {
+            (Some(state), Some(replay)) => (state, replay),
+            _ => return Ok(Some(response)),
+        };
+
+        let (uri, action) = match state.check(&replay.parts.uri, &response) {
+            Some(decision) => decision,
+            None => return Ok(Some(response)),
+        };
+
+        if state.hops() > redirect::MAX_HOPS {
+            return Err(Error::TooManyRedirects);
+        }
+
+        match action {
+            RedirectAction::Follow => {}
+            RedirectAction::Stop => return Ok(Some(response)),
+            RedirectAction::Reject(reason) => return Err(Error::RedirectRejected(reason)),
+        }
+
+        let body = if redirect::changes_method(response.status(), &replay.parts.method) {
+            replay.parts.method = http::Method::GET;
+            replay.parts.headers.remove(http::header::CONTENT_LENGTH);
+            replay.parts.headers.remove(http::header::CONTENT_TYPE);
+            replay.parts.headers.remove(http::header::TRANSFER_ENCODING);
+
+            Body::empty()
+        } else {
+            match replay.body.as_ref().and_then(Body::try_clone) {
+                Some(body) => body,
+                None => {
+                    log::debug!(
+                        "not following redirect to {}, request body cannot be resent",
+                        uri
+                    );
+                    return Ok(Some(response));
+                }
+            }
+        };
+
+        // Credentials are only meant for the origin they were given for.
+        if !redirect::same_origin(&uri, &replay.parts.uri) {
+            for name in &[
+                http::header::AUTHORIZATION,
+                http::header::COOKIE,
+                http::header::PROXY_AUTHORIZATION,
+            ] {
+                replay.parts.headers.remove(name);
+            }
+        }
+
+        if replay.parts.extensions.get::<AutoReferer>().is_some()
+            || client.defaults.get::<AutoReferer>().is_some()
+        {
+            if let Ok(referer) = replay.parts.uri.to_string().parse() {
+                replay.parts.headers.insert(http::header::REFERER, referer);
+            }
+        }
+
+        log::debug!("following redirect from {} to {}", replay.parts.uri, uri);
+        let previous_uri = mem::replace(&mut replay.parts.uri, uri);
+        state.followed(previous_uri, response.status());
+
+        // The original endpoint answered, and endpoints do not apply to the
+        // redirected request.
+        if let (Some(endpoints), Some(plan)) = (client.endpoints.as_ref(), self.failover.take()) {
+            endpoints.mark_healthy(plan.current());
+        }
+
+        let state = self.redirect.take().expect("redirect state checked above");
+        let replay = self.replay.take().expect("request copy checked above");
+
+        // Let response middleware see the redirect response, so that cookies
+        // it sets are stored before the next request is sent.
+        drop(self.complete(Ok(response)));
+
+        let mut request = Request::from_parts(replay.parts, body);
+        request.extensions_mut().insert(state);
+        self.intercepted = Some(Box::pin(client.send_async(request)));
+
+        Ok(None)
+    }
+
     fn complete(
         &self,
         result: Result<Response<ResponseBodyReader>, Error>,
     ) -> Result<Response<Body>, Error> {
+        if let (Ok(_), Some(endpoints), Some(plan)) = (
+            result.as_ref(),
+            self.client.endpoints.as_ref(),
+            self.failover.as_ref(),
+        ) {
+            endpoints.mark_healthy(plan.current());
+        }
+
         result.map(|response| {
             // Convert the reader into an opaque Body.
-            let mut response = response.map(|reader| {
-                let body = ResponseBody {
-                    inner: reader,
-                    // Extend the lifetime of the agent by including a reference
-                    // to its handle in the response body.
-                    agent: self.client.agent.clone(),
-                };
-
-                match body.inner.len() {
-                    Some(len) => Body::reader_sized(body, len),
-                    None => Body::reader(body),
+            let mut response =
+                response.map(|reader| response_body(reader, self.client.agent.clone()));
+
+            if let Some(uri) = self.request_uri.as_ref() {
+                response.extensions_mut().insert(RequestUri(uri.clone()));
+            }
+
+            if let Some(state) = self.redirect.as_ref() {
+                if !state.history().is_empty() {
+                    response
+                        .extensions_mut()
+                        .insert(RedirectHistory(state.history().to_vec()));
                 }
-            });
+            }
 
             // Apply response middleware, starting with the innermost
             // one.
@@ -919,12 +3401,48 @@ impl<'c> ResponseFuture<'c> {
     /// Block the current thread until the request is completed or aborted. This
     /// effectively turns the asynchronous request into a synchronous one.
     fn join(mut self) -> Result<Response<Body>, Error> {
+        if let Some(intercepted) = self.intercepted.take() {
+            return Join::join(intercepted);
+        }
+
         self.maybe_initialize()?;
 
-        if let Some(inner) = self.inner.take() {
-            self.complete(inner.join())
-        } else {
-            panic!("join called after poll");
+        loop {
+            let result = if let Some(inner) = self.inner.take() {
+                match inner.join() {
+                    Err(e) => match self
+                        .fail_over(e)
+                        .or_else(|e| self.fall_back_to_system_dns(e))
+                    {
+                        Ok(()) => continue,
+                        Err(e) => Err(e),
+                    },
+                    result => result,
+                }
+            } else {
+                panic!("join called after poll");
+            };
+
+            match self.retry_delay(&result) {
+                Some(delay) => {
+                    // Close the response being retried before waiting.
+                    drop(result);
+                    Delay::new(delay, self.client.clock.clone()).join();
+                    self.resend()?;
+                }
+                None => {
+                    let result = match result {
+                        Ok(response) => match self.follow_redirect(response)? {
+                            Some(response) => Ok(response),
+                            // Wait for the request following the redirect.
+                            None => return self.join(),
+                        },
+                        result => result,
+                    };
+
+                    return self.complete(result);
+                }
+            }
         }
     }
 }
@@ -933,17 +3451,128 @@ impl Future for ResponseFuture<'_> {
     type Output = Result<Response<Body>, Error>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Some(intercepted) = self.intercepted.as_mut() {
+            return intercepted.as_mut().poll(cx);
+        }
+
         self.maybe_initialize()?;
 
-        if let Some(inner) = self.inner.as_mut() {
-            Pin::new(inner).poll(cx).map(|result| self.complete(result))
-        } else {
-            // Invalid state (called poll() after ready), just return pending...
-            Poll::Pending
+        loop {
+            // Wait before sending the next attempt, if one is scheduled.
+            if let Some(delay) = self.delay.as_mut() {
+                match Pin::new(delay).poll(cx) {
+                    Poll::Ready(()) => {
+                        self.delay = None;
+                        self.resend()?;
+                    }
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+
+            let result = if let Some(inner) = self.inner.as_mut() {
+                match Pin::new(inner).poll(cx) {
+                    Poll::Ready(result) => result,
+                    Poll::Pending => return Poll::Pending,
+                }
+            } else {
+                // Invalid state (called poll() after ready), just return pending...
+                return Poll::Pending;
+            };
+
+            let result = match result {
+                // Poll the request sent to the next endpoint, if any.
+                Err(e) => match self
+                    .fail_over(e)
+                    .or_else(|e| self.fall_back_to_system_dns(e))
+                {
+                    Ok(()) => continue,
+                    Err(e) => Err(e),
+                },
+                result => result,
+            };
+
+            match self.retry_delay(&result) {
+                Some(delay) => {
+                    self.inner = None;
+                    self.delay = Some(Delay::new(delay, self.client.clock.clone()));
+                }
+                None => {
+                    let result = match result {
+                        Ok(response) => match self.follow_redirect(response)? {
+                            Some(response) => Ok(response),
+                            // Poll the request following the redirect.
+                            None => return self.poll(cx),
+                        },
+                        result => result,
+                    };
+
+                    return Poll::Ready(self.complete(result));
+                }
+            }
         }
     }
 }
 
+impl fmt::Debug for ResponseFuture<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture")
+            .field("intercepted", &self.intercepted.is_some())
+            .field("error", &self.error)
+            .field("request", &self.request)
+            .field("inner", &self.inner)
+            .field("replay", &self.replay)
+            .field("failover", &self.failover)
+            .field("retry", &self.retry)
+            .field("delay", &self.delay)
+            .field("redirect", &self.redirect)
+            .finish()
+    }
+}
+
+/// Get the path of the file recording the validator of a download in
+/// progress.
+fn download_validator_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(".validator");
+    path.with_file_name(name)
+}
+
+/// Get a validator of a response suitable for an `If-Range` header, which
+/// must be strong.
+///
+/// https://tools.ietf.org/html/rfc7233#section-3.2
+fn download_validator<T>(response: &Response<T>) -> Option<String> {
+    let validators = Validators::from_response(response)?;
+
+    validators
+        .etag()
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| validators.last_modified())
+        .map(ToOwned::to_owned)
+}
+
+fn remove_file_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Convert a response body reader into an opaque Body.
+fn response_body(reader: ResponseBodyReader, agent: Arc<agent::Handle>) -> Body {
+    let body = ResponseBody {
+        inner: reader,
+        // Extend the lifetime of the agent by including a reference to its
+        // handle in the response body.
+        agent,
+    };
+
+    match body.inner.len() {
+        Some(len) => Body::reader_sized(body, len),
+        None => Body::reader(body),
+    }
+}
+
 /// Response body stream. Holds a reference to the agent to ensure it is kept
 /// alive until at least this transfer is complete.
 #[derive(Debug)]
@@ -0,0 +1,92 @@
+//! Sources of time for the client's own timing logic.
+//!
+//! By default, a client reads the time from the system's monotonic clock. A
+//! different [`Clock`] can be given to a client using
+//! [`HttpClientBuilder::clock`](crate::HttpClientBuilder::clock), which is
+//! mostly useful for testing time-dependent behavior with a [`MockClock`]
+//! instead of waiting for real time to pass.
+//!
+//! The clock is used for deadlines that the client keeps track of itself, such
+//! as the acquire timeout of queued requests, the time left until a request's
+//! deadline, closing idle connections, how long failed endpoints are avoided,
+//! and how long to wait before retrying a request or reconnecting an event
+//! source. Timeouts of transfers in progress are enforced by curl, which always
+//! uses the system clock.
+//!
+//! Deadlines are only checked when the client has a reason to wake up, which
+//! happens at least every 100 milliseconds while any requests are active or
+//! waiting, idle connections are due to be closed, or retries are pending.
+//! Advancing a mock clock does not wake the client up by itself.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A source of the current time.
+pub trait Clock: fmt::Debug + Send + Sync + 'static {
+    /// Get the current time.
+    ///
+    /// The returned time must never go backwards.
+    fn now(&self) -> Instant;
+}
+
+/// A clock that reads the current time from the system's monotonic clock.
+///
+/// This is the default clock.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves forward when told to.
+///
+/// Clones of a mock clock share the same time, so one clone can be given to a
+/// client while another is used to advance the time.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::clock::{Clock, MockClock};
+/// use std::time::Duration;
+///
+/// let clock = MockClock::new();
+/// let start = clock.now();
+///
+/// clock.advance(Duration::from_secs(30));
+///
+/// assert_eq!(clock.now() - start, Duration::from_secs(30));
+/// ```
+#[derive(Clone, Debug)]
+pub struct MockClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl MockClock {
+    /// Create a new mock clock, starting at the current system time.
+    pub fn new() -> Self {
+        Self {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Move the time of this clock forward.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+}
@@ -3,10 +3,14 @@
 //! Individual options are separated out into multiple types. Each type acts
 //! both as a "field name" and the value of that option.
 
+use crate::clock::Clock;
+use std::fmt;
 use std::iter::FromIterator;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// A helper trait for applying a configuration value to a given curl handle.
 pub(crate) trait SetOpt {
@@ -30,6 +34,66 @@ pub enum RedirectPolicy {
     Limit(u32),
 }
 
+/// A function deciding whether to follow each redirect. Takes precedence over
+/// a redirect policy set in the same place.
+#[derive(Clone)]
+pub(crate) struct RedirectHandler(
+    pub(crate) Arc<dyn Fn(&RedirectAttempt) -> RedirectAction + Send + Sync>,
+);
+
+impl fmt::Debug for RedirectHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedirectHandler").finish()
+    }
+}
+
+/// Information about a redirect that a redirect handler is deciding whether
+/// to follow.
+#[derive(Debug)]
+pub struct RedirectAttempt {
+    pub(crate) status: http::StatusCode,
+    pub(crate) uri: http::Uri,
+    pub(crate) previous_uri: http::Uri,
+    pub(crate) hops: u32,
+}
+
+impl RedirectAttempt {
+    /// Get the status code of the redirect response.
+    pub fn status(&self) -> http::StatusCode {
+        self.status
+    }
+
+    /// Get the URI being redirected to.
+    pub fn uri(&self) -> &http::Uri {
+        &self.uri
+    }
+
+    /// Get the URI of the request that was redirected.
+    pub fn previous_uri(&self) -> &http::Uri {
+        &self.previous_uri
+    }
+
+    /// Get the number of redirects received for this request so far,
+    /// including this one.
+    pub fn hops(&self) -> u32 {
+        self.hops
+    }
+}
+
+/// What to do with a redirect, as decided by a redirect handler.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RedirectAction {
+    /// Follow the redirect.
+    Follow,
+    /// Do not follow the redirect, and return the redirect response as the
+    /// response to the request.
+    Stop,
+    /// Fail the request with
+    /// [`Error::RedirectRejected`](crate::Error::RedirectRejected), with the
+    /// given reason.
+    Reject(String),
+}
+
 impl Default for RedirectPolicy {
     fn default() -> Self {
         RedirectPolicy::None
@@ -55,6 +119,210 @@ impl SetOpt for RedirectPolicy {
     }
 }
 
+/// Priority of a request relative to other requests waiting to be sent by the
+/// same client.
+///
+/// Priorities only have an effect if the client limits the number of requests
+/// that may be in flight at once (see
+/// [`HttpClientBuilder::max_concurrent_requests`](crate::HttpClientBuilder::max_concurrent_requests)).
+/// When the limit is reached, new requests wait in a queue, and waiting
+/// requests with a higher priority are started before any waiting requests
+/// with a lower priority. Requests of the same priority are started in the
+/// order they were sent.
+///
+/// The default priority is [`Priority::Normal`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Priority {
+    /// Lowest priority, suitable for bulk background jobs.
+    Low,
+    /// The default priority.
+    Normal,
+    /// Highest priority, suitable for interactive requests.
+    High,
+}
+
+impl Default for Priority {
+    fn default() -> Self {
+        Priority::Normal
+    }
+}
+
+/// Configuration for deriving request timeouts from the response times
+/// previously observed for the same host.
+///
+/// When enabled, the client keeps track of how long the most recent requests
+/// to each host took to complete. Once enough requests to a host have
+/// completed, new requests to that host get a timeout of the given percentile
+/// of those response times, multiplied by the given factor. This lets a single
+/// client use tight timeouts for fast hosts and generous ones for slow hosts.
+///
+/// Until enough is known about a host, the client's regular timeout is used.
+/// A timeout set explicitly on a request always takes precedence.
+///
+/// See
+/// [`HttpClientBuilder::adaptive_timeout`](crate::HttpClientBuilder::adaptive_timeout)
+/// for enabling adaptive timeouts on a client.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::config::AdaptiveTimeout;
+/// use std::time::Duration;
+///
+/// // Allow three times the 99th percentile response time, but never less than
+/// // one second or more than thirty seconds.
+/// let adaptive = AdaptiveTimeout::new(99, 3)
+///     .min(Duration::from_secs(1))
+///     .max(Duration::from_secs(30));
+/// ```
+#[derive(Clone, Debug)]
+pub struct AdaptiveTimeout {
+    pub(crate) percentile: u8,
+    pub(crate) multiplier: u32,
+    pub(crate) min: Duration,
+    pub(crate) max: Option<Duration>,
+}
+
+impl AdaptiveTimeout {
+    /// Derive timeouts by multiplying the given percentile of response times
+    /// by `multiplier`.
+    ///
+    /// The percentile is clamped to the range 1 to 100.
+    pub fn new(percentile: u8, multiplier: u32) -> Self {
+        Self {
+            percentile: percentile.max(1).min(100),
+            multiplier,
+            min: Duration::from_secs(1),
+            max: None,
+        }
+    }
+
+    /// Set the smallest timeout that may be derived. The default is one
+    /// second.
+    pub fn min(mut self, min: Duration) -> Self {
+        self.min = min;
+        self
+    }
+
+    /// Set the largest timeout that may be derived. By default there is no
+    /// upper bound.
+    pub fn max(mut self, max: Duration) -> Self {
+        self.max = Some(max);
+        self
+    }
+}
+
+impl Default for AdaptiveTimeout {
+    fn default() -> Self {
+        Self::new(99, 3)
+    }
+}
+
+/// A policy for automatically retrying failed requests.
+///
+/// A request is retried if it could not reach the server at all, such as when
+/// connecting or resolving the host name fails. Requests with an idempotent
+/// method (such as `GET` or `PUT`) are also retried if the server did not
+/// respond or the response has one of the configured status codes, which by
+/// default are `429`, `502`, `503` and `504`.
+///
+/// Between attempts the client waits for an exponentially growing delay with
+/// random jitter applied, up to the maximum backoff. If the response has a
+/// `Retry-After` header, the client waits for as long as the server asks
+/// instead, up to a separate cap.
+///
+/// Requests with a streaming body cannot be sent more than once and are never
+/// retried.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::config::RetryPolicy;
+/// use std::time::Duration;
+///
+/// // Wait 100ms, then 200ms, for a total of up to three attempts.
+/// let policy = RetryPolicy::exponential(Duration::from_millis(100))
+///     .max_attempts(3);
+/// ```
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub(crate) max_attempts: u32,
+    pub(crate) base: Duration,
+    pub(crate) multiplier: u32,
+    pub(crate) max_backoff: Duration,
+    pub(crate) max_retry_after: Duration,
+    pub(crate) jitter: bool,
+    pub(crate) status_codes: Vec<http::StatusCode>,
+}
+
+impl RetryPolicy {
+    /// Retry with a delay that starts at `base` and doubles after each
+    /// attempt.
+    ///
+    /// By default, up to three attempts are made in total.
+    pub fn exponential(base: Duration) -> Self {
+        Self {
+            max_attempts: 3,
+            base,
+            multiplier: 2,
+            max_backoff: Duration::from_secs(30),
+            max_retry_after: Duration::from_secs(120),
+            jitter: true,
+            status_codes: vec![
+                http::StatusCode::TOO_MANY_REQUESTS,
+                http::StatusCode::BAD_GATEWAY,
+                http::StatusCode::SERVICE_UNAVAILABLE,
+                http::StatusCode::GATEWAY_TIMEOUT,
+            ],
+        }
+    }
+
+    /// Retry with the same delay between every attempt.
+    pub fn fixed(delay: Duration) -> Self {
+        Self {
+            multiplier: 1,
+            ..Self::exponential(delay)
+        }
+    }
+
+    /// Set the maximum number of attempts, including the first one. A value of
+    /// one disables retrying.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Set the longest delay to wait between attempts. The default is thirty
+    /// seconds.
+    pub fn max_backoff(mut self, max: Duration) -> Self {
+        self.max_backoff = max;
+        self
+    }
+
+    /// Set the longest delay to wait when the server asks for one with a
+    /// `Retry-After` header, so that a misbehaving server cannot stall the
+    /// client indefinitely. Longer delays are shortened to this value. The
+    /// default is two minutes.
+    pub fn max_retry_after(mut self, max: Duration) -> Self {
+        self.max_retry_after = max;
+        self
+    }
+
+    /// Enable or disable randomizing delays, which spreads out retries from
+    /// many clients failing at the same time. Enabled by default.
+    pub fn jitter(mut self, enable: bool) -> Self {
+        self.jitter = enable;
+        self
+    }
+
+    /// Set the response status codes that cause a request to be retried,
+    /// replacing the defaults.
+    pub fn status_codes(mut self, codes: impl IntoIterator<Item = http::StatusCode>) -> Self {
+        self.status_codes = codes.into_iter().collect();
+        self
+    }
+}
+
 /// A public key certificate file.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum ClientCertificate {
@@ -158,186 +426,1219 @@ impl SetOpt for PrivateKey {
     }
 }
 
-#[derive(Clone, Debug)]
-pub(crate) struct Timeout(pub(crate) Duration);
+/// A set of trusted certificate authorities to verify servers against,
+/// replacing the system certificate store.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CaCertificate {
+    /// A file containing one or more PEM-encoded CA certificates.
+    File(PathBuf),
+    /// One or more PEM-encoded CA certificates held in memory.
+    ///
+    /// Requires libcurl 7.77.0 or newer. Older versions will return an error
+    /// when attempting to send a request using this certificate.
+    Blob(Vec<u8>),
+}
 
-impl SetOpt for Timeout {
+impl SetOpt for CaCertificate {
     fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
-        easy.timeout(self.0)
+        // Not yet exposed by curl-sys.
+        const CURLOPT_CAINFO_BLOB: curl_sys::CURLoption = CURLOPTTYPE_BLOB + 309;
+
+        match self {
+            CaCertificate::File(path) => easy.cainfo(path),
+            CaCertificate::Blob(data) => setopt_blob(easy, CURLOPT_CAINFO_BLOB, data),
+        }
     }
 }
 
+/// A public key the server must present, either as a path to a PEM or DER
+/// file or as one or more `sha256//` hashes.
 #[derive(Clone, Debug)]
-pub(crate) struct ConnectTimeout(pub(crate) Duration);
+pub(crate) struct PinnedPublicKey(pub(crate) String);
 
-impl SetOpt for ConnectTimeout {
+impl SetOpt for PinnedPublicKey {
     fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
-        easy.connect_timeout(self.0)
+        // Not yet exposed by curl-sys.
+        const CURLOPT_PINNEDPUBLICKEY: curl_sys::CURLoption =
+            curl_sys::CURLOPTTYPE_OBJECTPOINT + 230;
+
+        setopt_str(easy, CURLOPT_PINNEDPUBLICKEY, &self.0)
     }
 }
 
-#[derive(Clone, Debug)]
-pub(crate) struct TcpKeepAlive(pub(crate) Duration);
+/// A version of the TLS protocol.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TlsVersion {
+    /// TLS 1.0.
+    V1_0,
+    /// TLS 1.1.
+    V1_1,
+    /// TLS 1.2.
+    V1_2,
+    /// TLS 1.3. Requires libcurl 7.52.0 or newer and a TLS backend that
+    /// supports it.
+    V1_3,
+}
 
-impl SetOpt for TcpKeepAlive {
-    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
-        easy.tcp_keepalive(true)?;
-        easy.tcp_keepintvl(self.0)
+impl TlsVersion {
+    /// Get the `CURL_SSLVERSION_*` value for this version.
+    fn curl_value(self) -> std::os::raw::c_long {
+        match self {
+            TlsVersion::V1_0 => 4,
+            TlsVersion::V1_1 => 5,
+            TlsVersion::V1_2 => 6,
+            TlsVersion::V1_3 => 7,
+        }
     }
 }
 
-#[derive(Clone, Debug)]
-pub(crate) struct PreferredHttpVersion(pub(crate) http::Version);
+/// A TLS engine that libcurl can be built with.
+///
+/// Builds of libcurl can include more than one TLS engine, in which case one
+/// of them can be selected at runtime with
+/// [`HttpClientBuilder::tls_backend`](crate::HttpClientBuilder::tls_backend).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TlsBackend {
+    /// OpenSSL, or a compatible fork such as LibreSSL or BoringSSL.
+    OpenSsl,
+    /// GnuTLS.
+    GnuTls,
+    /// Network Security Services.
+    Nss,
+    /// wolfSSL.
+    WolfSsl,
+    /// Windows Schannel.
+    Schannel,
+    /// Apple Secure Transport.
+    SecureTransport,
+    /// Mbed TLS.
+    MbedTls,
+    /// BearSSL.
+    BearSsl,
+    /// rustls.
+    Rustls,
+}
 
-impl SetOpt for PreferredHttpVersion {
-    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
-        easy.http_version(match self.0 {
-            http::Version::HTTP_10 => curl::easy::HttpVersion::V10,
-            http::Version::HTTP_11 => curl::easy::HttpVersion::V11,
-            http::Version::HTTP_2 => curl::easy::HttpVersion::V2,
-            _ => curl::easy::HttpVersion::Any,
-        })
+impl TlsBackend {
+    /// All known backends, along with their `curl_sslbackend` IDs and the
+    /// prefix of the name libcurl reports for them in its version string.
+    const ALL: &'static [(TlsBackend, std::os::raw::c_int, &'static str)] = &[
+        (TlsBackend::OpenSsl, 1, "OpenSSL"),
+        (TlsBackend::OpenSsl, 1, "LibreSSL"),
+        (TlsBackend::OpenSsl, 1, "BoringSSL"),
+        (TlsBackend::GnuTls, 2, "GnuTLS"),
+        (TlsBackend::Nss, 3, "NSS"),
+        (TlsBackend::WolfSsl, 7, "wolfSSL"),
+        (TlsBackend::Schannel, 8, "Schannel"),
+        (TlsBackend::SecureTransport, 9, "SecureTransport"),
+        (TlsBackend::MbedTls, 11, "mbedTLS"),
+        (TlsBackend::BearSsl, 13, "BearSSL"),
+        (TlsBackend::Rustls, 14, "rustls"),
+    ];
+
+    /// Get the TLS backend libcurl uses, if it can be determined.
+    ///
+    /// If libcurl includes several backends and none has been selected yet,
+    /// calling this selects the default one.
+    pub fn active() -> Option<Self> {
+        let version = curl::Version::get();
+
+        // Backends that are available but not in use are listed in
+        // parentheses.
+        let name = version
+            .ssl_version()?
+            .split_whitespace()
+            .find(|name| !name.starts_with('('))?;
+
+        Self::ALL
+            .iter()
+            .find(|(_, _, prefix)| name.starts_with(*prefix))
+            .map(|(backend, _, _)| *backend)
+    }
+
+    /// Select this backend for all of libcurl. This has to happen before
+    /// libcurl is initialized, and cannot be changed afterwards.
+    #[allow(unsafe_code)]
+    pub(crate) fn select(self) -> Result<(), crate::Error> {
+        // Not yet exposed by curl-sys.
+        extern "C" {
+            fn curl_global_sslset(
+                id: std::os::raw::c_int,
+                name: *const std::os::raw::c_char,
+                avail: *mut *const *const std::os::raw::c_void,
+            ) -> std::os::raw::c_int;
+        }
+
+        const CURLSSLSET_OK: std::os::raw::c_int = 0;
+        const CURLSSLSET_TOO_LATE: std::os::raw::c_int = 2;
+
+        let id = Self::ALL
+            .iter()
+            .find(|(backend, _, _)| *backend == self)
+            .map(|(_, id, _)| *id)
+            .unwrap();
+
+        match unsafe { curl_global_sslset(id, std::ptr::null(), std::ptr::null_mut()) } {
+            CURLSSLSET_OK => Ok(()),
+            CURLSSLSET_TOO_LATE => Err(crate::Error::SSLEngineError(Some(format!(
+                "cannot select {:?} after another TLS backend is already in use",
+                self
+            )))),
+            _ => Err(crate::Error::SSLEngineError(Some(format!(
+                "TLS backend {:?} is not available in this libcurl",
+                self
+            )))),
+        }
     }
 }
 
+/// The oldest TLS version to allow.
 #[derive(Clone, Copy, Debug)]
-pub(crate) struct TcpNoDelay;
+pub(crate) struct TlsMinVersion(pub(crate) TlsVersion);
 
-impl SetOpt for TcpNoDelay {
+/// The newest TLS version to allow.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct TlsMaxVersion(pub(crate) TlsVersion);
+
+/// The range of TLS versions to allow. Curl takes both bounds in a single
+/// option, so they are combined before being applied.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct TlsVersions {
+    pub(crate) min: Option<TlsVersion>,
+    pub(crate) max: Option<TlsVersion>,
+}
+
+impl SetOpt for TlsVersions {
     fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
-        easy.tcp_nodelay(true)
+        if self.min.is_none() && self.max.is_none() {
+            return Ok(());
+        }
+
+        // Unset bounds map to CURL_SSLVERSION_DEFAULT and
+        // CURL_SSLVERSION_MAX_NONE, which are both zero.
+        let min = self.min.map_or(0, TlsVersion::curl_value);
+        let max = self.max.map_or(0, |version| version.curl_value() << 16);
+
+        setopt_long(easy, curl_sys::CURLOPT_SSLVERSION, min | max)
     }
 }
 
-#[derive(Clone, Copy, Debug)]
-pub(crate) struct AutoReferer;
+/// A client certificate to present to an HTTPS proxy.
+#[derive(Clone, Debug)]
+pub(crate) struct ProxyClientCertificate(pub(crate) ClientCertificate);
 
-impl SetOpt for AutoReferer {
+impl SetOpt for ProxyClientCertificate {
     fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
-        easy.autoreferer(true)
+        // Not yet exposed by curl-sys.
+        const CURLOPT_PROXY_SSLCERT: curl_sys::CURLoption = curl_sys::CURLOPTTYPE_OBJECTPOINT + 254;
+        const CURLOPT_PROXY_SSLCERTTYPE: curl_sys::CURLoption =
+            curl_sys::CURLOPTTYPE_OBJECTPOINT + 255;
+        const CURLOPT_PROXY_SSLKEY: curl_sys::CURLoption = curl_sys::CURLOPTTYPE_OBJECTPOINT + 256;
+        const CURLOPT_PROXY_SSLKEYTYPE: curl_sys::CURLoption =
+            curl_sys::CURLOPTTYPE_OBJECTPOINT + 257;
+        const CURLOPT_PROXY_KEYPASSWD: curl_sys::CURLoption =
+            curl_sys::CURLOPTTYPE_OBJECTPOINT + 258;
+
+        let (path, cert_type, private_key, password) = match &self.0 {
+            ClientCertificate::PEM { path, private_key } => (path, "PEM", private_key, None),
+            ClientCertificate::DER { path, private_key } => (path, "DER", private_key, None),
+            ClientCertificate::P12 { path, password } => (path, "P12", &None, password.as_ref()),
+        };
+
+        setopt_path(easy, CURLOPT_PROXY_SSLCERT, path)?;
+        setopt_str(easy, CURLOPT_PROXY_SSLCERTTYPE, cert_type)?;
+
+        let password = match private_key {
+            Some(PrivateKey::PEM { path, password }) => {
+                setopt_path(easy, CURLOPT_PROXY_SSLKEY, path)?;
+                setopt_str(easy, CURLOPT_PROXY_SSLKEYTYPE, "PEM")?;
+                password.as_ref()
+            }
+            Some(PrivateKey::DER { path, password }) => {
+                setopt_path(easy, CURLOPT_PROXY_SSLKEY, path)?;
+                setopt_str(easy, CURLOPT_PROXY_SSLKEYTYPE, "DER")?;
+                password.as_ref()
+            }
+            None => password,
+        };
+
+        if let Some(password) = password {
+            setopt_str(easy, CURLOPT_PROXY_KEYPASSWD, password)?;
+        }
+
+        Ok(())
     }
 }
 
-#[derive(Clone, Copy, Debug)]
-pub(crate) struct MaxUploadSpeed(pub(crate) u64);
+/// CA certificates to verify an HTTPS proxy against.
+#[derive(Clone, Debug)]
+pub(crate) struct ProxyCaCertificate(pub(crate) CaCertificate);
 
-impl SetOpt for MaxUploadSpeed {
+impl SetOpt for ProxyCaCertificate {
     fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
-        easy.max_send_speed(self.0)
+        // Not yet exposed by curl-sys.
+        const CURLOPT_PROXY_CAINFO: curl_sys::CURLoption = curl_sys::CURLOPTTYPE_OBJECTPOINT + 246;
+        const CURLOPT_PROXY_CAINFO_BLOB: curl_sys::CURLoption = CURLOPTTYPE_BLOB + 310;
+
+        match &self.0 {
+            CaCertificate::File(path) => setopt_path(easy, CURLOPT_PROXY_CAINFO, path),
+            CaCertificate::Blob(data) => setopt_blob(easy, CURLOPT_PROXY_CAINFO_BLOB, data),
+        }
     }
 }
 
+/// Whether to skip verifying the certificate of an HTTPS proxy.
 #[derive(Clone, Copy, Debug)]
-pub(crate) struct MaxDownloadSpeed(pub(crate) u64);
+pub(crate) struct AllowUnsafeProxySsl(pub(crate) bool);
 
-impl SetOpt for MaxDownloadSpeed {
+impl SetOpt for AllowUnsafeProxySsl {
     fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
-        easy.max_recv_speed(self.0)
-    }
-}
+        // Not yet exposed by curl-sys.
+        const CURLOPT_PROXY_SSL_VERIFYPEER: curl_sys::CURLoption = curl_sys::CURLOPTTYPE_LONG + 248;
+        const CURLOPT_PROXY_SSL_VERIFYHOST: curl_sys::CURLoption = curl_sys::CURLOPTTYPE_LONG + 249;
 
-#[derive(Clone, Debug)]
-pub(crate) struct DnsServers(pub(crate) Vec<SocketAddr>);
+        let (verify_peer, verify_host) = if self.0 { (0, 0) } else { (1, 2) };
 
-impl FromIterator<SocketAddr> for DnsServers {
-    fn from_iter<I: IntoIterator<Item = SocketAddr>>(iter: I) -> Self {
-        DnsServers(Vec::from_iter(iter))
+        setopt_long(easy, CURLOPT_PROXY_SSL_VERIFYPEER, verify_peer)?;
+        setopt_long(easy, CURLOPT_PROXY_SSL_VERIFYHOST, verify_host)
     }
 }
 
-impl SetOpt for DnsServers {
-    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
-        let dns_string = self.0
-            .iter()
-            .map(ToString::to_string)
-            .collect::<Vec<_>>()
-            .join(",");
+/// Set a string option that the curl crate does not expose yet.
+#[allow(unsafe_code)]
+fn setopt_str<H>(
+    easy: &mut curl::easy::Easy2<H>,
+    option: curl_sys::CURLoption,
+    value: &str,
+) -> Result<(), curl::Error> {
+    let value = std::ffi::CString::new(value)
+        .map_err(|_| curl::Error::new(curl_sys::CURLE_BAD_FUNCTION_ARGUMENT))?;
 
-        // DNS servers should not be hard error.
-        if let Err(e) = easy.dns_servers(&dns_string) {
-            log::warn!("DNS servers could not be configured: {}", e);
+    // Curl makes its own copy of the string.
+    unsafe {
+        match curl_sys::curl_easy_setopt(easy.raw(), option, value.as_ptr()) {
+            curl_sys::CURLE_OK => Ok(()),
+            code => Err(curl::Error::new(code)),
         }
+    }
+}
 
-        Ok(())
+/// Set a path option that the curl crate does not expose yet.
+fn setopt_path<H>(
+    easy: &mut curl::easy::Easy2<H>,
+    option: curl_sys::CURLoption,
+    path: &std::path::Path,
+) -> Result<(), curl::Error> {
+    match path.to_str() {
+        Some(path) => setopt_str(easy, option, path),
+        None => Err(curl::Error::new(curl_sys::CURLE_BAD_FUNCTION_ARGUMENT)),
     }
 }
 
-/// DNS caching configuration.
-///
-/// The default configuration is for caching to be enabled with a 60 second
-/// entry timeout.
-///
-/// See [`HttpClientBuilder::dns_cache`](crate::HttpClientBuilder::dns_cache)
-/// for configuring a client's DNS cache.
-#[derive(Clone, Debug)]
-pub enum DnsCache {
-    /// Disable DNS caching entirely.
-    Disable,
+/// Option type for binary data, not yet exposed by curl-sys.
+const CURLOPTTYPE_BLOB: curl_sys::CURLoption = 40_000;
 
-    /// Enable DNS caching and keep entries in the cache for the given duration.
-    Timeout(Duration),
+/// Set a binary data option that the curl crate does not expose yet.
+#[allow(unsafe_code)]
+fn setopt_blob<H>(
+    easy: &mut curl::easy::Easy2<H>,
+    option: curl_sys::CURLoption,
+    data: &[u8],
+) -> Result<(), curl::Error> {
+    /// Mirrors `struct curl_blob`.
+    #[repr(C)]
+    struct CurlBlob {
+        data: *mut std::os::raw::c_void,
+        len: usize,
+        flags: std::os::raw::c_uint,
+    }
 
-    /// Enable DNS caching and cache entries forever.
-    Forever,
-}
+    /// Tells curl to make its own copy of the data.
+    const CURL_BLOB_COPY: std::os::raw::c_uint = 1;
 
-impl Default for DnsCache {
-    fn default() -> Self {
-        // Match curl's default.
-        Duration::from_secs(60).into()
+    let blob = CurlBlob {
+        data: data.as_ptr() as *mut _,
+        len: data.len(),
+        flags: CURL_BLOB_COPY,
+    };
+
+    unsafe {
+        match curl_sys::curl_easy_setopt(easy.raw(), option, &blob as *const CurlBlob) {
+            curl_sys::CURLE_OK => Ok(()),
+            code => Err(curl::Error::new(code)),
+        }
     }
 }
 
-impl From<Duration> for DnsCache {
-    fn from(duration: Duration) -> Self {
-        DnsCache::Timeout(duration)
+/// Set a long option that the curl crate does not expose yet.
+#[allow(unsafe_code)]
+fn setopt_long<H>(
+    easy: &mut curl::easy::Easy2<H>,
+    option: curl_sys::CURLoption,
+    value: std::os::raw::c_long,
+) -> Result<(), curl::Error> {
+    unsafe {
+        match curl_sys::curl_easy_setopt(easy.raw(), option, value) {
+            curl_sys::CURLE_OK => Ok(()),
+            code => Err(curl::Error::new(code)),
+        }
     }
 }
 
-impl SetOpt for DnsCache {
-    #[allow(unsafe_code)]
+#[derive(Clone, Debug)]
+pub(crate) struct Timeout(pub(crate) Duration);
+
+impl SetOpt for Timeout {
     fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
-        let value = match self {
-            DnsCache::Disable => 0,
-            DnsCache::Timeout(duration) => duration.as_secs() as i64,
-            DnsCache::Forever => -1,
-        };
+        easy.timeout(self.0)
+    }
+}
 
-        // Use unsafe API, because safe API doesn't let us set to -1.
-        unsafe {
-            match curl_sys::curl_easy_setopt(easy.raw(), curl_sys::CURLOPT_DNS_CACHE_TIMEOUT, value) {
-                curl_sys::CURLE_OK => Ok(()),
-                code => Err(curl::Error::new(code)),
-            }
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct AcquireTimeout(pub(crate) Duration);
+
+/// A point in time by which a request must be complete, across all attempts
+/// and redirects. Applied by the client to each attempt it sends.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Deadline(pub(crate) Instant);
+
+impl Deadline {
+    /// Get the time left until the deadline according to the given clock, or
+    /// a timeout error if it has already passed.
+    pub(crate) fn remaining(&self, clock: &dyn Clock) -> Result<Duration, crate::Error> {
+        let now = clock.now();
+
+        if self.0 > now {
+            Ok(self.0 - now)
+        } else {
+            Err(crate::Error::Timeout(None))
         }
     }
 }
 
-#[derive(Clone, Debug)]
-pub(crate) struct Proxy(pub(crate) http::Uri);
+/// Whether the client adds framing headers to requests automatically. This is
+/// handled when building the header list rather than by a curl option.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct AutomaticHeaders(pub(crate) bool);
 
-impl SetOpt for Proxy {
-    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
-        easy.proxy(&format!("{}", self.0))
+/// An encoding to compress request bodies with.
+///
+/// This type requires the `compression` feature to be enabled.
+#[cfg(feature = "compression")]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Compression {
+    /// The `gzip` encoding.
+    Gzip,
+    /// The `deflate` encoding, which is zlib-wrapped DEFLATE data.
+    Deflate,
+}
+
+#[cfg(feature = "compression")]
+impl Compression {
+    /// Get the name of this encoding as used in the `Content-Encoding`
+    /// header.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Deflate => "deflate",
+        }
+    }
+}
+
+/// A content encoding that response bodies can be compressed with.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ContentEncoding {
+    /// The `gzip` encoding.
+    Gzip,
+    /// The `deflate` encoding.
+    Deflate,
+    /// The `br` (Brotli) encoding. Requires libcurl to be built with Brotli
+    /// support.
+    Brotli,
+    /// The `zstd` (Zstandard) encoding. Requires libcurl 7.72.0 or newer built
+    /// with zstd support.
+    Zstd,
+}
+
+impl ContentEncoding {
+    /// Get the name of this encoding as used in HTTP headers.
+    fn name(self) -> &'static str {
+        match self {
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Deflate => "deflate",
+            ContentEncoding::Brotli => "br",
+            ContentEncoding::Zstd => "zstd",
+        }
     }
 }
 
+/// The encodings to advertise in the `Accept-Encoding` header.
 #[derive(Clone, Debug)]
-pub(crate) struct SslCiphers(pub(crate) Vec<String>);
+pub(crate) struct AcceptEncodings(pub(crate) Vec<ContentEncoding>);
 
-impl FromIterator<String> for SslCiphers {
-    fn from_iter<I: IntoIterator<Item = String>>(iter: I) -> Self {
-        SslCiphers(Vec::from_iter(iter))
+impl FromIterator<ContentEncoding> for AcceptEncodings {
+    fn from_iter<I: IntoIterator<Item = ContentEncoding>>(iter: I) -> Self {
+        AcceptEncodings(Vec::from_iter(iter))
     }
 }
 
-impl SetOpt for SslCiphers {
-    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
-        easy.ssl_cipher_list(&self.0.join(":"))
+impl AcceptEncodings {
+    /// Get the value of the `Accept-Encoding` header to send.
+    pub(crate) fn header_value(&self) -> String {
+        self.0
+            .iter()
+            .map(|encoding| encoding.name())
+            .collect::<Vec<_>>()
+            .join(", ")
     }
 }
 
-#[derive(Clone, Debug)]
+/// Whether compressed response bodies are decoded automatically.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct AutomaticDecompression(pub(crate) bool);
+
+/// Whether malformed response headers are rejected. This is enforced by the
+/// request handler rather than by curl.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Strict(pub(crate) bool);
+
+/// Maximum total size in bytes of the header of a response. Enforced by the
+/// request handler.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct MaxResponseHeaderSize(pub(crate) usize);
+
+/// Maximum number of header fields in a response. Enforced by the request
+/// handler.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct MaxResponseHeaders(pub(crate) usize);
+
+/// Maximum size of a response body in bytes. Curl rejects responses that
+/// announce a larger size up front, and the request handler enforces the limit
+/// on the bytes actually received.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct MaxResponseBodySize(pub(crate) u64);
+
+impl SetOpt for MaxResponseBodySize {
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        easy.max_filesize(self.0)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct ConnectTimeout(pub(crate) Duration);
+
+impl SetOpt for ConnectTimeout {
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        easy.connect_timeout(self.0)
+    }
+}
+
+/// Whether to collect metrics for requests.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct EnableMetrics(pub(crate) bool);
+
+/// Whether to collect details about the TLS connection of requests.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct EnableTlsInfo(pub(crate) bool);
+
+/// Whether to include the peer's certificate chain in the TLS details.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct EnablePeerCertificates(pub(crate) bool);
+
+/// The progress of a transfer, as reported to a progress callback.
+///
+/// See [`HttpClientBuilder::progress`](crate::HttpClientBuilder::progress).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Progress {
+    pub(crate) uploaded: u64,
+    pub(crate) upload_total: u64,
+    pub(crate) downloaded: u64,
+    pub(crate) download_total: u64,
+}
+
+impl Progress {
+    /// Get the number of bytes of the request body uploaded so far.
+    pub fn uploaded(&self) -> u64 {
+        self.uploaded
+    }
+
+    /// Get the total number of bytes of the request body to upload, or zero
+    /// if not known.
+    pub fn upload_total(&self) -> u64 {
+        self.upload_total
+    }
+
+    /// Get the number of bytes of the response body downloaded so far.
+    pub fn downloaded(&self) -> u64 {
+        self.downloaded
+    }
+
+    /// Get the total number of bytes of the response body to download, or
+    /// zero if not known.
+    pub fn download_total(&self) -> u64 {
+        self.download_total
+    }
+}
+
+/// A function to call with the progress of transfers.
+#[derive(Clone)]
+pub(crate) struct ProgressCallback(pub(crate) Arc<dyn Fn(Progress) + Send + Sync>);
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressCallback").finish()
+    }
+}
+
+/// A function to call with interim `1xx` responses.
+#[derive(Clone)]
+pub(crate) struct InformationalCallback(
+    pub(crate) Arc<dyn Fn(http::StatusCode, &http::HeaderMap) + Send + Sync>,
+);
+
+impl fmt::Debug for InformationalCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InformationalCallback").finish()
+    }
+}
+
+/// Maximum time for resolving the host name. Enforced by the request handler,
+/// since curl has no separate option for it.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct DnsTimeout(pub(crate) Duration);
+
+/// Maximum time for the TLS handshake. Enforced by the request handler, since
+/// curl has no separate option for it.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct TlsTimeout(pub(crate) Duration);
+
+/// Maximum time between sending the request and receiving the first byte of
+/// the response. Enforced by the request handler.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct FirstByteTimeout(pub(crate) Duration);
+
+/// Maximum time to go without receiving any data while receiving the
+/// response. Enforced by the request handler.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ReadTimeout(pub(crate) Duration);
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ConnectionIdleTimeout(pub(crate) Duration);
+
+impl SetOpt for ConnectionIdleTimeout {
+    #[allow(unsafe_code)]
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        // Not yet exposed by curl-sys.
+        const CURLOPT_MAXAGE_CONN: curl_sys::CURLoption = curl_sys::CURLOPTTYPE_LONG + 288;
+
+        let seconds = whole_seconds(self.0);
+
+        unsafe {
+            match curl_sys::curl_easy_setopt(easy.raw(), CURLOPT_MAXAGE_CONN, seconds) {
+                curl_sys::CURLE_OK => Ok(()),
+                curl_sys::CURLE_UNKNOWN_OPTION => {
+                    log::warn!("connection idle timeout requires libcurl 7.65 or newer, ignoring");
+                    Ok(())
+                }
+                code => Err(curl::Error::new(code)),
+            }
+        }
+    }
+}
+
+/// Maximum time since a connection was opened for it to be reused.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ConnectionMaxLifetime(pub(crate) Duration);
+
+impl SetOpt for ConnectionMaxLifetime {
+    #[allow(unsafe_code)]
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        // Not yet exposed by curl-sys.
+        const CURLOPT_MAXLIFETIME_CONN: curl_sys::CURLoption = curl_sys::CURLOPTTYPE_LONG + 314;
+
+        // Zero would mean no limit, which is why this is rounded up.
+        let seconds = whole_seconds(self.0);
+
+        unsafe {
+            match curl_sys::curl_easy_setopt(easy.raw(), CURLOPT_MAXLIFETIME_CONN, seconds) {
+                curl_sys::CURLE_OK => Ok(()),
+                curl_sys::CURLE_UNKNOWN_OPTION => {
+                    log::warn!("connection max lifetime requires libcurl 7.80 or newer, ignoring");
+                    Ok(())
+                }
+                code => Err(curl::Error::new(code)),
+            }
+        }
+    }
+}
+
+/// Convert a duration into a number of seconds for curl options that only
+/// support whole seconds. Rounds up, to at least one second, so that short
+/// durations don't turn into zero.
+fn whole_seconds(duration: Duration) -> std::os::raw::c_long {
+    let seconds = if duration.subsec_nanos() > 0 {
+        duration.as_secs() + 1
+    } else {
+        duration.as_secs()
+    };
+
+    seconds.max(1) as std::os::raw::c_long
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct TcpKeepAlive(pub(crate) Duration);
+
+impl SetOpt for TcpKeepAlive {
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        easy.tcp_keepalive(true)?;
+        easy.tcp_keepintvl(self.0)
+    }
+}
+
+/// Describes which HTTP versions may be used for a request, and how the
+/// version is negotiated with the server.
+///
+/// The default is [`VersionNegotiation::latest_compatible`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct VersionNegotiation(VersionFlag);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum VersionFlag {
+    LatestCompatible,
+    Http10,
+    Http11,
+    Http2,
+    Http2PriorKnowledge,
+}
+
+impl VersionNegotiation {
+    /// Use the newest HTTP version supported by both the client and the
+    /// server.
+    ///
+    /// HTTP/2 is negotiated over TLS connections where supported, and
+    /// HTTP/1.1 is used for cleartext connections.
+    ///
+    /// This is the default.
+    pub const fn latest_compatible() -> Self {
+        VersionNegotiation(VersionFlag::LatestCompatible)
+    }
+
+    /// Only use HTTP/1.0.
+    pub const fn http10() -> Self {
+        VersionNegotiation(VersionFlag::Http10)
+    }
+
+    /// Only use HTTP/1.1.
+    pub const fn http11() -> Self {
+        VersionNegotiation(VersionFlag::Http11)
+    }
+
+    /// Use HTTP/2.
+    ///
+    /// HTTP/2 is negotiated over TLS connections, and requested with an
+    /// `Upgrade` for cleartext connections. Servers that do not support
+    /// HTTP/2 may still answer using HTTP/1.1; check
+    /// [`Response::version`](http::Response::version) for the version that
+    /// was actually used.
+    pub const fn http2() -> Self {
+        VersionNegotiation(VersionFlag::Http2)
+    }
+
+    /// Use HTTP/2 with prior knowledge that the server supports it.
+    ///
+    /// HTTP/2 is spoken from the start of the connection, without negotiating
+    /// it first. This allows talking to servers that only support HTTP/2 over
+    /// cleartext connections (h2c), such as many gRPC and internal services.
+    /// Requests to servers that do not support HTTP/2 will fail.
+    ///
+    /// Requires the `http2` feature.
+    pub const fn http2_prior_knowledge() -> Self {
+        VersionNegotiation(VersionFlag::Http2PriorKnowledge)
+    }
+}
+
+impl Default for VersionNegotiation {
+    fn default() -> Self {
+        Self::latest_compatible()
+    }
+}
+
+impl From<http::Version> for VersionNegotiation {
+    fn from(version: http::Version) -> Self {
+        match version {
+            http::Version::HTTP_10 => Self::http10(),
+            http::Version::HTTP_11 => Self::http11(),
+            http::Version::HTTP_2 => Self::http2(),
+            _ => Self::latest_compatible(),
+        }
+    }
+}
+
+impl SetOpt for VersionNegotiation {
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        easy.http_version(match self.0 {
+            VersionFlag::LatestCompatible => curl::easy::HttpVersion::V2TLS,
+            VersionFlag::Http10 => curl::easy::HttpVersion::V10,
+            VersionFlag::Http11 => curl::easy::HttpVersion::V11,
+            VersionFlag::Http2 => curl::easy::HttpVersion::V2,
+            VersionFlag::Http2PriorKnowledge => curl::easy::HttpVersion::V2PriorKnowledge,
+        })
+    }
+}
+
+/// Which IP versions may be used to connect to servers.
+///
+/// The default is [`IpVersion::Any`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IpVersion {
+    /// Use both IPv4 and IPv6 addresses. If a host has addresses of both
+    /// versions, connection attempts are raced using Happy Eyeballs, starting
+    /// with the version of the first address returned by the resolver.
+    Any,
+    /// Only use IPv4 addresses.
+    V4,
+    /// Only use IPv6 addresses.
+    V6,
+}
+
+impl Default for IpVersion {
+    fn default() -> Self {
+        IpVersion::Any
+    }
+}
+
+impl SetOpt for IpVersion {
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        easy.ip_resolve(match self {
+            IpVersion::Any => curl::easy::IpResolve::Any,
+            IpVersion::V4 => curl::easy::IpResolve::V4,
+            IpVersion::V6 => curl::easy::IpResolve::V6,
+        })
+    }
+}
+
+/// A local network interface or address to bind outgoing connections to.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::config::NetworkInterface;
+/// use std::net::IpAddr;
+///
+/// // Send traffic through a specific interface.
+/// let interface = NetworkInterface::name("eth1");
+///
+/// // Send traffic from a specific local address.
+/// let interface = NetworkInterface::from(IpAddr::from([192, 168, 1, 2]));
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NetworkInterface(Option<String>);
+
+impl NetworkInterface {
+    /// Let the operating system choose the interface, as it does by default.
+    pub fn any() -> Self {
+        NetworkInterface(None)
+    }
+
+    /// Bind to the interface with the given name, such as `eth0`.
+    pub fn name(name: impl AsRef<str>) -> Self {
+        NetworkInterface(Some(format!("if!{}", name.as_ref())))
+    }
+}
+
+impl Default for NetworkInterface {
+    fn default() -> Self {
+        Self::any()
+    }
+}
+
+impl From<IpAddr> for NetworkInterface {
+    fn from(addr: IpAddr) -> Self {
+        NetworkInterface(Some(format!("host!{}", addr)))
+    }
+}
+
+impl SetOpt for NetworkInterface {
+    #[allow(unsafe_code)]
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        match self.0.as_ref() {
+            Some(interface) => easy.interface(interface),
+            // The safe API doesn't let us reset the option.
+            None => unsafe {
+                match curl_sys::curl_easy_setopt(
+                    easy.raw(),
+                    curl_sys::CURLOPT_INTERFACE,
+                    std::ptr::null::<std::os::raw::c_char>(),
+                ) {
+                    curl_sys::CURLE_OK => Ok(()),
+                    code => Err(curl::Error::new(code)),
+                }
+            },
+        }
+    }
+}
+
+/// Range of local ports to bind outgoing connections to.
+#[derive(Clone, Debug)]
+pub(crate) struct LocalPorts(pub(crate) RangeInclusive<u16>);
+
+impl SetOpt for LocalPorts {
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        let start = *self.0.start();
+
+        // An empty range would silently become a range of just its start.
+        if start > *self.0.end() {
+            return Err(curl::Error::new(curl_sys::CURLE_BAD_FUNCTION_ARGUMENT));
+        }
+
+        let count = self.0.end().saturating_sub(start).saturating_add(1);
+
+        easy.local_port(start)?;
+        easy.local_port_range(count)
+    }
+}
+
+/// Delay before racing a connection attempt using the other IP version than
+/// the first address returned by the resolver.
+#[derive(Clone, Debug)]
+pub(crate) struct HappyEyeballsTimeout(pub(crate) Duration);
+
+impl SetOpt for HappyEyeballsTimeout {
+    #[allow(unsafe_code)]
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        // Not yet exposed by curl-sys.
+        const CURLOPT_HAPPY_EYEBALLS_TIMEOUT_MS: curl_sys::CURLoption =
+            curl_sys::CURLOPTTYPE_LONG + 271;
+
+        let millis = self.0.as_millis() as std::os::raw::c_long;
+
+        unsafe {
+            match curl_sys::curl_easy_setopt(easy.raw(), CURLOPT_HAPPY_EYEBALLS_TIMEOUT_MS, millis)
+            {
+                curl_sys::CURLE_OK => Ok(()),
+                code => Err(curl::Error::new(code)),
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct TcpNoDelay(pub(crate) bool);
+
+impl SetOpt for TcpNoDelay {
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        easy.tcp_nodelay(self.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct AutoReferer;
+
+impl SetOpt for AutoReferer {
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        easy.autoreferer(true)
+    }
+}
+
+/// Minimum average speed of a transfer in bytes per second, and the amount of
+/// time it may stay below it. Enforced by the request handler rather than by
+/// curl, so that time spent waiting for the response body to be read is not
+/// mistaken for a slow connection.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct LowSpeedLimit(pub(crate) u64, pub(crate) Duration);
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct MaxUploadSpeed(pub(crate) u64);
+
+impl SetOpt for MaxUploadSpeed {
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        easy.max_send_speed(self.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct MaxDownloadSpeed(pub(crate) u64);
+
+impl SetOpt for MaxDownloadSpeed {
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        easy.max_recv_speed(self.0)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct DnsServers(pub(crate) Vec<SocketAddr>);
+
+impl FromIterator<SocketAddr> for DnsServers {
+    fn from_iter<I: IntoIterator<Item = SocketAddr>>(iter: I) -> Self {
+        DnsServers(Vec::from_iter(iter))
+    }
+}
+
+impl SetOpt for DnsServers {
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        let dns_string = self.0
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+
+        // DNS servers should not be hard error.
+        if let Err(e) = easy.dns_servers(&dns_string) {
+            log::warn!("DNS servers could not be configured: {}", e);
+        }
+
+        Ok(())
+    }
+}
+
+/// DNS caching configuration.
+///
+/// The default configuration is for caching to be enabled with a 60 second
+/// entry timeout.
+///
+/// See [`HttpClientBuilder::dns_cache`](crate::HttpClientBuilder::dns_cache)
+/// for configuring a client's DNS cache.
+#[derive(Clone, Debug)]
+pub enum DnsCache {
+    /// Disable DNS caching entirely.
+    Disable,
+
+    /// Enable DNS caching and keep entries in the cache for the given duration.
+    ///
+    /// Curl only supports whole seconds, so the duration is rounded up.
+    Timeout(Duration),
+
+    /// Enable DNS caching and cache entries forever.
+    Forever,
+}
+
+impl Default for DnsCache {
+    fn default() -> Self {
+        // Match curl's default.
+        Duration::from_secs(60).into()
+    }
+}
+
+impl From<Duration> for DnsCache {
+    fn from(duration: Duration) -> Self {
+        DnsCache::Timeout(duration)
+    }
+}
+
+impl SetOpt for DnsCache {
+    #[allow(unsafe_code)]
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        let value: std::os::raw::c_long = match self {
+            DnsCache::Disable => 0,
+            // Curl only supports whole seconds, and zero would disable the
+            // cache, so round up.
+            DnsCache::Timeout(duration) if duration.subsec_nanos() > 0 => {
+                duration.as_secs() as std::os::raw::c_long + 1
+            }
+            DnsCache::Timeout(duration) => duration.as_secs() as std::os::raw::c_long,
+            DnsCache::Forever => -1,
+        };
+
+        // Use unsafe API, because safe API doesn't let us set to -1.
+        unsafe {
+            match curl_sys::curl_easy_setopt(easy.raw(), curl_sys::CURLOPT_DNS_CACHE_TIMEOUT, value) {
+                curl_sys::CURLE_OK => Ok(()),
+                code => Err(curl::Error::new(code)),
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Proxy(pub(crate) http::Uri);
+
+impl SetOpt for Proxy {
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        easy.proxy(&format!("{}", self.0))
+    }
+}
+
+/// Configuration for resolving host names using DNS over HTTPS (DoH).
+///
+/// Name lookups are sent as HTTPS requests to the given server instead of
+/// going through the system resolver, which keeps them private from the local
+/// network. By default, requests fail if a name cannot be resolved this way.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::config::DnsOverHttps;
+///
+/// let doh = DnsOverHttps::new("https://dns.example/dns-query")
+///     .fallback_to_system(true);
+/// ```
+#[derive(Clone, Debug)]
+pub struct DnsOverHttps {
+    uri: String,
+    pub(crate) fallback: bool,
+}
+
+impl DnsOverHttps {
+    /// Resolve names using the DoH server at the given URI.
+    pub fn new(uri: impl Into<String>) -> Self {
+        Self {
+            uri: uri.into(),
+            fallback: false,
+        }
+    }
+
+    /// Set whether requests should be sent again using the system resolver if
+    /// a name could not be resolved using DoH.
+    ///
+    /// This trades privacy for availability, and is disabled by default.
+    pub fn fallback_to_system(mut self, fallback: bool) -> Self {
+        self.fallback = fallback;
+        self
+    }
+}
+
+impl From<&str> for DnsOverHttps {
+    fn from(uri: &str) -> Self {
+        Self::new(uri)
+    }
+}
+
+impl From<String> for DnsOverHttps {
+    fn from(uri: String) -> Self {
+        Self::new(uri)
+    }
+}
+
+impl From<http::Uri> for DnsOverHttps {
+    fn from(uri: http::Uri) -> Self {
+        Self::new(uri.to_string())
+    }
+}
+
+impl SetOpt for DnsOverHttps {
+    #[allow(unsafe_code)]
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        // Not yet exposed by curl-sys.
+        const CURLOPT_DOH_URL: curl_sys::CURLoption = curl_sys::CURLOPTTYPE_OBJECTPOINT + 279;
+
+        let uri = std::ffi::CString::new(self.uri.as_str())
+            .map_err(|_| curl::Error::new(curl_sys::CURLE_URL_MALFORMAT))?;
+
+        // Curl makes its own copy of the string.
+        unsafe {
+            match curl_sys::curl_easy_setopt(easy.raw(), CURLOPT_DOH_URL, uri.as_ptr()) {
+                curl_sys::CURLE_OK => Ok(()),
+                code => Err(curl::Error::new(code)),
+            }
+        }
+    }
+}
+
+/// Marks a request as falling back to the system resolver, skipping DNS over
+/// HTTPS.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SystemDns;
+
+/// A mapping of host names to fixed addresses, used instead of resolving the
+/// names through DNS.
+///
+/// Connections are still made using the original host name, so the `Host`
+/// header and TLS server name are unchanged. This is useful for testing, or
+/// for directing requests to a specific server behind a load balancer.
+///
+/// Curl stores mapped addresses permanently in the DNS cache shared by all
+/// requests of a client. A mapping set for a single request with
+/// [`RequestBuilderExt::dns_resolve`](crate::RequestBuilderExt::dns_resolve)
+/// therefore also applies to any later requests from the same client to the
+/// same host name and port, until another mapping for them replaces it, or
+/// the cache is discarded along with the client's idle connections (see
+/// [`HttpClient::prune_idle`](crate::HttpClient::prune_idle) and
+/// [`HttpClient::invalidate_connections`](crate::HttpClient::invalidate_connections)).
+///
+/// # Examples
+///
+/// ```
+/// use isahc::config::ResolveMap;
+/// use std::net::IpAddr;
+///
+/// let map = ResolveMap::new()
+///     .add("example.org", 443, [127, 0, 0, 1])
+///     .add("example.org", 80, "::1".parse::<IpAddr>().unwrap());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ResolveMap(Vec<String>);
+
+impl ResolveMap {
+    /// Create a new, empty mapping.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Map the given host name and port to a fixed address.
+    pub fn add(self, host: impl AsRef<str>, port: u16, addr: impl Into<IpAddr>) -> Self {
+        self.add_all(host.as_ref(), port, Some(addr.into()))
+    }
+
+    /// Map the given host name and port to a list of addresses, which are
+    /// tried in order.
+    pub(crate) fn add_all(
+        mut self,
+        host: &str,
+        port: u16,
+        addrs: impl IntoIterator<Item = IpAddr>,
+    ) -> Self {
+        let addrs = addrs
+            .into_iter()
+            .map(|addr| match addr {
+                IpAddr::V4(addr) => addr.to_string(),
+                IpAddr::V6(addr) => format!("[{}]", addr),
+            })
+            .collect::<Vec<_>>();
+
+        let entry = format!("{}:{}:{}", host, port, addrs.join(","));
+        self.0.push(entry);
+        self
+    }
+}
+
+impl SetOpt for ResolveMap {
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        let mut list = curl::easy::List::new();
+
+        for entry in self.0.iter() {
+            list.append(entry)?;
+        }
+
+        easy.resolve(list)
+    }
+}
+
+/// Path of a Unix domain socket to connect to instead of the host.
+#[derive(Clone, Debug)]
+pub(crate) struct UnixSocket(pub(crate) PathBuf);
+
+impl SetOpt for UnixSocket {
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        easy.unix_socket(&self.0.to_string_lossy())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct SslCiphers(pub(crate) Vec<String>);
+
+impl FromIterator<String> for SslCiphers {
+    fn from_iter<I: IntoIterator<Item = String>>(iter: I) -> Self {
+        SslCiphers(Vec::from_iter(iter))
+    }
+}
+
+impl SetOpt for SslCiphers {
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        easy.ssl_cipher_list(&self.0.join(":"))
+    }
+}
+
+#[derive(Clone, Debug)]
 pub(crate) struct AllowUnsafeSsl(pub(crate) bool);
 
 impl SetOpt for AllowUnsafeSsl {
@@ -346,3 +1647,152 @@ impl SetOpt for AllowUnsafeSsl {
         easy.ssl_verify_host(!self.0)
     }
 }
+
+/// A file containing a certificate revocation list in PEM format.
+#[derive(Clone, Debug)]
+pub(crate) struct CrlFile(pub(crate) PathBuf);
+
+impl SetOpt for CrlFile {
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        easy.crlfile(&self.0)
+    }
+}
+
+/// Whether to resume earlier TLS sessions using session IDs or tickets.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SslSessionCache(pub(crate) bool);
+
+impl SetOpt for SslSessionCache {
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        let enable = if self.0 { 1 } else { 0 };
+
+        setopt_long(easy, curl_sys::CURLOPT_SSL_SESSIONID_CACHE, enable)
+    }
+}
+
+/// Whether to require a valid stapled OCSP response from the server.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct VerifyStatus(pub(crate) bool);
+
+impl SetOpt for VerifyStatus {
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        // Not yet exposed by curl-sys.
+        const CURLOPT_SSL_VERIFYSTATUS: curl_sys::CURLoption = curl_sys::CURLOPTTYPE_LONG + 232;
+
+        setopt_long(easy, CURLOPT_SSL_VERIFYSTATUS, if self.0 { 1 } else { 0 })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct AcceptInvalidHostnames(pub(crate) bool);
+
+impl SetOpt for AcceptInvalidHostnames {
+    fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        easy.ssl_verify_host(!self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NoopHandler;
+
+    impl curl::easy::Handler for NoopHandler {}
+
+    fn easy() -> curl::easy::Easy2<NoopHandler> {
+        curl::easy::Easy2::new(NoopHandler)
+    }
+
+    #[test]
+    fn proxy_tls_options_are_accepted() {
+        let mut easy = easy();
+
+        ProxyClientCertificate(ClientCertificate::PEM {
+            path: "client.pem".into(),
+            private_key: Some(PrivateKey::PEM {
+                path: "client.key".into(),
+                password: Some("secret".into()),
+            }),
+        })
+        .set_opt(&mut easy)
+        .unwrap();
+        ProxyClientCertificate(ClientCertificate::P12 {
+            path: "client.p12".into(),
+            password: None,
+        })
+        .set_opt(&mut easy)
+        .unwrap();
+        ProxyCaCertificate(CaCertificate::File("ca.pem".into()))
+            .set_opt(&mut easy)
+            .unwrap();
+        AllowUnsafeProxySsl(true).set_opt(&mut easy).unwrap();
+        AllowUnsafeProxySsl(false).set_opt(&mut easy).unwrap();
+    }
+
+    #[test]
+    fn ca_certificates_are_accepted() {
+        CaCertificate::File("ca.pem".into())
+            .set_opt(&mut easy())
+            .unwrap();
+
+        // Blobs require libcurl 7.77.
+        let blob = CaCertificate::Blob(b"-----BEGIN CERTIFICATE-----".to_vec());
+
+        if let Err(e) = blob.set_opt(&mut easy()) {
+            assert!(e.is_unknown_option(), "{}", e);
+        }
+    }
+
+    #[test]
+    fn pinned_public_key_is_accepted() {
+        PinnedPublicKey("sha256//SuFgq2SLZGhU4ZE0QXtyy6BiZgwID4mGH2MafKLpIXs=".into())
+            .set_opt(&mut easy())
+            .unwrap();
+
+        let error = PinnedPublicKey("sha256//\0".into())
+            .set_opt(&mut easy())
+            .unwrap_err();
+
+        assert_eq!(error.code(), curl_sys::CURLE_BAD_FUNCTION_ARGUMENT);
+    }
+
+    #[test]
+    fn tls_version_ranges_are_accepted() {
+        let mut easy = easy();
+
+        TlsVersions {
+            min: Some(TlsVersion::V1_2),
+            max: Some(TlsVersion::V1_3),
+        }
+        .set_opt(&mut easy)
+        .unwrap();
+        TlsVersions {
+            min: None,
+            max: Some(TlsVersion::V1_2),
+        }
+        .set_opt(&mut easy)
+        .unwrap();
+        TlsVersions {
+            min: Some(TlsVersion::V1_0),
+            max: None,
+        }
+        .set_opt(&mut easy)
+        .unwrap();
+    }
+
+    #[test]
+    fn verify_status_is_accepted_if_supported() {
+        // Not every TLS engine can check OCSP responses.
+        if let Err(e) = VerifyStatus(true).set_opt(&mut easy()) {
+            assert!(e.is_not_built_in(), "{}", e);
+        }
+
+        VerifyStatus(false).set_opt(&mut easy()).unwrap();
+    }
+
+    #[test]
+    fn crl_file_is_accepted() {
+        CrlFile("crl.pem".into()).set_opt(&mut easy()).unwrap();
+    }
+}
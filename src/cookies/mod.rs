@@ -1,6 +1,8 @@
 //! Cookie state management.
 //!
 //! This module provides a cookie jar implementation conforming to RFC 6265.
+//! Cookie jars can be saved to and loaded from files in the Netscape cookie
+//! file format used by curl and many other tools.
 //!
 //! Everything in this module requires the `cookies` feature to be enabled.
 
@@ -13,14 +15,22 @@ use chrono::prelude::*;
 use chrono::Duration;
 use http::{Request, Response, Uri};
 use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
 use std::net::{Ipv4Addr, Ipv6Addr};
-use std::sync::RwLock;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[cfg(feature = "psl")]
 mod psl;
 
+/// Prefix of lines in a Netscape cookie file that hold an HttpOnly cookie.
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
 /// Information stored about an HTTP cookie.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Cookie {
     /// The name of the cookie.
     name: String,
@@ -32,6 +42,8 @@ pub struct Cookie {
     path: String,
     /// True if the cookie is marked as secure (limited in scope to HTTPS).
     secure: bool,
+    /// True if the cookie is marked as HttpOnly (not exposed to scripts).
+    http_only: bool,
     /// True if the cookie is a host-only cookie (i.e. the request's host must
     /// exactly match the domain of the cookie).
     host_only: bool,
@@ -41,9 +53,13 @@ pub struct Cookie {
 }
 
 impl Cookie {
-    /// Parse a cookie from a Set-Cookie header value, within the context of the
-    /// given URI.
-    fn parse(header: &str, uri: &Uri) -> Option<Self> {
+    /// Parse a cookie from a `Set-Cookie` header value, within the context of
+    /// the given URI.
+    ///
+    /// Returns `None` if the header is invalid, or if the server at the given
+    /// URI would not be allowed to set the cookie. Parsed cookies can be added
+    /// to a jar manually using [`CookieJar::add`].
+    pub fn parse(header: &str, uri: &Uri) -> Option<Self> {
         let mut attributes = header
             .split(';')
             .map(str::trim)
@@ -56,11 +72,12 @@ impl Cookie {
         let mut cookie_domain = None;
         let mut cookie_path = None;
         let mut cookie_secure = false;
+        let mut cookie_http_only = false;
         let mut cookie_expiration = None;
 
         // Look for known attribute names and parse them. Note that there are
         // multiple attributes in the spec that we don't parse right now because
-        // we do not care about them, including HttpOnly and SameSite.
+        // we do not care about them, including SameSite.
         for mut attribute in attributes {
             let name = attribute.next()?;
             let value = attribute.next();
@@ -87,6 +104,8 @@ impl Cookie {
                 cookie_path = value.map(ToOwned::to_owned);
             } else if name.eq_ignore_ascii_case("Secure") {
                 cookie_secure = true;
+            } else if name.eq_ignore_ascii_case("HttpOnly") {
+                cookie_http_only = true;
             }
         }
 
@@ -114,17 +133,23 @@ impl Cookie {
                 return None;
             }
 
-            // Check the PSL for bad domain suffixes if available.
+            // Check the PSL for bad domain suffixes if available. A server
+            // whose host is itself a public suffix may still set a host-only
+            // cookie for itself.
             // https://tools.ietf.org/html/rfc6265#section-5.3.5
             #[cfg(feature = "psl")]
             {
                 if psl::is_public_suffix(domain) {
-                    log::warn!(
-                        "cookie '{}' dropped, setting cookies for domain '{}' is not allowed",
-                        cookie_name,
-                        domain
-                    );
-                    return None;
+                    if domain.eq_ignore_ascii_case(uri.host()?) {
+                        cookie_domain = None;
+                    } else {
+                        log::warn!(
+                            "cookie '{}' dropped, setting cookies for domain '{}' is not allowed",
+                            cookie_name,
+                            domain
+                        );
+                        return None;
+                    }
                 }
             }
         }
@@ -133,6 +158,7 @@ impl Cookie {
             name: cookie_name,
             value: cookie_value,
             secure: cookie_secure,
+            http_only: cookie_http_only,
             expiration: cookie_expiration,
             host_only: cookie_domain.is_none(),
             domain: cookie_domain.or_else(|| uri.host().map(ToOwned::to_owned))?,
@@ -140,6 +166,110 @@ impl Cookie {
         })
     }
 
+    /// Get the name of the cookie.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the value of the cookie.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Get the domain the cookie belongs to.
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+
+    /// Get the path prefix the cookie belongs to.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Check whether the cookie is only sent over HTTPS.
+    pub fn is_secure(&self) -> bool {
+        self.secure
+    }
+
+    /// Check whether the cookie is marked as HttpOnly. Such cookies are still
+    /// sent with requests, but should not be exposed to scripts.
+    pub fn is_http_only(&self) -> bool {
+        self.http_only
+    }
+
+    /// Check whether the cookie is only sent to exactly its domain, and not to
+    /// its subdomains.
+    pub fn is_host_only(&self) -> bool {
+        self.host_only
+    }
+
+    /// Get the time when the cookie expires. Session cookies do not have an
+    /// expiration time, and are kept for as long as the jar exists.
+    pub fn expiration(&self) -> Option<SystemTime> {
+        self.expiration.map(|time| {
+            let seconds = time.timestamp();
+
+            if seconds >= 0 {
+                UNIX_EPOCH + std::time::Duration::from_secs(seconds as u64)
+            } else {
+                UNIX_EPOCH - std::time::Duration::from_secs(seconds.abs() as u64)
+            }
+        })
+    }
+
+    /// Parse a cookie from a line of a Netscape cookie file.
+    fn parse_netscape(line: &str) -> Option<Self> {
+        // HttpOnly cookies are marked with a prefix that makes older parsers
+        // treat the line as a comment.
+        let http_only = line.starts_with(HTTP_ONLY_PREFIX);
+        let line = if http_only {
+            &line[HTTP_ONLY_PREFIX.len()..]
+        } else {
+            line
+        };
+
+        let fields: Vec<&str> = line.split('\t').collect();
+
+        if fields.len() != 7 {
+            return None;
+        }
+
+        let expiration = match fields[4].parse::<i64>().ok()? {
+            // Session cookies are stored with an expiration time of zero.
+            0 => None,
+            seconds => Some(Utc.timestamp_opt(seconds, 0).single()?),
+        };
+
+        Some(Self {
+            name: fields[5].to_owned(),
+            value: fields[6].to_owned(),
+            domain: fields[0].trim_start_matches('.').to_lowercase(),
+            path: fields[2].to_owned(),
+            secure: fields[3].eq_ignore_ascii_case("TRUE"),
+            http_only,
+            host_only: !fields[1].eq_ignore_ascii_case("TRUE"),
+            expiration,
+        })
+    }
+
+    /// Format this cookie as a line of a Netscape cookie file.
+    fn to_netscape(&self) -> String {
+        let flag = |value: bool| if value { "TRUE" } else { "FALSE" };
+
+        format!(
+            "{}{}{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            if self.http_only { HTTP_ONLY_PREFIX } else { "" },
+            if self.host_only { "" } else { "." },
+            self.domain,
+            flag(!self.host_only),
+            self.path,
+            flag(self.secure),
+            self.expiration.map(|time| time.timestamp()).unwrap_or(0),
+            self.name,
+            self.value
+        )
+    }
+
     fn is_expired(&self) -> bool {
         match self.expiration {
             Some(time) => time < Utc::now(),
@@ -229,14 +359,120 @@ impl Cookie {
 
 /// Provides automatic cookie session management using an in-memory cookie
 /// store.
-#[derive(Debug, Default)]
+///
+/// Cloning a cookie jar is cheap, and the clone shares the same cookies. This
+/// makes it possible to inspect and change the cookies of a client by giving
+/// it a clone of a jar with
+/// [`HttpClientBuilder::cookie_jar`](crate::HttpClientBuilder::cookie_jar).
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::cookies::{Cookie, CookieJar};
+/// use isahc::prelude::*;
+///
+/// let jar = CookieJar::new();
+/// let uri = "https://example.org/".parse()?;
+///
+/// // Add a cookie before making any requests.
+/// jar.add(Cookie::parse("session=abc123; Path=/", &uri).into_iter());
+///
+/// let client = HttpClient::builder()
+///     .cookie_jar(jar.clone())
+///     .build()?;
+///
+/// client.get(&uri)?;
+///
+/// for cookie in jar.cookies_for_domain("example.org") {
+///     println!("{}={}", cookie.name(), cookie.value());
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug, Default)]
 pub struct CookieJar {
     /// A map of cookies indexed by a string of the format
     /// `{domain}.{path}.{name}`.
-    cookies: RwLock<HashMap<String, Cookie>>,
+    cookies: Arc<RwLock<HashMap<String, Cookie>>>,
 }
 
 impl CookieJar {
+    /// Create a new, empty cookie jar.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a cookie jar from a file in the Netscape cookie file format.
+    ///
+    /// Lines that cannot be parsed as a cookie are skipped, as are cookies
+    /// that have already expired.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let jar = Self::default();
+
+        jar.add(
+            fs::read_to_string(path)?
+                .lines()
+                .map(|line| line.trim_end_matches('\r'))
+                .filter(|line| {
+                    !line.is_empty()
+                        && (!line.starts_with('#') || line.starts_with(HTTP_ONLY_PREFIX))
+                })
+                .filter_map(|line| {
+                    Cookie::parse_netscape(line).or_else(|| {
+                        log::warn!("skipping invalid line in cookie file: {}", line);
+                        None
+                    })
+                }),
+        );
+
+        Ok(jar)
+    }
+
+    /// Save all unexpired cookies in this jar to a file in the Netscape cookie
+    /// file format, replacing the file if it already exists.
+    ///
+    /// The cookies are first written to a temporary file next to the given
+    /// path, which then replaces the file, so that the file is never left
+    /// half written.
+    ///
+    /// Session cookies are saved as well, so that they can be restored by a
+    /// later process continuing the same session.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let jar = self.cookies.read().unwrap();
+        let mut lines: Vec<String> = jar
+            .values()
+            .filter(|cookie| !cookie.is_expired())
+            .map(Cookie::to_netscape)
+            .collect();
+
+        lines.sort();
+
+        let path = path.as_ref();
+        let mut temp_name = path.file_name().map(OsString::from).unwrap_or_default();
+        temp_name.push(".tmp");
+        let temp_path = path.with_file_name(temp_name);
+
+        let write = || -> io::Result<()> {
+            let mut writer = BufWriter::new(File::create(&temp_path)?);
+
+            writeln!(writer, "# Netscape HTTP Cookie File")?;
+
+            for line in &lines {
+                writeln!(writer, "{}", line)?;
+            }
+
+            writer.flush()?;
+            writer.get_ref().sync_all()
+        };
+
+        match write().and_then(|_| fs::rename(&temp_path, path)) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                fs::remove_file(&temp_path).ok();
+                Err(e)
+            }
+        }
+    }
+
     /// Add all the cookies in the given iterator to the cookie jar.
     pub fn add(&self, cookies: impl Iterator<Item = Cookie>) {
         let mut jar = self.cookies.write().unwrap();
@@ -249,6 +485,76 @@ impl CookieJar {
         jar.retain(|_, cookie| !cookie.is_expired());
     }
 
+    /// Get all unexpired cookies in the jar.
+    pub fn cookies(&self) -> Vec<Cookie> {
+        self.find(|_| true)
+    }
+
+    /// Get the unexpired cookies that belong to the given domain, including
+    /// cookies set for a parent domain that apply to it.
+    pub fn cookies_for_domain(&self, domain: &str) -> Vec<Cookie> {
+        self.find(|cookie| {
+            if cookie.host_only {
+                cookie.domain.eq_ignore_ascii_case(domain)
+            } else {
+                Cookie::domain_matches(domain, &cookie.domain)
+            }
+        })
+    }
+
+    /// Get the cookies that would be sent with a request to the given URI,
+    /// taking the domain, path and whether the cookies are secure into
+    /// account.
+    pub fn cookies_for_uri(&self, uri: &Uri) -> Vec<Cookie> {
+        self.find(|cookie| cookie.matches(uri))
+    }
+
+    /// Remove the cookie with the given domain, path and name, returning it if
+    /// it was present.
+    pub fn remove(&self, domain: &str, path: &str, name: &str) -> Option<Cookie> {
+        let key = format!("{}.{}.{}", domain.to_lowercase(), path, name);
+
+        self.cookies.write().unwrap().remove(&key)
+    }
+
+    /// Remove all cookies for which the given predicate returns `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::cookies::CookieJar;
+    ///
+    /// let jar = CookieJar::new();
+    ///
+    /// // Forget all session cookies.
+    /// jar.retain(|cookie| cookie.expiration().is_some());
+    /// ```
+    pub fn retain(&self, mut f: impl FnMut(&Cookie) -> bool) {
+        self.cookies
+            .write()
+            .unwrap()
+            .retain(|_, cookie| !cookie.is_expired() && f(cookie));
+    }
+
+    /// Remove all cookies from the jar.
+    pub fn clear(&self) {
+        self.cookies.write().unwrap().clear();
+    }
+
+    fn find(&self, f: impl Fn(&Cookie) -> bool) -> Vec<Cookie> {
+        let mut cookies: Vec<Cookie> = self
+            .cookies
+            .read()
+            .unwrap()
+            .values()
+            .filter(|cookie| !cookie.is_expired() && f(cookie))
+            .cloned()
+            .collect();
+
+        cookies.sort_by(|a, b| a.key().cmp(&b.key()));
+        cookies
+    }
+
     fn get_cookies(&self, uri: &Uri) -> Option<String> {
         let jar = self.cookies.read().unwrap();
 
@@ -310,6 +616,47 @@ impl Middleware for CookieJar {
     }
 }
 
+/// A cookie jar that is loaded from a file when created, and saved back to
+/// the file when dropped.
+#[derive(Debug)]
+pub(crate) struct PersistentCookieJar {
+    jar: CookieJar,
+    path: PathBuf,
+}
+
+impl PersistentCookieJar {
+    pub(crate) fn open(path: PathBuf) -> Self {
+        let jar = match CookieJar::load(&path) {
+            Ok(jar) => jar,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => CookieJar::default(),
+            Err(e) => {
+                log::warn!("failed to load cookies from {}: {}", path.display(), e);
+                CookieJar::default()
+            }
+        };
+
+        Self { jar, path }
+    }
+}
+
+impl Middleware for PersistentCookieJar {
+    fn filter_request(&self, request: Request<Body>) -> Request<Body> {
+        self.jar.filter_request(request)
+    }
+
+    fn filter_response(&self, response: Response<Body>) -> Response<Body> {
+        self.jar.filter_response(response)
+    }
+}
+
+impl Drop for PersistentCookieJar {
+    fn drop(&mut self) {
+        if let Err(e) = self.jar.save(&self.path) {
+            log::warn!("failed to save cookies to {}: {}", self.path.display(), e);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -353,9 +700,20 @@ mod tests {
         if cfg!(feature = "psl") {
             // wi.us is a public suffix
             assert!(parse_cookie("foo=bar; domain=wi.us", "https://www.state.wi.us").is_none());
+            assert!(parse_cookie("foo=bar; domain=co.uk", "https://evil.co.uk").is_none());
+            assert!(parse_cookie("foo=bar; domain=evil.co.uk", "https://evil.co.uk").is_some());
         }
     }
 
+    #[cfg(feature = "psl")]
+    #[test]
+    fn public_suffix_host_may_set_host_only_cookies() {
+        let cookie = parse_cookie("foo=bar; domain=github.io", "https://github.io").unwrap();
+
+        assert_eq!(cookie.domain, "github.io");
+        assert!(cookie.host_only);
+    }
+
     #[test]
     fn domain_matches() {
         for case in &[
@@ -431,4 +789,162 @@ mod tests {
 
         assert_eq!(jar.get_cookies(&uri), None);
     }
+
+    #[test]
+    fn netscape_format_round_trip() {
+        let uri: Uri = "https://example.com/foo/bar".parse().unwrap();
+        let cookies = vec![
+            Cookie::parse("foo=bar", &uri).unwrap(),
+            Cookie::parse(
+                "baz=123; Domain=example.com; Path=/; Secure; Expires=Wed, 21 Oct 2099 07:28:00 GMT",
+                &uri,
+            )
+            .unwrap(),
+            Cookie::parse("session=1; HttpOnly", &uri).unwrap(),
+        ];
+
+        for cookie in cookies {
+            let parsed = Cookie::parse_netscape(&cookie.to_netscape()).unwrap();
+
+            assert_eq!(parsed.name, cookie.name);
+            assert_eq!(parsed.value, cookie.value);
+            assert_eq!(parsed.domain, cookie.domain);
+            assert_eq!(parsed.path, cookie.path);
+            assert_eq!(parsed.secure, cookie.secure);
+            assert_eq!(parsed.http_only, cookie.http_only);
+            assert_eq!(parsed.host_only, cookie.host_only);
+            assert_eq!(parsed.expiration, cookie.expiration);
+        }
+    }
+
+    #[test]
+    fn parse_netscape_lines() {
+        let cookie =
+            Cookie::parse_netscape("#HttpOnly_.example.com\tTRUE\t/\tFALSE\t0\tfoo\tbar").unwrap();
+
+        assert_eq!(cookie.domain, "example.com");
+        assert!(!cookie.host_only);
+        assert!(cookie.http_only);
+        assert!(cookie.expiration.is_none());
+
+        assert!(Cookie::parse_netscape("example.com\tFALSE\t/\tFALSE\tsoon\tfoo\tbar").is_none());
+        assert!(Cookie::parse_netscape("example.com\tFALSE\t/").is_none());
+    }
+
+    #[test]
+    fn save_and_load_jar() {
+        let uri: Uri = "https://example.com/".parse().unwrap();
+        let path = std::env::temp_dir().join(format!("isahc-cookies-{}.txt", std::process::id()));
+        let jar = CookieJar::default();
+
+        jar.add(Cookie::parse("foo=bar", &uri).into_iter());
+        jar.add(Cookie::parse("old=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT", &uri).into_iter());
+        jar.save(&path).unwrap();
+
+        let loaded = CookieJar::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.get_cookies(&uri).unwrap(), "foo=bar");
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let uri: Uri = "https://www.example.com/app/page".parse().unwrap();
+        let path = std::env::temp_dir().join(format!(
+            "isahc-cookies-round-trip-{}.txt",
+            std::process::id()
+        ));
+        let jar = CookieJar::new();
+
+        jar.add(
+            vec![
+                Cookie::parse("a=1; HttpOnly", &uri).unwrap(),
+                Cookie::parse("b=2; Domain=example.com; Path=/; Secure", &uri).unwrap(),
+                Cookie::parse("c=3; Expires=Wed, 21 Oct 2099 07:28:00 GMT", &uri).unwrap(),
+            ]
+            .into_iter(),
+        );
+
+        // Saving replaces any previous contents of the file.
+        fs::write(&path, "# stale contents\n").unwrap();
+        jar.save(&path).unwrap();
+
+        let mut temp_name = path.file_name().unwrap().to_owned();
+        temp_name.push(".tmp");
+        assert!(!path.with_file_name(temp_name).exists());
+
+        let loaded = CookieJar::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let mut cookies = loaded.cookies();
+        cookies.sort_by(|a, b| a.name.cmp(&b.name));
+
+        assert_eq!(cookies.len(), 3);
+
+        assert_eq!(cookies[0].name, "a");
+        assert_eq!(cookies[0].domain, "www.example.com");
+        assert_eq!(cookies[0].path, "/app");
+        assert!(cookies[0].http_only);
+        assert!(cookies[0].host_only);
+        assert!(cookies[0].expiration.is_none());
+
+        assert_eq!(cookies[1].name, "b");
+        assert_eq!(cookies[1].domain, "example.com");
+        assert_eq!(cookies[1].path, "/");
+        assert!(cookies[1].secure);
+        assert!(!cookies[1].http_only);
+        assert!(!cookies[1].host_only);
+
+        assert_eq!(cookies[2].name, "c");
+        assert_eq!(
+            cookies[2].expiration,
+            Some(Utc.ymd(2099, 10, 21).and_hms(7, 28, 0))
+        );
+    }
+
+    #[test]
+    fn inspect_and_remove_cookies() {
+        let jar = CookieJar::new();
+        let uri: Uri = "https://www.example.com/app/page".parse().unwrap();
+
+        jar.add(
+            vec![
+                Cookie::parse("a=1", &uri).unwrap(),
+                Cookie::parse("b=2; Domain=example.com; Path=/", &uri).unwrap(),
+                Cookie::parse("c=3; Path=/other", &uri).unwrap(),
+            ]
+            .into_iter(),
+        );
+
+        let names = |cookies: Vec<Cookie>| {
+            cookies
+                .iter()
+                .map(|cookie| cookie.name().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(jar.cookies().len(), 3);
+        assert_eq!(names(jar.cookies_for_domain("example.com")), vec!["b"]);
+        assert_eq!(jar.cookies_for_domain("www.example.com").len(), 3);
+        assert_eq!(names(jar.cookies_for_uri(&uri)), vec!["b", "a"]);
+
+        assert!(jar.remove("example.com", "/", "b").is_some());
+        assert!(jar.remove("example.com", "/", "b").is_none());
+
+        jar.retain(|cookie| cookie.path() != "/other");
+        assert_eq!(names(jar.cookies()), vec!["a"]);
+
+        jar.clear();
+        assert!(jar.cookies().is_empty());
+    }
+
+    #[test]
+    fn clones_share_cookies() {
+        let jar = CookieJar::new();
+        let uri: Uri = "https://example.com/".parse().unwrap();
+
+        jar.clone().add(Cookie::parse("foo=bar", &uri).into_iter());
+
+        assert_eq!(jar.get_cookies(&uri).unwrap(), "foo=bar");
+    }
 }
@@ -1,5 +1,7 @@
 //! Types for error handling.
 
+use http::{HeaderMap, StatusCode};
+use std::borrow::Cow;
 use std::error::Error as StdError;
 use std::fmt;
 use std::io;
@@ -9,10 +11,21 @@ use std::io;
 pub enum Error {
     /// The request was aborted before it could be completed.
     Aborted,
+    /// The request waited longer than the configured acquire timeout for the
+    /// client to have room to send it.
+    AcquireTimeout,
     /// A problem occurred with the local certificate.
     BadClientCertificate(Option<String>),
     /// The server certificate could not be validated.
     BadServerCertificate(Option<String>),
+    /// The server responded with a `4xx` or `5xx` status code. Only returned
+    /// by [`ResponseExt::error_for_status`](crate::ResponseExt::error_for_status).
+    BadStatus(StatusError),
+    /// The client has been shut down and no longer accepts requests.
+    ClientShutdown,
+    /// The request was canceled with a
+    /// [`CancellationToken`](crate::CancellationToken).
+    Canceled,
     /// Failed to connect to the server.
     ConnectFailed,
     /// Couldn't resolve host name.
@@ -35,18 +48,98 @@ pub enum Error {
     NoResponse,
     /// The server does not support or accept range requests.
     RangeRequestUnsupported,
+    /// A redirect was rejected by a redirect handler, for the given reason.
+    RedirectRejected(String),
     /// An error occurred while writing the request body.
     RequestBodyError(Option<String>),
     /// An error occurred while reading the response body.
     ResponseBodyError(Option<String>),
+    /// The response header was larger than the configured maximum size, or
+    /// had more fields than allowed.
+    ResponseHeadersTooLarge,
+    /// The response body was larger than the configured maximum size.
+    ResponseTooLarge,
     /// Failed to connect over a secure socket.
     SSLConnectFailed(Option<String>),
     /// An error ocurred in the secure socket engine.
     SSLEngineError(Option<String>),
-    /// An ongoing request took longer than the configured timeout time.
-    Timeout,
+    /// An ongoing request took longer than the configured timeout time, or
+    /// a phase of the request took longer than the timeout for that phase.
+    ///
+    /// Contains the phase the request was in when time ran out, if known.
+    /// This is a breaking change from 0.7, where `Timeout` had no fields;
+    /// match on `Timeout(_)` to ignore the phase.
+    Timeout(Option<Phase>),
     /// Number of redirects hit the maximum amount.
     TooManyRedirects,
+    /// The transfer was slower than the configured low speed limit for too
+    /// long.
+    TransferTooSlow,
+}
+
+impl Error {
+    /// Get a stable, machine-readable code identifying the kind of error.
+    ///
+    /// Unlike the `Display` output, these codes are guaranteed not to change
+    /// between releases, and are suitable for logging, metrics, or mapping
+    /// failures onto your own error types.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// assert_eq!(isahc::Error::Timeout(None).code(), "timeout");
+    /// ```
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Aborted => "aborted",
+            Error::AcquireTimeout => "acquire_timeout",
+            Error::BadClientCertificate(_) => "bad_client_certificate",
+            Error::BadServerCertificate(_) => "bad_server_certificate",
+            Error::BadStatus(_) => "bad_status",
+            Error::ClientShutdown => "client_shutdown",
+            Error::Canceled => "canceled",
+            Error::ConnectFailed => "connect_failed",
+            Error::CouldntResolveHost => "couldnt_resolve_host",
+            Error::CouldntResolveProxy => "couldnt_resolve_proxy",
+            Error::Curl(_) => "curl",
+            Error::InvalidContentEncoding(_) => "invalid_content_encoding",
+            Error::InvalidCredentials => "invalid_credentials",
+            Error::InvalidHttpFormat(_) => "invalid_http_format",
+            Error::InvalidUtf8 => "invalid_utf8",
+            Error::Io(_) => "io",
+            Error::NoResponse => "no_response",
+            Error::RangeRequestUnsupported => "range_request_unsupported",
+            Error::RedirectRejected(_) => "redirect_rejected",
+            Error::RequestBodyError(_) => "request_body_error",
+            Error::ResponseBodyError(_) => "response_body_error",
+            Error::ResponseHeadersTooLarge => "response_headers_too_large",
+            Error::ResponseTooLarge => "response_too_large",
+            Error::SSLConnectFailed(_) => "ssl_connect_failed",
+            Error::SSLEngineError(_) => "ssl_engine_error",
+            Error::Timeout(_) => "timeout",
+            Error::TooManyRedirects => "too_many_redirects",
+            Error::TransferTooSlow => "transfer_too_slow",
+        }
+    }
+
+    /// Get the phase of the request that failed, if known.
+    ///
+    /// This is currently only known for timeouts.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::{Error, Phase};
+    ///
+    /// assert_eq!(Error::Timeout(Some(Phase::Connect)).phase(), Some(Phase::Connect));
+    /// assert_eq!(Error::ConnectFailed.phase(), None);
+    /// ```
+    pub fn phase(&self) -> Option<Phase> {
+        match self {
+            Error::Timeout(phase) => *phase,
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for Error {
@@ -59,8 +152,12 @@ impl StdError for Error {
     fn description(&self) -> &str {
         match self {
             Error::Aborted => "request aborted unexpectedly",
+            Error::AcquireTimeout => "request timed out waiting for the client to send it",
             Error::BadClientCertificate(Some(ref e)) => e,
             Error::BadServerCertificate(Some(ref e)) => e,
+            Error::BadStatus(_) => "server responded with an error status code",
+            Error::ClientShutdown => "client has been shut down",
+            Error::Canceled => "request was canceled",
             Error::ConnectFailed => "failed to connect to the server",
             Error::CouldntResolveHost => "couldn't resolve host name",
             Error::CouldntResolveProxy => "couldn't resolve proxy host name",
@@ -72,17 +169,21 @@ impl StdError for Error {
             Error::Io(ref e) => e.description(),
             Error::NoResponse => "server did not send a response",
             Error::RangeRequestUnsupported => "server does not support or accept range requests",
+            Error::RedirectRejected(ref e) => e,
             Error::RequestBodyError(Some(ref e)) => e,
             Error::ResponseBodyError(Some(ref e)) => e,
+            Error::ResponseHeadersTooLarge => "response header exceeded the maximum size",
+            Error::ResponseTooLarge => "response body exceeded the maximum size",
             Error::SSLConnectFailed(Some(ref e)) => e,
             Error::SSLEngineError(Some(ref e)) => e,
-            Error::Timeout => "request took longer than the configured timeout",
+            Error::Timeout(_) => "request took longer than the configured timeout",
             Error::TooManyRedirects => "max redirect limit exceeded",
+            Error::TransferTooSlow => "transfer speed was below the low speed limit for too long",
             _ => "unknown error",
         }
     }
 
-    fn cause(&self) -> Option<&dyn StdError> {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
         match self {
             Error::InvalidHttpFormat(e) => Some(e),
             Error::Io(e) => Some(e),
@@ -91,12 +192,82 @@ impl StdError for Error {
     }
 }
 
+/// A phase of a request, used to report where a request timed out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// Resolving the host name of the server.
+    Resolve,
+    /// Establishing a connection to the server.
+    Connect,
+    /// Performing the TLS handshake with the server.
+    TlsHandshake,
+    /// Sending the request and waiting for the first byte of the response.
+    WaitingForResponse,
+    /// Receiving the response.
+    Transfer,
+}
+
+/// Details of a response with an error status code.
+///
+/// Most APIs describe what went wrong in the body of an error response, so
+/// the beginning of the body is kept along with the status and headers.
+#[derive(Debug)]
+pub struct StatusError {
+    pub(crate) status: StatusCode,
+    pub(crate) headers: HeaderMap,
+    pub(crate) body: Vec<u8>,
+}
+
+impl StatusError {
+    /// Get the status code of the response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Get the headers of the response.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Get the captured bytes of the response body. This may be a truncated
+    /// version of the full body, depending on the limit that was given.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Get the captured response body as a string, replacing any invalid
+    /// UTF-8 sequences.
+    pub fn text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
+
+    /// Deserialize the captured response body as JSON into a given type.
+    ///
+    /// This method requires the `json` feature to be enabled.
+    #[cfg(feature = "json")]
+    pub fn json<D>(&self) -> Result<D, serde_json::Error>
+    where
+        D: serde::de::DeserializeOwned,
+    {
+        serde_json::from_slice(&self.body)
+    }
+}
+
+// Not yet exposed by curl-sys.
+const CURLE_SSL_PINNEDPUBKEYNOTMATCH: curl_sys::CURLcode = 90;
+const CURLE_SSL_INVALIDCERTSTATUS: curl_sys::CURLcode = 91;
+
 #[doc(hidden)]
 impl From<curl::Error> for Error {
     fn from(error: curl::Error) -> Error {
-        if error.is_ssl_certproblem() || error.is_ssl_cacert_badfile() {
+        if error.is_ssl_certproblem() || error.is_ssl_cacert_badfile() || error.is_ssl_crl_badfile()
+        {
             Error::BadClientCertificate(error.extra_description().map(str::to_owned))
-        } else if error.is_peer_failed_verification() || error.is_ssl_cacert() {
+        } else if error.is_peer_failed_verification()
+            || error.is_ssl_cacert()
+            || error.code() == CURLE_SSL_PINNEDPUBKEYNOTMATCH
+            || error.code() == CURLE_SSL_INVALIDCERTSTATUS
+        {
             Error::BadServerCertificate(error.extra_description().map(str::to_owned))
         } else if error.is_couldnt_connect() {
             Error::ConnectFailed
@@ -124,9 +295,11 @@ impl From<curl::Error> for Error {
         {
             Error::SSLEngineError(error.extra_description().map(str::to_owned))
         } else if error.is_operation_timedout() {
-            Error::Timeout
+            Error::Timeout(None)
         } else if error.is_too_many_redirects() {
             Error::TooManyRedirects
+        } else if error.is_filesize_exceeded() {
+            Error::ResponseTooLarge
         } else {
             Error::Curl(error.description().to_owned())
         }
@@ -150,9 +323,20 @@ impl From<http::Error> for Error {
 #[doc(hidden)]
 impl From<io::Error> for Error {
     fn from(error: io::Error) -> Error {
+        // Errors raised while reading a response body may wrap one of ours.
+        match error
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<Error>())
+        {
+            Some(Error::Canceled) => return Error::Canceled,
+            Some(Error::ResponseTooLarge) => return Error::ResponseTooLarge,
+            Some(Error::TransferTooSlow) => return Error::TransferTooSlow,
+            _ => {}
+        }
+
         match error.kind() {
             io::ErrorKind::ConnectionRefused => Error::ConnectFailed,
-            io::ErrorKind::TimedOut => Error::Timeout,
+            io::ErrorKind::TimedOut => Error::Timeout(None),
             _ => Error::Io(error),
         }
     }
@@ -162,9 +346,13 @@ impl From<io::Error> for Error {
 impl From<Error> for io::Error {
     fn from(error: Error) -> io::Error {
         match error {
+            Error::AcquireTimeout => io::ErrorKind::TimedOut.into(),
             Error::ConnectFailed => io::ErrorKind::ConnectionRefused.into(),
             Error::Io(e) => e,
-            Error::Timeout => io::ErrorKind::TimedOut.into(),
+            Error::Canceled | Error::ResponseTooLarge | Error::TransferTooSlow => {
+                io::Error::new(io::ErrorKind::Other, error)
+            }
+            Error::Timeout(_) => io::ErrorKind::TimedOut.into(),
             _ => io::ErrorKind::Other.into(),
         }
     }
@@ -0,0 +1,229 @@
+//! Failover of requests across a set of equivalent endpoints.
+
+use crate::clock::Clock;
+use crate::Error;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long an endpoint is avoided after a request to it failed to connect.
+const UNHEALTHY_DURATION: Duration = Duration::from_secs(30);
+
+/// An ordered list of base URIs that serve the same content, along with the
+/// health of each one.
+#[derive(Debug)]
+pub(crate) struct Endpoints {
+    /// Base URIs, without trailing slashes.
+    bases: Vec<String>,
+
+    /// For each endpoint, when it may be tried first again after a failure.
+    unhealthy_until: Mutex<Vec<Option<Instant>>>,
+
+    /// Source of the current time for health tracking.
+    clock: Arc<dyn Clock>,
+}
+
+/// The endpoints a single request should be sent to, in order.
+#[derive(Debug)]
+pub(crate) struct Plan {
+    /// The part of the request URI following the base URI.
+    rest: String,
+
+    /// Indices of endpoints to try.
+    order: Vec<usize>,
+
+    /// Position in `order` of the endpoint currently being tried.
+    position: usize,
+}
+
+impl Endpoints {
+    pub(crate) fn new(bases: impl IntoIterator<Item = http::Uri>, clock: Arc<dyn Clock>) -> Self {
+        let bases = bases
+            .into_iter()
+            .map(|uri| uri.to_string().trim_end_matches('/').to_owned())
+            .collect::<Vec<_>>();
+        let unhealthy_until = Mutex::new(vec![None; bases.len()]);
+
+        Self {
+            bases,
+            unhealthy_until,
+            clock,
+        }
+    }
+
+    /// Plan the endpoints to send a request to, if its URI points to one of
+    /// the endpoints.
+    ///
+    /// Healthy endpoints are tried first, in the order they were configured.
+    /// Unhealthy endpoints are tried last, in case all others fail too.
+    pub(crate) fn plan(&self, uri: &http::Uri) -> Option<Plan> {
+        let uri = uri.to_string();
+
+        let rest = self.bases.iter().find_map(|base| {
+            if uri.starts_with(base.as_str()) {
+                let rest = &uri[base.len()..];
+
+                if rest.is_empty() || rest.starts_with('/') || rest.starts_with('?') {
+                    return Some(rest.to_owned());
+                }
+            }
+
+            None
+        })?;
+
+        let now = self.clock.now();
+        let unhealthy_until = self.unhealthy_until.lock().unwrap();
+        let is_healthy = |&index: &usize| match unhealthy_until[index] {
+            Some(until) => until <= now,
+            None => true,
+        };
+
+        let mut order = (0..self.bases.len()).filter(is_healthy).collect::<Vec<_>>();
+        order.extend((0..self.bases.len()).filter(|index| !is_healthy(index)));
+
+        Some(Plan {
+            rest,
+            order,
+            position: 0,
+        })
+    }
+
+    /// Get the URI to send a request to for the endpoint currently selected by
+    /// the given plan.
+    pub(crate) fn uri(&self, plan: &Plan) -> Result<http::Uri, Error> {
+        format!("{}{}", self.bases[plan.current()], plan.rest)
+            .parse::<http::Uri>()
+            .map_err(|e| Error::InvalidHttpFormat(e.into()))
+    }
+
+    /// Record that connecting to an endpoint failed.
+    pub(crate) fn mark_failed(&self, index: usize) {
+        log::debug!("marking endpoint {} as unhealthy", self.bases[index]);
+        self.unhealthy_until.lock().unwrap()[index] = Some(self.clock.now() + UNHEALTHY_DURATION);
+    }
+
+    /// Record that a request to an endpoint succeeded.
+    pub(crate) fn mark_healthy(&self, index: usize) {
+        self.unhealthy_until.lock().unwrap()[index] = None;
+    }
+}
+
+impl Plan {
+    /// Get the index of the endpoint currently being tried.
+    pub(crate) fn current(&self) -> usize {
+        self.order[self.position]
+    }
+
+    /// Move on to the next endpoint. Returns `false` if all endpoints have
+    /// been tried.
+    pub(crate) fn advance(&mut self) -> bool {
+        if self.position + 1 < self.order.len() {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Check if an error means that the request never reached the server, making
+/// it safe to send it to another endpoint instead.
+pub(crate) fn is_connect_error(error: &Error) -> bool {
+    match error {
+        Error::ConnectFailed | Error::CouldntResolveHost | Error::SSLConnectFailed(_) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    fn endpoints_with_clock(clock: MockClock) -> Endpoints {
+        Endpoints::new(
+            vec![
+                "https://primary.example.org".parse().unwrap(),
+                "https://backup.example.org/".parse().unwrap(),
+            ],
+            Arc::new(clock),
+        )
+    }
+
+    fn endpoints() -> Endpoints {
+        endpoints_with_clock(MockClock::new())
+    }
+
+    #[test]
+    fn unrelated_uris_are_not_planned() {
+        let endpoints = endpoints();
+
+        assert!(endpoints
+            .plan(&"https://example.com/".parse().unwrap())
+            .is_none());
+        assert!(endpoints
+            .plan(&"https://primary.example.org.evil.com/".parse().unwrap())
+            .is_none());
+    }
+
+    #[test]
+    fn endpoints_are_tried_in_order() {
+        let endpoints = endpoints();
+        let mut plan = endpoints
+            .plan(
+                &"https://backup.example.org/api/items?page=2"
+                    .parse()
+                    .unwrap(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            endpoints.uri(&plan).unwrap(),
+            "https://primary.example.org/api/items?page=2"
+        );
+        assert!(plan.advance());
+        assert_eq!(
+            endpoints.uri(&plan).unwrap(),
+            "https://backup.example.org/api/items?page=2"
+        );
+        assert!(!plan.advance());
+    }
+
+    #[test]
+    fn unhealthy_endpoints_are_tried_last() {
+        let endpoints = endpoints();
+        endpoints.mark_failed(0);
+
+        let plan = endpoints
+            .plan(&"https://primary.example.org/".parse().unwrap())
+            .unwrap();
+        assert_eq!(plan.order, vec![1, 0]);
+
+        endpoints.mark_healthy(0);
+
+        let plan = endpoints
+            .plan(&"https://primary.example.org/".parse().unwrap())
+            .unwrap();
+        assert_eq!(plan.order, vec![0, 1]);
+    }
+
+    #[test]
+    fn unhealthy_endpoints_recover_over_time() {
+        let clock = MockClock::new();
+        let endpoints = endpoints_with_clock(clock.clone());
+        endpoints.mark_failed(0);
+
+        clock.advance(UNHEALTHY_DURATION - Duration::from_secs(1));
+
+        let plan = endpoints
+            .plan(&"https://primary.example.org/".parse().unwrap())
+            .unwrap();
+        assert_eq!(plan.order, vec![1, 0]);
+
+        clock.advance(Duration::from_secs(1));
+
+        let plan = endpoints
+            .plan(&"https://primary.example.org/".parse().unwrap())
+            .unwrap();
+        assert_eq!(plan.order, vec![0, 1]);
+    }
+}
@@ -0,0 +1,112 @@
+//! Building `application/x-www-form-urlencoded` request bodies.
+
+use crate::Body;
+use std::fmt::Write;
+use std::iter::FromIterator;
+
+/// The content type of URL-encoded form bodies.
+const CONTENT_TYPE: &str = "application/x-www-form-urlencoded";
+
+/// A builder for an `application/x-www-form-urlencoded` body, the format
+/// browsers use to submit simple HTML forms.
+///
+/// Requests with a form body are sent with the appropriate `Content-Type`
+/// header, unless the request sets a different one. For uploading files, use
+/// [`multipart::FormData`](crate::multipart::FormData) instead.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::prelude::*;
+/// use isahc::Form;
+///
+/// let form = Form::new()
+///     .append("name", "Jane Doe")
+///     .append("favorite color", "blue & green");
+///
+/// let response = isahc::post("https://httpbin.org/post", form)?;
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Form {
+    encoded: String,
+}
+
+impl Form {
+    /// Create a new, empty form.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a field to the form.
+    ///
+    /// The same name may be added more than once.
+    pub fn append(mut self, name: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        if !self.encoded.is_empty() {
+            self.encoded.push('&');
+        }
+
+        encode(name.as_ref(), &mut self.encoded);
+        self.encoded.push('=');
+        encode(value.as_ref(), &mut self.encoded);
+
+        self
+    }
+
+    /// Get the form in its encoded form.
+    pub fn as_str(&self) -> &str {
+        &self.encoded
+    }
+}
+
+impl<K: AsRef<str>, V: AsRef<str>> FromIterator<(K, V)> for Form {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(Self::new(), |form, (name, value)| form.append(name, value))
+    }
+}
+
+impl From<Form> for Body {
+    fn from(form: Form) -> Self {
+        Body::from(form.encoded).with_content_type(CONTENT_TYPE)
+    }
+}
+
+/// Percent-encode a string according to the URL-encoded form serializer in
+/// the WHATWG URL standard.
+fn encode(input: &str, output: &mut String) {
+    for &byte in input.as_bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'*' | b'-' | b'.' | b'_' => {
+                output.push(char::from(byte))
+            }
+            b' ' => output.push('+'),
+            _ => write!(output, "%{:02X}", byte).unwrap(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_are_encoded() {
+        let form = Form::new()
+            .append("name", "Jane Doe")
+            .append("q", "a&b=c/d")
+            .append("emoji", "\u{1f600}");
+
+        assert_eq!(
+            form.as_str(),
+            "name=Jane+Doe&q=a%26b%3Dc%2Fd&emoji=%F0%9F%98%80"
+        );
+    }
+
+    #[test]
+    fn collect_from_pairs() {
+        let form: Form = vec![("a", "1"), ("a", "2")].into_iter().collect();
+
+        assert_eq!(form.as_str(), "a=1&a=2");
+    }
+}
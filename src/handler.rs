@@ -1,4 +1,14 @@
-use crate::{parse, response::EffectiveUri, Body, Error};
+use crate::{
+    cancel::CancellationToken,
+    config::{InformationalCallback, Progress, ProgressCallback},
+    latency::LatencyTracker,
+    metrics::{Metrics, Times},
+    parse,
+    response::{ConnectionInfo, EffectiveUri, QueueTime, Redirect, RedirectHistory, Trailer},
+    stats::StatsRegistry,
+    tls::TlsInfo,
+    Body, Error, Phase,
+};
 use crossbeam_channel::{Receiver, Sender, TryRecvError};
 use crossbeam_utils::atomic::AtomicCell;
 use curl::easy::{InfoType, ReadError, SeekResult, WriteError};
@@ -13,11 +23,13 @@ use std::ffi::CStr;
 use std::fmt;
 use std::future::Future;
 use std::io;
-use std::os::raw::c_char;
+use std::mem;
+use std::os::raw::{c_char, c_double, c_int, c_long};
 use std::pin::Pin;
 use std::ptr;
 use std::sync::Arc;
 use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
 
 /// Manages the state of a single request/response life cycle.
 ///
@@ -56,6 +68,21 @@ pub(crate) struct RequestHandler {
     /// Response headers received so far.
     response_headers: http::HeaderMap,
 
+    /// URI of the response currently being received.
+    response_uri: Option<Uri>,
+
+    /// Redirects followed by curl so far.
+    redirects: Vec<Redirect>,
+
+    /// Which part of the current response is being received.
+    response_part: ResponsePart,
+
+    /// Trailing headers received after the response body so far.
+    trailer_headers: http::HeaderMap,
+
+    /// Trailer of the response, filled in once the transfer is complete.
+    trailer: Trailer,
+
     /// Writing end of the pipe where the response body is written.
     response_body_writer: pipe::PipeWriter,
 
@@ -63,6 +90,94 @@ pub(crate) struct RequestHandler {
     /// an agent when the request is initialized.
     response_body_waker: Option<Waker>,
 
+    /// Maximum total size of the response header, if limited.
+    max_header_size: Option<usize>,
+
+    /// Maximum number of fields in the response header, if limited.
+    max_headers: Option<usize>,
+
+    /// Size of the current response header received so far.
+    header_size: usize,
+
+    /// Number of fields in the current response header received so far.
+    header_count: usize,
+
+    /// Set if the response header exceeded one of the limits.
+    headers_too_large: bool,
+
+    /// Maximum number of response body bytes to accept, if limited.
+    max_response_body_size: Option<u64>,
+
+    /// Number of response body bytes received so far.
+    response_body_size: u64,
+
+    /// How long the request waited in the agent's queue before it was started.
+    queue_time: Duration,
+
+    /// When the request was started by the agent.
+    started_at: Option<Instant>,
+
+    /// The host the request is sent to, used as the key for accounting.
+    host: Option<String>,
+
+    /// Where to record how long the request took.
+    latency_tracker: Option<Arc<LatencyTracker>>,
+
+    /// Where to record the request and the number of bytes transferred.
+    stats: Option<Arc<StatsRegistry>>,
+
+    /// Whether to reject malformed response headers instead of tolerating
+    /// them.
+    strict: bool,
+
+    /// Whether the request uses HTTP. Other protocols enabled by crate
+    /// features do not produce HTTP response headers.
+    is_http: bool,
+
+    /// Metrics to update as the transfer progresses, if enabled.
+    metrics: Option<Metrics>,
+
+    /// Function to call with the progress of the transfer, if any.
+    progress_callback: Option<ProgressCallback>,
+
+    /// Function to call with interim responses, if any.
+    informational_callback: Option<InformationalCallback>,
+
+    /// Details about the TLS connection collected so far, if enabled.
+    tls_info: Option<TlsInfo>,
+
+    /// Whether to collect the peer's certificate chain into the TLS details.
+    collect_peer_certificates: bool,
+
+    /// Token for canceling the request, if any.
+    cancellation_token: Option<CancellationToken>,
+
+    /// When writing the response body was paused because the reader was not
+    /// keeping up.
+    write_paused_at: Option<Instant>,
+
+    /// Timeouts for individual phases of the request.
+    phase_timeouts: PhaseTimeouts,
+
+    /// When data of the response was last received.
+    last_received_at: Option<Instant>,
+
+    /// Minimum transfer speed in bytes per second and how long the transfer
+    /// may stay below it, if limited.
+    low_speed_limit: Option<(u64, Duration)>,
+
+    /// Start of the period the transfer speed is currently measured over,
+    /// and the number of bytes transferred at that point.
+    low_speed_window: Option<(Instant, u64)>,
+
+    /// The phase that exceeded its timeout, if the request was aborted
+    /// because of one.
+    timed_out_phase: Option<Phase>,
+
+    /// Name of the most recently received response header, used to join
+    /// obsolete folded header lines.
+    last_header_name: Option<http::header::HeaderName>,
+
     /// Raw pointer to the associated curl easy handle. The pointer is not owned
     /// by this struct, but the parent struct to this one, so we know it will be
     /// valid at least for the lifetime of this struct (assuming all other
@@ -70,6 +185,19 @@ pub(crate) struct RequestHandler {
     handle_raw: Option<UnsafeSend<*mut CURL>>,
 }
 
+/// The parts of an HTTP response, in the order they are received.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ResponsePart {
+    /// The status line and header fields.
+    Header,
+
+    /// The body, which starts once the header is complete.
+    Body,
+
+    /// Trailer fields, which are only sent after the last chunk of the body.
+    Trailer,
+}
+
 struct UnsafeSend<T>(T);
 
 impl<T: Clone> Clone for UnsafeSend<T> {
@@ -96,6 +224,19 @@ struct Shared {
     completed: AtomicCell<bool>,
     future_dropped: AtomicCell<bool>,
     response_body_dropped: AtomicCell<bool>,
+
+    /// Set if the transfer was aborted because the response body exceeded the
+    /// maximum size.
+    response_too_large: AtomicCell<bool>,
+
+    /// Set if the transfer timed out.
+    timed_out: AtomicCell<bool>,
+
+    /// Set if the transfer was aborted for being too slow.
+    too_slow: AtomicCell<bool>,
+
+    /// Set if the request was canceled with a cancellation token.
+    canceled: AtomicCell<bool>,
 }
 
 impl RequestHandler {
@@ -108,6 +249,10 @@ impl RequestHandler {
             completed: AtomicCell::new(false),
             future_dropped: AtomicCell::new(false),
             response_body_dropped: AtomicCell::new(false),
+            response_too_large: AtomicCell::new(false),
+            timed_out: AtomicCell::new(false),
+            too_slow: AtomicCell::new(false),
+            canceled: AtomicCell::new(false),
         });
         let (response_body_reader, response_body_writer) = pipe::pipe();
 
@@ -120,8 +265,40 @@ impl RequestHandler {
                 response_status_code: None,
                 response_version: None,
                 response_headers: http::HeaderMap::new(),
+                response_uri: None,
+                redirects: Vec::new(),
+                response_part: ResponsePart::Header,
+                trailer_headers: http::HeaderMap::new(),
+                trailer: Trailer::new(),
                 response_body_writer,
                 response_body_waker: None,
+                max_header_size: None,
+                max_headers: None,
+                header_size: 0,
+                header_count: 0,
+                headers_too_large: false,
+                max_response_body_size: None,
+                response_body_size: 0,
+                queue_time: Duration::from_secs(0),
+                started_at: None,
+                host: None,
+                latency_tracker: None,
+                stats: None,
+                strict: true,
+                is_http: true,
+                metrics: None,
+                progress_callback: None,
+                informational_callback: None,
+                tls_info: None,
+                collect_peer_certificates: false,
+                cancellation_token: None,
+                write_paused_at: None,
+                phase_timeouts: PhaseTimeouts::default(),
+                last_received_at: None,
+                low_speed_limit: None,
+                low_speed_window: None,
+                timed_out_phase: None,
+                last_header_name: None,
                 handle_raw: None,
             },
             RequestHandlerFuture {
@@ -154,17 +331,180 @@ impl RequestHandler {
         self.handle_raw = Some(UnsafeSend(handle));
         self.request_body_waker = Some(request_waker);
         self.response_body_waker = Some(response_waker);
+        self.started_at = Some(Instant::now());
+    }
+
+    /// Set the host the request is sent to. Completed requests are only
+    /// accounted for if a host is set.
+    pub(crate) fn set_host(&mut self, host: String) {
+        self.host = Some(host);
+    }
+
+    /// Record the response time of this request in a tracker once the request
+    /// completes successfully.
+    pub(crate) fn set_latency_tracker(&mut self, tracker: Arc<LatencyTracker>) {
+        self.latency_tracker = Some(tracker);
+    }
+
+    /// Record this request in a statistics registry once it completes.
+    pub(crate) fn set_stats(&mut self, stats: Arc<StatsRegistry>) {
+        self.stats = Some(stats);
+    }
+
+    /// Set whether the request uses HTTP, as opposed to some other protocol
+    /// supported by curl.
+    pub(crate) fn set_is_http(&mut self, is_http: bool) {
+        self.is_http = is_http;
+    }
+
+    /// Collect metrics for this request. Progress reporting must be enabled
+    /// for them to be updated while the transfer is in progress.
+    pub(crate) fn set_metrics(&mut self, metrics: Metrics) {
+        self.metrics = Some(metrics);
+    }
+
+    /// Call a function with the progress of this request. Progress reporting
+    /// must be enabled for it to be called.
+    pub(crate) fn set_progress_callback(&mut self, callback: ProgressCallback) {
+        self.progress_callback = Some(callback);
+    }
+
+    pub(crate) fn set_informational_callback(&mut self, callback: InformationalCallback) {
+        self.informational_callback = Some(callback);
+    }
+
+    /// Collect details about the TLS connection for the response, optionally
+    /// including the peer's certificate chain. Verbose output must be enabled
+    /// for most details to be reported by curl.
+    pub(crate) fn set_tls_info(&mut self, peer_certificates: bool) {
+        self.tls_info = Some(TlsInfo::default());
+        self.collect_peer_certificates = peer_certificates;
+    }
+
+    /// Allow the request to be canceled with the given token.
+    pub(crate) fn set_cancellation_token(&mut self, token: CancellationToken) {
+        self.cancellation_token = Some(token);
+    }
+
+    /// Get the token the request can be canceled with, if any.
+    pub(crate) fn cancellation_token(&self) -> Option<&CancellationToken> {
+        self.cancellation_token.as_ref()
+    }
+
+    /// Check whether the request has been canceled with its token.
+    pub(crate) fn is_canceled(&self) -> bool {
+        self.cancellation_token
+            .as_ref()
+            .map_or(false, CancellationToken::is_canceled)
+    }
+
+    /// Set timeouts for individual phases of the request. These are checked
+    /// from the progress callback, which must be enabled for them to apply.
+    pub(crate) fn set_phase_timeouts(&mut self, timeouts: PhaseTimeouts) {
+        self.phase_timeouts = timeouts;
+    }
+
+    /// Abort the transfer if its speed stays below the given number of bytes
+    /// per second for the given time. Checked from the progress callback,
+    /// which must be enabled for it to apply.
+    pub(crate) fn set_low_speed_limit(&mut self, bytes_per_second: u64, time: Duration) {
+        self.low_speed_limit = Some((bytes_per_second, time));
+    }
+
+    /// Set limits on the size and number of fields of the response header.
+    pub(crate) fn set_header_limits(&mut self, size: Option<usize>, count: Option<usize>) {
+        self.max_header_size = size;
+        self.max_headers = count;
+    }
+
+    /// Set the maximum number of response body bytes to accept before aborting
+    /// the transfer.
+    pub(crate) fn set_max_response_body_size(&mut self, size: u64) {
+        self.max_response_body_size = Some(size);
+    }
+
+    /// Set whether malformed response headers cause the request to fail.
+    pub(crate) fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Record how long the request had to wait in a queue before it could be
+    /// started.
+    pub(crate) fn set_queue_time(&mut self, queue_time: Duration) {
+        self.queue_time = queue_time;
     }
 
     /// Handle a result produced by curl for this handler's current transfer.
     pub(crate) fn on_result(&mut self, result: Result<(), curl::Error>) {
         self.shared.completed.store(true);
 
+        self.record_completion(result.is_ok());
+        self.update_average_speeds();
+        self.update_times();
+
+        let trailer_headers = mem::replace(&mut self.trailer_headers, http::HeaderMap::new());
+        self.trailer.set(trailer_headers);
+
         match result {
             Ok(()) => self.flush_response_headers(),
             Err(e) => {
                 log::debug!("curl error: {}", e);
-                self.complete(Err(e.into()));
+
+                let error = if self.shared.too_slow.load() {
+                    Error::TransferTooSlow
+                } else if self.headers_too_large {
+                    Error::ResponseHeadersTooLarge
+                } else if self.shared.response_too_large.load() {
+                    Error::ResponseTooLarge
+                } else if let Some(phase) = self.timed_out_phase {
+                    Error::Timeout(Some(phase))
+                } else if e.is_operation_timedout() {
+                    Error::Timeout(self.get_phase_timings().map(|t| t.current_phase()))
+                } else {
+                    e.into()
+                };
+
+                // Let the response body know in case the response has already
+                // been returned.
+                if let Error::Timeout(_) = error {
+                    self.shared.timed_out.store(true);
+                }
+
+                self.complete(Err(error));
+            }
+        }
+    }
+
+    /// Fail the request with the given error without it ever being started.
+    pub(crate) fn fail(&mut self, error: Error) {
+        self.shared.completed.store(true);
+        self.complete(Err(error));
+    }
+
+    /// Handle the request being canceled, after it has been removed from the
+    /// multi handle.
+    pub(crate) fn on_canceled(&mut self) {
+        log::debug!("request canceled with token [id={:?}]", self.shared.id);
+        self.shared.canceled.store(true);
+        self.shared.completed.store(true);
+        self.record_completion(false);
+        self.complete(Err(Error::Canceled));
+    }
+
+    /// Account for the completed request in any trackers of the client.
+    fn record_completion(&mut self, success: bool) {
+        if let Some(host) = self.host.as_ref() {
+            if success {
+                if let (Some(tracker), Some(started_at)) =
+                    (self.latency_tracker.as_ref(), self.started_at)
+                {
+                    tracker.record(host, started_at.elapsed());
+                }
+            }
+
+            if let Some(stats) = self.stats.as_ref() {
+                let (sent, received) = self.get_transfer_sizes();
+                stats.record(host, success, sent, received);
             }
         }
     }
@@ -179,7 +519,11 @@ impl RequestHandler {
                 builder.status(status);
             }
 
-            if let Some(version) = self.response_version.take() {
+            // Prefer the version curl reports for the connection, since
+            // the status line of HTTP/2 responses is synthesized.
+            let version = self.response_version.take();
+
+            if let Some(version) = self.get_http_version().or(version) {
                 builder.version(version);
             }
 
@@ -193,10 +537,58 @@ impl RequestHandler {
                 builder.extension(EffectiveUri(uri));
             }
 
+            if !self.redirects.is_empty() {
+                let redirects = mem::replace(&mut self.redirects, Vec::new());
+                builder.extension(RedirectHistory(redirects));
+            }
+
+            if let Some(mut info) = self.tls_info.take() {
+                if self.collect_peer_certificates {
+                    self.get_peer_certificates(&mut info);
+                }
+
+                // curl only speaks HTTP/2 over TLS if the server agreed to it
+                // with ALPN, which is more reliable than the informational
+                // output.
+                let https = self
+                    .get_effective_uri()
+                    .map_or(false, |uri| uri.scheme_str() == Some("https"));
+
+                if https && self.get_http_version() == Some(http::Version::HTTP_2) {
+                    info.set_alpn_protocol("h2");
+                }
+
+                if !info.is_empty() {
+                    builder.extension(info);
+                }
+            }
+
+            if let Some(info) = self.get_connection_info() {
+                builder.extension(info);
+            }
+
+            builder.extension(QueueTime(self.queue_time));
+            builder.extension(self.trailer.clone());
+
+            if let Some(metrics) = self.metrics.clone() {
+                self.update_times();
+                builder.extension(metrics);
+            }
+
             self.complete(Ok(builder));
         }
     }
 
+    /// Check whether the future was dropped before the response was handed
+    /// to it, meaning no one is interested in the request any more.
+    ///
+    /// The future is also dropped once it has returned the response, but the
+    /// transfer then goes on for the response body, which is tracked
+    /// separately.
+    fn is_future_dropped(&self) -> bool {
+        self.sender.is_some() && self.shared.future_dropped.load()
+    }
+
     /// Complete the associated future with a result.
     fn complete(&mut self, result: Result<http::response::Builder, Error>) {
         if let Some(sender) = self.sender.take() {
@@ -219,6 +611,320 @@ impl RequestHandler {
         }
     }
 
+    /// Check whether the transfer has stayed below the low speed limit for
+    /// longer than allowed, given the total number of bytes transferred so
+    /// far.
+    ///
+    /// The speed is averaged over periods of the allowed length. Periods in
+    /// which writing the response body was paused are not counted, since the
+    /// transfer is then held up by the reader rather than the connection.
+    fn is_too_slow(&mut self, transferred: u64) -> bool {
+        let (bytes_per_second, time) = match self.low_speed_limit {
+            Some(limit) => limit,
+            None => return false,
+        };
+
+        if self.write_paused_at.is_some() {
+            self.low_speed_window = None;
+            return false;
+        }
+
+        let now = Instant::now();
+        let (start, transferred_at_start) =
+            *self.low_speed_window.get_or_insert((now, transferred));
+        let elapsed = now - start;
+
+        if elapsed < time {
+            return false;
+        }
+
+        let bytes = u128::from(transferred.saturating_sub(transferred_at_start));
+
+        if bytes * 1000 < u128::from(bytes_per_second) * elapsed.as_millis() {
+            log::debug!(
+                "transfer was slower than {} bytes per second for {:?}",
+                bytes_per_second,
+                elapsed
+            );
+            return true;
+        }
+
+        self.low_speed_window = Some((now, transferred));
+        false
+    }
+
+    /// Account for a line of the response header or trailer, returning false
+    /// if it has grown beyond the configured limits.
+    fn account_header_line(&mut self, len: usize, is_field: bool) -> bool {
+        self.header_size += len;
+
+        if is_field {
+            self.header_count += 1;
+        }
+
+        let exceeded = match (self.max_header_size, self.max_headers) {
+            (Some(max), _) if self.header_size > max => true,
+            (_, Some(max)) if self.header_count > max => true,
+            _ => false,
+        };
+
+        if exceeded {
+            log::debug!("response header exceeded the configured limits");
+            self.headers_too_large = true;
+            return false;
+        }
+
+        true
+    }
+
+    /// Get the total number of bytes sent and received so far, including
+    /// headers.
+    #[allow(unsafe_code)]
+    fn get_transfer_sizes(&self) -> (u64, u64) {
+        let handle = match self.handle_raw.as_ref() {
+            Some(UnsafeSend(handle)) => *handle,
+            None => return (0, 0),
+        };
+
+        unsafe {
+            let mut request_size: c_long = 0;
+            let mut upload_size: c_double = 0.0;
+            let mut header_size: c_long = 0;
+            let mut download_size: c_double = 0.0;
+
+            // Any of these failing simply leaves the size at zero.
+            curl_sys::curl_easy_getinfo(handle, curl_sys::CURLINFO_REQUEST_SIZE, &mut request_size);
+            curl_sys::curl_easy_getinfo(handle, curl_sys::CURLINFO_SIZE_UPLOAD, &mut upload_size);
+            curl_sys::curl_easy_getinfo(handle, curl_sys::CURLINFO_HEADER_SIZE, &mut header_size);
+            curl_sys::curl_easy_getinfo(
+                handle,
+                curl_sys::CURLINFO_SIZE_DOWNLOAD,
+                &mut download_size,
+            );
+
+            (
+                request_size.max(0) as u64 + upload_size.max(0.0) as u64,
+                header_size.max(0) as u64 + download_size.max(0.0) as u64,
+            )
+        }
+    }
+
+    /// Update the average transfer speeds in the metrics, if enabled.
+    #[allow(unsafe_code)]
+    fn update_average_speeds(&self) {
+        let (metrics, handle) = match (self.metrics.as_ref(), self.handle_raw.as_ref()) {
+            (Some(metrics), Some(UnsafeSend(handle))) => (metrics, *handle),
+            _ => return,
+        };
+
+        let mut upload_speed: c_double = 0.0;
+        let mut download_speed: c_double = 0.0;
+
+        unsafe {
+            curl_sys::curl_easy_getinfo(handle, curl_sys::CURLINFO_SPEED_UPLOAD, &mut upload_speed);
+            curl_sys::curl_easy_getinfo(
+                handle,
+                curl_sys::CURLINFO_SPEED_DOWNLOAD,
+                &mut download_speed,
+            );
+        }
+
+        metrics.set_average_speeds(upload_speed, download_speed);
+    }
+
+    /// Update the phase times in the metrics, if enabled.
+    #[allow(unsafe_code)]
+    fn update_times(&self) {
+        let (metrics, handle) = match (self.metrics.as_ref(), self.handle_raw.as_ref()) {
+            (Some(metrics), Some(UnsafeSend(handle))) => (metrics, *handle),
+            _ => return,
+        };
+
+        let mut times = Times::default();
+
+        // Any of these failing simply leaves the time at zero.
+        unsafe {
+            curl_sys::curl_easy_getinfo(
+                handle,
+                curl_sys::CURLINFO_NAMELOOKUP_TIME,
+                &mut times.name_lookup,
+            );
+            curl_sys::curl_easy_getinfo(
+                handle,
+                curl_sys::CURLINFO_CONNECT_TIME,
+                &mut times.connect,
+            );
+            curl_sys::curl_easy_getinfo(
+                handle,
+                curl_sys::CURLINFO_APPCONNECT_TIME,
+                &mut times.app_connect,
+            );
+            curl_sys::curl_easy_getinfo(
+                handle,
+                curl_sys::CURLINFO_STARTTRANSFER_TIME,
+                &mut times.start_transfer,
+            );
+            curl_sys::curl_easy_getinfo(
+                handle,
+                curl_sys::CURLINFO_REDIRECT_TIME,
+                &mut times.redirect,
+            );
+            curl_sys::curl_easy_getinfo(handle, curl_sys::CURLINFO_TOTAL_TIME, &mut times.total);
+        }
+
+        metrics.set_times(times);
+    }
+
+    /// Get the times at which each connection phase of the request completed.
+    #[allow(unsafe_code)]
+    fn get_phase_timings(&self) -> Option<PhaseTimings> {
+        let handle = match self.handle_raw.as_ref() {
+            Some(UnsafeSend(handle)) => *handle,
+            None => return None,
+        };
+
+        let mut timings = PhaseTimings::default();
+
+        // Any of these failing simply leaves the phase incomplete.
+        unsafe {
+            curl_sys::curl_easy_getinfo(
+                handle,
+                curl_sys::CURLINFO_NAMELOOKUP_TIME,
+                &mut timings.resolved,
+            );
+            curl_sys::curl_easy_getinfo(
+                handle,
+                curl_sys::CURLINFO_CONNECT_TIME,
+                &mut timings.connected,
+            );
+            curl_sys::curl_easy_getinfo(
+                handle,
+                curl_sys::CURLINFO_PRETRANSFER_TIME,
+                &mut timings.ready,
+            );
+            curl_sys::curl_easy_getinfo(
+                handle,
+                curl_sys::CURLINFO_STARTTRANSFER_TIME,
+                &mut timings.first_byte,
+            );
+        }
+
+        Some(timings)
+    }
+
+    /// Add the certificate chain presented by the server to the given TLS
+    /// details.
+    #[allow(unsafe_code)]
+    fn get_peer_certificates(&self, info: &mut TlsInfo) {
+        // Not yet exposed by curl-sys.
+        const CURLINFO_CERTINFO: curl_sys::CURLINFO = curl_sys::CURLINFO_SLIST + 34;
+
+        /// Mirrors `struct curl_certinfo`.
+        #[repr(C)]
+        struct CertInfo {
+            num_of_certs: c_int,
+            certinfo: *mut *mut curl_sys::curl_slist,
+        }
+
+        let handle = match self.handle_raw.as_ref() {
+            Some(UnsafeSend(handle)) => *handle,
+            None => return,
+        };
+
+        unsafe {
+            let mut certinfo = ptr::null::<CertInfo>();
+
+            if curl_sys::curl_easy_getinfo(handle, CURLINFO_CERTINFO, &mut certinfo)
+                != curl_sys::CURLE_OK
+                || certinfo.is_null()
+            {
+                return;
+            }
+
+            let certinfo = &*certinfo;
+
+            // Each certificate is described by a list of "name:value" fields.
+            for i in 0..certinfo.num_of_certs.max(0) as usize {
+                let mut field = *certinfo.certinfo.add(i);
+
+                while !field.is_null() {
+                    if !(*field).data.is_null() {
+                        info.add_certificate_field(
+                            &CStr::from_ptr((*field).data).to_string_lossy(),
+                        );
+                    }
+
+                    field = (*field).next;
+                }
+            }
+        }
+    }
+
+    /// Get the HTTP version used for the most recent response.
+    #[allow(unsafe_code)]
+    fn get_http_version(&self) -> Option<http::Version> {
+        // Not yet exposed by curl-sys.
+        const CURLINFO_HTTP_VERSION: curl_sys::CURLINFO = curl_sys::CURLINFO_LONG + 46;
+
+        let handle = match self.handle_raw.as_ref() {
+            Some(UnsafeSend(handle)) => *handle,
+            None => return None,
+        };
+
+        let mut version: c_long = 0;
+
+        unsafe {
+            if curl_sys::curl_easy_getinfo(handle, CURLINFO_HTTP_VERSION, &mut version)
+                != curl_sys::CURLE_OK
+            {
+                return None;
+            }
+        }
+
+        match version {
+            1 => Some(http::Version::HTTP_10),
+            2 => Some(http::Version::HTTP_11),
+            3 => Some(http::Version::HTTP_2),
+            _ => None,
+        }
+    }
+
+    /// Get information about the connection used for the most recent
+    /// response.
+    #[allow(unsafe_code)]
+    fn get_connection_info(&self) -> Option<ConnectionInfo> {
+        // Not yet exposed by curl-sys.
+        const CURLINFO_OFF_T: curl_sys::CURLINFO = 0x60_0000;
+        const CURLINFO_CONN_ID: curl_sys::CURLINFO = CURLINFO_OFF_T + 64;
+
+        let handle = match self.handle_raw.as_ref() {
+            Some(UnsafeSend(handle)) => *handle,
+            None => return None,
+        };
+
+        let mut new_connections: c_long = 0;
+        let mut id: i64 = -1;
+
+        unsafe {
+            if curl_sys::curl_easy_getinfo(
+                handle,
+                curl_sys::CURLINFO_NUM_CONNECTS,
+                &mut new_connections,
+            ) != curl_sys::CURLE_OK
+            {
+                return None;
+            }
+
+            // Only supported by newer libcurl versions, leaving the ID unset.
+            curl_sys::curl_easy_getinfo(handle, CURLINFO_CONN_ID, &mut id);
+        }
+
+        Some(ConnectionInfo {
+            id: if id >= 0 { Some(id as u64) } else { None },
+            reused: new_connections == 0,
+        })
+    }
+
     #[allow(unsafe_code)]
     fn get_effective_uri(&mut self) -> Option<Uri> {
         self.handle_raw
@@ -241,40 +947,192 @@ impl RequestHandler {
     }
 }
 
+/// Maximum durations of individual phases of a request, checked from the
+/// progress callback.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct PhaseTimeouts {
+    /// Maximum time for resolving the host name.
+    pub(crate) dns: Option<Duration>,
+
+    /// Maximum time for the TLS handshake.
+    pub(crate) tls: Option<Duration>,
+
+    /// Maximum time from sending the request to receiving the first byte of
+    /// the response.
+    pub(crate) first_byte: Option<Duration>,
+
+    /// Maximum time between receiving data while receiving the response.
+    pub(crate) read: Option<Duration>,
+}
+
+impl PhaseTimeouts {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.dns.is_none() && self.tls.is_none() && self.first_byte.is_none() && self.read.is_none()
+    }
+}
+
+/// Seconds since the start of a request at which each connection phase
+/// completed, as reported by curl. Zero if the phase has not completed yet.
+#[derive(Default)]
+struct PhaseTimings {
+    resolved: c_double,
+    connected: c_double,
+    ready: c_double,
+    first_byte: c_double,
+}
+
+impl PhaseTimings {
+    /// Get the phase the request is currently in.
+    fn current_phase(&self) -> Phase {
+        if self.resolved <= 0.0 {
+            Phase::Resolve
+        } else if self.connected <= 0.0 {
+            Phase::Connect
+        } else if self.ready <= 0.0 {
+            // For plain HTTP this is effectively instant after connecting.
+            Phase::TlsHandshake
+        } else if self.first_byte <= 0.0 {
+            Phase::WaitingForResponse
+        } else {
+            Phase::Transfer
+        }
+    }
+}
+
 impl curl::easy::Handler for RequestHandler {
     /// Gets called by curl for each line of data in the HTTP response header.
     fn header(&mut self, data: &[u8]) -> bool {
         // Abort the request if it has been canceled.
-        if self.shared.future_dropped.load() {
+        if self.is_future_dropped() {
             return false;
         }
 
+        self.last_received_at = Some(Instant::now());
+
         // Curl calls this function for all lines in the response not part of
         // the response body, not just for headers. We need to inspect the
         // contents of the string in order to determine what it is and how to
         // parse it, just as if we were reading from the socket of a HTTP/1.0 or
         // HTTP/1.1 connection ourselves.
 
+        // Other protocols report control messages, such as FTP server replies,
+        // through the header callback. These are not HTTP headers, so the
+        // response is left with a default status and no headers.
+        if !self.is_http {
+            return true;
+        }
+
+        let status_line = parse::parse_status_line(data);
+
+        // Every line counts towards the header limits, including lines that
+        // are skipped and trailer lines, so that a server cannot get around
+        // them by sending lines that are not valid header fields. Each status
+        // line starts a new response, and with it a new header.
+        if status_line.is_some() {
+            self.header_size = 0;
+            self.header_count = 0;
+        }
+
+        let is_field = status_line.is_none()
+            && data != b"\r\n"
+            && data != b"\n"
+            && !data.starts_with(b" ")
+            && !data.starts_with(b"\t");
+
+        if !self.account_header_line(data.len(), is_field) {
+            return false;
+        }
+
+        // Lines must end with CRLF, but some servers only send LF.
+        if self.strict && !data.ends_with(b"\r\n") {
+            log::debug!("response header line does not end with CRLF");
+            return false;
+        }
+
         // Is this the status line?
-        if let Some((version, status)) = parse::parse_status_line(data) {
+        if let Some((version, status)) = status_line {
+            // A new response after a redirect means curl followed it.
+            if let (Some(previous), Some(uri)) =
+                (self.response_status_code, self.response_uri.take())
+            {
+                if previous.is_redirection() {
+                    self.redirects.push(Redirect {
+                        uri,
+                        status: previous,
+                    });
+                }
+            }
+
+            self.response_uri = self.get_effective_uri();
             self.response_version = Some(version);
             self.response_status_code = Some(status);
 
             // Also clear any pre-existing headers that might be left over from
             // a previous intermediate response.
             self.response_headers.clear();
+            self.trailer_headers.clear();
+            self.last_header_name = None;
+            self.response_part = ResponsePart::Header;
+
+            return true;
+        }
+
+        // Is this a continuation of the previous header using obsolete line
+        // folding? If so, join it onto the previous value with a space.
+        if !self.strict
+            && self.response_part == ResponsePart::Header
+            && (data.starts_with(b" ") || data.starts_with(b"\t"))
+        {
+            if let Some(name) = self.last_header_name.clone() {
+                let continuation = parse::trim(data);
+
+                // Only the most recent value of the header is continued.
+                if let Ok(http::header::Entry::Occupied(mut entry)) =
+                    self.response_headers.entry(name)
+                {
+                    if let Some(value) = entry.iter_mut().last() {
+                        let mut folded = value.as_bytes().to_vec();
+                        folded.push(b' ');
+                        folded.extend_from_slice(continuation);
+
+                        if let Ok(folded) = http::HeaderValue::from_bytes(&folded) {
+                            *value = folded;
+                        }
+                    }
+                }
+            }
 
             return true;
         }
 
         // Is this a header line?
         if let Some((name, value)) = parse::parse_header(data) {
-            self.response_headers.insert(name, value);
+            // Header fields received after the body has started can only be
+            // trailers following the last chunk. This is also the case if the
+            // body is empty and nothing was written.
+            if self.response_part != ResponsePart::Header {
+                self.response_part = ResponsePart::Trailer;
+                self.trailer_headers.append(name, value);
+                return true;
+            }
+
+            self.last_header_name = Some(name.clone());
+            self.response_headers.append(name, value);
             return true;
         }
 
-        // Is this the end of the response header?
-        if data == b"\r\n" {
+        // Is this the end of the response header, or of the trailer?
+        if data == b"\r\n" || data == b"\n" {
+            // Interim responses are followed by the final response, so this is
+            // the only chance to observe them.
+            if let Some(status) = self.response_status_code.filter(|s| s.is_informational()) {
+                if let Some(callback) = self.informational_callback.as_ref() {
+                    (callback.0)(status, &self.response_headers);
+                }
+            } else if self.response_part == ResponsePart::Header {
+                self.response_part = ResponsePart::Body;
+            }
+
             // We will acknowledge the end of the header, but we can't complete
             // our response future yet. If curl decides to follow a redirect,
             // then this current response is not the final response and not the
@@ -285,14 +1143,103 @@ impl curl::easy::Handler for RequestHandler {
             return true;
         }
 
-        // Unknown header line we don't know how to parse.
-        false
+        // Unknown header line we don't know how to parse. Skip it if parsing
+        // is lenient.
+        log::debug!(
+            "invalid response header line: {:?}",
+            String::from_utf8_lossy(data)
+        );
+        !self.strict
+    }
+
+    /// Gets called by curl periodically while the request is in progress, if
+    /// progress reporting is enabled.
+    fn progress(&mut self, dltotal: f64, dlnow: f64, ultotal: f64, ulnow: f64) -> bool {
+        // Abort the request if it has been canceled.
+        if self.is_future_dropped() {
+            return false;
+        }
+
+        if let Some(callback) = self.progress_callback.as_ref() {
+            (callback.0)(Progress {
+                uploaded: ulnow as u64,
+                upload_total: ultotal as u64,
+                downloaded: dlnow as u64,
+                download_total: dltotal as u64,
+            });
+        }
+
+        if let Some(metrics) = self.metrics.as_ref() {
+            metrics.record_progress(
+                Instant::now(),
+                ulnow as u64,
+                dlnow as u64,
+                self.write_paused_at.is_some(),
+            );
+            self.update_average_speeds();
+            self.update_times();
+        }
+
+        let elapsed = match self.started_at {
+            Some(started_at) => started_at.elapsed(),
+            None => return true,
+        };
+
+        let timings = match self.get_phase_timings() {
+            Some(timings) => timings,
+            None => return true,
+        };
+
+        let phase = timings.current_phase();
+        let timeouts = self.phase_timeouts;
+
+        // How long ago a point in time reported by curl was.
+        let since = |seconds: c_double| {
+            elapsed
+                .checked_sub(Duration::from_micros((seconds * 1e6) as u64))
+                .unwrap_or_default()
+        };
+
+        let exceeded = match phase {
+            Phase::Resolve => timeouts.dns.map_or(false, |timeout| elapsed > timeout),
+            Phase::TlsHandshake => timeouts
+                .tls
+                .map_or(false, |timeout| since(timings.connected) > timeout),
+            Phase::WaitingForResponse => timeouts
+                .first_byte
+                .map_or(false, |timeout| since(timings.ready) > timeout),
+            // Time spent waiting for the response body to be read does not
+            // count as the connection being idle.
+            Phase::Transfer => match (timeouts.read, self.last_received_at) {
+                (Some(timeout), Some(received_at)) if self.write_paused_at.is_none() => {
+                    received_at.elapsed() > timeout
+                }
+                _ => false,
+            },
+            _ => false,
+        };
+
+        if exceeded {
+            log::debug!("request exceeded the timeout for phase {:?}", phase);
+            self.timed_out_phase = Some(phase);
+            return false;
+        }
+
+        // The speed limit only applies once the connection is established.
+        if let Phase::WaitingForResponse | Phase::Transfer = phase {
+            if self.is_too_slow((dlnow + ulnow) as u64) {
+                self.shared.too_slow.store(true);
+                return false;
+            }
+        }
+
+        true
     }
 
     /// Gets called by curl when attempting to send bytes of the request body.
     fn read(&mut self, data: &mut [u8]) -> Result<usize, ReadError> {
         // Abort the request if it has been canceled.
-        if self.shared.future_dropped.load() {
+        if self.is_future_dropped() {
             return Err(ReadError::Abort);
         }
 
@@ -343,8 +1290,23 @@ impl curl::easy::Handler for RequestHandler {
             return Ok(0);
         }
 
+        // Abort the transfer once the body grows beyond the allowed size. If
+        // this happens in the first chunk, the response future fails instead
+        // of completing.
+        if let Some(limit) = self.max_response_body_size {
+            if self.response_body_size + data.len() as u64 > limit {
+                log::debug!("response body exceeded the maximum size of {} bytes", limit);
+                self.shared.response_too_large.store(true);
+                return Ok(0);
+            }
+        }
+
         // Now that we've started receiving the response body, we know no more
         // redirects can happen and we can complete the future safely.
+        if self.response_part == ResponsePart::Header {
+            self.response_part = ResponsePart::Body;
+        }
+
         self.flush_response_headers();
 
         // Create a task context using a waker provided by the agent so we can
@@ -353,8 +1315,21 @@ impl curl::easy::Handler for RequestHandler {
             let mut context = Context::from_waker(waker);
 
             match Pin::new(&mut self.response_body_writer).poll_write(&mut context, data) {
-                Poll::Pending => Err(WriteError::Pause),
-                Poll::Ready(Ok(len)) => Ok(len),
+                Poll::Pending => {
+                    self.write_paused_at.get_or_insert_with(Instant::now);
+                    Err(WriteError::Pause)
+                }
+                Poll::Ready(Ok(len)) => {
+                    self.response_body_size += len as u64;
+                    self.last_received_at = Some(Instant::now());
+
+                    if let Some(paused_at) = self.write_paused_at.take() {
+                        if let Some(metrics) = self.metrics.as_ref() {
+                            metrics.record_consumer_stall(paused_at.elapsed());
+                        }
+                    }
+                    Ok(len)
+                }
                 Poll::Ready(Err(e)) => {
                     if e.kind() == io::ErrorKind::BrokenPipe {
                         log::warn!(
@@ -389,6 +1364,12 @@ impl curl::easy::Handler for RequestHandler {
             .unwrap_or_else(|_| String::from("<binary>"))
         }
 
+        if let InfoType::Text = kind {
+            if let Some(info) = self.tls_info.as_mut() {
+                info.parse_debug_line(&String::from_utf8_lossy(data));
+            }
+        }
+
         match kind {
             InfoType::Text => log::debug!(target: "isahc::curl", "{}", String::from_utf8_lossy(data).trim_end()),
             InfoType::HeaderIn | InfoType::DataIn => log::trace!(target: "isahc::wire", "<< {}", format_byte_string(data)),
@@ -484,6 +1465,13 @@ impl fmt::Debug for RequestHandlerFuture {
     }
 }
 
+impl Drop for RequestHandler {
+    fn drop(&mut self) {
+        // Make sure no one waits for a trailer that will never arrive.
+        self.trailer.set(http::HeaderMap::new());
+    }
+}
+
 impl Drop for RequestHandlerFuture {
     fn drop(&mut self) {
         self.shared.future_dropped.store(true);
@@ -515,10 +1503,18 @@ impl AsyncRead for ResponseBodyReader {
         pin_mut!(inner);
 
         match inner.poll_read(cx, buf) {
-            // On EOF, check to see if the transfer was cancelled, and if so,
-            // return an error.
+            // On EOF, check to see if the transfer was cancelled or aborted,
+            // and if so, return an error.
             Poll::Ready(Ok(0)) => {
-                if !self.shared.completed.load() {
+                if self.shared.canceled.load() {
+                    Poll::Ready(Err(Error::Canceled.into()))
+                } else if self.shared.response_too_large.load() {
+                    Poll::Ready(Err(Error::ResponseTooLarge.into()))
+                } else if self.shared.too_slow.load() {
+                    Poll::Ready(Err(Error::TransferTooSlow.into()))
+                } else if self.shared.timed_out.load() {
+                    Poll::Ready(Err(io::ErrorKind::TimedOut.into()))
+                } else if !self.shared.completed.load() {
                     Poll::Ready(Err(io::ErrorKind::ConnectionAborted.into()))
                 } else {
                     Poll::Ready(Ok(0))
@@ -0,0 +1,144 @@
+//! Support for internationalized domain names.
+//!
+//! The [`Uri`] type only accepts ASCII characters, so a URI with a host name
+//! like `bücher.example` cannot be parsed directly. The functions in this
+//! module convert such host names to their ASCII-compatible ("punycode") form,
+//! such as `xn--bcher-kva.example`, and back again for display.
+//!
+//! When the `idna` feature is enabled, URI strings given to the client's
+//! request helpers, such as [`get`](crate::get) and
+//! [`HttpClient::post`](crate::HttpClient::post), are converted automatically.
+//! Request builders from the [`http`] crate parse their URIs themselves, so
+//! pass them a URI returned by [`parse_uri`] instead of a string. The
+//! [effective URI](crate::ResponseExt::effective_uri) of a response holds the
+//! converted host name, which [`unicode_host`] maps back.
+//!
+//! Everything in this module requires the `idna` feature to be enabled.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! let response = isahc::get("https://bücher.example/")?;
+//! # Ok::<(), isahc::Error>(())
+//! ```
+//!
+//! ```
+//! use isahc::idn;
+//!
+//! let uri = idn::parse_uri("https://bücher.example/katalog?q=ä")?;
+//! assert_eq!(uri.host(), Some("xn--bcher-kva.example"));
+//! assert_eq!(idn::unicode_host(&uri).as_ref().map(String::as_str), Some("bücher.example"));
+//! # Ok::<(), isahc::Error>(())
+//! ```
+
+use crate::Error;
+use http::Uri;
+
+/// Parse a URI that may contain a Unicode host name.
+///
+/// The host name is converted to its ASCII-compatible form according to
+/// UTS #46. Any other non-ASCII characters in the URI, such as in the path or
+/// query, are percent-encoded as UTF-8.
+pub fn parse_uri(uri: &str) -> Result<Uri, Error> {
+    let (start, end) = host_range(uri);
+    let host = &uri[start..end];
+
+    let mut converted = String::with_capacity(uri.len());
+    converted.push_str(&uri[..start]);
+
+    match idna::domain_to_ascii(host) {
+        Ok(ascii) => converted.push_str(&ascii),
+        // Leave invalid names as-is, parsing the URI will report the error.
+        Err(_) => converted.push_str(host),
+    }
+
+    for c in uri[end..].chars() {
+        if c.is_ascii() {
+            converted.push(c);
+        } else {
+            let mut buf = [0; 4];
+
+            for byte in c.encode_utf8(&mut buf).bytes() {
+                converted.push_str(&format!("%{:02X}", byte));
+            }
+        }
+    }
+
+    converted
+        .parse()
+        .map_err(|e: http::uri::InvalidUri| Error::InvalidHttpFormat(e.into()))
+}
+
+/// Get the host name of a URI in its Unicode form, for display purposes.
+///
+/// This is useful for showing the effective URI of a response to users.
+/// Returns `None` if the URI does not have a host.
+pub fn unicode_host(uri: &Uri) -> Option<String> {
+    uri.host().map(|host| idna::domain_to_unicode(host).0)
+}
+
+/// Find the byte range of the host name in a URI string.
+fn host_range(uri: &str) -> (usize, usize) {
+    let authority_start = uri.find("://").map(|i| i + 3).unwrap_or(0);
+    let authority_end = uri[authority_start..]
+        .find(|c| c == '/' || c == '?' || c == '#')
+        .map(|i| authority_start + i)
+        .unwrap_or_else(|| uri.len());
+    let authority = &uri[authority_start..authority_end];
+
+    let host_start = authority.rfind('@').map(|i| i + 1).unwrap_or(0);
+    let host_and_port = &authority[host_start..];
+
+    // IP literals never need conversion.
+    if host_and_port.starts_with('[') {
+        return (authority_start + host_start, authority_start + host_start);
+    }
+
+    let host_len = match host_and_port.rfind(':') {
+        Some(i) if host_and_port[i + 1..].bytes().all(|b| b.is_ascii_digit()) => i,
+        _ => host_and_port.len(),
+    };
+
+    (
+        authority_start + host_start,
+        authority_start + host_start + host_len,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(uri: &str) -> &str {
+        let (start, end) = host_range(uri);
+        &uri[start..end]
+    }
+
+    #[test]
+    fn host_is_found() {
+        assert_eq!(host("https://example.org"), "example.org");
+        assert_eq!(host("https://example.org:8080/path"), "example.org");
+        assert_eq!(host("https://user:pw@example.org/path?q"), "example.org");
+        assert_eq!(host("example.org/path"), "example.org");
+        assert_eq!(host("http://[::1]:80/"), "");
+    }
+
+    #[test]
+    fn unicode_host_is_converted() {
+        let uri = parse_uri("http://user@münchen.example:8080/straße?q=ä").unwrap();
+
+        assert_eq!(
+            uri,
+            "http://user@xn--mnchen-3ya.example:8080/stra%C3%9Fe?q=%C3%A4"
+        );
+        assert_eq!(unicode_host(&uri).unwrap(), "münchen.example");
+    }
+
+    #[test]
+    fn ascii_uri_is_unchanged() {
+        assert_eq!(
+            parse_uri("https://example.org/a?b=c").unwrap(),
+            "https://example.org/a?b=c"
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! HTTP client interceptor API.
+//!
+//! Interceptors wrap the sending of each request made by a client, and can
+//! inspect or change the request before it is sent, decide not to send it at
+//! all, send it more than once, or inspect or change the response. This makes
+//! them suitable for things like signing requests, logging, caching and
+//! tracing, layered on top of the client.
+//!
+//! Unlike middleware, interceptors are asynchronous and get to decide when and
+//! whether the rest of the chain is invoked, using the given [`Next`] handle.
+
+use crate::{Body, Error, HttpClient};
+use http::{Request, Response};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+
+/// The future returned by an interceptor, resolving to the response for the
+/// intercepted request.
+pub type InterceptorFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send + 'a>>;
+
+/// Base trait for interceptors.
+///
+/// Since clients may be used to send requests concurrently, all interceptors
+/// must be synchronized and must be able to account for multiple requests
+/// being made in parallel.
+///
+/// # Examples
+///
+/// ```no_run
+/// use futures::future::FutureExt;
+/// use isahc::interceptor::{Interceptor, InterceptorFuture, Next};
+/// use isahc::prelude::*;
+///
+/// /// Logs the status of every response.
+/// struct Logger;
+///
+/// impl Interceptor for Logger {
+///     fn intercept<'a>(&'a self, request: Request<Body>, next: Next<'a>) -> InterceptorFuture<'a> {
+///         let uri = request.uri().clone();
+///
+///         Box::pin(next.run(request).map(move |result| {
+///             if let Ok(response) = &result {
+///                 println!("{}: {}", uri, response.status());
+///             }
+///             result
+///         }))
+///     }
+/// }
+///
+/// let client = HttpClient::builder()
+///     .interceptor(Logger)
+///     .build()?;
+/// # Ok::<(), isahc::Error>(())
+/// ```
+pub trait Interceptor: Send + Sync + 'static {
+    /// Intercept a request, returning a future for its response.
+    ///
+    /// To continue sending the request, pass it on to the next interceptor in
+    /// the chain using [`Next::run`].
+    fn intercept<'a>(&'a self, request: Request<Body>, next: Next<'a>) -> InterceptorFuture<'a>;
+}
+
+/// A handle for invoking the rest of the interceptor chain, and eventually
+/// sending the request.
+pub struct Next<'a> {
+    client: &'a HttpClient,
+    interceptors: &'a [Box<dyn Interceptor>],
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(client: &'a HttpClient, interceptors: &'a [Box<dyn Interceptor>]) -> Self {
+        Self {
+            client,
+            interceptors,
+        }
+    }
+
+    /// Pass a request on to the next interceptor, or send it if there are no
+    /// more interceptors.
+    pub fn run(self, request: Request<Body>) -> InterceptorFuture<'a> {
+        match self.interceptors.split_first() {
+            Some((interceptor, rest)) => {
+                interceptor.intercept(request, Next::new(self.client, rest))
+            }
+            None => Box::pin(self.client.send_async_without_interceptors(request)),
+        }
+    }
+
+    /// Send a request in the background, skipping the rest of the chain, and
+    /// pass the response to the given function on another thread.
+    pub(crate) fn send_detached(
+        &self,
+        request: Request<Body>,
+        f: impl FnOnce(Result<Response<Body>, Error>) + Send + 'static,
+    ) -> Result<(), Error> {
+        self.client.send_detached(request, f)
+    }
+}
+
+impl fmt::Debug for Next<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Next")
+            .field("remaining", &self.interceptors.len())
+            .finish()
+    }
+}
@@ -1,11 +1,15 @@
-use futures_io::AsyncRead;
+use futures_io::{AsyncRead, AsyncWrite};
 use futures_util::future::FutureExt;
 use futures_util::io::{AsyncReadExt, ReadToString};
+use std::fmt;
 use std::future::Future;
-use std::io::Error;
+use std::io::{Error, ErrorKind};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
+/// Size of the buffer used when copying from a reader into a writer.
+const COPY_BUFFER_SIZE: usize = 8 * 1024;
+
 /// A future that produces a string from an [`AsyncRead`] reader.
 #[derive(Debug)]
 pub struct Text<'r, R: Unpin> {
@@ -55,3 +59,102 @@ impl<'r, R: Unpin> Drop for Text<'r, R> {
         self.inner.take();
     }
 }
+
+/// A future that copies all data from an [`AsyncRead`] reader into an
+/// [`AsyncWrite`] writer, producing the number of bytes copied.
+pub struct CopyTo<'a, R: Unpin, W: Unpin> {
+    reader: &'a mut R,
+    writer: &'a mut W,
+    buffer: Box<[u8]>,
+    position: usize,
+    filled: usize,
+    copied: u64,
+    eof: bool,
+}
+
+impl<'a, R: AsyncRead + Unpin, W: AsyncWrite + Unpin> CopyTo<'a, R, W> {
+    /// Create a new future copying from a given reader into a given writer.
+    pub(crate) fn new(reader: &'a mut R, writer: &'a mut W) -> Self {
+        Self {
+            reader,
+            writer,
+            buffer: vec![0; COPY_BUFFER_SIZE].into_boxed_slice(),
+            position: 0,
+            filled: 0,
+            copied: 0,
+            eof: false,
+        }
+    }
+}
+
+impl<'a, R: AsyncRead + Unpin, W: AsyncWrite + Unpin> Future for CopyTo<'a, R, W> {
+    type Output = Result<u64, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        loop {
+            // Refill the buffer once everything in it has been written.
+            if this.position == this.filled && !this.eof {
+                match Pin::new(&mut *this.reader).poll_read(cx, &mut this.buffer) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                    Poll::Ready(Ok(0)) => this.eof = true,
+                    Poll::Ready(Ok(len)) => {
+                        this.position = 0;
+                        this.filled = len;
+                    }
+                }
+            }
+
+            while this.position < this.filled {
+                match Pin::new(&mut *this.writer)
+                    .poll_write(cx, &this.buffer[this.position..this.filled])
+                {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                    Poll::Ready(Ok(0)) => return Poll::Ready(Err(ErrorKind::WriteZero.into())),
+                    Poll::Ready(Ok(len)) => {
+                        this.position += len;
+                        this.copied += len as u64;
+                    }
+                }
+            }
+
+            if this.eof {
+                return match Pin::new(&mut *this.writer).poll_flush(cx) {
+                    Poll::Pending => Poll::Pending,
+                    Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+                    Poll::Ready(Ok(())) => Poll::Ready(Ok(this.copied)),
+                };
+            }
+        }
+    }
+}
+
+impl<R: Unpin, W: Unpin> fmt::Debug for CopyTo<'_, R, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CopyTo")
+            .field("copied", &self.copied)
+            .field("eof", &self.eof)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::Join;
+
+    #[test]
+    fn copy_to_copies_everything() {
+        let data = vec![7; COPY_BUFFER_SIZE * 3 + 5];
+        let mut reader = futures_util::io::AllowStdIo::new(std::io::Cursor::new(data.clone()));
+        let mut writer = futures_util::io::AllowStdIo::new(Vec::new());
+
+        let copied = CopyTo::new(&mut reader, &mut writer).join().unwrap();
+
+        assert_eq!(copied, data.len() as u64);
+        assert_eq!(writer.into_inner(), data);
+    }
+}
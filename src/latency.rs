@@ -0,0 +1,153 @@
+//! Tracking of response times per host, used to derive adaptive timeouts.
+
+use crate::config::AdaptiveTimeout;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Number of most recent response times kept for each host.
+const WINDOW_SIZE: usize = 100;
+
+/// Number of response times that must be known for a host before a timeout is
+/// derived from them.
+const MIN_SAMPLES: usize = 20;
+
+/// Keeps a sliding window of response times for every host a client talks to.
+#[derive(Debug)]
+pub(crate) struct LatencyTracker {
+    config: AdaptiveTimeout,
+    hosts: Mutex<HashMap<String, VecDeque<Duration>>>,
+}
+
+impl LatencyTracker {
+    pub(crate) fn new(config: AdaptiveTimeout) -> Self {
+        Self {
+            config,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record the time it took to complete a request to the given host.
+    pub(crate) fn record(&self, host: &str, latency: Duration) {
+        let mut hosts = self.hosts.lock().unwrap();
+
+        let samples = hosts
+            .entry(host.to_owned())
+            .or_insert_with(|| VecDeque::with_capacity(WINDOW_SIZE));
+
+        if samples.len() == WINDOW_SIZE {
+            samples.pop_front();
+        }
+
+        samples.push_back(latency);
+    }
+
+    /// Get the timeout to use for a new request to the given host, if enough
+    /// is known about the host to derive one.
+    pub(crate) fn timeout(&self, host: &str) -> Option<Duration> {
+        let hosts = self.hosts.lock().unwrap();
+        let samples = hosts
+            .get(host)
+            .filter(|samples| samples.len() >= MIN_SAMPLES)?;
+
+        let mut timeout = percentile(samples, self.config.percentile) * self.config.multiplier;
+
+        if timeout < self.config.min {
+            timeout = self.config.min;
+        }
+
+        if let Some(max) = self.config.max {
+            if timeout > max {
+                timeout = max;
+            }
+        }
+
+        Some(timeout)
+    }
+}
+
+/// Get the value below which the given percentage of samples fall.
+fn percentile(samples: &VecDeque<Duration>, percentile: u8) -> Duration {
+    let mut sorted = samples.iter().cloned().collect::<Vec<_>>();
+    sorted.sort();
+
+    let rank = (sorted.len() * percentile as usize + 99) / 100;
+
+    sorted[rank.max(1) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker() -> LatencyTracker {
+        LatencyTracker::new(
+            AdaptiveTimeout::new(90, 2)
+                .min(Duration::from_millis(50))
+                .max(Duration::from_secs(10)),
+        )
+    }
+
+    #[test]
+    fn no_timeout_without_enough_samples() {
+        let tracker = tracker();
+
+        for _ in 1..MIN_SAMPLES {
+            tracker.record("example.org", Duration::from_millis(100));
+        }
+
+        assert_eq!(tracker.timeout("example.org"), None);
+        assert_eq!(tracker.timeout("example.com"), None);
+    }
+
+    #[test]
+    fn timeout_is_percentile_times_multiplier() {
+        let tracker = tracker();
+
+        for i in 1..=100 {
+            tracker.record("example.org", Duration::from_millis(i * 10));
+        }
+
+        assert_eq!(
+            tracker.timeout("example.org"),
+            Some(Duration::from_millis(1800))
+        );
+    }
+
+    #[test]
+    fn timeout_is_clamped() {
+        let tracker = tracker();
+
+        for _ in 0..MIN_SAMPLES {
+            tracker.record("fast.example.org", Duration::from_millis(1));
+            tracker.record("slow.example.org", Duration::from_secs(60));
+        }
+
+        assert_eq!(
+            tracker.timeout("fast.example.org"),
+            Some(Duration::from_millis(50))
+        );
+        assert_eq!(
+            tracker.timeout("slow.example.org"),
+            Some(Duration::from_secs(10))
+        );
+    }
+
+    #[test]
+    fn only_recent_samples_are_kept() {
+        let tracker = tracker();
+
+        for _ in 0..WINDOW_SIZE {
+            tracker.record("example.org", Duration::from_secs(5));
+        }
+
+        for _ in 0..WINDOW_SIZE {
+            tracker.record("example.org", Duration::from_millis(100));
+        }
+
+        assert_eq!(
+            tracker.timeout("example.org"),
+            Some(Duration::from_millis(200))
+        );
+    }
+}
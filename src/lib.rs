@@ -149,30 +149,63 @@ use lazy_static::lazy_static;
 #[cfg(feature = "cookies")]
 pub mod cookies;
 
+#[cfg(feature = "idna")]
+pub mod idn;
+
 #[cfg(feature = "middleware-api")]
 pub mod middleware;
 #[cfg(not(feature = "middleware-api"))]
 #[allow(unreachable_pub, unused)]
 mod middleware;
 
+#[cfg(feature = "test-utils")]
+pub mod testing;
+
 mod agent;
 mod body;
+pub mod cache;
+mod cancel;
 mod client;
+pub mod clock;
 pub mod config;
 mod error;
+mod failover;
+mod form;
 mod handler;
+pub mod interceptor;
 mod io;
+mod latency;
+mod metrics;
+pub mod multipart;
 mod parse;
+mod proxy;
+mod redirect;
 mod request;
+pub mod resolver;
 mod response;
+mod retry;
+pub mod sniff;
+pub mod sse;
+mod stats;
 mod task;
+mod tls;
+#[cfg(feature = "upgrade")]
+pub mod upgrade;
+mod uri;
+pub mod validators;
 
 pub use crate::{
     body::Body,
+    cancel::CancellationToken,
     client::{HttpClient, HttpClientBuilder, ResponseFuture},
-    error::Error,
+    error::{Error, Phase, StatusError},
+    form::Form,
+    metrics::Metrics,
     request::{RequestBuilderExt, RequestExt},
-    response::ResponseExt,
+    response::{ConnectionInfo, Redirect, ResponseExt, Trailer},
+    stats::HostStats,
+    tls::TlsInfo,
+    uri::IntoUri,
 };
 
 /// Re-export of the standard HTTP types.
@@ -191,7 +224,7 @@ pub mod prelude {
 /// [`HttpClient::get`] for details.
 pub fn get<U>(uri: U) -> Result<Response<Body>, Error>
 where
-    http::Uri: http::HttpTryFrom<U>,
+    U: IntoUri,
 {
     HttpClient::shared().get(uri)
 }
@@ -202,7 +235,7 @@ where
 /// [`HttpClient::get_async`] for details.
 pub fn get_async<U>(uri: U) -> ResponseFuture<'static>
 where
-    http::Uri: http::HttpTryFrom<U>,
+    U: IntoUri,
 {
     HttpClient::shared().get_async(uri)
 }
@@ -213,7 +246,7 @@ where
 /// [`HttpClient::head`] for details.
 pub fn head<U>(uri: U) -> Result<Response<Body>, Error>
 where
-    http::Uri: http::HttpTryFrom<U>,
+    U: IntoUri,
 {
     HttpClient::shared().head(uri)
 }
@@ -224,7 +257,7 @@ where
 /// [`HttpClient::head_async`] for details.
 pub fn head_async<U>(uri: U) -> ResponseFuture<'static>
 where
-    http::Uri: http::HttpTryFrom<U>,
+    U: IntoUri,
 {
     HttpClient::shared().head_async(uri)
 }
@@ -235,7 +268,7 @@ where
 /// [`HttpClient::post`] for details.
 pub fn post<U>(uri: U, body: impl Into<Body>) -> Result<Response<Body>, Error>
 where
-    http::Uri: http::HttpTryFrom<U>,
+    U: IntoUri,
 {
     HttpClient::shared().post(uri, body)
 }
@@ -247,7 +280,7 @@ where
 /// [`HttpClient::post_async`] for details.
 pub fn post_async<U>(uri: U, body: impl Into<Body>) -> ResponseFuture<'static>
 where
-    http::Uri: http::HttpTryFrom<U>,
+    U: IntoUri,
 {
     HttpClient::shared().post_async(uri, body)
 }
@@ -258,7 +291,7 @@ where
 /// [`HttpClient::put`] for details.
 pub fn put<U>(uri: U, body: impl Into<Body>) -> Result<Response<Body>, Error>
 where
-    http::Uri: http::HttpTryFrom<U>,
+    U: IntoUri,
 {
     HttpClient::shared().put(uri, body)
 }
@@ -270,7 +303,7 @@ where
 /// [`HttpClient::put_async`] for details.
 pub fn put_async<U>(uri: U, body: impl Into<Body>) -> ResponseFuture<'static>
 where
-    http::Uri: http::HttpTryFrom<U>,
+    U: IntoUri,
 {
     HttpClient::shared().put_async(uri, body)
 }
@@ -281,7 +314,7 @@ where
 /// [`HttpClient::delete`] for details.
 pub fn delete<U>(uri: U) -> Result<Response<Body>, Error>
 where
-    http::Uri: http::HttpTryFrom<U>,
+    U: IntoUri,
 {
     HttpClient::shared().delete(uri)
 }
@@ -292,7 +325,7 @@ where
 /// [`HttpClient::delete_async`] for details.
 pub fn delete_async<U>(uri: U) -> ResponseFuture<'static>
 where
-    http::Uri: http::HttpTryFrom<U>,
+    U: IntoUri,
 {
     HttpClient::shared().delete_async(uri)
 }
@@ -0,0 +1,304 @@
+//! Metrics collected for individual requests.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Shortest interval over which a transfer speed is measured when looking for
+/// the peak speed, so that single bursts of data do not distort it.
+const PEAK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Metrics about the transfer of a single request and response.
+///
+/// Metrics are only collected if enabled with
+/// [`HttpClientBuilder::metrics`](crate::HttpClientBuilder::metrics) or
+/// [`RequestBuilderExt::metrics`](crate::RequestBuilderExt::metrics), and are
+/// retrieved with [`ResponseExt::metrics`](crate::ResponseExt::metrics).
+///
+/// The response body is still being transferred when a response is returned,
+/// so the values are updated live until the body has been read completely.
+/// Cloning returns a handle to the same metrics.
+#[derive(Clone, Default)]
+pub struct Metrics {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    upload_speed: f64,
+    download_speed: f64,
+    peak_upload_speed: f64,
+    peak_download_speed: f64,
+    server_stall_time: Duration,
+    consumer_stall_time: Duration,
+    last_sample: Option<Sample>,
+    times: Times,
+}
+
+/// Seconds since the start of a transfer at which each of its phases
+/// completed, as reported by curl. Zero if the phase has not completed yet.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Times {
+    pub(crate) name_lookup: f64,
+    pub(crate) connect: f64,
+    pub(crate) app_connect: f64,
+    pub(crate) start_transfer: f64,
+    pub(crate) redirect: f64,
+    pub(crate) total: f64,
+}
+
+#[derive(Clone, Copy)]
+struct Sample {
+    time: Instant,
+    uploaded: u64,
+    downloaded: u64,
+}
+
+impl Metrics {
+    /// Get the average upload speed of the request body in bytes per second.
+    pub fn upload_speed(&self) -> f64 {
+        self.inner.lock().unwrap().upload_speed
+    }
+
+    /// Get the average download speed of the response body in bytes per
+    /// second.
+    pub fn download_speed(&self) -> f64 {
+        self.inner.lock().unwrap().download_speed
+    }
+
+    /// Get the highest upload speed sustained over a short interval, in bytes
+    /// per second.
+    pub fn peak_upload_speed(&self) -> f64 {
+        self.inner.lock().unwrap().peak_upload_speed
+    }
+
+    /// Get the highest download speed sustained over a short interval, in
+    /// bytes per second.
+    pub fn peak_download_speed(&self) -> f64 {
+        self.inner.lock().unwrap().peak_download_speed
+    }
+
+    /// Get the total time the transfer was stalled waiting for the server,
+    /// with no data being sent or received.
+    pub fn server_stall_time(&self) -> Duration {
+        self.inner.lock().unwrap().server_stall_time
+    }
+
+    /// Get the total time the transfer was paused because the response body
+    /// was not being read fast enough.
+    pub fn consumer_stall_time(&self) -> Duration {
+        self.inner.lock().unwrap().consumer_stall_time
+    }
+
+    /// Get the time it took to resolve the host name.
+    pub fn name_lookup_time(&self) -> Duration {
+        seconds(self.inner.lock().unwrap().times.name_lookup)
+    }
+
+    /// Get the time it took to establish a connection to the server once the
+    /// host name was resolved.
+    pub fn connect_time(&self) -> Duration {
+        let times = self.inner.lock().unwrap().times;
+
+        if times.connect > 0.0 {
+            seconds(times.connect - times.name_lookup)
+        } else {
+            Duration::default()
+        }
+    }
+
+    /// Get the time it took to complete the TLS handshake once connected. This
+    /// is zero for requests not using TLS.
+    pub fn secure_connect_time(&self) -> Duration {
+        let times = self.inner.lock().unwrap().times;
+
+        if times.app_connect > 0.0 {
+            seconds(times.app_connect - times.connect)
+        } else {
+            Duration::default()
+        }
+    }
+
+    /// Get the time from the start of the request until the first byte of the
+    /// response was received.
+    pub fn time_to_first_byte(&self) -> Duration {
+        seconds(self.inner.lock().unwrap().times.start_transfer)
+    }
+
+    /// Get the time spent on redirects before the final request was started.
+    /// This is zero if no redirects were followed.
+    pub fn redirect_time(&self) -> Duration {
+        seconds(self.inner.lock().unwrap().times.redirect)
+    }
+
+    /// Get the total time of the transfer so far, including redirects.
+    pub fn total_time(&self) -> Duration {
+        seconds(self.inner.lock().unwrap().times.total)
+    }
+
+    /// Record the progress of the transfer at a point in time.
+    ///
+    /// If no data moved since the previous sample and the consumer was not
+    /// holding up the transfer, the time in between is attributed to the
+    /// server.
+    pub(crate) fn record_progress(
+        &self,
+        time: Instant,
+        uploaded: u64,
+        downloaded: u64,
+        consumer_stalled: bool,
+    ) {
+        let mut inner = self.inner.lock().unwrap();
+        let sample = Sample {
+            time,
+            uploaded,
+            downloaded,
+        };
+
+        let last = match inner.last_sample {
+            Some(last) => last,
+            None => {
+                inner.last_sample = Some(sample);
+                return;
+            }
+        };
+
+        let elapsed = time.duration_since(last.time);
+
+        if elapsed < PEAK_INTERVAL {
+            return;
+        }
+
+        let seconds = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1e9;
+        let upload_speed = uploaded.saturating_sub(last.uploaded) as f64 / seconds;
+        let download_speed = downloaded.saturating_sub(last.downloaded) as f64 / seconds;
+
+        inner.peak_upload_speed = inner.peak_upload_speed.max(upload_speed);
+        inner.peak_download_speed = inner.peak_download_speed.max(download_speed);
+
+        if uploaded == last.uploaded && downloaded == last.downloaded && !consumer_stalled {
+            inner.server_stall_time += elapsed;
+        }
+
+        inner.last_sample = Some(sample);
+    }
+
+    /// Record time the transfer spent paused waiting for the consumer.
+    pub(crate) fn record_consumer_stall(&self, duration: Duration) {
+        self.inner.lock().unwrap().consumer_stall_time += duration;
+    }
+
+    /// Update the times at which each phase of the transfer completed.
+    pub(crate) fn set_times(&self, times: Times) {
+        self.inner.lock().unwrap().times = times;
+    }
+
+    /// Update the average transfer speeds, in bytes per second.
+    pub(crate) fn set_average_speeds(&self, upload: f64, download: f64) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.upload_speed = upload;
+        inner.download_speed = download;
+    }
+}
+
+impl fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metrics")
+            .field("upload_speed", &self.upload_speed())
+            .field("download_speed", &self.download_speed())
+            .field("peak_upload_speed", &self.peak_upload_speed())
+            .field("peak_download_speed", &self.peak_download_speed())
+            .field("server_stall_time", &self.server_stall_time())
+            .field("consumer_stall_time", &self.consumer_stall_time())
+            .field("name_lookup_time", &self.name_lookup_time())
+            .field("connect_time", &self.connect_time())
+            .field("secure_connect_time", &self.secure_connect_time())
+            .field("time_to_first_byte", &self.time_to_first_byte())
+            .field("redirect_time", &self.redirect_time())
+            .field("total_time", &self.total_time())
+            .finish()
+    }
+}
+
+/// Convert a number of seconds reported by curl into a duration.
+fn seconds(seconds: f64) -> Duration {
+    Duration::from_nanos((seconds.max(0.0) * 1e9) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peak_speed_is_measured_between_samples() {
+        let metrics = Metrics::default();
+        let start = Instant::now();
+
+        metrics.record_progress(start, 0, 0, false);
+        metrics.record_progress(start + Duration::from_secs(1), 0, 1000, false);
+        metrics.record_progress(start + Duration::from_secs(2), 0, 4000, false);
+        metrics.record_progress(start + Duration::from_secs(3), 0, 5000, false);
+
+        assert_eq!(metrics.peak_download_speed(), 3000.0);
+        assert_eq!(metrics.peak_upload_speed(), 0.0);
+    }
+
+    #[test]
+    fn short_intervals_are_merged() {
+        let metrics = Metrics::default();
+        let start = Instant::now();
+
+        metrics.record_progress(start, 0, 0, false);
+        metrics.record_progress(start + Duration::from_millis(10), 0, 1000, false);
+        metrics.record_progress(start + Duration::from_millis(500), 0, 1000, false);
+
+        assert_eq!(metrics.peak_download_speed(), 2000.0);
+    }
+
+    #[test]
+    fn phase_times_are_relative_to_previous_phase() {
+        let metrics = Metrics::default();
+
+        metrics.set_times(Times {
+            name_lookup: 0.01,
+            connect: 0.03,
+            app_connect: 0.07,
+            start_transfer: 0.2,
+            redirect: 0.0,
+            total: 0.25,
+        });
+
+        assert_eq!(metrics.name_lookup_time(), Duration::from_millis(10));
+        assert_eq!(metrics.connect_time(), Duration::from_millis(20));
+        assert_eq!(metrics.secure_connect_time(), Duration::from_millis(40));
+        assert_eq!(metrics.time_to_first_byte(), Duration::from_millis(200));
+        assert_eq!(metrics.total_time(), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn incomplete_phases_are_zero() {
+        let metrics = Metrics::default();
+
+        metrics.set_times(Times {
+            name_lookup: 0.01,
+            ..Times::default()
+        });
+
+        assert_eq!(metrics.connect_time(), Duration::default());
+        assert_eq!(metrics.secure_connect_time(), Duration::default());
+    }
+
+    #[test]
+    fn stalls_are_attributed() {
+        let metrics = Metrics::default();
+        let start = Instant::now();
+
+        metrics.record_progress(start, 0, 0, false);
+        metrics.record_progress(start + Duration::from_secs(1), 0, 0, false);
+        metrics.record_progress(start + Duration::from_secs(2), 0, 0, true);
+        metrics.record_consumer_stall(Duration::from_secs(1));
+
+        assert_eq!(metrics.server_stall_time(), Duration::from_secs(1));
+        assert_eq!(metrics.consumer_stall_time(), Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,361 @@
+//! Building `multipart/form-data` request bodies.
+//!
+//! This is the format used by HTML forms to upload files, as defined in
+//! [RFC 7578](https://tools.ietf.org/html/rfc7578).
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use isahc::multipart::{FormData, Part};
+//! use isahc::prelude::*;
+//!
+//! let form = FormData::new()
+//!     .text("title", "Holiday photos")
+//!     .part("notes", Part::bytes("day one").content_type("text/markdown"))
+//!     .file("photo", "beach.jpg")?;
+//!
+//! let response = Request::post("https://httpbin.org/post")
+//!     .body(form)?
+//!     .send()?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::Body;
+use bytes::Bytes;
+use futures_io::AsyncRead;
+use std::collections::hash_map::RandomState;
+use std::collections::VecDeque;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// A builder for a `multipart/form-data` body.
+///
+/// Parts are sent in the order they are added. The finished form can be
+/// converted into a [`Body`]. Requests with a form body are sent with a
+/// `Content-Type` of [`FormData::content_type`], which includes the boundary
+/// separating the parts, unless the request sets a different one.
+pub struct FormData {
+    boundary: String,
+    parts: Vec<(String, Part)>,
+}
+
+impl FormData {
+    /// Create a new, empty form with a random boundary.
+    pub fn new() -> Self {
+        Self::with_boundary(random_boundary())
+    }
+
+    /// Create a new, empty form using the given boundary.
+    ///
+    /// The boundary must not appear anywhere in the contents of the parts. A
+    /// random boundary as chosen by [`FormData::new`] should be used unless a
+    /// predictable one is needed, such as in tests.
+    pub fn with_boundary(boundary: impl Into<String>) -> Self {
+        Self {
+            boundary: boundary.into(),
+            parts: Vec::new(),
+        }
+    }
+
+    /// Get the boundary that separates the parts of this form.
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Get the value to use for the `Content-Type` header of a request with
+    /// this form as its body.
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Add a text field to the form.
+    pub fn text(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.part(name, Part::text(value))
+    }
+
+    /// Add a file field to the form, whose contents are streamed from the
+    /// file at the given path when the request is sent.
+    pub fn file(self, name: impl Into<String>, path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(self.part(name, Part::file(path)?))
+    }
+
+    /// Add a part to the form.
+    pub fn part(mut self, name: impl Into<String>, part: Part) -> Self {
+        self.parts.push((name.into(), part));
+        self
+    }
+
+    /// Finish the form and turn it into a body.
+    ///
+    /// If all parts are stored in memory, so is the body. Otherwise the body
+    /// streams the parts one after another, and has a known length only if
+    /// all parts do.
+    pub fn into_body(self) -> Body {
+        let content_type = self.content_type();
+        let mut segments = VecDeque::new();
+        let mut buffer = Vec::new();
+
+        for (name, part) in self.parts {
+            buffer.extend_from_slice(b"--");
+            buffer.extend_from_slice(self.boundary.as_bytes());
+            buffer.extend_from_slice(b"\r\n");
+            part.write_headers(&name, &mut buffer);
+
+            if let Some(bytes) = part.body.as_bytes() {
+                buffer.extend_from_slice(bytes);
+            } else if !part.body.is_empty() {
+                segments.push_back(Body::bytes(buffer.split_off(0)));
+                segments.push_back(part.body);
+            }
+
+            buffer.extend_from_slice(b"\r\n");
+        }
+
+        buffer.extend_from_slice(b"--");
+        buffer.extend_from_slice(self.boundary.as_bytes());
+        buffer.extend_from_slice(b"--\r\n");
+
+        if segments.is_empty() {
+            return Body::bytes(buffer).with_content_type(content_type);
+        }
+
+        segments.push_back(Body::bytes(buffer));
+
+        let length = segments
+            .iter()
+            .map(Body::len)
+            .fold(Some(0), |total, len| Some(total? + len?));
+        let reader = Concat(segments);
+
+        match length {
+            Some(length) => Body::reader_sized(reader, length),
+            None => Body::reader(reader),
+        }
+        .with_content_type(content_type)
+    }
+}
+
+impl Default for FormData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<FormData> for Body {
+    fn from(form: FormData) -> Self {
+        form.into_body()
+    }
+}
+
+impl fmt::Debug for FormData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FormData")
+            .field("boundary", &self.boundary)
+            .field("parts", &self.parts)
+            .finish()
+    }
+}
+
+/// A single part of a multipart form.
+#[derive(Debug)]
+pub struct Part {
+    body: Body,
+    file_name: Option<String>,
+    content_type: Option<String>,
+}
+
+impl Part {
+    /// Create a part containing text.
+    pub fn text(value: impl Into<String>) -> Self {
+        Self::new(Body::from(value.into()))
+    }
+
+    /// Create a part containing bytes stored in memory.
+    pub fn bytes(bytes: impl Into<Bytes>) -> Self {
+        Self::new(Body::bytes(bytes))
+    }
+
+    /// Create a part from a body, such as a streaming reader.
+    pub fn body(body: impl Into<Body>) -> Self {
+        Self::new(body.into())
+    }
+
+    /// Create a part that streams the contents of a file.
+    ///
+    /// The file is opened immediately, and its name is used as the file name
+    /// of the part. The content type defaults to `application/octet-stream`.
+    pub fn file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut part = Self::new(Body::from_file(path)?);
+        part.file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        part.content_type = Some(String::from("application/octet-stream"));
+
+        Ok(part)
+    }
+
+    fn new(body: Body) -> Self {
+        Self {
+            body,
+            file_name: None,
+            content_type: None,
+        }
+    }
+
+    /// Set the file name of this part, which tells the server that the part
+    /// is an uploaded file.
+    pub fn file_name(mut self, file_name: impl Into<String>) -> Self {
+        self.file_name = Some(file_name.into());
+        self
+    }
+
+    /// Set the content type of this part.
+    ///
+    /// If not set, the server assumes `text/plain`.
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    fn write_headers(&self, name: &str, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(b"Content-Disposition: form-data; name=\"");
+        buffer.extend_from_slice(escape(name).as_bytes());
+        buffer.push(b'"');
+
+        if let Some(file_name) = self.file_name.as_ref() {
+            buffer.extend_from_slice(b"; filename=\"");
+            buffer.extend_from_slice(escape(file_name).as_bytes());
+            buffer.push(b'"');
+        }
+
+        buffer.extend_from_slice(b"\r\n");
+
+        if let Some(content_type) = self.content_type.as_ref() {
+            buffer.extend_from_slice(b"Content-Type: ");
+            buffer.extend_from_slice(content_type.as_bytes());
+            buffer.extend_from_slice(b"\r\n");
+        }
+
+        buffer.extend_from_slice(b"\r\n");
+    }
+}
+
+/// Escape a name for use in a quoted `Content-Disposition` parameter, the
+/// same way browsers do.
+fn escape(name: &str) -> String {
+    name.replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn random_boundary() -> String {
+    // Each RandomState is seeded with fresh random keys.
+    let random = |seed| {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(seed);
+        hasher.finish()
+    };
+
+    format!("isahc-boundary-{:016x}{:016x}", random(0), random(1))
+}
+
+/// A reader that reads several bodies one after another.
+struct Concat(VecDeque<Body>);
+
+impl AsyncRead for Concat {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        while let Some(body) = self.0.front_mut() {
+            match AsyncRead::poll_read(Pin::new(body), cx, buf) {
+                Poll::Ready(Ok(0)) if !buf.is_empty() => {
+                    self.0.pop_front();
+                }
+                result => return result,
+            }
+        }
+
+        Poll::Ready(Ok(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Read};
+
+    #[test]
+    fn in_memory_form() {
+        let mut body = FormData::with_boundary("XyZ")
+            .text("name", "Jane \"JD\" Doe")
+            .part(
+                "avatar",
+                Part::bytes(&b"\x89PNG"[..])
+                    .file_name("me.png")
+                    .content_type("image/png"),
+            )
+            .into_body();
+
+        let expected = &b"--XyZ\r\n\
+            Content-Disposition: form-data; name=\"name\"\r\n\
+            \r\n\
+            Jane \"JD\" Doe\r\n\
+            --XyZ\r\n\
+            Content-Disposition: form-data; name=\"avatar\"; filename=\"me.png\"\r\n\
+            Content-Type: image/png\r\n\
+            \r\n\
+            \x89PNG\r\n\
+            --XyZ--\r\n"[..];
+
+        assert_eq!(body.as_bytes(), Some(expected));
+        assert_eq!(body.len(), Some(expected.len() as u64));
+
+        let mut bytes = Vec::new();
+        body.read_to_end(&mut bytes).unwrap();
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn names_are_escaped() {
+        let body = FormData::with_boundary("b")
+            .text("a\"\r\nb", "")
+            .into_body();
+
+        assert!(String::from_utf8_lossy(body.as_bytes().unwrap()).contains("name=\"a%22%0D%0Ab\""));
+    }
+
+    #[test]
+    fn streaming_form() {
+        let mut body = FormData::with_boundary("b")
+            .part(
+                "a",
+                Part::body(Body::reader_sized(Cursor::new(b"abc".to_vec()), 3)),
+            )
+            .part("b", Part::body(Body::reader(Cursor::new(b"def".to_vec()))))
+            .into_body();
+
+        assert_eq!(body.len(), None);
+
+        let mut text = String::new();
+        body.read_to_string(&mut text).unwrap();
+
+        assert_eq!(
+            text,
+            "--b\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nabc\r\n\
+             --b\r\nContent-Disposition: form-data; name=\"b\"\r\n\r\ndef\r\n\
+             --b--\r\n"
+        );
+    }
+
+    #[test]
+    fn boundaries_are_random() {
+        assert_ne!(FormData::new().boundary(), FormData::new().boundary());
+    }
+}
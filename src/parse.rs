@@ -1,5 +1,6 @@
 use http::header::{HeaderName, HeaderValue};
 use http::{StatusCode, Version};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 pub(crate) fn parse_status_line(line: &[u8]) -> Option<(Version, StatusCode)> {
     let mut parts = line.split(u8::is_ascii_whitespace);
@@ -31,28 +32,337 @@ pub(crate) fn parse_header(line: &[u8]) -> Option<(HeaderName, HeaderValue)> {
     let split_index = line.iter().position(|&f| f == b':')?;
 
     let name = HeaderName::from_bytes(&line[..split_index]).ok()?;
-    let mut value = &line[split_index + 1..];
+    let value = HeaderValue::from_bytes(trim(&line[split_index + 1..])).ok()?;
 
-    // Trim whitespace
-    while let Some((byte, right)) = value.split_first() {
+    Some((name, value))
+}
+
+/// Trim leading and trailing ASCII whitespace from a byte slice.
+pub(crate) fn trim(mut bytes: &[u8]) -> &[u8] {
+    while let Some((byte, right)) = bytes.split_first() {
         if byte.is_ascii_whitespace() {
-            value = right;
+            bytes = right;
         } else {
             break;
         }
     }
 
-    while let Some((byte, left)) = value.split_last() {
+    while let Some((byte, left)) = bytes.split_last() {
         if byte.is_ascii_whitespace() {
-            value = left;
+            bytes = left;
         } else {
             break;
         }
     }
 
-    let value = HeaderValue::from_bytes(value).ok()?;
+    bytes
+}
 
-    Some((name, value))
+/// Extract a filename suitable for saving to disk from a `Content-Disposition`
+/// header value.
+///
+/// The extended `filename*` parameter defined in RFC 5987 is preferred over a
+/// plain `filename` parameter if both are present.
+pub(crate) fn parse_content_disposition_filename(value: &[u8]) -> Option<String> {
+    let value = String::from_utf8_lossy(value);
+    let mut filename = None;
+    let mut extended_filename = None;
+
+    // The first item is the disposition type, which we don't care about.
+    for param in split_params(&value).into_iter().skip(1) {
+        let split_index = match param.find('=') {
+            Some(i) => i,
+            None => continue,
+        };
+        let name = param[..split_index].trim().to_ascii_lowercase();
+        let value = param[split_index + 1..].trim();
+
+        match name.as_str() {
+            "filename" => filename = Some(unquote(value)),
+            "filename*" => extended_filename = decode_extended_value(value),
+            _ => {}
+        }
+    }
+
+    extended_filename
+        .or(filename)
+        .and_then(|name| sanitize_filename(&name))
+}
+
+/// Make a filename suggested by a server safe to use as a local file name.
+///
+/// Any directory components are stripped, as well as characters that are
+/// not allowed in file names on common platforms. Returns `None` if nothing
+/// usable remains.
+pub(crate) fn sanitize_filename(name: &str) -> Option<String> {
+    let name = name.rsplit(|c| c == '/' || c == '\\').next().unwrap_or("");
+
+    let name: String = name
+        .chars()
+        .filter(|&c| !c.is_control() && !"<>:\"|?*".contains(c))
+        .collect();
+    let name = name.trim_matches(|c| c == '.' || c == ' ');
+
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.to_owned())
+    }
+}
+
+/// Parse the value of a `Content-Range` header in bytes. Returns the first and
+/// last byte positions of the range, if one was satisfied, and the complete
+/// length of the resource, if known.
+///
+/// https://tools.ietf.org/html/rfc7233#section-4.2
+pub(crate) fn parse_content_range(value: &str) -> Option<(Option<(u64, u64)>, Option<u64>)> {
+    let value = value.trim();
+
+    if !value.get(..6)?.eq_ignore_ascii_case("bytes ") {
+        return None;
+    }
+
+    let mut parts = value[6..].trim_start().splitn(2, '/');
+    let range = parts.next()?.trim();
+    let length = parts.next()?.trim();
+
+    let range = if range == "*" {
+        None
+    } else {
+        let mut positions = range.splitn(2, '-');
+        let first = positions.next()?.parse::<u64>().ok()?;
+        let last = positions.next()?.parse::<u64>().ok()?;
+
+        if first > last {
+            return None;
+        }
+
+        Some((first, last))
+    };
+
+    let length = if length == "*" {
+        None
+    } else {
+        Some(length.parse().ok()?)
+    };
+
+    // At least one of the two must be given.
+    if range.is_none() && length.is_none() {
+        return None;
+    }
+
+    Some((range, length))
+}
+
+/// Parse an HTTP date in any of the formats recipients are required to accept
+/// by [RFC 7231, section 7.1.1.1](https://tools.ietf.org/html/rfc7231#section-7.1.1.1):
+///
+/// - IMF-fixdate, such as `Sun, 06 Nov 1994 08:49:37 GMT`.
+/// - The obsolete RFC 850 format, such as `Sunday, 06-Nov-94 08:49:37 GMT`.
+/// - The obsolete asctime format, such as `Sun Nov  6 08:49:37 1994`.
+///
+/// Two-digit years of the RFC 850 format are taken to be between 1970 and
+/// 2069, since earlier dates cannot be represented anyway.
+pub(crate) fn parse_http_date(value: &str) -> Option<SystemTime> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+
+    let (year, month, day, time) = match parts.as_slice() {
+        [weekday, day, month, year, time, "GMT"] if is_weekday(weekday, ",", true) => {
+            if year.len() != 4 {
+                return None;
+            }
+
+            (
+                year.parse().ok()?,
+                parse_month(month)?,
+                day.parse().ok()?,
+                time,
+            )
+        }
+        [weekday, date, time, "GMT"] if is_weekday(weekday, ",", false) => {
+            let mut date = date.split('-');
+            let (day, month, year) = match (date.next(), date.next(), date.next(), date.next()) {
+                (Some(day), Some(month), Some(year), None) if year.len() == 2 => (day, month, year),
+                _ => return None,
+            };
+
+            let year = match year.parse::<u64>().ok()? {
+                year if year < 70 => 2000 + year,
+                year => 1900 + year,
+            };
+
+            (year, parse_month(month)?, day.parse().ok()?, time)
+        }
+        [weekday, month, day, time, year] if is_weekday(weekday, "", true) => {
+            if year.len() != 4 {
+                return None;
+            }
+
+            (
+                year.parse().ok()?,
+                parse_month(month)?,
+                day.parse().ok()?,
+                time,
+            )
+        }
+        _ => return None,
+    };
+
+    let mut time = time.split(':').map(|part| part.parse::<u64>().ok());
+    let (hour, minute, second) = match (time.next(), time.next(), time.next(), time.next()) {
+        (Some(Some(h)), Some(Some(m)), Some(Some(s)), None) => (h, m, s),
+        _ => return None,
+    };
+
+    if !(1970..=9999).contains(&year) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Leap seconds are allowed, but folded into the following minute.
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let seconds = days_since_epoch(year, month, day) * 86400 + hour * 3600 + minute * 60 + second;
+
+    Some(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// Check whether a word of an HTTP date is the name of a weekday, either
+/// abbreviated or in full, followed by the given suffix.
+fn is_weekday(word: &str, suffix: &str, abbreviated: bool) -> bool {
+    const WEEKDAYS: [&str; 7] = [
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+        "Thursday",
+        "Friday",
+        "Saturday",
+        "Sunday",
+    ];
+
+    if !word.ends_with(suffix) {
+        return false;
+    }
+
+    let name = &word[..word.len() - suffix.len()];
+
+    WEEKDAYS.iter().any(|weekday| {
+        if abbreviated {
+            name == &weekday[..3]
+        } else {
+            name == *weekday
+        }
+    })
+}
+
+/// Parse the abbreviated name of a month in an HTTP date.
+fn parse_month(name: &str) -> Option<u64> {
+    Some(match name {
+        "Jan" => 1,
+        "Feb" => 2,
+        "Mar" => 3,
+        "Apr" => 4,
+        "May" => 5,
+        "Jun" => 6,
+        "Jul" => 7,
+        "Aug" => 8,
+        "Sep" => 9,
+        "Oct" => 10,
+        "Nov" => 11,
+        "Dec" => 12,
+        _ => return None,
+    })
+}
+
+/// Get the number of days between the Unix epoch and a date in the proleptic
+/// Gregorian calendar, for years from 1970 onwards.
+fn days_since_epoch(year: u64, month: u64, day: u64) -> u64 {
+    // Count years from March so that the leap day comes last.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    // 719468 is the number of days from 0000-03-01 to 1970-01-01.
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Split a header value into `;`-separated parameters, ignoring separators
+/// inside quoted strings.
+fn split_params(value: &str) -> Vec<&str> {
+    let mut params = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for (i, c) in value.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if quoted && c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            quoted = !quoted;
+        } else if c == ';' && !quoted {
+            params.push(&value[start..i]);
+            start = i + 1;
+        }
+    }
+
+    params.push(&value[start..]);
+    params
+}
+
+fn unquote(value: &str) -> String {
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        let mut unquoted = String::with_capacity(value.len() - 2);
+        let mut chars = value[1..value.len() - 1].chars();
+
+        while let Some(c) = chars.next() {
+            if c == '\\' {
+                if let Some(c) = chars.next() {
+                    unquoted.push(c);
+                }
+            } else {
+                unquoted.push(c);
+            }
+        }
+
+        unquoted
+    } else {
+        value.to_owned()
+    }
+}
+
+/// Decode an RFC 5987 extended parameter value, such as
+/// `UTF-8''%e2%82%ac%20rates`.
+fn decode_extended_value(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let charset = parts.next()?.to_ascii_lowercase();
+    let _language = parts.next()?;
+    let encoded = parts.next()?.as_bytes();
+
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+
+    while i < encoded.len() {
+        if encoded[i] == b'%' && i + 2 < encoded.len() {
+            let hex = std::str::from_utf8(&encoded[i + 1..i + 3]).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            bytes.push(encoded[i]);
+            i += 1;
+        }
+    }
+
+    match charset.as_str() {
+        "utf-8" => String::from_utf8(bytes).ok(),
+        // ISO-8859-1 maps directly onto the first 256 code points.
+        "iso-8859-1" => Some(bytes.into_iter().map(char::from).collect()),
+        _ => None,
+    }
 }
 
 #[cfg(test)]
@@ -127,4 +437,101 @@ mod tests {
         assert_eq!(parse_header(b"a\nheader: bar"), None);
         assert_eq!(parse_header(b"foo : bar\r"), None);
     }
+
+    #[test]
+    fn parse_content_disposition() {
+        let parse = |value: &str| parse_content_disposition_filename(value.as_bytes());
+
+        assert_eq!(parse("attachment"), None);
+        assert_eq!(parse("inline; filename=foo.txt"), Some("foo.txt".into()));
+        assert_eq!(
+            parse("attachment; filename=\"a \\\"quoted\\\"; name.pdf\""),
+            Some("a quoted; name.pdf".into())
+        );
+        assert_eq!(
+            parse("attachment; filename=\"EURO rates\"; filename*=utf-8''%e2%82%ac%20rates"),
+            Some("€ rates".into())
+        );
+        assert_eq!(
+            parse("attachment; FILENAME*=iso-8859-1'en'%A3%20rates"),
+            Some("£ rates".into())
+        );
+        assert_eq!(parse("attachment; filename*=utf-8''%zz"), None);
+    }
+
+    #[test]
+    fn sanitize_filenames() {
+        assert_eq!(sanitize_filename("../../etc/passwd"), Some("passwd".into()));
+        assert_eq!(
+            sanitize_filename("C:\\Windows\\evil.exe"),
+            Some("evil.exe".into())
+        );
+        assert_eq!(sanitize_filename("what?.txt"), Some("what.txt".into()));
+        assert_eq!(sanitize_filename(".."), None);
+        assert_eq!(sanitize_filename(""), None);
+    }
+
+    #[test]
+    fn parse_http_dates() {
+        assert_eq!(
+            parse_http_date("Thu, 01 Jan 1970 00:00:00 GMT"),
+            Some(UNIX_EPOCH)
+        );
+        assert_eq!(
+            parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(UNIX_EPOCH + Duration::from_secs(784_111_777))
+        );
+        assert_eq!(
+            parse_http_date("Thu, 29 Feb 2024 12:00:00 GMT"),
+            Some(UNIX_EPOCH + Duration::from_secs(1_709_208_000))
+        );
+    }
+
+    #[test]
+    fn parse_obsolete_http_dates() {
+        let date = Some(UNIX_EPOCH + Duration::from_secs(784_111_777));
+
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), date);
+        assert_eq!(parse_http_date("Sun Nov  6 08:49:37 1994"), date);
+        assert_eq!(parse_http_date("Sun Nov 06 08:49:37 1994"), date);
+
+        assert_eq!(
+            parse_http_date("Thursday, 29-Feb-24 12:00:00 GMT"),
+            Some(UNIX_EPOCH + Duration::from_secs(1_709_208_000))
+        );
+    }
+
+    #[test]
+    fn parse_invalid_http_dates() {
+        assert_eq!(parse_http_date(""), None);
+        assert_eq!(parse_http_date("120"), None);
+        assert_eq!(parse_http_date("Sunday, 06 Nov 1994 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06-Nov-94 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sunday, 06-Nov-1994 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun Nov  6 08:49:37 1994 GMT"), None);
+        assert_eq!(parse_http_date("Sun, Nov  6 08:49:37 1994"), None);
+        assert_eq!(parse_http_date("Sun, 06 Foo 1994 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 PST"), None);
+    }
+
+    #[test]
+    fn parse_content_ranges() {
+        assert_eq!(
+            parse_content_range("bytes 0-499/1234"),
+            Some((Some((0, 499)), Some(1234)))
+        );
+        assert_eq!(
+            parse_content_range("bytes 500-999/*"),
+            Some((Some((500, 999)), None))
+        );
+        assert_eq!(
+            parse_content_range("bytes */1234"),
+            Some((None, Some(1234)))
+        );
+        assert_eq!(parse_content_range("bytes */*"), None);
+        assert_eq!(parse_content_range("bytes 10-5/20"), None);
+        assert_eq!(parse_content_range("items 0-1/2"), None);
+        assert_eq!(parse_content_range("bytes 0-1"), None);
+    }
 }
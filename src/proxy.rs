@@ -0,0 +1,265 @@
+//! Deciding which proxy requests are sent through, if any.
+
+use http::Uri;
+use std::env;
+use std::net::IpAddr;
+
+/// Proxies configured through environment variables, as read when the client
+/// was built.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct EnvProxies {
+    http: Option<String>,
+    https: Option<String>,
+    blacklist: ProxyBlacklist,
+}
+
+impl EnvProxies {
+    /// Read the proxy environment variables of the current process.
+    pub(crate) fn from_env() -> Self {
+        Self::from_vars(|name| env::var(name).ok())
+    }
+
+    /// Read proxy variables using the given lookup function, following the
+    /// conventions of curl. Lowercase names take precedence, and only the
+    /// lowercase `http_proxy` is used, since `HTTP_PROXY` may be set from a
+    /// request header by CGI servers.
+    fn from_vars(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let var = |names: &[&str]| {
+            names
+                .iter()
+                .filter_map(|name| lookup(name))
+                .find(|value| !value.is_empty())
+        };
+
+        let all = var(&["all_proxy", "ALL_PROXY"]);
+
+        Self {
+            http: var(&["http_proxy"]).or_else(|| all.clone()),
+            https: var(&["https_proxy", "HTTPS_PROXY"]).or(all),
+            blacklist: var(&["no_proxy", "NO_PROXY"])
+                .map(|value| ProxyBlacklist::new(value.split(',')))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Get the proxy to use for a request to the given URI, if any.
+    pub(crate) fn proxy_for(&self, uri: &Uri) -> Option<&str> {
+        if uri
+            .host()
+            .map_or(false, |host| self.blacklist.contains(host))
+        {
+            return None;
+        }
+
+        let proxy = match uri.scheme_str() {
+            Some("https") => self.https.as_ref(),
+            Some("http") => self.http.as_ref(),
+            _ => None,
+        };
+
+        proxy.map(String::as_str)
+    }
+}
+
+/// A list of hosts that requests should be sent to directly, bypassing any
+/// proxy.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ProxyBlacklist {
+    rules: Vec<Rule>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Rule {
+    /// Matches every host.
+    All,
+    /// Matches a domain name and all of its subdomains.
+    Domain(String),
+    /// Matches IP addresses in a network, given as an address and prefix
+    /// length.
+    Network(IpAddr, u8),
+}
+
+impl ProxyBlacklist {
+    /// Parse a list of entries, each of which may be `*`, a domain name, an
+    /// IP address, or a network in CIDR notation. Invalid entries are
+    /// ignored.
+    pub(crate) fn new<I, T>(entries: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        Self {
+            rules: entries
+                .into_iter()
+                .filter_map(|entry| {
+                    let rule = Rule::parse(entry.as_ref());
+
+                    if rule.is_none() {
+                        log::warn!("ignoring invalid proxy blacklist entry: {}", entry.as_ref());
+                    }
+
+                    rule
+                })
+                .collect(),
+        }
+    }
+
+    /// Check whether the given host should bypass the proxy.
+    pub(crate) fn contains(&self, host: &str) -> bool {
+        let host = host.trim_matches(|c| c == '[' || c == ']').to_lowercase();
+        let host = host.trim_end_matches('.');
+        let addr = host.parse::<IpAddr>().ok();
+
+        self.rules.iter().any(|rule| match (rule, addr) {
+            (Rule::All, _) => true,
+            (Rule::Domain(domain), None) => {
+                host == domain.as_str()
+                    || (host.ends_with(domain.as_str())
+                        && host[..host.len() - domain.len()].ends_with('.'))
+            }
+            (Rule::Network(network, prefix), Some(addr)) => in_network(addr, *network, *prefix),
+            _ => false,
+        })
+    }
+}
+
+impl Rule {
+    fn parse(entry: &str) -> Option<Self> {
+        let entry = entry.trim().to_lowercase();
+
+        if entry == "*" {
+            return Some(Rule::All);
+        }
+
+        if let Some(i) = entry.find('/') {
+            let addr = entry[..i].parse::<IpAddr>().ok()?;
+            let prefix = entry[i + 1..].parse::<u8>().ok()?;
+
+            if prefix > max_prefix(addr) {
+                return None;
+            }
+
+            return Some(Rule::Network(addr, prefix));
+        }
+
+        let entry = entry.trim_matches(|c| c == '[' || c == ']');
+
+        if let Ok(addr) = entry.parse::<IpAddr>() {
+            return Some(Rule::Network(addr, max_prefix(addr)));
+        }
+
+        let domain = entry.trim_start_matches('.').trim_end_matches('.');
+
+        if domain.is_empty() {
+            None
+        } else {
+            Some(Rule::Domain(domain.to_owned()))
+        }
+    }
+}
+
+fn max_prefix(addr: IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+/// Check whether an address is in the network with the given address and
+/// prefix length.
+fn in_network(addr: IpAddr, network: IpAddr, prefix: u8) -> bool {
+    match (addr, network) {
+        (IpAddr::V4(addr), IpAddr::V4(network)) => {
+            let mask = u32::max_value()
+                .checked_shl(32 - u32::from(prefix))
+                .unwrap_or(0);
+
+            u32::from(addr) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(addr), IpAddr::V6(network)) => {
+            let mask = u128::max_value()
+                .checked_shl(128 - u32::from(prefix))
+                .unwrap_or(0);
+
+            u128::from(addr) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_proxies_by_scheme() {
+        let proxies = EnvProxies::from_vars(|name| match name {
+            "http_proxy" => Some("http://proxy:3128".into()),
+            "HTTPS_PROXY" => Some("http://secure-proxy:3128".into()),
+            "NO_PROXY" => Some("localhost, 10.0.0.0/8".into()),
+            _ => None,
+        });
+
+        let proxy_for = |uri: &str| proxies.proxy_for(&uri.parse().unwrap());
+
+        assert_eq!(proxy_for("http://example.org"), Some("http://proxy:3128"));
+        assert_eq!(
+            proxy_for("https://example.org"),
+            Some("http://secure-proxy:3128")
+        );
+        assert_eq!(proxy_for("http://localhost:8080"), None);
+        assert_eq!(proxy_for("https://10.1.2.3"), None);
+    }
+
+    #[test]
+    fn env_proxies_ignore_uppercase_http_proxy() {
+        let proxies = EnvProxies::from_vars(|name| match name {
+            "HTTP_PROXY" => Some("http://evil:3128".into()),
+            "ALL_PROXY" => Some("socks5h://proxy:1080".into()),
+            _ => None,
+        });
+
+        assert_eq!(
+            proxies.proxy_for(&"http://example.org".parse().unwrap()),
+            Some("socks5h://proxy:1080")
+        );
+    }
+
+    #[test]
+    fn wildcard_matches_everything() {
+        let blacklist = ProxyBlacklist::new(&["*"]);
+
+        assert!(blacklist.contains("example.org"));
+        assert!(blacklist.contains("127.0.0.1"));
+    }
+
+    #[test]
+    fn domains_match_subdomains() {
+        let blacklist = ProxyBlacklist::new(&["example.org", ".internal"]);
+
+        assert!(blacklist.contains("example.org"));
+        assert!(blacklist.contains("www.Example.org"));
+        assert!(blacklist.contains("service.internal"));
+        assert!(!blacklist.contains("notexample.org"));
+        assert!(!blacklist.contains("example.com"));
+    }
+
+    #[test]
+    fn networks_match_addresses() {
+        let blacklist = ProxyBlacklist::new(&["10.0.0.0/8", "192.168.1.1", "fd00::/8"]);
+
+        assert!(blacklist.contains("10.1.2.3"));
+        assert!(!blacklist.contains("11.0.0.1"));
+        assert!(blacklist.contains("192.168.1.1"));
+        assert!(!blacklist.contains("192.168.1.2"));
+        assert!(blacklist.contains("[fd12::1]"));
+        assert!(!blacklist.contains("[fe80::1]"));
+    }
+
+    #[test]
+    fn invalid_entries_are_ignored() {
+        let blacklist = ProxyBlacklist::new(&["10.0.0.0/33", "", "example.org"]);
+
+        assert_eq!(blacklist.rules, vec![Rule::Domain("example.org".into())]);
+    }
+}
@@ -0,0 +1,424 @@
+//! Support for following redirects according to a redirect handler.
+
+use crate::config::{RedirectAction, RedirectAttempt, RedirectHandler, RedirectPolicy};
+use crate::response::Redirect;
+use http::{header, Extensions, Method, Response, StatusCode, Uri};
+
+/// Maximum number of redirects followed for a single request, regardless of
+/// what the redirect policy decides.
+pub(crate) const MAX_HOPS: u32 = 50;
+
+/// Tracks the redirects followed for a single request.
+#[derive(Debug)]
+pub(crate) struct RedirectState {
+    handler: RedirectHandler,
+    hops: u32,
+    history: Vec<Redirect>,
+}
+
+impl RedirectState {
+    pub(crate) fn new(handler: RedirectHandler) -> Self {
+        Self {
+            handler,
+            hops: 0,
+            history: Vec::new(),
+        }
+    }
+
+    /// Get the number of redirects received so far.
+    pub(crate) fn hops(&self) -> u32 {
+        self.hops
+    }
+
+    /// Get the redirects followed so far.
+    pub(crate) fn history(&self) -> &[Redirect] {
+        &self.history
+    }
+
+    /// Record that a redirect from the given URI is being followed.
+    pub(crate) fn followed(&mut self, uri: Uri, status: StatusCode) {
+        self.history.push(Redirect { uri, status });
+    }
+
+    /// Ask the redirect policy what to do with a response to a request for
+    /// the given URI. Returns `None` if the response is not a redirect, or
+    /// otherwise the URI being redirected to along with the decision made.
+    pub(crate) fn check<T>(
+        &mut self,
+        previous_uri: &Uri,
+        response: &Response<T>,
+    ) -> Option<(Uri, RedirectAction)> {
+        if !is_redirect(response.status()) {
+            return None;
+        }
+
+        let location = response.headers().get(header::LOCATION)?.to_str().ok()?;
+        let uri = resolve_location(previous_uri, location)?;
+
+        self.hops += 1;
+
+        let attempt = RedirectAttempt {
+            status: response.status(),
+            uri,
+            previous_uri: previous_uri.clone(),
+            hops: self.hops,
+        };
+        let action = (self.handler.0)(&attempt);
+
+        Some((attempt.uri, action))
+    }
+}
+
+/// Get the redirect handler that applies to a request, if any.
+///
+/// A handler set for the request wins over one set for the client, and so
+/// does a redirect policy set for the request.
+pub(crate) fn handler(extensions: &Extensions, defaults: &Extensions) -> Option<RedirectHandler> {
+    if let Some(handler) = extensions.get::<RedirectHandler>() {
+        return Some(handler.clone());
+    }
+
+    if extensions.get::<RedirectPolicy>().is_some() {
+        return None;
+    }
+
+    defaults.get::<RedirectHandler>().cloned()
+}
+
+/// Check if a status code is a redirect that can be followed.
+fn is_redirect(status: StatusCode) -> bool {
+    match status.as_u16() {
+        301 | 302 | 303 | 307 | 308 => true,
+        _ => false,
+    }
+}
+
+/// Check if a redirect with the given status code is followed with a `GET`
+/// request without a body, instead of repeating the original request.
+///
+/// Like curl, this only changes `POST` requests for `301` and `302`
+/// redirects, and all requests other than `HEAD` for `303` redirects.
+pub(crate) fn changes_method(status: StatusCode, method: &Method) -> bool {
+    match status.as_u16() {
+        301 | 302 => *method == Method::POST,
+        303 => *method != Method::HEAD,
+        _ => false,
+    }
+}
+
+/// Check if two URIs have the same origin, meaning the same scheme, host and
+/// port.
+pub(crate) fn same_origin(a: &Uri, b: &Uri) -> bool {
+    a.scheme_str() == b.scheme_str() && a.host() == b.host() && port(a) == port(b)
+}
+
+/// Get the port of a URI, or the default port of its scheme.
+fn port(uri: &Uri) -> Option<u16> {
+    match (uri.port_part(), uri.scheme_str()) {
+        (Some(port), _) => Some(port.as_u16()),
+        (None, Some("https")) => Some(443),
+        (None, Some("http")) => Some(80),
+        _ => None,
+    }
+}
+
+/// Resolve the value of a `Location` header against the URI of the request
+/// that was redirected.
+///
+/// The location is resolved as a URI reference, following
+/// https://tools.ietf.org/html/rfc3986#section-5.2. Fragments are dropped, as
+/// they are never sent to a server.
+///
+/// https://tools.ietf.org/html/rfc7231#section-7.1.2
+fn resolve_location(base: &Uri, location: &str) -> Option<Uri> {
+    let location = location.trim();
+    let location = location.split('#').next().unwrap_or(location);
+    let reference = Reference::parse(location);
+
+    let scheme = match reference.scheme {
+        Some(scheme) => scheme,
+        None => base.scheme_str()?,
+    };
+
+    let (authority, path, query) = if reference.scheme.is_some() || reference.authority.is_some() {
+        (
+            reference.authority,
+            remove_dot_segments(reference.path),
+            reference.query,
+        )
+    } else {
+        let authority = Some(base.authority_part()?.as_str());
+
+        if reference.path.is_empty() {
+            (
+                authority,
+                base.path().to_owned(),
+                reference.query.or_else(|| base.query()),
+            )
+        } else if reference.path.starts_with('/') {
+            (
+                authority,
+                remove_dot_segments(reference.path),
+                reference.query,
+            )
+        } else {
+            (
+                authority,
+                remove_dot_segments(&merge(base.path(), reference.path)),
+                reference.query,
+            )
+        }
+    };
+
+    let mut resolved = format!("{}:", scheme);
+
+    if let Some(authority) = authority {
+        resolved.push_str("//");
+        resolved.push_str(authority);
+    }
+
+    resolved.push_str(&path);
+
+    if let Some(query) = query {
+        resolved.push('?');
+        resolved.push_str(query);
+    }
+
+    resolved.parse().ok()
+}
+
+/// The components of a URI reference without a fragment.
+struct Reference<'a> {
+    scheme: Option<&'a str>,
+    authority: Option<&'a str>,
+    path: &'a str,
+    query: Option<&'a str>,
+}
+
+impl<'a> Reference<'a> {
+    /// Split a URI reference into its components.
+    ///
+    /// https://tools.ietf.org/html/rfc3986#section-4.1
+    fn parse(mut s: &'a str) -> Self {
+        let mut scheme = None;
+        let mut authority = None;
+        let mut query = None;
+
+        // A scheme ends at the first colon, if that comes before any slash or
+        // question mark and the scheme itself is valid.
+        if let Some(i) = s.find(|c| c == ':' || c == '/' || c == '?') {
+            if s[i..].starts_with(':') && is_scheme(&s[..i]) {
+                scheme = Some(&s[..i]);
+                s = &s[i + 1..];
+            }
+        }
+
+        if s.starts_with("//") {
+            let end = s[2..]
+                .find(|c| c == '/' || c == '?')
+                .map_or(s.len(), |i| i + 2);
+            authority = Some(&s[2..end]);
+            s = &s[end..];
+        }
+
+        if let Some(i) = s.find('?') {
+            query = Some(&s[i + 1..]);
+            s = &s[..i];
+        }
+
+        Self {
+            scheme,
+            authority,
+            path: s,
+            query,
+        }
+    }
+}
+
+/// Check if a string is a valid URI scheme.
+fn is_scheme(s: &str) -> bool {
+    let mut chars = s.chars();
+
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() => {}
+        _ => return false,
+    }
+
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+}
+
+/// Merge a relative path with the path of the base URI.
+///
+/// https://tools.ietf.org/html/rfc3986#section-5.2.3
+fn merge(base: &str, path: &str) -> String {
+    let directory = &base[..base.rfind('/').map(|i| i + 1).unwrap_or(0)];
+
+    if directory.is_empty() {
+        format!("/{}", path)
+    } else {
+        format!("{}{}", directory, path)
+    }
+}
+
+/// Remove `.` and `..` segments from a path.
+///
+/// https://tools.ietf.org/html/rfc3986#section-5.2.4
+fn remove_dot_segments(mut input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+
+    // Remove the last segment and its preceding slash, if any.
+    fn pop(output: &mut String) {
+        let end = output.rfind('/').unwrap_or(0);
+        output.truncate(end);
+    }
+
+    while !input.is_empty() {
+        if input.starts_with("../") {
+            input = &input[3..];
+        } else if input.starts_with("./") || input.starts_with("/./") {
+            input = &input[2..];
+        } else if input == "/." {
+            input = "/";
+        } else if input.starts_with("/../") {
+            input = &input[3..];
+            pop(&mut output);
+        } else if input == "/.." {
+            input = "/";
+            pop(&mut output);
+        } else if input == "." || input == ".." {
+            input = "";
+        } else {
+            let start = if input.starts_with('/') { 1 } else { 0 };
+            let end = input[start..].find('/').map_or(input.len(), |i| i + start);
+
+            output.push_str(&input[..end]);
+            input = &input[end..];
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn resolve(location: &str) -> String {
+        let base = "https://example.org/a/b?c=d".parse().unwrap();
+        resolve_location(&base, location).unwrap().to_string()
+    }
+
+    #[test]
+    fn resolve_absolute_location() {
+        assert_eq!(resolve("http://example.com/x"), "http://example.com/x");
+    }
+
+    #[test]
+    fn resolve_scheme_relative_location() {
+        assert_eq!(resolve("//example.com/x"), "https://example.com/x");
+    }
+
+    #[test]
+    fn resolve_absolute_path() {
+        assert_eq!(resolve("/x?y=z"), "https://example.org/x?y=z");
+    }
+
+    #[test]
+    fn resolve_relative_path() {
+        assert_eq!(resolve("x"), "https://example.org/a/x");
+        assert_eq!(resolve("?y=z"), "https://example.org/a/b?y=z");
+    }
+
+    #[test]
+    fn resolve_dot_segments() {
+        assert_eq!(resolve("../x"), "https://example.org/x");
+        assert_eq!(resolve("../../../x"), "https://example.org/x");
+        assert_eq!(resolve("./x"), "https://example.org/a/x");
+        assert_eq!(resolve("."), "https://example.org/a/");
+        assert_eq!(resolve(".."), "https://example.org/");
+        assert_eq!(resolve("x/./y/../z"), "https://example.org/a/x/z");
+        assert_eq!(resolve("/x/../y"), "https://example.org/y");
+        assert_eq!(resolve("//example.com/x/../y"), "https://example.com/y");
+        assert_eq!(
+            resolve("http://example.com/x/./y"),
+            "http://example.com/x/y"
+        );
+    }
+
+    #[test]
+    fn resolve_fragments() {
+        assert_eq!(resolve("#f"), "https://example.org/a/b?c=d");
+        assert_eq!(resolve("x#f"), "https://example.org/a/x");
+        assert_eq!(resolve("?y=z#f"), "https://example.org/a/b?y=z");
+        assert_eq!(resolve(""), "https://example.org/a/b?c=d");
+    }
+
+    #[test]
+    fn dot_segments_are_removed() {
+        assert_eq!(remove_dot_segments("/a/b/c/./../../g"), "/a/g");
+        assert_eq!(remove_dot_segments("mid/content=5/../6"), "mid/6");
+        assert_eq!(remove_dot_segments("/a/b/.."), "/a/");
+        assert_eq!(remove_dot_segments("/a/./b/."), "/a/b/");
+        assert_eq!(remove_dot_segments("/a/.b/..c"), "/a/.b/..c");
+    }
+
+    #[test]
+    fn only_post_changes_method_for_moved_and_found() {
+        for &status in &[StatusCode::MOVED_PERMANENTLY, StatusCode::FOUND] {
+            assert!(changes_method(status, &Method::POST));
+            assert!(!changes_method(status, &Method::GET));
+            assert!(!changes_method(status, &Method::PUT));
+            assert!(!changes_method(status, &Method::DELETE));
+        }
+
+        assert!(changes_method(StatusCode::SEE_OTHER, &Method::PUT));
+        assert!(!changes_method(StatusCode::SEE_OTHER, &Method::HEAD));
+        assert!(!changes_method(
+            StatusCode::TEMPORARY_REDIRECT,
+            &Method::POST
+        ));
+        assert!(!changes_method(
+            StatusCode::PERMANENT_REDIRECT,
+            &Method::POST
+        ));
+    }
+
+    #[test]
+    fn origins_compare_scheme_host_and_port() {
+        fn same(a: &str, b: &str) -> bool {
+            same_origin(&a.parse().unwrap(), &b.parse().unwrap())
+        }
+
+        assert!(same("https://example.org/a", "https://example.org:443/b"));
+        assert!(same("http://example.org/a", "http://example.org:80/b"));
+        assert!(!same("https://example.org/", "http://example.org/"));
+        assert!(!same("http://example.org/", "http://example.org:8080/"));
+        assert!(!same("http://example.org/", "http://example.com/"));
+    }
+
+    #[test]
+    fn only_redirect_statuses_are_checked() {
+        let mut state = RedirectState::new(RedirectHandler(Arc::new(|_: &RedirectAttempt| {
+            RedirectAction::Follow
+        })));
+        let base = "https://example.org/".parse().unwrap();
+
+        let response = Response::builder()
+            .status(200)
+            .header("Location", "/x")
+            .body(())
+            .unwrap();
+        assert!(state.check(&base, &response).is_none());
+
+        let response = Response::builder()
+            .status(302)
+            .header("Location", "/x")
+            .body(())
+            .unwrap();
+        let (uri, action) = state.check(&base, &response).unwrap();
+        assert_eq!(uri, "https://example.org/x");
+        assert_eq!(action, RedirectAction::Follow);
+        assert_eq!(state.hops, 1);
+    }
+}
@@ -1,10 +1,16 @@
+use crate::cache::CacheMode;
 use crate::client::ResponseFuture;
 use crate::config::*;
-use crate::{Body, Error};
+use crate::proxy::ProxyBlacklist;
+use crate::validators::Validators;
+use crate::{Body, CancellationToken, Error};
 use http::{Request, Response};
 use std::iter::FromIterator;
 use std::net::SocketAddr;
-use std::time::Duration;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// Provides additional methods when building a request for configuring various
 /// execution-related options on how the request should be sent.
@@ -30,11 +36,217 @@ pub trait RequestBuilderExt {
     /// ```
     fn timeout(&mut self, timeout: Duration) -> &mut Self;
 
+    /// Set a point in time by which the request must be complete.
+    ///
+    /// Unlike [`timeout`](RequestBuilderExt::timeout), which applies to each
+    /// attempt of sending the request separately, a deadline is a budget for
+    /// the request as a whole. Every retry and redirect followed by a custom
+    /// redirect policy only gets whatever time is left, and a retry is not
+    /// attempted at all if the deadline would pass while waiting for it. Time
+    /// spent waiting to be sent counts as well. If a timeout is also set, or
+    /// the client uses adaptive timeouts, the earlier of the two applies.
+    ///
+    /// The time left is measured with the client's
+    /// [`Clock`](crate::clock::Clock).
+    ///
+    /// A request that misses its deadline fails with [`Error::Timeout`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::prelude::*;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let deadline = Instant::now() + Duration::from_secs(10);
+    ///
+    /// // Both requests together may take at most 10 seconds.
+    /// let first = Request::get("https://example.org/a")
+    ///     .deadline(deadline)
+    ///     .body(())?
+    ///     .send()?;
+    /// let second = Request::get("https://example.org/b")
+    ///     .deadline(deadline)
+    ///     .body(())?
+    ///     .send()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    fn deadline(&mut self, deadline: Instant) -> &mut Self;
+
     /// Set a timeout for the initial connection phase.
     ///
     /// If not set, a connect timeout of 300 seconds will be used.
     fn connect_timeout(&mut self, timeout: Duration) -> &mut Self;
 
+    /// Set a timeout for resolving the host name of the server.
+    ///
+    /// If not set, the client's DNS timeout is used, if any. See
+    /// [`HttpClientBuilder::dns_timeout`](crate::HttpClientBuilder::dns_timeout)
+    /// for details.
+    fn dns_timeout(&mut self, timeout: Duration) -> &mut Self;
+
+    /// Set a timeout for the TLS handshake with the server.
+    ///
+    /// If not set, the client's TLS timeout is used, if any. See
+    /// [`HttpClientBuilder::tls_timeout`](crate::HttpClientBuilder::tls_timeout)
+    /// for details.
+    fn tls_timeout(&mut self, timeout: Duration) -> &mut Self;
+
+    /// Set a timeout for the server to begin responding.
+    ///
+    /// If not set, the client's first byte timeout is used, if any. See
+    /// [`HttpClientBuilder::first_byte_timeout`](crate::HttpClientBuilder::first_byte_timeout)
+    /// for details.
+    fn first_byte_timeout(&mut self, timeout: Duration) -> &mut Self;
+
+    /// Set how long receiving the response may go without any data arriving.
+    ///
+    /// If not set, the client's read timeout is used, if any. See
+    /// [`HttpClientBuilder::read_timeout`](crate::HttpClientBuilder::read_timeout)
+    /// for details.
+    fn read_timeout(&mut self, timeout: Duration) -> &mut Self;
+
+    /// Allow this request to be canceled with the given token.
+    ///
+    /// Once the token is canceled, the request is aborted and fails with
+    /// [`Error::Canceled`]. See [`CancellationToken`] for details.
+    fn cancellation_token(&mut self, token: CancellationToken) -> &mut Self;
+
+    /// Set the priority of this request relative to other requests waiting to
+    /// be sent by the same client.
+    ///
+    /// Priorities only matter if the client limits the number of concurrent
+    /// requests. See [`Priority`] for details.
+    ///
+    /// The default priority is [`Priority::Normal`].
+    fn priority(&mut self, priority: Priority) -> &mut Self;
+
+    /// Set a maximum amount of time that the request is allowed to wait for
+    /// the client to have room to send it.
+    ///
+    /// Requests only have to wait if the client limits the number of
+    /// concurrent requests. A request that is still waiting once this timeout
+    /// is reached fails with [`Error::AcquireTimeout`] without ever being sent.
+    /// Time spent waiting is not part of the regular
+    /// [`timeout`](RequestBuilderExt::timeout), which only starts counting once
+    /// the request is sent. The actual waiting time of a request is available
+    /// from [`ResponseExt::queue_time`](crate::ResponseExt::queue_time).
+    ///
+    /// If not set, the client's acquire timeout is used, if any.
+    fn acquire_timeout(&mut self, timeout: Duration) -> &mut Self;
+
+    /// Set whether the client adds framing headers to this request
+    /// automatically.
+    ///
+    /// If not set, the client's setting is used, which is to add them. See
+    /// [`HttpClientBuilder::automatic_headers`](crate::HttpClientBuilder::automatic_headers)
+    /// for details.
+    fn automatic_headers(&mut self, enable: bool) -> &mut Self;
+
+    /// Compress the request body with the given encoding before sending it,
+    /// and set the `Content-Encoding` header accordingly.
+    ///
+    /// If not set, the client's setting is used, which is to send bodies
+    /// unchanged. See
+    /// [`HttpClientBuilder::request_compression`](crate::HttpClientBuilder::request_compression)
+    /// for details.
+    ///
+    /// This method requires the `compression` feature to be enabled.
+    #[cfg(feature = "compression")]
+    fn request_compression(&mut self, compression: Compression) -> &mut Self;
+
+    /// Set exactly which encodings to advertise in the `Accept-Encoding`
+    /// header of this request.
+    ///
+    /// If not set, the client's setting is used, which is to advertise all
+    /// encodings supported by curl. See
+    /// [`HttpClientBuilder::accept_encodings`](crate::HttpClientBuilder::accept_encodings)
+    /// for details.
+    fn accept_encodings(
+        &mut self,
+        encodings: impl IntoIterator<Item = ContentEncoding>,
+    ) -> &mut Self;
+
+    /// Set whether a compressed response body is decoded automatically.
+    ///
+    /// If not set, the client's setting is used, which is to decode. See
+    /// [`HttpClientBuilder::automatic_decompression`](crate::HttpClientBuilder::automatic_decompression)
+    /// for details.
+    fn automatic_decompression(&mut self, enable: bool) -> &mut Self;
+
+    /// Enable or disable collecting [`Metrics`](crate::Metrics) for this
+    /// request.
+    ///
+    /// If not set, the client's setting is used, which is disabled by default.
+    fn metrics(&mut self, enable: bool) -> &mut Self;
+
+    /// Enable or disable collecting [`TlsInfo`](crate::TlsInfo) about the
+    /// secure connection of this request.
+    ///
+    /// If not set, the client's setting is used, which is disabled by default.
+    /// See [`HttpClientBuilder::tls_info`](crate::HttpClientBuilder::tls_info)
+    /// for details.
+    fn tls_info(&mut self, enable: bool) -> &mut Self;
+
+    /// Enable or disable including the certificate chain presented by the
+    /// server in the [`TlsInfo`](crate::TlsInfo) of the response.
+    ///
+    /// If not set, the client's setting is used, which is disabled by default.
+    fn tls_peer_certificates(&mut self, enable: bool) -> &mut Self;
+
+    /// Set whether malformed response headers are rejected.
+    ///
+    /// If not set, the client's setting is used, which is strict by default.
+    /// See [`HttpClientBuilder::strict`](crate::HttpClientBuilder::strict) for
+    /// details.
+    fn strict(&mut self, strict: bool) -> &mut Self;
+
+    /// Set the maximum total size of the response header, in bytes.
+    ///
+    /// If not set, the client's setting is used. See
+    /// [`HttpClientBuilder::max_response_header_size`](crate::HttpClientBuilder::max_response_header_size)
+    /// for details.
+    fn max_response_header_size(&mut self, bytes: usize) -> &mut Self;
+
+    /// Set the maximum number of header fields the response may have.
+    ///
+    /// If not set, the client's setting is used, which is unlimited by
+    /// default.
+    fn max_response_headers(&mut self, count: usize) -> &mut Self;
+
+    /// Set the maximum size of the response body, in bytes.
+    ///
+    /// If not set, the client's setting is used, which is unlimited by
+    /// default. See
+    /// [`HttpClientBuilder::max_response_body_size`](crate::HttpClientBuilder::max_response_body_size)
+    /// for details.
+    fn max_response_body_size(&mut self, bytes: u64) -> &mut Self;
+
+    /// Set a policy for automatically retrying this request if it fails.
+    ///
+    /// If not set, the client's policy is used, which does not retry by
+    /// default. See
+    /// [`HttpClientBuilder::retry_policy`](crate::HttpClientBuilder::retry_policy)
+    /// for details.
+    fn retry_policy(&mut self, policy: RetryPolicy) -> &mut Self;
+
+    /// Set a function to call with the progress of this request.
+    ///
+    /// If not set, the client's callback is used, if any. See
+    /// [`HttpClientBuilder::progress`](crate::HttpClientBuilder::progress)
+    /// for details.
+    fn progress(&mut self, f: impl Fn(Progress) + Send + Sync + 'static) -> &mut Self;
+
+    /// Set a function to call whenever an interim `1xx` response is received
+    /// for this request.
+    ///
+    /// If not set, the client's callback is used, if any. See
+    /// [`HttpClientBuilder::on_informational`](crate::HttpClientBuilder::on_informational)
+    /// for details.
+    fn on_informational(
+        &mut self,
+        f: impl Fn(http::StatusCode, &http::HeaderMap) + Send + Sync + 'static,
+    ) -> &mut Self;
+
     /// Set a policy for automatically following server redirects.
     ///
     /// The default is to not follow redirects.
@@ -61,21 +273,152 @@ pub trait RequestBuilderExt {
     /// ```
     fn redirect_policy(&mut self, policy: RedirectPolicy) -> &mut Self;
 
+    /// Decide whether to follow each redirect of this request by calling the
+    /// given function.
+    ///
+    /// A redirect handler takes precedence over a redirect policy set for the
+    /// same request, and overrides any redirect handler or policy of the
+    /// client. See
+    /// [`HttpClientBuilder::redirect_handler`](crate::HttpClientBuilder::redirect_handler)
+    /// for details.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::config::RedirectAction;
+    /// use isahc::prelude::*;
+    ///
+    /// // Only follow redirects to the same host.
+    /// let response = Request::get("https://httpbin.org/redirect/1")
+    ///     .redirect_handler(|attempt| {
+    ///         if attempt.uri().host() == attempt.previous_uri().host() {
+    ///             RedirectAction::Follow
+    ///         } else {
+    ///             RedirectAction::Stop
+    ///         }
+    ///     })
+    ///     .body(())?
+    ///     .send()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    fn redirect_handler(
+        &mut self,
+        f: impl Fn(&RedirectAttempt) -> RedirectAction + Send + Sync + 'static,
+    ) -> &mut Self;
+
     /// Update the `Referer` header automatically when following redirects.
     fn auto_referer(&mut self) -> &mut Self;
 
+    /// Make this request conditional on the resource having changed since a
+    /// previous response was received.
+    ///
+    /// The `ETag` and `Last-Modified` headers of the previous response, if
+    /// present, are sent back as `If-None-Match` and `If-Modified-Since`
+    /// headers. If the resource did not change, the server can answer with a
+    /// `304 Not Modified` response without a body, which can be combined with
+    /// the previous response using
+    /// [`merge_not_modified`](crate::validators::merge_not_modified).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::prelude::*;
+    ///
+    /// let previous = isahc::get("https://example.org/feed")?;
+    ///
+    /// let response = Request::get("https://example.org/feed")
+    ///     .revalidate(&previous)
+    ///     .body(())?
+    ///     .send()?;
+    ///
+    /// if response.status() == 304 {
+    ///     println!("feed did not change");
+    /// }
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    fn revalidate<T>(&mut self, previous: &Response<T>) -> &mut Self;
+
+    /// Set how the client's response cache is used for this request. See
+    /// [`CacheMode`] for the available modes.
+    ///
+    /// This has no effect unless a response cache is enabled with
+    /// [`HttpClientBuilder::response_cache`](crate::HttpClientBuilder::response_cache).
+    /// The default mode is [`CacheMode::Default`].
+    fn cache_mode(&mut self, mode: CacheMode) -> &mut Self;
+
     /// Set a preferred HTTP version the client should attempt to use to
     /// communicate to the server with.
     ///
     /// This is treated as a suggestion. A different version may be used if the
     /// server does not support it or negotiates a different version.
+    ///
+    /// This is a shorthand for
+    /// [`version_negotiation`](RequestBuilderExt::version_negotiation).
     fn preferred_http_version(&mut self, version: http::Version) -> &mut Self;
 
+    /// Set which HTTP versions may be used to send this request, and how the
+    /// version is negotiated.
+    ///
+    /// The version actually used for the response is available from
+    /// [`Response::version`](http::Response::version).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::config::VersionNegotiation;
+    /// use isahc::prelude::*;
+    ///
+    /// let response = Request::get("https://example.org")
+    ///     .version_negotiation(VersionNegotiation::http2())
+    ///     .body(())?
+    ///     .send()?;
+    ///
+    /// println!("used {:?}", response.version());
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    fn version_negotiation(&mut self, negotiation: VersionNegotiation) -> &mut Self;
+
     /// Enable TCP keepalive with a given probe interval.
     fn tcp_keepalive(&mut self, interval: Duration) -> &mut Self;
 
-    /// Enables the `TCP_NODELAY` option on connect.
-    fn tcp_nodelay(&mut self) -> &mut Self;
+    /// Enable or disable the `TCP_NODELAY` option on connect, which turns off
+    /// Nagle's algorithm.
+    ///
+    /// If not set, the client's setting is used. See
+    /// [`HttpClientBuilder::tcp_nodelay`](crate::HttpClientBuilder::tcp_nodelay)
+    /// for details.
+    fn tcp_nodelay(&mut self, enable: bool) -> &mut Self;
+
+    /// Restrict which IP versions may be used to connect to the server.
+    ///
+    /// If not set, the client's setting is used. See
+    /// [`HttpClientBuilder::ip_version`](crate::HttpClientBuilder::ip_version)
+    /// for details.
+    fn ip_version(&mut self, version: IpVersion) -> &mut Self;
+
+    /// Bind the connection for this request to the given local network
+    /// interface or address.
+    ///
+    /// If not set, the client's setting is used. See
+    /// [`HttpClientBuilder::interface`](crate::HttpClientBuilder::interface)
+    /// for details.
+    fn interface(&mut self, interface: impl Into<NetworkInterface>) -> &mut Self;
+
+    /// Restrict the local port the connection for this request is made from
+    /// to the given range.
+    ///
+    /// If not set, the client's setting is used. See
+    /// [`HttpClientBuilder::local_ports`](crate::HttpClientBuilder::local_ports)
+    /// for details.
+    fn local_ports(&mut self, ports: RangeInclusive<u16>) -> &mut Self;
+
+    /// Set how long to wait for a connection attempt before racing it with an
+    /// attempt over the other IP version.
+    ///
+    /// If not set, the client's setting is used. See
+    /// [`HttpClientBuilder::happy_eyeballs_timeout`](crate::HttpClientBuilder::happy_eyeballs_timeout)
+    /// for details.
+    fn happy_eyeballs_timeout(&mut self, timeout: Duration) -> &mut Self;
 
     /// Set a proxy to use for requests.
     ///
@@ -93,6 +436,45 @@ pub trait RequestBuilderExt {
     /// `http_proxy` or `https_proxy` environment variables.
     fn proxy(&mut self, proxy: http::Uri) -> &mut Self;
 
+    /// Set a list of hosts that should be sent this request directly, without
+    /// going through a proxy.
+    ///
+    /// If not set, the client's list is used, if any. See
+    /// [`HttpClientBuilder::proxy_blacklist`](crate::HttpClientBuilder::proxy_blacklist)
+    /// for the supported entries.
+    fn proxy_blacklist<I, T>(&mut self, hosts: I) -> &mut Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>;
+
+    /// Send this request over the Unix domain socket at the given path,
+    /// instead of connecting to the host named in the request URI.
+    ///
+    /// If not set, the client's socket is used, if any. See
+    /// [`HttpClientBuilder::unix_socket`](crate::HttpClientBuilder::unix_socket)
+    /// for details.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::prelude::*;
+    ///
+    /// let response = Request::get("http://localhost/containers/json")
+    ///     .unix_socket("/var/run/docker.sock")
+    ///     .body(())?
+    ///     .send()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    fn unix_socket(&mut self, path: impl Into<PathBuf>) -> &mut Self;
+
+    /// Abort the request if its transfer speed stays below a given number of
+    /// bytes per second for a given amount of time.
+    ///
+    /// If not set, the client's low speed limit is used, if any. See
+    /// [`HttpClientBuilder::low_speed_limit`](crate::HttpClientBuilder::low_speed_limit)
+    /// for details.
+    fn low_speed_limit(&mut self, bytes_per_second: u64, time: Duration) -> &mut Self;
+
     /// Set a maximum upload speed for the request body, in bytes per second.
     ///
     /// The default is unlimited.
@@ -110,6 +492,15 @@ pub trait RequestBuilderExt {
     /// [c-ares](https://c-ares.haxx.se), otherwise this option has no effect.
     fn dns_servers(&mut self, servers: impl IntoIterator<Item = SocketAddr>) -> &mut Self;
 
+    /// Set a mapping of host names to fixed addresses, used instead of
+    /// resolving those names through DNS when sending this request.
+    ///
+    /// If not set, the client's mapping is used, if any. The mapping stays in
+    /// the client's DNS cache after the request, and applies to later
+    /// requests to the same hosts as well. See
+    /// [`ResolveMap`](crate::config::ResolveMap) for details.
+    fn dns_resolve(&mut self, map: ResolveMap) -> &mut Self;
+
     /// Set a list of ciphers to use for SSL/TLS connections.
     ///
     /// The list of valid cipher names is dependent on the underlying SSL/TLS
@@ -147,9 +538,54 @@ pub trait RequestBuilderExt {
     /// # Ok::<(), isahc::Error>(())
     /// ```
     fn ssl_client_certificate(&mut self, certificate: ClientCertificate) -> &mut Self;
-    /// Controls the use of certificate validation.
+
+    /// Set the CA certificates to verify the server against, instead of the
+    /// system certificate store.
     ///
-    /// Defaults to `false` as per libcurl's default
+    /// The default value is none, which uses the system store.
+    fn ssl_ca_certificate(&mut self, certificate: CaCertificate) -> &mut Self;
+
+    /// Pin the public key the server is expected to present, either as a
+    /// path to a PEM or DER encoded public key file, or as one or more
+    /// `sha256//` hashes separated by `;`.
+    ///
+    /// If the server presents any other key, the request fails with
+    /// [`Error::BadServerCertificate`].
+    fn pinned_public_key(&mut self, key: impl Into<String>) -> &mut Self;
+
+    /// Set the oldest version of TLS to allow when connecting to the server.
+    ///
+    /// The default is chosen by the underlying TLS engine.
+    fn tls_min_version(&mut self, version: TlsVersion) -> &mut Self;
+
+    /// Set the newest version of TLS to allow when connecting to the server.
+    ///
+    /// By default the newest version supported by the TLS engine is allowed.
+    fn tls_max_version(&mut self, version: TlsVersion) -> &mut Self;
+
+    /// Check the server certificate against a certificate revocation list
+    /// read from a file in PEM format.
+    ///
+    /// The default value is none.
+    fn crl_file(&mut self, path: impl Into<PathBuf>) -> &mut Self;
+
+    /// Enable or disable resuming an earlier TLS session using a session ID or
+    /// session ticket.
+    ///
+    /// Defaults to `true`.
+    fn ssl_session_cache(&mut self, enable: bool) -> &mut Self;
+
+    /// Require the server to staple a valid OCSP response to the TLS
+    /// handshake, failing with [`Error::BadServerCertificate`] otherwise.
+    ///
+    /// Defaults to `false`.
+    fn ssl_verify_status(&mut self, enable: bool) -> &mut Self;
+
+    /// Accept invalid server certificates, including self-signed, expired and
+    /// mismatched ones, and skip hostname verification.
+    ///
+    /// Defaults to `false`, so certificates are validated as per libcurl's
+    /// default.
     ///
     /// # Warning
     ///
@@ -157,8 +593,46 @@ pub trait RequestBuilderExt {
     /// invalid certificates are trusted, *any* certificate for *any* site
     /// will be trusted for use. This includes expired certificates. This
     /// introduces significant vulnerabilities, and should only be used
-    /// as a last resort.
-    fn danger_allow_unsafe_ssl(&mut self, no_verify: bool) -> &mut Self;
+    /// as a last resort, such as when testing against a self-signed
+    /// development server.
+    fn danger_accept_invalid_certs(&mut self, accept_invalid: bool) -> &mut Self;
+
+    /// Accept a server certificate that does not match the hostname being
+    /// connected to, while still validating it otherwise.
+    ///
+    /// This takes precedence over the hostname check implied by
+    /// [`RequestBuilderExt::danger_accept_invalid_certs`].
+    ///
+    /// # Warning
+    ///
+    /// Without hostname verification, any server holding a valid certificate
+    /// for *any* site can impersonate the server being connected to.
+    fn danger_accept_invalid_hostnames(&mut self, accept_invalid: bool) -> &mut Self;
+
+    /// Controls the use of certificate validation.
+    #[deprecated(note = "renamed to `danger_accept_invalid_certs`")]
+    fn danger_allow_unsafe_ssl(&mut self, no_verify: bool) -> &mut Self {
+        self.danger_accept_invalid_certs(no_verify)
+    }
+
+    /// Set a client certificate to present to an HTTPS proxy.
+    ///
+    /// The default value is none.
+    fn proxy_ssl_client_certificate(&mut self, certificate: ClientCertificate) -> &mut Self;
+
+    /// Set the CA certificates to verify an HTTPS proxy against, instead of
+    /// the default certificate store.
+    fn proxy_ssl_ca_certificate(&mut self, certificate: CaCertificate) -> &mut Self;
+
+    /// Controls the use of certificate validation for an HTTPS proxy.
+    ///
+    /// Defaults to `false`, so proxy certificates are validated.
+    ///
+    /// # Warning
+    ///
+    /// As with [`RequestBuilderExt::danger_accept_invalid_certs`], this should
+    /// only be used as a last resort.
+    fn danger_accept_invalid_proxy_certs(&mut self, accept_invalid: bool) -> &mut Self;
 }
 
 impl RequestBuilderExt for http::request::Builder {
@@ -166,34 +640,190 @@ impl RequestBuilderExt for http::request::Builder {
         self.extension(Timeout(timeout))
     }
 
+    fn deadline(&mut self, deadline: Instant) -> &mut Self {
+        self.extension(Deadline(deadline))
+    }
+
     fn connect_timeout(&mut self, timeout: Duration) -> &mut Self {
         self.extension(ConnectTimeout(timeout))
     }
 
+    fn dns_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.extension(DnsTimeout(timeout))
+    }
+
+    fn tls_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.extension(TlsTimeout(timeout))
+    }
+
+    fn first_byte_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.extension(FirstByteTimeout(timeout))
+    }
+
+    fn read_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.extension(ReadTimeout(timeout))
+    }
+
+    fn cancellation_token(&mut self, token: CancellationToken) -> &mut Self {
+        self.extension(token)
+    }
+
+    fn priority(&mut self, priority: Priority) -> &mut Self {
+        self.extension(priority)
+    }
+
+    fn acquire_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.extension(AcquireTimeout(timeout))
+    }
+
+    fn automatic_headers(&mut self, enable: bool) -> &mut Self {
+        self.extension(AutomaticHeaders(enable))
+    }
+
+    #[cfg(feature = "compression")]
+    fn request_compression(&mut self, compression: Compression) -> &mut Self {
+        self.extension(compression)
+    }
+
+    fn accept_encodings(
+        &mut self,
+        encodings: impl IntoIterator<Item = ContentEncoding>,
+    ) -> &mut Self {
+        self.extension(AcceptEncodings::from_iter(encodings))
+    }
+
+    fn automatic_decompression(&mut self, enable: bool) -> &mut Self {
+        self.extension(AutomaticDecompression(enable))
+    }
+
+    fn metrics(&mut self, enable: bool) -> &mut Self {
+        self.extension(EnableMetrics(enable))
+    }
+
+    fn tls_info(&mut self, enable: bool) -> &mut Self {
+        self.extension(EnableTlsInfo(enable))
+    }
+
+    fn tls_peer_certificates(&mut self, enable: bool) -> &mut Self {
+        self.extension(EnablePeerCertificates(enable))
+    }
+
+    fn strict(&mut self, strict: bool) -> &mut Self {
+        self.extension(Strict(strict))
+    }
+
+    fn max_response_header_size(&mut self, bytes: usize) -> &mut Self {
+        self.extension(MaxResponseHeaderSize(bytes))
+    }
+
+    fn max_response_headers(&mut self, count: usize) -> &mut Self {
+        self.extension(MaxResponseHeaders(count))
+    }
+
+    fn max_response_body_size(&mut self, bytes: u64) -> &mut Self {
+        self.extension(MaxResponseBodySize(bytes))
+    }
+
+    fn retry_policy(&mut self, policy: RetryPolicy) -> &mut Self {
+        self.extension(policy)
+    }
+
+    fn progress(&mut self, f: impl Fn(Progress) + Send + Sync + 'static) -> &mut Self {
+        self.extension(ProgressCallback(Arc::new(f)))
+    }
+
+    fn on_informational(
+        &mut self,
+        f: impl Fn(http::StatusCode, &http::HeaderMap) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.extension(InformationalCallback(Arc::new(f)))
+    }
+
     fn redirect_policy(&mut self, policy: RedirectPolicy) -> &mut Self {
         self.extension(policy)
     }
 
+    fn redirect_handler(
+        &mut self,
+        f: impl Fn(&RedirectAttempt) -> RedirectAction + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.extension(RedirectHandler(Arc::new(f)))
+    }
+
     fn auto_referer(&mut self) -> &mut Self {
         self.extension(AutoReferer)
     }
 
+    fn revalidate<T>(&mut self, previous: &Response<T>) -> &mut Self {
+        if let Some(validators) = Validators::from_response(previous) {
+            if let Some(etag) = validators.etag() {
+                self.header(http::header::IF_NONE_MATCH, etag);
+            }
+
+            if let Some(date) = validators.last_modified() {
+                self.header(http::header::IF_MODIFIED_SINCE, date);
+            }
+        }
+
+        self
+    }
+
+    fn cache_mode(&mut self, mode: CacheMode) -> &mut Self {
+        self.extension(mode)
+    }
+
     fn preferred_http_version(&mut self, version: http::Version) -> &mut Self {
-        self.extension(PreferredHttpVersion(version))
+        self.version_negotiation(version.into())
+    }
+
+    fn version_negotiation(&mut self, negotiation: VersionNegotiation) -> &mut Self {
+        self.extension(negotiation)
     }
 
     fn tcp_keepalive(&mut self, interval: Duration) -> &mut Self {
         self.extension(TcpKeepAlive(interval))
     }
 
-    fn tcp_nodelay(&mut self) -> &mut Self {
-        self.extension(TcpNoDelay)
+    fn tcp_nodelay(&mut self, enable: bool) -> &mut Self {
+        self.extension(TcpNoDelay(enable))
+    }
+
+    fn ip_version(&mut self, version: IpVersion) -> &mut Self {
+        self.extension(version)
+    }
+
+    fn interface(&mut self, interface: impl Into<NetworkInterface>) -> &mut Self {
+        self.extension(interface.into())
+    }
+
+    fn local_ports(&mut self, ports: RangeInclusive<u16>) -> &mut Self {
+        self.extension(LocalPorts(ports))
+    }
+
+    fn happy_eyeballs_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.extension(HappyEyeballsTimeout(timeout))
     }
 
     fn proxy(&mut self, proxy: http::Uri) -> &mut Self {
         self.extension(Proxy(proxy))
     }
 
+    fn proxy_blacklist<I, T>(&mut self, hosts: I) -> &mut Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        self.extension(ProxyBlacklist::new(hosts))
+    }
+
+    fn unix_socket(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.extension(UnixSocket(path.into()))
+    }
+
+    fn low_speed_limit(&mut self, bytes_per_second: u64, time: Duration) -> &mut Self {
+        self.extension(LowSpeedLimit(bytes_per_second, time))
+    }
+
     fn max_upload_speed(&mut self, max: u64) -> &mut Self {
         self.extension(MaxUploadSpeed(max))
     }
@@ -206,6 +836,10 @@ impl RequestBuilderExt for http::request::Builder {
         self.extension(DnsServers::from_iter(servers))
     }
 
+    fn dns_resolve(&mut self, map: ResolveMap) -> &mut Self {
+        self.extension(map)
+    }
+
     fn ssl_ciphers(&mut self, servers: impl IntoIterator<Item = String>) -> &mut Self {
         self.extension(SslCiphers::from_iter(servers))
     }
@@ -213,8 +847,53 @@ impl RequestBuilderExt for http::request::Builder {
     fn ssl_client_certificate(&mut self, certificate: ClientCertificate) -> &mut Self {
         self.extension(certificate)
     }
-    fn danger_allow_unsafe_ssl(&mut self, allow_unsafe: bool) -> &mut Self {
-        self.extension(AllowUnsafeSsl(allow_unsafe))
+
+    fn ssl_ca_certificate(&mut self, certificate: CaCertificate) -> &mut Self {
+        self.extension(certificate)
+    }
+
+    fn pinned_public_key(&mut self, key: impl Into<String>) -> &mut Self {
+        self.extension(PinnedPublicKey(key.into()))
+    }
+
+    fn tls_min_version(&mut self, version: TlsVersion) -> &mut Self {
+        self.extension(TlsMinVersion(version))
+    }
+
+    fn tls_max_version(&mut self, version: TlsVersion) -> &mut Self {
+        self.extension(TlsMaxVersion(version))
+    }
+
+    fn crl_file(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.extension(CrlFile(path.into()))
+    }
+
+    fn ssl_session_cache(&mut self, enable: bool) -> &mut Self {
+        self.extension(SslSessionCache(enable))
+    }
+
+    fn ssl_verify_status(&mut self, enable: bool) -> &mut Self {
+        self.extension(VerifyStatus(enable))
+    }
+
+    fn danger_accept_invalid_certs(&mut self, accept_invalid: bool) -> &mut Self {
+        self.extension(AllowUnsafeSsl(accept_invalid))
+    }
+
+    fn danger_accept_invalid_hostnames(&mut self, accept_invalid: bool) -> &mut Self {
+        self.extension(AcceptInvalidHostnames(accept_invalid))
+    }
+
+    fn proxy_ssl_client_certificate(&mut self, certificate: ClientCertificate) -> &mut Self {
+        self.extension(ProxyClientCertificate(certificate))
+    }
+
+    fn proxy_ssl_ca_certificate(&mut self, certificate: CaCertificate) -> &mut Self {
+        self.extension(ProxyCaCertificate(certificate))
+    }
+
+    fn danger_accept_invalid_proxy_certs(&mut self, accept_invalid: bool) -> &mut Self {
+        self.extension(AllowUnsafeProxySsl(accept_invalid))
     }
 }
 
@@ -0,0 +1,160 @@
+//! Pluggable host name resolution.
+//!
+//! By default, host names are resolved by curl using the system resolver. A
+//! client can instead be given a custom [`Resolver`], which is consulted
+//! before each request is sent. This allows resolving names using a different
+//! DNS implementation, a service discovery system, or a custom cache.
+//!
+//! The addresses returned by the resolver are handed to curl as if they were
+//! set with
+//! [`RequestBuilderExt::dns_resolve`](crate::RequestBuilderExt::dns_resolve),
+//! so the `Host` header and TLS server name still use the original host name.
+//! Requests that already have a mapping set this way, and requests to IP
+//! addresses, are left alone. Redirects followed automatically by curl are
+//! not seen by the resolver.
+//!
+//! See
+//! [`HttpClientBuilder::resolver`](crate::HttpClientBuilder::resolver) for
+//! enabling a resolver.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use futures::future;
+//! use isahc::prelude::*;
+//! use isahc::resolver::{ResolveFuture, Resolver};
+//! use std::net::IpAddr;
+//!
+//! /// Sends all requests to the local host.
+//! struct Loopback;
+//!
+//! impl Resolver for Loopback {
+//!     fn resolve<'a>(&'a self, _host: &str, _port: u16) -> ResolveFuture<'a> {
+//!         Box::pin(future::ready(Ok(vec![IpAddr::from([127, 0, 0, 1])])))
+//!     }
+//! }
+//!
+//! let client = HttpClient::builder()
+//!     .resolver(Loopback)
+//!     .build()?;
+//! # Ok::<(), isahc::Error>(())
+//! ```
+
+use crate::config::ResolveMap;
+use crate::interceptor::{Interceptor, InterceptorFuture, Next};
+use crate::{Body, Error};
+use futures_util::future::{self, FutureExt};
+use http::{Request, Uri};
+use std::future::Future;
+use std::io;
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// The future returned by a resolver, resolving to the addresses of a host.
+pub type ResolveFuture<'a> = Pin<Box<dyn Future<Output = io::Result<Vec<IpAddr>>> + Send + 'a>>;
+
+/// Resolves host names to addresses.
+///
+/// Since clients may be used to send requests concurrently, resolvers must be
+/// synchronized and must be able to handle multiple lookups in parallel.
+pub trait Resolver: Send + Sync + 'static {
+    /// Resolve the given host name, to be connected to on the given port.
+    ///
+    /// The returned addresses are tried in order. Returning an error or no
+    /// addresses fails the request with
+    /// [`Error::CouldntResolveHost`](crate::Error::CouldntResolveHost).
+    fn resolve<'a>(&'a self, host: &str, port: u16) -> ResolveFuture<'a>;
+}
+
+/// Interceptor consulting a resolver before sending each request.
+pub(crate) struct ResolverInterceptor(pub(crate) Arc<dyn Resolver>);
+
+impl Interceptor for ResolverInterceptor {
+    fn intercept<'a>(
+        &'a self,
+        mut request: Request<Body>,
+        next: Next<'a>,
+    ) -> InterceptorFuture<'a> {
+        if request.extensions().get::<ResolveMap>().is_some() {
+            return next.run(request);
+        }
+
+        let (host, port) = match target(request.uri()) {
+            Some(target) => target,
+            None => return next.run(request),
+        };
+
+        Box::pin(self.0.resolve(&host, port).then(move |result| {
+            match result {
+                Ok(ref addrs) if addrs.is_empty() => {
+                    log::debug!("resolver returned no addresses for {}", host);
+                }
+                Ok(addrs) => {
+                    log::trace!("resolved {} to {:?}", host, addrs);
+                    request
+                        .extensions_mut()
+                        .insert(ResolveMap::new().add_all(&host, port, addrs));
+
+                    return next.run(request);
+                }
+                Err(e) => {
+                    log::debug!("failed to resolve {}: {}", host, e);
+                }
+            }
+
+            Box::pin(future::ready(Err(Error::CouldntResolveHost))) as InterceptorFuture<'a>
+        }))
+    }
+}
+
+/// Get the host name and port a request to the given URI will connect to, if
+/// the host needs to be resolved.
+pub(crate) fn target(uri: &Uri) -> Option<(String, u16)> {
+    let host = uri.host()?;
+
+    // IP addresses need no resolving. IPv6 addresses may be in brackets.
+    if host
+        .trim_matches(|c| c == '[' || c == ']')
+        .parse::<IpAddr>()
+        .is_ok()
+    {
+        return None;
+    }
+
+    let port = match (uri.port_part(), uri.scheme_str()) {
+        (Some(port), _) => port.as_u16(),
+        (None, Some("https")) => 443,
+        (None, Some("http")) => 80,
+        _ => return None,
+    };
+
+    Some((host.to_owned(), port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_of_host_names() {
+        assert_eq!(
+            target(&"http://example.org/".parse().unwrap()),
+            Some(("example.org".to_owned(), 80))
+        );
+        assert_eq!(
+            target(&"https://example.org/".parse().unwrap()),
+            Some(("example.org".to_owned(), 443))
+        );
+        assert_eq!(
+            target(&"http://example.org:8080/".parse().unwrap()),
+            Some(("example.org".to_owned(), 8080))
+        );
+    }
+
+    #[test]
+    fn ip_addresses_are_not_resolved() {
+        assert_eq!(target(&"http://127.0.0.1/".parse().unwrap()), None);
+        assert_eq!(target(&"http://[::1]:8080/".parse().unwrap()), None);
+    }
+}
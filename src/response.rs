@@ -1,10 +1,24 @@
-use crate::io::Text;
-use crate::Error;
-use futures_io::AsyncRead;
-use http::{Response, Uri};
+use crate::cache::CacheStatus;
+use crate::io::{CopyTo, Text};
+use crate::parse;
+use crate::sniff;
+use crate::sse::EventStream;
+use crate::task::Join;
+use crate::validators::NotModified;
+use crate::{Error, Metrics, StatusError, TlsInfo};
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_util::future;
+use http::{HeaderMap, Response, StatusCode, Uri};
+use lazy_static::lazy_static;
+use std::fmt;
 use std::fs::File;
+use std::future::Future;
 use std::io::{self, Read, Write};
-use std::path::Path;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::task::{Poll, Waker};
+use std::time::Duration;
 
 /// Provides extension methods for working with HTTP responses.
 pub trait ResponseExt<T> {
@@ -14,8 +28,142 @@ pub trait ResponseExt<T> {
     ///
     /// This information is only available if populated by the HTTP client that
     /// produced the response.
+    ///
+    /// With the `idna` feature enabled, Unicode host names are converted to
+    /// their ASCII-compatible form before a request is sent, so this URI holds
+    /// the converted host name.
+    /// [`idn::unicode_host`](crate::idn::unicode_host) maps it back for
+    /// display.
     fn effective_uri(&self) -> Option<&Uri>;
 
+    /// Get the redirects that were followed to arrive at this response, in
+    /// the order they were received.
+    ///
+    /// Each entry holds the URI that responded with a redirect and the
+    /// status code of that response. The URI that the last redirect pointed
+    /// to is the [effective URI](ResponseExt::effective_uri) of this response.
+    /// The list is empty if no redirects were followed.
+    ///
+    /// This information is only available if populated by the HTTP client that
+    /// produced the response.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::config::RedirectPolicy;
+    /// use isahc::prelude::*;
+    ///
+    /// let response = Request::get("https://httpbin.org/redirect/3")
+    ///     .redirect_policy(RedirectPolicy::Follow)
+    ///     .body(())?
+    ///     .send()?;
+    ///
+    /// for redirect in response.redirect_history() {
+    ///     println!("{} {}", redirect.status(), redirect.uri());
+    /// }
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    fn redirect_history(&self) -> &[Redirect];
+
+    /// Get how long the request waited in the client's queue before it could
+    /// be sent.
+    ///
+    /// Requests only have to wait if the client limits the number of requests
+    /// that may be in flight at once and that limit was reached. Otherwise this
+    /// is zero.
+    ///
+    /// This information is only available if populated by the HTTP client that
+    /// produced the response.
+    fn queue_time(&self) -> Option<Duration>;
+
+    /// If this is a `304 Not Modified` response to a request that the client
+    /// made conditional automatically, get information about the unchanged
+    /// resource.
+    ///
+    /// This is only ever populated if conditional requests are enabled with
+    /// [`HttpClientBuilder::conditional_requests`](crate::HttpClientBuilder::conditional_requests).
+    fn not_modified(&self) -> Option<&NotModified>;
+
+    /// Get whether this response was served from the client's response cache.
+    ///
+    /// This is only ever populated if a response cache is enabled with
+    /// [`HttpClientBuilder::response_cache`](crate::HttpClientBuilder::response_cache).
+    fn cache_status(&self) -> Option<CacheStatus>;
+
+    /// Get the metrics collected for this request and response, if enabled.
+    ///
+    /// See [`Metrics`] for details.
+    fn metrics(&self) -> Option<&Metrics>;
+
+    /// Get details about the TLS connection the response was received over,
+    /// if enabled.
+    ///
+    /// See [`TlsInfo`] for details.
+    fn tls_info(&self) -> Option<&TlsInfo>;
+
+    /// Get information about the connection the response was received over,
+    /// such as whether it was reused from the connection pool.
+    ///
+    /// This information is only available if populated by the HTTP client that
+    /// produced the response.
+    fn connection_info(&self) -> Option<&ConnectionInfo>;
+
+    /// Get the trailer of the response, containing any headers sent by the
+    /// server after the response body.
+    ///
+    /// Trailers are only available once the response body has been received
+    /// in full; see [`Trailer`] for ways to wait for them. Responses not
+    /// produced by an HTTP client have an empty trailer.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::prelude::*;
+    ///
+    /// let mut response = isahc::get("https://example.org")?;
+    /// let body = response.text()?;
+    ///
+    /// if let Some(status) = response.trailers().wait().get("grpc-status") {
+    ///     println!("status: {:?}", status);
+    /// }
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    fn trailers(&self) -> &Trailer;
+
+    /// Get a file name suggested for saving the response body to disk.
+    ///
+    /// The name is taken from the `Content-Disposition` header if present,
+    /// including extended `filename*` parameters as defined in RFC 5987.
+    /// Otherwise the last segment of the effective URI's path is used.
+    ///
+    /// The returned name is sanitized: directory components and characters
+    /// that are not valid in file names are removed, so it is safe to join
+    /// onto a local directory.
+    fn suggested_filename(&self) -> Option<String>;
+
+    /// Turn a response with a `4xx` or `5xx` status code into an
+    /// [`Error::BadStatus`] error, leaving other responses untouched.
+    ///
+    /// Up to `body_limit` bytes of the response body are read and attached to
+    /// the error, since most APIs carry the details of an error in the body.
+    /// Pass zero to skip reading the body.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::prelude::*;
+    ///
+    /// let mut response = isahc::get("https://httpbin.org/status/404")?;
+    ///
+    /// if let Err(isahc::Error::BadStatus(e)) = response.error_for_status(4096) {
+    ///     println!("{}: {}", e.status(), e.text());
+    /// }
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    fn error_for_status(&mut self, body_limit: u64) -> Result<(), Error>
+    where
+        T: Read;
+
     /// Copy the response body into a writer.
     ///
     /// Returns the number of bytes that were written.
@@ -46,6 +194,37 @@ pub trait ResponseExt<T> {
         File::create(path).and_then(|f| self.copy_to(f))
     }
 
+    /// Write the response body to a file in the given directory, using the
+    /// name returned by [`ResponseExt::suggested_filename`].
+    ///
+    /// If the response does not suggest a name, the file is named `download`.
+    ///
+    /// Returns the path of the file that was written.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::prelude::*;
+    ///
+    /// let path = isahc::get("https://httpbin.org/image/jpeg")?
+    ///     .copy_to_dir("downloads")?;
+    /// println!("saved to {}", path.display());
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    fn copy_to_dir(&mut self, dir: impl AsRef<Path>) -> io::Result<PathBuf>
+    where
+        T: Read,
+    {
+        let name = self
+            .suggested_filename()
+            .unwrap_or_else(|| String::from("download"));
+        let path = dir.as_ref().join(name);
+
+        self.copy_to_file(&path)?;
+
+        Ok(path)
+    }
+
     /// Get the response body as a string.
     ///
     /// This method consumes the entire response body stream and can only be
@@ -64,6 +243,20 @@ pub trait ResponseExt<T> {
     where
         T: Read;
 
+    /// Get the response body as a string, detecting its text encoding.
+    ///
+    /// Unlike [`ResponseExt::text`], this does not assume the body is UTF-8.
+    /// The encoding is determined from a byte order mark if present, then from
+    /// the `charset` parameter of the `Content-Type` header, and finally by
+    /// [sniffing](crate::sniff::encoding) the body itself. Invalid sequences
+    /// are replaced rather than causing an error.
+    ///
+    /// This method consumes the entire response body stream and can only be
+    /// called once, unless you can rewind this response body.
+    fn text_sniffed(&mut self) -> Result<String, Error>
+    where
+        T: Read;
+
     /// Get the response body as a string asynchronously.
     ///
     /// This method consumes the entire response body stream and can only be
@@ -72,6 +265,52 @@ pub trait ResponseExt<T> {
     where
         T: AsyncRead + Unpin;
 
+    /// Turn the response into a stream of [Server-Sent Events](crate::sse),
+    /// parsed incrementally from the response body.
+    ///
+    /// The stream ends with the response body. To reconnect automatically
+    /// instead, use
+    /// [`HttpClient::event_source`](crate::HttpClient::event_source).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::prelude::*;
+    ///
+    /// let response = Request::get("https://example.org/updates")
+    ///     .header("Accept", "text/event-stream")
+    ///     .body(())?
+    ///     .send()?;
+    ///
+    /// for event in response.into_event_stream() {
+    ///     println!("{}", event?.data());
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    fn into_event_stream(self) -> EventStream<T>
+    where
+        T: AsyncRead + Unpin;
+
+    /// Copy the response body into an asynchronous writer.
+    ///
+    /// The writer is flushed once the body has been copied completely. The
+    /// returned future produces the number of bytes that were written.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// use futures::io::AllowStdIo;
+    /// use isahc::prelude::*;
+    ///
+    /// let mut response = isahc::get_async("https://example.org").await?;
+    /// let mut stdout = AllowStdIo::new(std::io::stdout());
+    /// response.copy_to_async(&mut stdout).await?;
+    /// ```
+    fn copy_to_async<'a, W>(&'a mut self, writer: &'a mut W) -> CopyTo<'a, T, W>
+    where
+        T: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin;
+
     /// Deserialize the response body as JSON into a given type.
     ///
     /// This method requires the `json` feature to be enabled.
@@ -98,6 +337,80 @@ impl<T> ResponseExt<T> for Response<T> {
         self.extensions().get::<EffectiveUri>().map(|v| &v.0)
     }
 
+    fn redirect_history(&self) -> &[Redirect] {
+        self.extensions()
+            .get::<RedirectHistory>()
+            .map(|v| v.0.as_slice())
+            .unwrap_or(&[])
+    }
+
+    fn queue_time(&self) -> Option<Duration> {
+        self.extensions().get::<QueueTime>().map(|v| v.0)
+    }
+
+    fn not_modified(&self) -> Option<&NotModified> {
+        self.extensions().get()
+    }
+
+    fn cache_status(&self) -> Option<CacheStatus> {
+        self.extensions().get().cloned()
+    }
+
+    fn metrics(&self) -> Option<&Metrics> {
+        self.extensions().get()
+    }
+
+    fn tls_info(&self) -> Option<&TlsInfo> {
+        self.extensions().get()
+    }
+
+    fn connection_info(&self) -> Option<&ConnectionInfo> {
+        self.extensions().get()
+    }
+
+    fn trailers(&self) -> &Trailer {
+        lazy_static! {
+            static ref EMPTY: Trailer = {
+                let trailer = Trailer::new();
+                trailer.set(HeaderMap::new());
+                trailer
+            };
+        }
+
+        self.extensions().get().unwrap_or(&EMPTY)
+    }
+
+    fn suggested_filename(&self) -> Option<String> {
+        self.headers()
+            .get(http::header::CONTENT_DISPOSITION)
+            .and_then(|value| parse::parse_content_disposition_filename(value.as_bytes()))
+            .or_else(|| {
+                self.effective_uri()
+                    .and_then(|uri| uri.path().rsplit('/').next())
+                    .and_then(parse::sanitize_filename)
+            })
+    }
+
+    fn error_for_status(&mut self, body_limit: u64) -> Result<(), Error>
+    where
+        T: Read,
+    {
+        let status = self.status();
+
+        if !status.is_client_error() && !status.is_server_error() {
+            return Ok(());
+        }
+
+        let mut body = Vec::new();
+        Read::take(self.body_mut(), body_limit).read_to_end(&mut body)?;
+
+        Err(Error::BadStatus(StatusError {
+            status,
+            headers: self.headers().clone(),
+            body,
+        }))
+    }
+
     fn copy_to(&mut self, mut writer: impl Write) -> io::Result<u64>
     where
         T: Read,
@@ -114,6 +427,42 @@ impl<T> ResponseExt<T> for Response<T> {
         Ok(s)
     }
 
+    fn text_sniffed(&mut self) -> Result<String, Error>
+    where
+        T: Read,
+    {
+        let mut bytes = Vec::new();
+        self.body_mut().read_to_end(&mut bytes)?;
+
+        let declared = self
+            .headers()
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| {
+                value.split(';').skip(1).find_map(|param| {
+                    let mut pair = param.splitn(2, '=');
+                    match (pair.next(), pair.next()) {
+                        (Some(name), Some(label))
+                            if name.trim().eq_ignore_ascii_case("charset") =>
+                        {
+                            sniff::Encoding::from_label(label.trim_matches('"'))
+                        }
+                        _ => None,
+                    }
+                })
+            });
+
+        let encoding = match (sniff::bom(&bytes), declared) {
+            // A byte order mark is more reliable than the header.
+            (Some(encoding), _) => encoding,
+            // So is the body not being valid UTF-8 when the header claims it is.
+            (None, Some(sniff::Encoding::Utf8)) | (None, None) => sniff::encoding(&bytes),
+            (None, Some(declared)) => declared,
+        };
+
+        Ok(encoding.decode(&bytes))
+    }
+
     fn text_async(&mut self) -> Text<'_, T>
     where
         T: AsyncRead + Unpin,
@@ -121,6 +470,21 @@ impl<T> ResponseExt<T> for Response<T> {
         Text::new(self.body_mut())
     }
 
+    fn into_event_stream(self) -> EventStream<T>
+    where
+        T: AsyncRead + Unpin,
+    {
+        EventStream::new(self.into_body())
+    }
+
+    fn copy_to_async<'a, W>(&'a mut self, writer: &'a mut W) -> CopyTo<'a, T, W>
+    where
+        T: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        CopyTo::new(self.body_mut(), writer)
+    }
+
     #[cfg(feature = "json")]
     fn json<D>(&mut self) -> Result<D, serde_json::Error>
     where
@@ -132,3 +496,142 @@ impl<T> ResponseExt<T> for Response<T> {
 }
 
 pub(crate) struct EffectiveUri(pub(crate) Uri);
+
+/// The URI of the request that a response was received for, before any
+/// redirects were followed or endpoints failed over to.
+pub(crate) struct RequestUri(pub(crate) Uri);
+
+/// A redirect that was followed while making a request.
+///
+/// See [`ResponseExt::redirect_history`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Redirect {
+    pub(crate) uri: Uri,
+    pub(crate) status: StatusCode,
+}
+
+impl Redirect {
+    /// Get the URI that responded with the redirect.
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// Get the status code of the redirect response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+}
+
+pub(crate) struct RedirectHistory(pub(crate) Vec<Redirect>);
+
+/// Information about the connection a response was received over.
+///
+/// See [`ResponseExt::connection_info`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConnectionInfo {
+    pub(crate) id: Option<u64>,
+    pub(crate) reused: bool,
+}
+
+impl ConnectionInfo {
+    /// Get an identifier for the connection, unique among the connections
+    /// opened by the same client.
+    ///
+    /// Responses with the same identifier were received over the same
+    /// connection. Requires libcurl 8.2 or newer, and is `None` otherwise.
+    pub fn id(&self) -> Option<u64> {
+        self.id
+    }
+
+    /// Get whether an existing connection from the connection pool was used
+    /// for the request, rather than opening a new one.
+    pub fn is_reused(&self) -> bool {
+        self.reused
+    }
+}
+
+pub(crate) struct QueueTime(pub(crate) Duration);
+
+/// Headers sent by the server after the response body.
+///
+/// Chunked HTTP/1.1 responses and HTTP/2 responses may carry trailing
+/// headers, such as checksums or the `grpc-status` of gRPC calls. The
+/// trailer is filled in once the response body has been received in full, or
+/// left empty if the request fails or the server does not send any.
+///
+/// See [`ResponseExt::trailers`].
+#[derive(Clone)]
+pub struct Trailer {
+    shared: Arc<Mutex<TrailerState>>,
+}
+
+#[derive(Default)]
+struct TrailerState {
+    headers: Option<HeaderMap>,
+    wakers: Vec<Waker>,
+}
+
+impl Trailer {
+    pub(crate) fn new() -> Self {
+        Self {
+            shared: Arc::new(Mutex::new(TrailerState::default())),
+        }
+    }
+
+    /// Fill in the trailer, waking up anyone waiting for it.
+    pub(crate) fn set(&self, headers: HeaderMap) {
+        let wakers = {
+            let mut state = self.shared.lock().unwrap();
+
+            if state.headers.is_some() {
+                return;
+            }
+
+            state.headers = Some(headers);
+            mem::replace(&mut state.wakers, Vec::new())
+        };
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Get the trailing headers if they have been received, without waiting.
+    pub fn try_get(&self) -> Option<HeaderMap> {
+        self.shared.lock().unwrap().headers.clone()
+    }
+
+    /// Block the current thread until the trailing headers have been
+    /// received.
+    ///
+    /// This will block forever if the response body is not being read on
+    /// another thread, so make sure to read the body first.
+    pub fn wait(&self) -> HeaderMap {
+        self.wait_async().join()
+    }
+
+    /// Wait asynchronously until the trailing headers have been received.
+    pub fn wait_async(&self) -> impl Future<Output = HeaderMap> {
+        let shared = self.shared.clone();
+
+        future::poll_fn(move |cx| {
+            let mut state = shared.lock().unwrap();
+
+            match state.headers.as_ref() {
+                Some(headers) => Poll::Ready(headers.clone()),
+                None => {
+                    if !state.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                        state.wakers.push(cx.waker().clone());
+                    }
+                    Poll::Pending
+                }
+            }
+        })
+    }
+}
+
+impl fmt::Debug for Trailer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Trailer").field(&self.try_get()).finish()
+    }
+}
@@ -0,0 +1,418 @@
+//! Support for automatically retrying failed requests.
+
+use crate::clock::Clock;
+use crate::config::RetryPolicy;
+use crate::{failover, parse, Error, Phase};
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use futures_util::task::AtomicWaker;
+use http::{Method, Response};
+use lazy_static::lazy_static;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the timer checks the clocks of pending delays, which may be moved
+/// forward without time passing for real.
+const TIMER_INTERVAL: Duration = Duration::from_millis(100);
+
+lazy_static! {
+    /// Sends delays to the timer thread shared by all clients.
+    static ref TIMER: Sender<Arc<Timeout>> = {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+
+        thread::Builder::new()
+            .name(String::from("isahc-timer"))
+            .spawn(move || run_timer(receiver))
+            .expect("failed to spawn timer thread");
+
+        sender
+    };
+}
+
+/// Tracks the attempts made for a single request.
+#[derive(Debug)]
+pub(crate) struct RetryState {
+    policy: RetryPolicy,
+    attempts: u32,
+}
+
+impl RetryState {
+    pub(crate) fn new(policy: RetryPolicy) -> Self {
+        Self {
+            policy,
+            attempts: 1,
+        }
+    }
+
+    /// Decide whether the request should be sent again after an attempt with
+    /// the given result. If so, returns how long to wait before doing so.
+    pub(crate) fn next_delay<T>(
+        &mut self,
+        method: &Method,
+        result: &Result<Response<T>, Error>,
+    ) -> Option<Duration> {
+        if self.attempts >= self.policy.max_attempts {
+            return None;
+        }
+
+        let retry_after = match result {
+            Ok(response) => {
+                if !method.is_idempotent() || !self.policy.status_codes.contains(&response.status())
+                {
+                    return None;
+                }
+
+                response
+                    .headers()
+                    .get(http::header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(parse_retry_after)
+            }
+            Err(error) if is_retryable(error, method) => None,
+            Err(_) => return None,
+        };
+
+        let delay = match retry_after {
+            Some(delay) => delay.min(self.policy.max_retry_after),
+            None => self.backoff(),
+        };
+
+        self.attempts += 1;
+
+        Some(delay)
+    }
+
+    /// Get the delay to wait after the current attempt.
+    fn backoff(&self) -> Duration {
+        let factor = self
+            .policy
+            .multiplier
+            .checked_pow(self.attempts - 1)
+            .unwrap_or(u32::max_value());
+        let delay = self
+            .policy
+            .base
+            .checked_mul(factor)
+            .unwrap_or(self.policy.max_backoff)
+            .min(self.policy.max_backoff);
+
+        if self.policy.jitter {
+            // Wait somewhere between half and all of the full delay.
+            let half = delay / 2;
+            let micros = half.as_micros() as u64;
+
+            if micros > 0 {
+                return half + Duration::from_micros(random() % micros);
+            }
+        }
+
+        delay
+    }
+}
+
+/// Check whether a failed request may be sent again.
+///
+/// Requests that never reached the server can always be retried. Others are
+/// only retried if sending them more than once has no additional effect.
+fn is_retryable(error: &Error, method: &Method) -> bool {
+    match error {
+        Error::Timeout(Some(Phase::Resolve))
+        | Error::Timeout(Some(Phase::Connect))
+        | Error::Timeout(Some(Phase::TlsHandshake)) => true,
+        Error::NoResponse | Error::Timeout(_) => method.is_idempotent(),
+        error => failover::is_connect_error(error),
+    }
+}
+
+/// Parse the value of a `Retry-After` header, which is either a number of
+/// seconds or a date to wait until.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    parse::parse_http_date(value)
+        .map(|date| date.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+fn random() -> u64 {
+    // Each RandomState is seeded with fresh random keys.
+    RandomState::new().build_hasher().finish()
+}
+
+/// A future that completes once the given time has passed, as measured by the
+/// given clock.
+///
+/// Pending delays are woken by a single timer thread shared by all clients,
+/// which only learns about a delay if it is polled before the time has passed.
+pub(crate) struct Delay {
+    timeout: Arc<Timeout>,
+    scheduled: bool,
+}
+
+/// The state of a delay shared with the timer thread.
+struct Timeout {
+    deadline: Instant,
+    clock: Arc<dyn Clock>,
+    waker: AtomicWaker,
+}
+
+impl Timeout {
+    fn is_elapsed(&self) -> bool {
+        self.clock.now() >= self.deadline
+    }
+}
+
+impl Delay {
+    pub(crate) fn new(duration: Duration, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            timeout: Arc::new(Timeout {
+                deadline: clock.now() + duration,
+                clock,
+                waker: AtomicWaker::new(),
+            }),
+            scheduled: false,
+        }
+    }
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.timeout.is_elapsed() {
+            return Poll::Ready(());
+        }
+
+        self.timeout.waker.register(cx.waker());
+
+        if !self.scheduled {
+            TIMER
+                .send(self.timeout.clone())
+                .expect("timer thread stopped");
+            self.scheduled = true;
+        }
+
+        // Check again in case the timer woke us before we registered.
+        if self.timeout.is_elapsed() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl fmt::Debug for Delay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Delay")
+            .field("deadline", &self.timeout.deadline)
+            .finish()
+    }
+}
+
+/// Wake pending delays once their time has passed.
+fn run_timer(receiver: Receiver<Arc<Timeout>>) {
+    let mut pending: Vec<Arc<Timeout>> = Vec::new();
+
+    loop {
+        let mut wait = TIMER_INTERVAL;
+
+        pending.retain(|timeout| {
+            // Forget about delays that were dropped.
+            if Arc::strong_count(timeout) == 1 {
+                return false;
+            }
+
+            let now = timeout.clock.now();
+
+            if now >= timeout.deadline {
+                timeout.waker.wake();
+                false
+            } else {
+                wait = wait.min(timeout.deadline - now);
+                true
+            }
+        });
+
+        let received = if pending.is_empty() {
+            receiver.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            receiver.recv_timeout(wait)
+        };
+
+        match received {
+            Ok(timeout) => pending.push(timeout),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::task::Join;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::exponential(Duration::from_millis(100)).jitter(false)
+    }
+
+    fn response(status: u16) -> Result<Response<()>, Error> {
+        Ok(Response::builder().status(status).body(()).unwrap())
+    }
+
+    #[test]
+    fn delay_is_measured_by_the_clock() {
+        let clock = MockClock::new();
+        let delay = Delay::new(Duration::from_secs(3600), Arc::new(clock.clone()));
+        let start = Instant::now();
+
+        let advance = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            clock.advance(Duration::from_secs(3600));
+        });
+
+        delay.join();
+        advance.join().unwrap();
+
+        assert!(start.elapsed() < Duration::from_secs(60));
+    }
+
+    #[test]
+    fn delays_share_the_timer() {
+        let clock: Arc<dyn Clock> = Arc::new(crate::clock::SystemClock);
+        let delays = (1..=10)
+            .map(|i| Delay::new(Duration::from_millis(i * 10), clock.clone()))
+            .collect::<Vec<_>>();
+        let start = Instant::now();
+
+        futures_util::future::join_all(delays).join();
+
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn delays_grow_exponentially_until_max_attempts() {
+        let mut state = RetryState::new(policy().max_attempts(4));
+        let error = Err::<Response<()>, _>(Error::ConnectFailed);
+
+        assert_eq!(
+            state.next_delay(&Method::POST, &error),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(
+            state.next_delay(&Method::POST, &error),
+            Some(Duration::from_millis(200))
+        );
+        assert_eq!(
+            state.next_delay(&Method::POST, &error),
+            Some(Duration::from_millis(400))
+        );
+        assert_eq!(state.next_delay(&Method::POST, &error), None);
+    }
+
+    #[test]
+    fn delays_are_capped() {
+        let mut state = RetryState::new(
+            policy()
+                .max_attempts(10)
+                .max_backoff(Duration::from_millis(150)),
+        );
+
+        assert_eq!(
+            state.next_delay(&Method::GET, &response(503)),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(
+            state.next_delay(&Method::GET, &response(503)),
+            Some(Duration::from_millis(150))
+        );
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let mut state =
+            RetryState::new(RetryPolicy::fixed(Duration::from_millis(100)).max_attempts(100));
+
+        for _ in 0..50 {
+            let delay = state.next_delay(&Method::GET, &response(503)).unwrap();
+            assert!(delay >= Duration::from_millis(50));
+            assert!(delay <= Duration::from_millis(100));
+        }
+    }
+
+    #[test]
+    fn only_idempotent_requests_are_retried_after_being_sent() {
+        let mut state = RetryState::new(policy());
+
+        assert_eq!(state.next_delay(&Method::POST, &response(503)), None);
+        assert_eq!(
+            state.next_delay(&Method::POST, &Err::<Response<()>, _>(Error::NoResponse)),
+            None
+        );
+        assert!(state
+            .next_delay(&Method::PUT, &Err::<Response<()>, _>(Error::NoResponse))
+            .is_some());
+    }
+
+    #[test]
+    fn other_statuses_are_not_retried() {
+        let mut state = RetryState::new(policy());
+
+        assert_eq!(state.next_delay(&Method::GET, &response(200)), None);
+        assert_eq!(state.next_delay(&Method::GET, &response(500)), None);
+    }
+
+    #[test]
+    fn retry_after_header_is_honored() {
+        let mut state = RetryState::new(policy().max_backoff(Duration::from_millis(500)));
+        let response = Ok(Response::builder()
+            .status(429)
+            .header("Retry-After", "2")
+            .body(())
+            .unwrap());
+
+        assert_eq!(
+            state.next_delay(&Method::GET, &response),
+            Some(Duration::from_secs(2))
+        );
+    }
+
+    #[test]
+    fn retry_after_header_is_capped() {
+        let mut state = RetryState::new(policy().max_retry_after(Duration::from_secs(10)));
+        let response = Ok(Response::builder()
+            .status(503)
+            .header("Retry-After", "86400")
+            .body(())
+            .unwrap());
+
+        assert_eq!(
+            state.next_delay(&Method::GET, &response),
+            Some(Duration::from_secs(10))
+        );
+    }
+
+    #[test]
+    fn parse_retry_after_values() {
+        assert_eq!(parse_retry_after(" 120 "), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Thu, 01 Jan 1970 00:00:00 GMT"),
+            Some(Duration::from_secs(0))
+        );
+        assert!(
+            parse_retry_after("Fri, 31 Dec 9999 23:59:59 GMT").unwrap()
+                > Duration::from_secs(86400)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+}
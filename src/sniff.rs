@@ -0,0 +1,222 @@
+//! Detecting the real type of response bodies.
+//!
+//! Servers do not always send an accurate `Content-Type` header. The functions
+//! in this module inspect the first bytes of a body instead, so that the type
+//! of content and its text encoding can be determined when the header is
+//! missing or wrong.
+//!
+//! Sniffing is always opt-in; nothing in the client changes how bodies are
+//! interpreted on its own. See
+//! [`ResponseExt::text_sniffed`](crate::ResponseExt::text_sniffed) for a
+//! convenient way to decode text using these rules.
+
+/// How many bytes at the start of a body are considered when sniffing.
+pub const SNIFF_LEN: usize = 512;
+
+/// Guess the media type of a body from its first bytes.
+///
+/// Only the first [`SNIFF_LEN`] bytes are inspected. Binary content that is
+/// not recognized is reported as `application/octet-stream`.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::sniff;
+///
+/// assert_eq!(sniff::content_type(b"\x89PNG\r\n\x1a\n..."), "image/png");
+/// assert_eq!(sniff::content_type(b"  {\"a\": 1}"), "application/json");
+/// ```
+pub fn content_type(bytes: &[u8]) -> &'static str {
+    let bytes = &bytes[..bytes.len().min(SNIFF_LEN)];
+
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"\x1f\x8b\x08", "application/gzip"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x00asm", "application/wasm"),
+    ];
+
+    for &(signature, mime) in SIGNATURES {
+        if bytes.starts_with(signature) {
+            return mime;
+        }
+    }
+
+    if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(&b"WEBP"[..]) {
+        return "image/webp";
+    }
+
+    let (encoding, bom_len) = detect_bom(bytes).unwrap_or((Encoding::Utf8, 0));
+    let text = &bytes[bom_len..];
+
+    // Markup and JSON are only recognized in ASCII-compatible encodings.
+    if encoding == Encoding::Utf8 {
+        let trimmed = skip_whitespace(text);
+        let lowercase = trimmed[..trimmed.len().min(14)].to_ascii_lowercase();
+
+        if lowercase.starts_with(b"<!doctype html") || lowercase.starts_with(b"<html") {
+            return "text/html";
+        }
+
+        if lowercase.starts_with(b"<?xml") {
+            return "application/xml";
+        }
+
+        if trimmed.starts_with(b"{") || trimmed.starts_with(b"[") {
+            return "application/json";
+        }
+    }
+
+    if bom_len > 0 || !text.iter().any(|&b| is_binary_byte(b)) {
+        "text/plain"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+/// A text encoding that can be detected by sniffing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// UTF-8, or plain ASCII.
+    Utf8,
+    /// UTF-16, little endian.
+    Utf16Le,
+    /// UTF-16, big endian.
+    Utf16Be,
+    /// ISO-8859-1, used as the fallback for text that is not valid UTF-8.
+    Latin1,
+}
+
+impl Encoding {
+    /// Get an encoding from a charset label, such as the `charset` parameter
+    /// of a `Content-Type` header. Returns `None` for unsupported labels.
+    pub fn from_label(label: &str) -> Option<Self> {
+        match label.trim().to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" | "us-ascii" | "ascii" => Some(Encoding::Utf8),
+            "utf-16le" => Some(Encoding::Utf16Le),
+            "utf-16be" | "utf-16" => Some(Encoding::Utf16Be),
+            "iso-8859-1" | "latin1" | "l1" => Some(Encoding::Latin1),
+            _ => None,
+        }
+    }
+
+    /// Decode bytes in this encoding into a string, skipping a leading byte
+    /// order mark if present. Invalid sequences are replaced with U+FFFD.
+    pub fn decode(self, bytes: &[u8]) -> String {
+        let bytes = match detect_bom(bytes) {
+            Some((encoding, len)) if encoding == self => &bytes[len..],
+            _ => bytes,
+        };
+
+        match self {
+            Encoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+            Encoding::Utf16Le => decode_utf16(bytes, u16::from_le_bytes),
+            Encoding::Utf16Be => decode_utf16(bytes, u16::from_be_bytes),
+            Encoding::Latin1 => bytes.iter().map(|&b| char::from(b)).collect(),
+        }
+    }
+}
+
+/// Guess the text encoding of a body from its bytes.
+///
+/// A byte order mark takes precedence. Otherwise the body is assumed to be
+/// UTF-8 if it is valid UTF-8, and ISO-8859-1 if not.
+pub fn encoding(bytes: &[u8]) -> Encoding {
+    if let Some(encoding) = bom(bytes) {
+        return encoding;
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(_) => Encoding::Utf8,
+        // The body might have been cut off in the middle of a character.
+        Err(e) if e.error_len().is_none() => Encoding::Utf8,
+        Err(_) => Encoding::Latin1,
+    }
+}
+
+/// Get the encoding indicated by a byte order mark at the start of a body, if
+/// there is one.
+pub fn bom(bytes: &[u8]) -> Option<Encoding> {
+    detect_bom(bytes).map(|(encoding, _)| encoding)
+}
+
+fn detect_bom(bytes: &[u8]) -> Option<(Encoding, usize)> {
+    if bytes.starts_with(b"\xef\xbb\xbf") {
+        Some((Encoding::Utf8, 3))
+    } else if bytes.starts_with(b"\xff\xfe") {
+        Some((Encoding::Utf16Le, 2))
+    } else if bytes.starts_with(b"\xfe\xff") {
+        Some((Encoding::Utf16Be, 2))
+    } else {
+        None
+    }
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> String {
+    let units = bytes
+        .chunks(2)
+        .map(|pair| from_bytes([pair[0], pair.get(1).cloned().unwrap_or(0)]));
+
+    std::char::decode_utf16(units)
+        .map(|c| c.unwrap_or(std::char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+fn skip_whitespace(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or_else(|| bytes.len());
+
+    &bytes[start..]
+}
+
+/// Control bytes that do not appear in text. Mirrors the binary data bytes of
+/// the WHATWG MIME sniffing standard.
+fn is_binary_byte(byte: u8) -> bool {
+    match byte {
+        0x00..=0x08 | 0x0b | 0x0e..=0x1a | 0x1c..=0x1f => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_content_types() {
+        assert_eq!(content_type(b"GIF89a\x01\x00"), "image/gif");
+        assert_eq!(content_type(b"RIFF\x00\x00\x00\x00WEBPVP8 "), "image/webp");
+        assert_eq!(content_type(b"\n<!DOCTYPE HTML><p>hi"), "text/html");
+        assert_eq!(
+            content_type(b"\xef\xbb\xbf<?xml version"),
+            "application/xml"
+        );
+        assert_eq!(content_type(b"[1, 2]"), "application/json");
+        assert_eq!(content_type(b"hello world"), "text/plain");
+        assert_eq!(content_type(b"\x00\x01\x02"), "application/octet-stream");
+        assert_eq!(content_type(b""), "text/plain");
+    }
+
+    #[test]
+    fn detect_encodings() {
+        assert_eq!(encoding(b"plain"), Encoding::Utf8);
+        assert_eq!(encoding("caf\u{e9}".as_bytes()), Encoding::Utf8);
+        assert_eq!(encoding(b"caf\xc3"), Encoding::Utf8);
+        assert_eq!(encoding(b"caf\xe9!"), Encoding::Latin1);
+        assert_eq!(encoding(b"\xff\xfeh\x00"), Encoding::Utf16Le);
+    }
+
+    #[test]
+    fn decode_text() {
+        assert_eq!(Encoding::Latin1.decode(b"caf\xe9"), "caf\u{e9}");
+        assert_eq!(Encoding::Utf16Le.decode(b"\xff\xfeh\x00i\x00"), "hi");
+        assert_eq!(Encoding::Utf16Be.decode(b"\x00h\x00i"), "hi");
+        assert_eq!(Encoding::Utf8.decode(b"\xef\xbb\xbfhi"), "hi");
+    }
+}
@@ -0,0 +1,526 @@
+//! Client for [Server-Sent Events][sse].
+//!
+//! A response with the `text/event-stream` content type can be turned into a
+//! stream of [`Event`]s with
+//! [`ResponseExt::into_event_stream`](crate::ResponseExt::into_event_stream).
+//! The stream is parsed incrementally as the body arrives, and implements both
+//! [`Stream`] and [`Iterator`].
+//!
+//! To keep receiving events when the connection drops, use
+//! [`HttpClient::event_source`](crate::HttpClient::event_source) instead. The
+//! returned [`EventSource`] reconnects automatically, sending the ID of the
+//! last event received in a `Last-Event-ID` header so that the server can
+//! resume where it left off.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use isahc::prelude::*;
+//!
+//! let client = HttpClient::new()?;
+//!
+//! for event in client.event_source("https://example.org/updates")? {
+//!     let event = event?;
+//!     println!("{}: {}", event.event(), event.data());
+//! }
+//! # Ok::<(), isahc::Error>(())
+//! ```
+//!
+//! [sse]: https://html.spec.whatwg.org/multipage/server-sent-events.html
+
+use crate::retry::Delay;
+use crate::task::Join;
+use crate::{Body, Error, HttpClient, ResponseFuture, StatusError};
+use futures_io::AsyncRead;
+use futures_util::stream::{Stream, StreamExt};
+use http::{header, Request, Response, Uri};
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// UTF-8 byte order mark, which may appear at the start of a stream.
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Time to wait before reconnecting if the server does not say otherwise.
+const DEFAULT_RETRY: Duration = Duration::from_secs(3);
+
+/// A single event received from a server.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Event {
+    id: Option<String>,
+    event: String,
+    data: String,
+}
+
+impl Event {
+    /// Get the ID of the last event received, if the server set one. Once
+    /// set, the ID applies to all following events until changed again.
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_ref().map(String::as_str)
+    }
+
+    /// Get the type of the event. This is `message` unless the server gave a
+    /// different type.
+    pub fn event(&self) -> &str {
+        &self.event
+    }
+
+    /// Get the data of the event. Multiple data lines are joined with line
+    /// feeds.
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+}
+
+/// A stream of events parsed from a response body.
+///
+/// Created with
+/// [`ResponseExt::into_event_stream`](crate::ResponseExt::into_event_stream).
+/// The stream ends when the response body does.
+pub struct EventStream<R> {
+    reader: R,
+    parser: Parser,
+    buffer: Box<[u8]>,
+    eof: bool,
+}
+
+impl<R: AsyncRead + Unpin> EventStream<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self {
+            reader,
+            parser: Parser::default(),
+            buffer: vec![0; 8192].into_boxed_slice(),
+            eof: false,
+        }
+    }
+
+    /// Get the ID of the last event received, if the server set one.
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.parser.last_event_id.as_ref().map(String::as_str)
+    }
+
+    /// Get how long the server asked clients to wait before reconnecting, if
+    /// it did.
+    pub fn retry(&self) -> Option<Duration> {
+        self.parser.retry
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for EventStream<R> {
+    type Item = io::Result<Event>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Some(event) = this.parser.events.pop_front() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+
+            // An incomplete event at the end of the stream is discarded.
+            if this.eof {
+                return Poll::Ready(None);
+            }
+
+            match Pin::new(&mut this.reader).poll_read(cx, &mut this.buffer) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(Ok(0)) => this.eof = true,
+                Poll::Ready(Ok(len)) => this.parser.feed(&this.buffer[..len]),
+            }
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> Iterator for EventStream<R> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        StreamExt::next(self).join()
+    }
+}
+
+impl<R> fmt::Debug for EventStream<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventStream")
+            .field("last_event_id", &self.parser.last_event_id)
+            .field("retry", &self.parser.retry)
+            .finish()
+    }
+}
+
+/// A stream of events from a URI that reconnects automatically.
+///
+/// Created with
+/// [`HttpClient::event_source`](crate::HttpClient::event_source).
+///
+/// When the connection is closed or fails, the event source waits for the
+/// time requested by the server, or three seconds by default, and connects
+/// again. The stream only ends with an error if the server responds with
+/// anything other than a successful `text/event-stream` response, as servers
+/// do to tell clients to stop reconnecting.
+pub struct EventSource<'c> {
+    client: &'c HttpClient,
+    uri: Uri,
+    last_event_id: Option<String>,
+    retry: Duration,
+    state: State<'c>,
+}
+
+enum State<'c> {
+    Connecting(ResponseFuture<'c>),
+    Streaming(EventStream<Body>),
+    Waiting(Delay),
+    Closed,
+}
+
+impl<'c> EventSource<'c> {
+    pub(crate) fn new(client: &'c HttpClient, uri: Uri) -> Self {
+        let mut source = Self {
+            client,
+            uri,
+            last_event_id: None,
+            retry: DEFAULT_RETRY,
+            state: State::Closed,
+        };
+
+        source.state = State::Connecting(source.connect());
+        source
+    }
+
+    /// Get the ID of the last event received, if the server set one.
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.last_event_id.as_ref().map(String::as_str)
+    }
+
+    fn connect(&self) -> ResponseFuture<'c> {
+        let mut request = Request::get(self.uri.clone());
+        request
+            .header(header::ACCEPT, "text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache");
+
+        if let Some(id) = self
+            .last_event_id
+            .as_ref()
+            .and_then(|id| header::HeaderValue::from_str(id).ok())
+        {
+            request.header("Last-Event-ID", id);
+        }
+
+        self.client
+            .send_async(request.body(()).expect("event source request is valid"))
+    }
+
+    fn reconnect_later(&mut self) {
+        log::debug!(
+            "event stream from {} closed, reconnecting in {:?}",
+            self.uri,
+            self.retry
+        );
+        self.state = State::Waiting(Delay::new(self.retry, self.client.clock().clone()));
+    }
+}
+
+impl Stream for EventSource<'_> {
+    type Item = Result<Event, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            match &mut this.state {
+                State::Connecting(future) => match Pin::new(future).poll(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Ok(response)) => {
+                        if let Err(e) = check_response(&response) {
+                            this.state = State::Closed;
+                            return Poll::Ready(Some(Err(e)));
+                        }
+
+                        this.state = State::Streaming(EventStream::new(response.into_body()));
+                    }
+                    Poll::Ready(Err(e)) => {
+                        log::debug!("failed to connect to event stream: {}", e);
+                        this.reconnect_later();
+                    }
+                },
+                State::Streaming(stream) => match Pin::new(&mut *stream).poll_next(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Some(Ok(event))) => {
+                        this.last_event_id = stream.last_event_id().map(ToOwned::to_owned);
+
+                        if let Some(retry) = stream.retry() {
+                            this.retry = retry;
+                        }
+
+                        return Poll::Ready(Some(Ok(event)));
+                    }
+                    Poll::Ready(Some(Err(e))) => {
+                        log::debug!("error reading event stream: {}", e);
+                        this.reconnect_later();
+                    }
+                    Poll::Ready(None) => this.reconnect_later(),
+                },
+                State::Waiting(delay) => match Pin::new(delay).poll(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(()) => this.state = State::Connecting(this.connect()),
+                },
+                State::Closed => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+impl Iterator for EventSource<'_> {
+    type Item = Result<Event, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        StreamExt::next(self).join()
+    }
+}
+
+impl fmt::Debug for EventSource<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventSource")
+            .field("uri", &self.uri)
+            .field("last_event_id", &self.last_event_id)
+            .field("retry", &self.retry)
+            .finish()
+    }
+}
+
+/// Check that a response is an event stream that should be read.
+fn check_response(response: &Response<Body>) -> Result<(), Error> {
+    let is_event_stream = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map_or(false, |value| {
+            value.trim().eq_ignore_ascii_case("text/event-stream")
+        });
+
+    if response.status() == http::StatusCode::OK && is_event_stream {
+        Ok(())
+    } else {
+        Err(Error::BadStatus(StatusError {
+            status: response.status(),
+            headers: response.headers().clone(),
+            body: Vec::new(),
+        }))
+    }
+}
+
+/// Incremental parser for the event stream format.
+///
+/// https://html.spec.whatwg.org/multipage/server-sent-events.html#event-stream-interpretation
+#[derive(Default)]
+struct Parser {
+    /// The incomplete line received so far.
+    line: Vec<u8>,
+    /// Whether a line feed should be skipped, because the previous line ended
+    /// with a carriage return.
+    skip_lf: bool,
+    /// Whether the start of the stream was checked for a byte order mark.
+    started: bool,
+    event: String,
+    data: String,
+    last_event_id: Option<String>,
+    retry: Option<Duration>,
+    /// Events parsed but not yet returned.
+    events: VecDeque<Event>,
+}
+
+impl Parser {
+    fn feed(&mut self, bytes: &[u8]) {
+        if self.started {
+            self.feed_lines(bytes);
+            return;
+        }
+
+        // Hold back the start of the stream until it is known whether it
+        // begins with a byte order mark, which is skipped.
+        self.line.extend_from_slice(bytes);
+
+        if self.line.len() < BOM.len() && BOM.starts_with(&self.line) {
+            return;
+        }
+
+        self.started = true;
+
+        let mut pending = mem::replace(&mut self.line, Vec::new());
+
+        if pending.starts_with(BOM) {
+            pending.drain(..BOM.len());
+        }
+
+        self.feed_lines(&pending);
+    }
+
+    fn feed_lines(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            match byte {
+                b'\n' if self.skip_lf => self.skip_lf = false,
+                b'\r' | b'\n' => {
+                    self.skip_lf = byte == b'\r';
+
+                    let line = mem::replace(&mut self.line, Vec::new());
+                    self.process_line(&String::from_utf8_lossy(&line));
+                }
+                _ => {
+                    self.skip_lf = false;
+                    self.line.push(byte);
+                }
+            }
+        }
+    }
+
+    fn process_line(&mut self, line: &str) {
+        if line.is_empty() {
+            self.dispatch();
+            return;
+        }
+
+        // Comment.
+        if line.starts_with(':') {
+            return;
+        }
+
+        let (field, mut value) = match line.find(':') {
+            Some(i) => (&line[..i], &line[i + 1..]),
+            None => (line, ""),
+        };
+
+        if value.starts_with(' ') {
+            value = &value[1..];
+        }
+
+        match field {
+            "event" => self.event = value.to_owned(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" if !value.contains('\0') => {
+                self.last_event_id = if value.is_empty() {
+                    None
+                } else {
+                    Some(value.to_owned())
+                };
+            }
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                if let Ok(millis) = value.parse() {
+                    self.retry = Some(Duration::from_millis(millis));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn dispatch(&mut self) {
+        let event = mem::replace(&mut self.event, String::new());
+        let mut data = mem::replace(&mut self.data, String::new());
+
+        if data.is_empty() {
+            return;
+        }
+
+        // Remove the line feed added after the last data line.
+        data.pop();
+
+        self.events.push_back(Event {
+            id: self.last_event_id.clone(),
+            event: if event.is_empty() {
+                String::from("message")
+            } else {
+                event
+            },
+            data,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(chunks: &[&[u8]]) -> Parser {
+        let mut parser = Parser::default();
+
+        for chunk in chunks {
+            parser.feed(chunk);
+        }
+
+        parser
+    }
+
+    fn event(id: Option<&str>, event: &str, data: &str) -> Event {
+        Event {
+            id: id.map(ToOwned::to_owned),
+            event: event.to_owned(),
+            data: data.to_owned(),
+        }
+    }
+
+    #[test]
+    fn parse_simple_events() {
+        let parser = parse(&[b"data: hello\n\ndata: world\n\n"]);
+
+        assert_eq!(
+            parser.events,
+            vec![
+                event(None, "message", "hello"),
+                event(None, "message", "world"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_multi_line_data_and_fields() {
+        let parser = parse(&[b": comment\nevent: update\nid: 7\ndata: a\ndata:b\n\n"]);
+
+        assert_eq!(parser.events, vec![event(Some("7"), "update", "a\nb")]);
+        assert_eq!(parser.last_event_id.as_ref().unwrap(), "7");
+    }
+
+    #[test]
+    fn parse_lines_split_across_chunks() {
+        let parser = parse(&[b"\xEF\xBB", b"\xBFda", b"ta: hel", b"lo\r", b"\n\r\n"]);
+
+        assert_eq!(parser.events, vec![event(None, "message", "hello")]);
+    }
+
+    #[test]
+    fn parse_retry_hints() {
+        let parser = parse(&[b"retry: 1500\nretry: soon\n\n"]);
+
+        assert!(parser.events.is_empty());
+        assert_eq!(parser.retry, Some(Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn incomplete_events_are_not_dispatched() {
+        let parser = parse(&[b"data: hello\n"]);
+
+        assert!(parser.events.is_empty());
+    }
+
+    #[test]
+    fn ids_persist_until_reset() {
+        let parser = parse(&[b"id: 1\ndata: a\n\ndata: b\n\nid\ndata: c\n\n"]);
+
+        assert_eq!(
+            parser.events,
+            vec![
+                event(Some("1"), "message", "a"),
+                event(Some("1"), "message", "b"),
+                event(None, "message", "c"),
+            ]
+        );
+    }
+}
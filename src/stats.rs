@@ -0,0 +1,93 @@
+//! Accounting of requests and transferred bytes per host.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Statistics about the requests a client has sent to a single host.
+///
+/// See [`HttpClient::host_stats`](crate::HttpClient::host_stats) for retrieving
+/// the statistics of a client.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct HostStats {
+    requests: u64,
+    failed_requests: u64,
+    bytes_sent: u64,
+    bytes_received: u64,
+}
+
+impl HostStats {
+    /// Get the number of requests that have been completed, whether
+    /// successfully or not.
+    pub fn requests(&self) -> u64 {
+        self.requests
+    }
+
+    /// Get the number of requests that failed with an error.
+    ///
+    /// Requests that received an error status code from the server are not
+    /// counted as failed.
+    pub fn failed_requests(&self) -> u64 {
+        self.failed_requests
+    }
+
+    /// Get the number of bytes sent, including request headers.
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent
+    }
+
+    /// Get the number of bytes received, including response headers.
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+}
+
+/// Collects statistics for every host a client talks to.
+#[derive(Debug, Default)]
+pub(crate) struct StatsRegistry {
+    hosts: Mutex<HashMap<String, HostStats>>,
+}
+
+impl StatsRegistry {
+    /// Record a completed request to the given host.
+    pub(crate) fn record(&self, host: &str, success: bool, bytes_sent: u64, bytes_received: u64) {
+        let mut hosts = self.hosts.lock().unwrap();
+        let stats = hosts.entry(host.to_owned()).or_default();
+
+        stats.requests += 1;
+        stats.bytes_sent += bytes_sent;
+        stats.bytes_received += bytes_received;
+
+        if !success {
+            stats.failed_requests += 1;
+        }
+    }
+
+    /// Get a copy of the statistics of all hosts.
+    pub(crate) fn snapshot(&self) -> HashMap<String, HostStats> {
+        self.hosts.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_are_accounted_per_host() {
+        let registry = StatsRegistry::default();
+
+        registry.record("example.org", true, 100, 1000);
+        registry.record("example.org", false, 50, 0);
+        registry.record("example.com:8080", true, 10, 20);
+
+        let stats = registry.snapshot();
+
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats["example.org"].requests(), 2);
+        assert_eq!(stats["example.org"].failed_requests(), 1);
+        assert_eq!(stats["example.org"].bytes_sent(), 150);
+        assert_eq!(stats["example.org"].bytes_received(), 1000);
+        assert_eq!(stats["example.com:8080"].requests(), 1);
+        assert_eq!(stats["example.com:8080"].failed_requests(), 0);
+    }
+}
@@ -0,0 +1,291 @@
+//! Injection of faults into responses.
+
+use super::xorshift;
+use crate::{middleware::Middleware, Body};
+use futures_io::AsyncRead;
+use http::{Response, StatusCode};
+use std::io;
+use std::mem;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Middleware that randomly injects faults into the responses received by a
+/// client, for testing how an application copes with unreliable servers.
+///
+/// Each kind of fault is applied independently with its own probability,
+/// given as a number between `0.0` (never) and `1.0` (always). Faults are
+/// chosen using a pseudo-random generator with a fixed seed, so a test sending
+/// the same requests in the same order sees the same faults every time.
+///
+/// Use
+/// [`HttpClientBuilder::fault_injector`](crate::HttpClientBuilder::fault_injector)
+/// to add a fault injector to a client.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::prelude::*;
+/// use isahc::testing::FaultInjector;
+/// use std::time::Duration;
+///
+/// let client = HttpClient::builder()
+///     .fault_injector(
+///         FaultInjector::new()
+///             .latency(0.5, Duration::from_millis(200))
+///             .status(0.1, http::StatusCode::SERVICE_UNAVAILABLE),
+///     )
+///     .build()?;
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct FaultInjector {
+    latency: Option<(f64, Duration)>,
+    connection_reset: Option<f64>,
+    truncated_body: Option<(f64, u64)>,
+    status: Option<(f64, StatusCode)>,
+    rng: Mutex<u64>,
+}
+
+impl FaultInjector {
+    /// Create a new fault injector that does not inject any faults yet.
+    pub fn new() -> Self {
+        Self {
+            latency: None,
+            connection_reset: None,
+            truncated_body: None,
+            status: None,
+            rng: Mutex::new(0x2545_f491_4f6c_dd1d),
+        }
+    }
+
+    /// Delay the response body by the given amount of time.
+    pub fn latency(mut self, probability: f64, delay: Duration) -> Self {
+        self.latency = Some((probability, delay));
+        self
+    }
+
+    /// Fail reading the response body with a
+    /// [`ConnectionReset`](std::io::ErrorKind::ConnectionReset) error, as if
+    /// the server closed the connection.
+    pub fn connection_reset(mut self, probability: f64) -> Self {
+        self.connection_reset = Some(probability);
+        self
+    }
+
+    /// End the response body early after at most `length` bytes.
+    pub fn truncated_body(mut self, probability: f64, length: u64) -> Self {
+        self.truncated_body = Some((probability, length));
+        self
+    }
+
+    /// Replace the response with an empty response with the given status
+    /// code.
+    pub fn status(mut self, probability: f64, status: StatusCode) -> Self {
+        self.status = Some((probability, status));
+        self
+    }
+
+    /// Set the seed of the pseudo-random generator used to decide which faults
+    /// to inject.
+    pub fn seed(self, seed: u64) -> Self {
+        // Xorshift gets stuck on zero.
+        *self.rng.lock().unwrap() = seed.max(1);
+        self
+    }
+
+    /// Randomly decide whether to inject a fault with the given probability.
+    fn roll(&self, probability: f64) -> bool {
+        let value = xorshift(&mut self.rng.lock().unwrap());
+        let sample = (value >> 11) as f64 / (1u64 << 53) as f64;
+
+        sample < probability
+    }
+}
+
+impl Default for FaultInjector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Middleware for FaultInjector {
+    fn filter_response(&self, mut response: Response<Body>) -> Response<Body> {
+        if let Some((probability, status)) = self.status {
+            if self.roll(probability) {
+                log::debug!("injecting {} response", status);
+
+                let mut replacement = Response::new(Body::empty());
+                *replacement.status_mut() = status;
+                *replacement.version_mut() = response.version();
+                *replacement.extensions_mut() =
+                    mem::replace(response.extensions_mut(), http::Extensions::new());
+
+                return replacement;
+            }
+        }
+
+        let delay = match self.latency {
+            Some((probability, delay)) if self.roll(probability) => Some(delay),
+            _ => None,
+        };
+        let reset = match self.connection_reset {
+            Some(probability) => self.roll(probability),
+            None => false,
+        };
+        let limit = match self.truncated_body {
+            Some((probability, length)) if self.roll(probability) => Some(length),
+            _ => None,
+        };
+
+        if delay.is_none() && !reset && limit.is_none() {
+            return response;
+        }
+
+        log::debug!(
+            "injecting faults into response body [delay={:?}, reset={}, limit={:?}]",
+            delay,
+            reset,
+            limit
+        );
+
+        // The body length is no longer reliable.
+        response.headers_mut().remove(http::header::CONTENT_LENGTH);
+
+        let inner = mem::replace(response.body_mut(), Body::empty());
+
+        *response.body_mut() = Body::reader(FaultyBody {
+            inner,
+            delay,
+            deadline: None,
+            reset,
+            remaining: limit,
+        });
+
+        response
+    }
+}
+
+/// A response body with faults applied to it.
+struct FaultyBody {
+    inner: Body,
+    delay: Option<Duration>,
+    deadline: Option<Instant>,
+    reset: bool,
+    remaining: Option<u64>,
+}
+
+impl AsyncRead for FaultyBody {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if let Some(delay) = self.delay.take() {
+            let deadline = Instant::now() + delay;
+            let waker = cx.waker().clone();
+            self.deadline = Some(deadline);
+
+            // There is no timer to register with, so use a thread instead.
+            thread::spawn(move || {
+                thread::sleep(delay);
+                waker.wake();
+            });
+        }
+
+        if let Some(deadline) = self.deadline {
+            if Instant::now() < deadline {
+                return Poll::Pending;
+            }
+
+            self.deadline = None;
+        }
+
+        if self.reset {
+            return Poll::Ready(Err(io::ErrorKind::ConnectionReset.into()));
+        }
+
+        let max = match self.remaining {
+            Some(0) => return Poll::Ready(Ok(0)),
+            Some(remaining) if remaining < buf.len() as u64 => remaining as usize,
+            _ => buf.len(),
+        };
+
+        let result = AsyncRead::poll_read(Pin::new(&mut self.inner), cx, &mut buf[..max]);
+
+        if let (Poll::Ready(Ok(len)), Some(remaining)) = (&result, self.remaining.as_mut()) {
+            *remaining -= *len as u64;
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn response(body: &'static str) -> Response<Body> {
+        Response::new(Body::from(body))
+    }
+
+    #[test]
+    fn no_faults_by_default() {
+        let injector = FaultInjector::new();
+        let mut response = injector.filter_response(response("hello world"));
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body_mut().text().unwrap(), "hello world");
+    }
+
+    #[test]
+    fn status_is_replaced() {
+        let injector = FaultInjector::new().status(1.0, StatusCode::BAD_GATEWAY);
+        let mut response = injector.filter_response(response("hello world"));
+
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(response.body_mut().text().unwrap(), "");
+    }
+
+    #[test]
+    fn body_is_truncated() {
+        let injector = FaultInjector::new().truncated_body(1.0, 5);
+        let mut response = injector.filter_response(response("hello world"));
+
+        assert_eq!(response.body_mut().text().unwrap(), "hello");
+    }
+
+    #[test]
+    fn body_is_reset() {
+        let injector = FaultInjector::new().connection_reset(1.0);
+        let mut response = injector.filter_response(response("hello world"));
+
+        let error = response.body_mut().read(&mut [0; 16]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::ConnectionReset);
+    }
+
+    #[test]
+    fn body_is_delayed() {
+        let injector = FaultInjector::new().latency(1.0, Duration::from_millis(100));
+        let mut response = injector.filter_response(response("hello world"));
+        let start = Instant::now();
+
+        assert_eq!(response.body_mut().text().unwrap(), "hello world");
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn faults_are_random_with_given_probability() {
+        let injector = FaultInjector::new().status(0.25, StatusCode::BAD_GATEWAY);
+
+        let failed = (0..1000)
+            .map(|_| injector.filter_response(response("")))
+            .filter(|response| response.status() == StatusCode::BAD_GATEWAY)
+            .count();
+
+        assert!(failed > 150 && failed < 350, "{} responses failed", failed);
+    }
+}
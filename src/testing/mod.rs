@@ -0,0 +1,57 @@
+//! Utilities for testing code that sends HTTP requests.
+//!
+//! This module provides a tiny HTTP [`Server`] that runs in a background
+//! thread and listens on a local port. Handlers can be registered per path to
+//! answer requests, and every request received is recorded so that tests can
+//! make assertions about it afterwards. The server is intended for tests only.
+//! It reads one request per connection, and does not support TLS or HTTP/2.
+//!
+//! A [`FaultInjector`] can be added to a client to make responses randomly
+//! slow, broken, or unsuccessful, for testing how an application copes with
+//! unreliable servers.
+//!
+//! A [`Schedule`] replays reads from a [`Body`](crate::Body) or any other
+//! reader in chunks of varying size, interleaved with reads that are not ready
+//! yet, for testing that code reading streams handles all of these cases.
+//!
+//! Everything in this module requires the `test-utils` feature to be enabled.
+//!
+//! # Examples
+//!
+//! ```
+//! use isahc::prelude::*;
+//! use isahc::testing::Server;
+//!
+//! let server = Server::new()?;
+//!
+//! server.handle("/hello", |request| {
+//!     Response::new(format!("hello {}", request.method()).into_bytes())
+//! });
+//!
+//! let mut response = isahc::get(server.url("/hello"))?;
+//! assert_eq!(response.text()?, "hello GET");
+//!
+//! assert_eq!(server.requests().len(), 1);
+//! assert_eq!(server.requests()[0].path(), "/hello");
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+mod faults;
+mod schedule;
+mod server;
+
+pub use self::{
+    faults::FaultInjector,
+    schedule::{Schedule, Scheduled, Step},
+    server::{ReceivedRequest, Server},
+};
+
+/// Advance a xorshift pseudo-random generator and return the next value.
+///
+/// The state must never be zero.
+fn xorshift(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
@@ -0,0 +1,200 @@
+//! Replaying reads against a fixed schedule of chunk sizes and pending polls.
+
+use super::xorshift;
+use crate::Body;
+use futures_io::AsyncRead;
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// A single step in a [`Schedule`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Step {
+    /// Read at most the given number of bytes, regardless of how large the
+    /// buffer given by the caller is. A limit of zero is treated as one.
+    Read(usize),
+
+    /// Return [`Poll::Pending`] once, waking the task right away.
+    Pending,
+}
+
+/// A schedule of short reads and pending polls for exercising code that reads
+/// streams.
+///
+/// Code reading from a stream should produce the same result no matter how
+/// the data is split up into individual reads, and no matter how often reads
+/// are not ready yet. A schedule wraps a reader or a [`Body`] so that it
+/// follows a given pattern of steps, repeating the pattern until the end of
+/// the stream. Trying many different schedules, such as with
+/// [`Schedule::random`] and a range of seeds, makes it easy to check this
+/// property for code built on top of response bodies.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::Body;
+/// use isahc::testing::Schedule;
+///
+/// for seed in 0..100 {
+///     let mut body = Schedule::random(seed).wrap_body(Body::from("hello world"));
+///
+///     assert_eq!(body.text()?, "hello world");
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Schedule {
+    steps: Vec<Step>,
+}
+
+impl Schedule {
+    /// Create a schedule from the given steps, which are repeated for as long
+    /// as the stream lasts.
+    ///
+    /// An empty list of steps reads as much as possible at each step.
+    pub fn new(steps: impl IntoIterator<Item = Step>) -> Self {
+        let mut steps = steps.into_iter().collect::<Vec<_>>();
+
+        if steps.is_empty() {
+            steps.push(Step::Read(usize::max_value()));
+        }
+
+        Self { steps }
+    }
+
+    /// Create a pseudo-random schedule derived from the given seed.
+    ///
+    /// The same seed always produces the same schedule, so failures found with
+    /// a random schedule can be reproduced.
+    pub fn random(seed: u64) -> Self {
+        let mut state = seed.max(1);
+
+        Self::new((0..64).map(|_| match xorshift(&mut state) % 4 {
+            0 => Step::Pending,
+            1 => Step::Read(1),
+            _ => Step::Read((xorshift(&mut state) % 64 + 1) as usize),
+        }))
+    }
+
+    /// Get the steps of this schedule.
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Wrap a reader so that reads from it follow this schedule.
+    pub fn wrap<R: AsyncRead + Unpin>(&self, inner: R) -> Scheduled<R> {
+        Scheduled {
+            inner,
+            steps: self.steps.clone(),
+            position: 0,
+        }
+    }
+
+    /// Wrap a body so that reads from it follow this schedule.
+    ///
+    /// The returned body has the same length as the original.
+    pub fn wrap_body(&self, body: Body) -> Body {
+        let len = body.len();
+        let reader = self.wrap(body);
+
+        match len {
+            Some(len) => Body::reader_sized(reader, len),
+            None => Body::reader(reader),
+        }
+    }
+}
+
+/// A reader whose reads follow a [`Schedule`].
+pub struct Scheduled<R> {
+    inner: R,
+    steps: Vec<Step>,
+    position: usize,
+}
+
+impl<R> Scheduled<R> {
+    /// Unwrap this reader, returning the wrapped reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn next_step(&mut self) -> Step {
+        let step = self.steps[self.position];
+        self.position = (self.position + 1) % self.steps.len();
+        step
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for Scheduled<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if buf.is_empty() {
+            return Pin::new(&mut self.inner).poll_read(cx, buf);
+        }
+
+        match self.next_step() {
+            Step::Pending => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Step::Read(max) => {
+                let len = max.max(1).min(buf.len());
+                Pin::new(&mut self.inner).poll_read(cx, &mut buf[..len])
+            }
+        }
+    }
+}
+
+impl<R> fmt::Debug for Scheduled<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scheduled")
+            .field("steps", &self.steps)
+            .field("position", &self.position)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::io::AsyncReadExt;
+
+    #[test]
+    fn reads_follow_schedule() {
+        let schedule = Schedule::new(vec![Step::Read(1), Step::Pending, Step::Read(3)]);
+        let mut reader = schedule.wrap(&b"hello world"[..]);
+        let mut buf = [0; 16];
+        let mut lens = Vec::new();
+
+        loop {
+            let len = futures::executor::block_on(reader.read(&mut buf)).unwrap();
+
+            if len == 0 {
+                break;
+            }
+
+            lens.push(len);
+        }
+
+        assert_eq!(lens, vec![1, 3, 1, 3, 1, 2]);
+    }
+
+    #[test]
+    fn random_schedules_are_reproducible() {
+        assert_eq!(Schedule::random(42), Schedule::random(42));
+        assert_ne!(Schedule::random(42), Schedule::random(43));
+    }
+
+    #[test]
+    fn wrapped_body_keeps_contents_and_length() {
+        for seed in 0..32 {
+            let mut body = Schedule::random(seed).wrap_body(Body::from("hello world"));
+
+            assert_eq!(body.len(), Some(11));
+            assert_eq!(body.text().unwrap(), "hello world");
+        }
+    }
+}
@@ -0,0 +1,419 @@
+//! A local HTTP server for use in tests.
+
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use http::{Method, Response, StatusCode};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+type Handler = Arc<dyn Fn(&ReceivedRequest) -> Response<Vec<u8>> + Send + Sync>;
+
+/// A request received by a test [`Server`].
+#[derive(Clone, Debug)]
+pub struct ReceivedRequest {
+    method: Method,
+    uri: String,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl ReceivedRequest {
+    /// Get the request method.
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// Get the request target, including the query string if any.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// Get the path of the request target, without the query string.
+    pub fn path(&self) -> &str {
+        self.uri.split('?').next().unwrap_or("")
+    }
+
+    /// Get the request headers.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Get the request body. If the request was sent using chunked encoding,
+    /// this is the decoded body.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+}
+
+#[derive(Default)]
+struct State {
+    handlers: HashMap<String, Handler>,
+    requests: Vec<ReceivedRequest>,
+}
+
+/// A local HTTP server for use in tests.
+///
+/// The server starts listening as soon as it is created, and shuts down when
+/// dropped. Requests to paths without a registered handler are answered with
+/// `404 Not Found`.
+pub struct Server {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Server {
+    /// Start a new server listening on a random local port.
+    pub fn new() -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State::default()));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let state = state.clone();
+            let shutdown = shutdown.clone();
+
+            thread::Builder::new()
+                .name("isahc test server".into())
+                .spawn(move || {
+                    for stream in listener.incoming() {
+                        if shutdown.load(Ordering::SeqCst) {
+                            break;
+                        }
+
+                        match stream {
+                            Ok(stream) => {
+                                let state = state.clone();
+
+                                thread::spawn(move || {
+                                    if let Err(e) = handle_connection(stream, &state) {
+                                        log::debug!("test server connection failed: {}", e);
+                                    }
+                                });
+                            }
+                            Err(e) => log::debug!("test server failed to accept: {}", e),
+                        }
+                    }
+                })?
+        };
+
+        Ok(Self {
+            addr,
+            state,
+            shutdown,
+            thread: Some(thread),
+        })
+    }
+
+    /// Get the address the server is listening on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Get a URL pointing to the given path on this server.
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// Register a handler for requests to the given path, replacing any
+    /// existing handler for the same path.
+    ///
+    /// The path is matched exactly, ignoring the query string.
+    pub fn handle<F>(&self, path: impl Into<String>, handler: F)
+    where
+        F: Fn(&ReceivedRequest) -> Response<Vec<u8>> + Send + Sync + 'static,
+    {
+        self.state
+            .lock()
+            .unwrap()
+            .handlers
+            .insert(path.into(), Arc::new(handler));
+    }
+
+    /// Get all requests received by this server so far, in the order they
+    /// were received.
+    pub fn requests(&self) -> Vec<ReceivedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Get all requests received by this server so far with the given method
+    /// and path, in the order they were received.
+    ///
+    /// The path is matched exactly, ignoring the query string.
+    pub fn requests_to(&self, method: &str, path: &str) -> Vec<ReceivedRequest> {
+        self.state
+            .lock()
+            .unwrap()
+            .requests
+            .iter()
+            .filter(|request| request.method().as_str() == method && request.path() == path)
+            .cloned()
+            .collect()
+    }
+
+    /// Assert that the server received exactly `times` requests with the given
+    /// method and path.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of matching requests differs, listing all requests
+    /// received so far.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::testing::Server;
+    ///
+    /// let server = Server::new()?;
+    ///
+    /// isahc::post(server.url("/v1/items"), "first")?;
+    /// isahc::post(server.url("/v1/items"), "second")?;
+    ///
+    /// server.assert_called("POST", "/v1/items", 2);
+    /// server.assert_called("DELETE", "/v1/items", 0);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn assert_called(&self, method: &str, path: &str, times: usize) {
+        let matching = self.requests_to(method, path).len();
+
+        if matching != times {
+            let received = self
+                .requests()
+                .iter()
+                .map(|request| format!("\n  {} {}", request.method(), request.uri()))
+                .collect::<String>();
+
+            panic!(
+                "expected {} {} to be called {} times, but it was called {} times; received requests:{}",
+                method, path, times, matching, received
+            );
+        }
+    }
+}
+
+impl fmt::Debug for Server {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Server").field("addr", &self.addr).finish()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+
+        // Wake up the accept loop so that it notices the shutdown.
+        let _ = TcpStream::connect(self.addr);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, state: &Mutex<State>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let request = match read_request(&mut reader, &mut stream)? {
+        Some(request) => request,
+        None => return Ok(()),
+    };
+
+    let handler = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(request.clone());
+        state.handlers.get(request.path()).cloned()
+    };
+
+    let response = match handler {
+        Some(handler) => handler(&request),
+        None => {
+            let mut response = Response::new(Vec::new());
+            *response.status_mut() = StatusCode::NOT_FOUND;
+            response
+        }
+    };
+
+    write_response(&mut stream, &request, response)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_request(
+    reader: &mut impl BufRead,
+    writer: &mut impl Write,
+) -> io::Result<Option<ReceivedRequest>> {
+    let mut line = String::new();
+
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+
+    let mut request_line = line.trim_end().split(' ');
+    let method = request_line
+        .next()
+        .and_then(|method| method.parse().ok())
+        .ok_or_else(|| invalid_data("invalid request method"))?;
+    let uri = request_line
+        .next()
+        .ok_or_else(|| invalid_data("missing request target"))?
+        .to_owned();
+
+    let mut headers = HeaderMap::new();
+
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+
+        let header = line.trim_end();
+
+        if header.is_empty() {
+            break;
+        }
+
+        let mut split = header.splitn(2, ':');
+        let name = split.next().unwrap_or("");
+        let value = split.next().unwrap_or("").trim();
+
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            headers.append(name, value);
+        }
+    }
+
+    if headers
+        .get(http::header::EXPECT)
+        .map_or(false, |value| value == "100-continue")
+    {
+        writer.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+        writer.flush()?;
+    }
+
+    let chunked = headers
+        .get(http::header::TRANSFER_ENCODING)
+        .map_or(false, |value| value == "chunked");
+    let content_length = headers
+        .get(http::header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+
+    let body = if chunked {
+        read_chunked_body(reader)?
+    } else if let Some(length) = content_length {
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+        body
+    } else {
+        Vec::new()
+    };
+
+    Ok(Some(ReceivedRequest {
+        method,
+        uri,
+        headers,
+        body,
+    }))
+}
+
+fn read_chunked_body(reader: &mut impl BufRead) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+
+        let size = line.trim_end().split(';').next().unwrap_or("");
+        let size =
+            usize::from_str_radix(size, 16).map_err(|_| invalid_data("invalid chunk size"))?;
+
+        if size == 0 {
+            // Skip any trailers up to the final empty line.
+            loop {
+                line.clear();
+
+                if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+                    return Ok(body);
+                }
+            }
+        }
+
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..])?;
+
+        // Consume the line break following the chunk.
+        line.clear();
+        reader.read_line(&mut line)?;
+    }
+}
+
+fn write_response(
+    writer: &mut impl Write,
+    request: &ReceivedRequest,
+    response: Response<Vec<u8>>,
+) -> io::Result<()> {
+    let (parts, body) = response.into_parts();
+
+    write!(
+        writer,
+        "HTTP/1.1 {} {}\r\n",
+        parts.status.as_u16(),
+        parts.status.canonical_reason().unwrap_or("")
+    )?;
+
+    for (name, value) in parts.headers.iter() {
+        write!(writer, "{}: ", name)?;
+        writer.write_all(value.as_bytes())?;
+        writer.write_all(b"\r\n")?;
+    }
+
+    if !parts.headers.contains_key(http::header::CONTENT_LENGTH) {
+        write!(writer, "content-length: {}\r\n", body.len())?;
+    }
+
+    writer.write_all(b"connection: close\r\n\r\n")?;
+
+    if request.method != Method::HEAD {
+        writer.write_all(&body)?;
+    }
+
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunked_body_is_decoded() {
+        let mut input = &b"5\r\nhello\r\n6;ext=1\r\n world\r\n0\r\nTrailer: yes\r\n\r\n"[..];
+
+        assert_eq!(read_chunked_body(&mut input).unwrap(), b"hello world");
+    }
+
+    #[test]
+    fn request_is_parsed() {
+        let mut input =
+            &b"POST /items?page=2 HTTP/1.1\r\nHost: localhost\r\nContent-Length: 3\r\n\r\nabc"[..];
+        let mut output = Vec::new();
+
+        let request = read_request(&mut input, &mut output).unwrap().unwrap();
+
+        assert_eq!(request.method(), Method::POST);
+        assert_eq!(request.uri(), "/items?page=2");
+        assert_eq!(request.path(), "/items");
+        assert_eq!(request.headers()["host"], "localhost");
+        assert_eq!(request.body(), b"abc");
+        assert!(output.is_empty());
+    }
+}
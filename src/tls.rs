@@ -0,0 +1,219 @@
+//! Details about the secure channel a response was received over.
+
+use std::fmt;
+
+/// Details about the TLS connection a response was received over, such as the
+/// negotiated protocol version and cipher.
+///
+/// TLS details are only collected if enabled with
+/// [`HttpClientBuilder::tls_info`](crate::HttpClientBuilder::tls_info) or
+/// [`RequestBuilderExt::tls_info`](crate::RequestBuilderExt::tls_info), and
+/// are retrieved with [`ResponseExt::tls_info`](crate::ResponseExt::tls_info).
+///
+/// Collecting these details is best effort. The version and cipher, as well as
+/// the ALPN protocol of connections that do not use HTTP/2, are taken from
+/// curl's informational messages about the handshake. These messages are not
+/// a stable interface, so they may be missing or incomplete depending on the
+/// TLS engine and the version of curl. They are also only reported when a
+/// handshake takes place, so responses received over a reused connection may
+/// lack them. The `h2` ALPN protocol is instead derived from the HTTP version
+/// curl reports for the connection, which does not depend on these messages.
+///
+/// Collecting the messages requires curl's verbose mode for the request. The
+/// messages are not written to standard error, but only parsed and passed to
+/// the `isahc::curl` log target at the debug level, as they are whenever that
+/// level is enabled.
+#[derive(Clone, Default, Eq, PartialEq)]
+pub struct TlsInfo {
+    version: Option<String>,
+    cipher: Option<String>,
+    alpn_protocol: Option<String>,
+    peer_certificates: Vec<Vec<u8>>,
+}
+
+impl TlsInfo {
+    /// Get the name of the negotiated TLS protocol version, such as
+    /// `TLSv1.3`.
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_ref().map(String::as_str)
+    }
+
+    /// Get the name of the negotiated cipher suite, as named by the TLS
+    /// engine.
+    pub fn cipher(&self) -> Option<&str> {
+        self.cipher.as_ref().map(String::as_str)
+    }
+
+    /// Get the application protocol the server agreed to use through ALPN,
+    /// such as `h2` or `http/1.1`.
+    pub fn alpn_protocol(&self) -> Option<&str> {
+        self.alpn_protocol.as_ref().map(String::as_str)
+    }
+
+    /// Get the certificate chain presented by the server, in DER form,
+    /// starting with the server's own certificate.
+    ///
+    /// The chain is only collected if enabled with
+    /// [`HttpClientBuilder::tls_peer_certificates`](crate::HttpClientBuilder::tls_peer_certificates),
+    /// and is otherwise empty.
+    pub fn peer_certificates(&self) -> &[Vec<u8>] {
+        &self.peer_certificates
+    }
+
+    /// Check whether any details were collected at all.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.version.is_none()
+            && self.cipher.is_none()
+            && self.alpn_protocol.is_none()
+            && self.peer_certificates.is_empty()
+    }
+
+    /// Set the application protocol the server agreed to use, if known from a
+    /// more reliable source than curl's informational output.
+    pub(crate) fn set_alpn_protocol(&mut self, protocol: &str) {
+        self.alpn_protocol = Some(protocol.to_owned());
+    }
+
+    /// Update the details from a line of curl's informational output.
+    pub(crate) fn parse_debug_line(&mut self, line: &str) {
+        const CONNECTION_PREFIX: &str = "SSL connection using ";
+        const ALPN_PREFIXES: &[&str] = &["ALPN, server accepted to use ", "ALPN: server accepted "];
+
+        let line = line.trim();
+
+        if line.starts_with(CONNECTION_PREFIX) {
+            // Such as "TLSv1.3 / TLS_AES_256_GCM_SHA384", possibly followed by
+            // more details.
+            let mut parts = line[CONNECTION_PREFIX.len()..].split(" / ");
+
+            self.version = parts.next().map(str::trim).map(str::to_owned);
+            self.cipher = parts.next().map(str::trim).map(str::to_owned);
+        } else if let Some(prefix) = ALPN_PREFIXES.iter().find(|p| line.starts_with(*p)) {
+            self.alpn_protocol = Some(line[prefix.len()..].trim().to_owned());
+        }
+    }
+
+    /// Add a certificate of the peer's chain, given as one of the fields
+    /// reported by curl for it. Fields other than the PEM-encoded certificate
+    /// are ignored.
+    pub(crate) fn add_certificate_field(&mut self, field: &str) {
+        const PREFIX: &str = "Cert:";
+
+        if field.starts_with(PREFIX) {
+            if let Some(der) = pem_to_der(&field[PREFIX.len()..]) {
+                self.peer_certificates.push(der);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for TlsInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsInfo")
+            .field("version", &self.version)
+            .field("cipher", &self.cipher)
+            .field("alpn_protocol", &self.alpn_protocol)
+            .field("peer_certificates", &self.peer_certificates.len())
+            .finish()
+    }
+}
+
+/// Decode a single PEM-encoded block into its DER contents.
+fn pem_to_der(pem: &str) -> Option<Vec<u8>> {
+    let mut lines = pem
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| !line.starts_with("-----BEGIN "));
+
+    lines.next()?;
+
+    let mut der = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for line in lines {
+        if line.starts_with("-----END ") {
+            return Some(der);
+        }
+
+        for byte in line.bytes() {
+            let value = match byte {
+                b'A'..=b'Z' => byte - b'A',
+                b'a'..=b'z' => byte - b'a' + 26,
+                b'0'..=b'9' => byte - b'0' + 52,
+                b'+' => 62,
+                b'/' => 63,
+                b'=' => continue,
+                _ => return None,
+            };
+
+            buffer = (buffer << 6) | u32::from(value);
+            bits += 6;
+
+            if bits >= 8 {
+                bits -= 8;
+                der.push((buffer >> bits) as u8);
+                buffer &= (1 << bits) - 1;
+            }
+        }
+    }
+
+    // The end marker is missing.
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_openssl_connection_line() {
+        let mut info = TlsInfo::default();
+        info.parse_debug_line(
+            "SSL connection using TLSv1.3 / TLS_AES_256_GCM_SHA384 / X25519 / RSASSA-PSS\n",
+        );
+
+        assert_eq!(info.version(), Some("TLSv1.3"));
+        assert_eq!(info.cipher(), Some("TLS_AES_256_GCM_SHA384"));
+    }
+
+    #[test]
+    fn parse_alpn_lines() {
+        let mut info = TlsInfo::default();
+
+        info.parse_debug_line("ALPN, server accepted to use h2");
+        assert_eq!(info.alpn_protocol(), Some("h2"));
+
+        info.parse_debug_line("ALPN: server accepted http/1.1");
+        assert_eq!(info.alpn_protocol(), Some("http/1.1"));
+    }
+
+    #[test]
+    fn unrelated_lines_are_ignored() {
+        let mut info = TlsInfo::default();
+        info.parse_debug_line("Connected to example.org (93.184.216.34) port 443 (#0)");
+
+        assert!(info.is_empty());
+    }
+
+    #[test]
+    fn certificate_fields_are_decoded() {
+        let mut info = TlsInfo::default();
+
+        info.add_certificate_field("Subject:CN = example.org");
+        info.add_certificate_field(
+            "Cert:-----BEGIN CERTIFICATE-----\nAAECAwQF\nBgc=\n-----END CERTIFICATE-----\n",
+        );
+
+        assert_eq!(info.peer_certificates(), &[vec![0, 1, 2, 3, 4, 5, 6, 7]]);
+    }
+
+    #[test]
+    fn truncated_pem_is_rejected() {
+        assert_eq!(pem_to_der("-----BEGIN CERTIFICATE-----\nAAEC\n"), None);
+        assert_eq!(
+            pem_to_der("-----BEGIN CERTIFICATE-----\nA!EC\n-----END CERTIFICATE-----"),
+            None
+        );
+    }
+}
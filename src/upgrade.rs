@@ -0,0 +1,207 @@
+//! Taking over connections after an HTTP/1.1 protocol upgrade.
+//!
+//! Some protocols, most notably WebSocket, start out as an HTTP/1.1 request
+//! with an `Upgrade` header. If the server agrees to switch protocols, it
+//! responds with `101 Switching Protocols`, after which the connection no
+//! longer speaks HTTP. Use
+//! [`HttpClient::upgrade`](crate::HttpClient::upgrade) to send such a request
+//! and take over the connection as an [`Upgraded`] stream, which a codec for
+//! the new protocol can then drive.
+//!
+//! This module requires the `upgrade` feature to be enabled.
+
+use crate::{parse, Error, StatusError};
+use curl::easy::{Easy2, Handler};
+use http::{Response, StatusCode};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::mem::ManuallyDrop;
+use std::net::TcpStream;
+
+/// Maximum size of the response head sent by the server.
+const MAX_HEAD_SIZE: usize = 64 * 1024;
+
+/// A connection that was taken over after the server agreed to switch
+/// protocols.
+///
+/// Reads and writes go directly to the connection, through TLS if the request
+/// was made over HTTPS. Both block until they can make progress.
+pub struct Upgraded {
+    easy: Easy2<Connection>,
+    response: Response<()>,
+    /// Data received after the response head, which belongs to the new
+    /// protocol.
+    buffered: io::Cursor<Vec<u8>>,
+}
+
+/// Handler for the connect-only curl handle of an upgraded connection. The
+/// connection is driven directly, so no callbacks are needed.
+pub(crate) struct Connection;
+
+impl Handler for Connection {}
+
+impl Upgraded {
+    /// Send the given request head on a connection established by curl, and
+    /// read the response.
+    pub(crate) fn handshake(mut easy: Easy2<Connection>, head: &[u8]) -> Result<Self, Error> {
+        set_blocking(&easy)?;
+
+        let mut written = 0;
+
+        while written < head.len() {
+            written += easy.send(&head[written..])?;
+        }
+
+        let mut received = Vec::new();
+        let mut buf = [0; 8192];
+
+        let head_len = loop {
+            if let Some(i) = find_head_end(&received) {
+                break i;
+            }
+
+            if received.len() > MAX_HEAD_SIZE {
+                return Err(Error::Io(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "response head too large",
+                )));
+            }
+
+            match easy.recv(&mut buf)? {
+                0 => return Err(Error::NoResponse),
+                len => received.extend_from_slice(&buf[..len]),
+            }
+        };
+
+        let rest = received.split_off(head_len);
+        let response = parse_head(&received)?;
+
+        if response.status() != StatusCode::SWITCHING_PROTOCOLS {
+            return Err(Error::BadStatus(StatusError {
+                status: response.status(),
+                headers: response.headers().clone(),
+                body: rest,
+            }));
+        }
+
+        Ok(Self {
+            easy,
+            response,
+            buffered: io::Cursor::new(rest),
+        })
+    }
+
+    /// Get the `101 Switching Protocols` response sent by the server.
+    pub fn response(&self) -> &Response<()> {
+        &self.response
+    }
+}
+
+impl Read for Upgraded {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if (self.buffered.position() as usize) < self.buffered.get_ref().len() {
+            return self.buffered.read(buf);
+        }
+
+        self.easy.recv(buf).map_err(|e| Error::from(e).into())
+    }
+}
+
+impl Write for Upgraded {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.easy.send(buf).map_err(|e| Error::from(e).into())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Upgraded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Upgraded")
+            .field("response", &self.response)
+            .finish()
+    }
+}
+
+/// Find the end of the response head in the data received so far.
+fn find_head_end(data: &[u8]) -> Option<usize> {
+    data.windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .map(|i| i + 4)
+}
+
+/// Parse a response head into a response without a body.
+fn parse_head(head: &[u8]) -> Result<Response<()>, Error> {
+    // Trailing carriage returns are ignored by the parsers.
+    let mut lines = head.split(|&b| b == b'\n');
+
+    let (version, status) = lines
+        .next()
+        .and_then(parse::parse_status_line)
+        .ok_or(Error::NoResponse)?;
+
+    let mut builder = Response::builder();
+    builder.version(version).status(status);
+
+    for line in lines {
+        if let Some((name, value)) = parse::parse_header(line) {
+            builder.header(name, value);
+        }
+    }
+
+    Ok(builder.body(())?)
+}
+
+/// Switch the socket of an established connection to blocking mode, so that
+/// reads and writes wait until they can make progress.
+#[allow(unsafe_code)]
+fn set_blocking(easy: &Easy2<Connection>) -> io::Result<()> {
+    // Not yet exposed by curl-sys.
+    const CURLINFO_ACTIVESOCKET: curl_sys::CURLINFO = 0x50_0000 + 44;
+
+    let mut socket: curl_sys::curl_socket_t = curl_sys::CURL_SOCKET_BAD;
+
+    unsafe {
+        if curl_sys::curl_easy_getinfo(easy.raw(), CURLINFO_ACTIVESOCKET, &mut socket)
+            != curl_sys::CURLE_OK
+            || socket == curl_sys::CURL_SOCKET_BAD
+        {
+            return Err(io::ErrorKind::NotConnected.into());
+        }
+    }
+
+    #[cfg(unix)]
+    let stream: TcpStream = unsafe { std::os::unix::io::FromRawFd::from_raw_fd(socket) };
+    #[cfg(windows)]
+    let stream: TcpStream =
+        unsafe { std::os::windows::io::FromRawSocket::from_raw_socket(socket as _) };
+
+    // The socket is still owned by curl, so it must not be closed here.
+    let stream = ManuallyDrop::new(stream);
+
+    stream.set_nonblocking(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_switching_protocols_head() {
+        let head = b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\r\n";
+
+        assert_eq!(find_head_end(head), Some(head.len()));
+
+        let response = parse_head(head).unwrap();
+        assert_eq!(response.status(), 101);
+        assert_eq!(response.headers()["upgrade"], "websocket");
+    }
+
+    #[test]
+    fn find_head_end_in_partial_data() {
+        assert_eq!(find_head_end(b"HTTP/1.1 101 Switching Protocols\r\n"), None);
+        assert_eq!(find_head_end(b"HTTP/1.1 101 OK\r\n\r\nabc"), Some(19));
+    }
+}
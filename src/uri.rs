@@ -0,0 +1,109 @@
+//! Conversion of the URIs given to the client's request helpers.
+
+use crate::Error;
+use bytes::Bytes;
+use http::{uri::Parts, HttpTryFrom, Uri};
+
+/// A value that can be converted into the URI of a request.
+///
+/// This is implemented for the same types that a [`Uri`] can be created from,
+/// and is accepted by helpers such as [`get`](crate::get) and
+/// [`HttpClient::post`](crate::HttpClient::post).
+///
+/// With the `idna` feature enabled, Unicode host names in strings are
+/// converted to their ASCII-compatible form, so that a URI such as
+/// `https://bücher.example/` can be used as-is. See the [`idn`](crate::idn)
+/// module for details.
+pub trait IntoUri {
+    /// Convert this value into a URI.
+    fn into_uri(self) -> Result<Uri, Error>;
+}
+
+impl IntoUri for Uri {
+    fn into_uri(self) -> Result<Uri, Error> {
+        Ok(self)
+    }
+}
+
+impl<'a> IntoUri for &'a Uri {
+    fn into_uri(self) -> Result<Uri, Error> {
+        Ok(self.clone())
+    }
+}
+
+impl<'a> IntoUri for &'a str {
+    fn into_uri(self) -> Result<Uri, Error> {
+        parse_str(self)
+    }
+}
+
+impl IntoUri for String {
+    fn into_uri(self) -> Result<Uri, Error> {
+        parse_str(&self)
+    }
+}
+
+impl<'a> IntoUri for &'a String {
+    fn into_uri(self) -> Result<Uri, Error> {
+        parse_str(self)
+    }
+}
+
+impl IntoUri for Bytes {
+    fn into_uri(self) -> Result<Uri, Error> {
+        try_from(self)
+    }
+}
+
+impl IntoUri for Parts {
+    fn into_uri(self) -> Result<Uri, Error> {
+        try_from(self)
+    }
+}
+
+/// Parse a URI string, converting a Unicode host name if IDN support is
+/// enabled.
+fn parse_str(uri: &str) -> Result<Uri, Error> {
+    #[cfg(feature = "idna")]
+    {
+        if !uri.is_ascii() {
+            return crate::idn::parse_uri(uri);
+        }
+    }
+
+    try_from(uri)
+}
+
+fn try_from<T>(value: T) -> Result<Uri, Error>
+where
+    Uri: HttpTryFrom<T>,
+{
+    HttpTryFrom::try_from(value).map_err(|e| Error::InvalidHttpFormat(e.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strings_are_parsed() {
+        assert_eq!(
+            "https://example.org/a".into_uri().unwrap(),
+            "https://example.org/a"
+        );
+        assert_eq!(
+            String::from("https://example.org/a").into_uri().unwrap(),
+            "https://example.org/a"
+        );
+        assert!("https://exa mple.org/".into_uri().is_err());
+    }
+
+    #[cfg(feature = "idna")]
+    #[test]
+    fn unicode_host_names_are_converted() {
+        assert_eq!(
+            "https://bücher.example/".into_uri().unwrap(),
+            "https://xn--bcher-kva.example/"
+        );
+    }
+}
@@ -0,0 +1,414 @@
+//! Automatic conditional requests using remembered validators.
+//!
+//! A client with conditional requests enabled remembers the `ETag` and
+//! `Last-Modified` headers of successful responses. When the same URI is
+//! requested again, the client attaches `If-None-Match` and
+//! `If-Modified-Since` headers, letting the server answer with a short
+//! `304 Not Modified` response if the resource did not change.
+//!
+//! See
+//! [`HttpClientBuilder::conditional_requests`](crate::HttpClientBuilder::conditional_requests)
+//! for enabling conditional requests.
+//!
+//! Conditional requests can also be made by hand, for example by a poller
+//! that keeps the last response around itself. Use
+//! [`RequestBuilderExt::revalidate`](crate::RequestBuilderExt::revalidate) to
+//! make a request conditional on a previous response, and
+//! [`merge_not_modified`] to turn a `304 Not Modified` answer into an
+//! up-to-date copy of the previous response.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use isahc::prelude::*;
+//! use isahc::validators::merge_not_modified;
+//!
+//! // Send a request and keep the response body as a string.
+//! fn fetch(request: Request<()>) -> Result<Response<String>, isahc::Error> {
+//!     let (parts, mut body) = request.send()?.into_parts();
+//!     Ok(Response::from_parts(parts, body.text()?))
+//! }
+//!
+//! let uri = "https://example.org/feed";
+//! let previous = fetch(Request::get(uri).body(())?)?;
+//!
+//! // Later...
+//! let response = fetch(Request::get(uri).revalidate(&previous).body(())?)?;
+//!
+//! let current = if response.status() == 304 {
+//!     merge_not_modified(previous, &response)
+//! } else {
+//!     response
+//! };
+//! # Ok::<(), isahc::Error>(())
+//! ```
+
+use crate::{middleware::Middleware, response::RequestUri, Body};
+use http::{Request, Response};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Validators of a previously received response, used to check whether the
+/// resource has changed since.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Validators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl Validators {
+    /// Get the entity tag of the response, if any.
+    pub fn etag(&self) -> Option<&str> {
+        self.etag.as_ref().map(String::as_str)
+    }
+
+    /// Get the last modification date of the response, if any.
+    pub fn last_modified(&self) -> Option<&str> {
+        self.last_modified.as_ref().map(String::as_str)
+    }
+
+    /// Get the validators of a response, if it has any.
+    pub fn from_response<T>(response: &Response<T>) -> Option<Self> {
+        Self::from_headers(response.headers())
+    }
+
+    /// Read the validators from the headers of a response.
+    fn from_headers(headers: &http::HeaderMap) -> Option<Self> {
+        let get = |name| {
+            headers
+                .get(name)
+                .and_then(|value: &http::HeaderValue| value.to_str().ok())
+                .map(ToOwned::to_owned)
+        };
+
+        let validators = Self {
+            etag: get(http::header::ETAG),
+            last_modified: get(http::header::LAST_MODIFIED),
+        };
+
+        if validators.etag.is_some() || validators.last_modified.is_some() {
+            Some(validators)
+        } else {
+            None
+        }
+    }
+}
+
+/// Marker attached to a `304 Not Modified` response to a request that was
+/// made conditional automatically.
+///
+/// Use [`ResponseExt::not_modified`](crate::ResponseExt::not_modified) to
+/// check for this on a response.
+#[derive(Clone, Debug)]
+pub struct NotModified {
+    validators: Validators,
+}
+
+impl NotModified {
+    /// Get the validators that the resource was found to still match.
+    pub fn validators(&self) -> &Validators {
+        &self.validators
+    }
+}
+
+/// Combine a previous response with a `304 Not Modified` response to a
+/// request made conditional on it, producing an up-to-date copy of the
+/// previous response.
+///
+/// The body and status of the previous response are kept, while any headers
+/// sent along with the `304` replace the stored ones, as described in RFC 7234.
+/// If `not_modified` is not a `304` response, the previous response is
+/// returned unchanged.
+pub fn merge_not_modified<T, U>(
+    mut previous: Response<T>,
+    not_modified: &Response<U>,
+) -> Response<T> {
+    if not_modified.status() == http::StatusCode::NOT_MODIFIED {
+        update_headers(previous.headers_mut(), not_modified.headers());
+    }
+
+    previous
+}
+
+/// Update the stored headers of a response with those of a `304 Not Modified`
+/// response confirming that it is still valid.
+///
+/// https://tools.ietf.org/html/rfc7234#section-4.3.4
+pub(crate) fn update_headers(stored: &mut http::HeaderMap, headers: &http::HeaderMap) {
+    for name in headers.keys() {
+        if name == http::header::CONTENT_LENGTH || name == http::header::TRANSFER_ENCODING {
+            continue;
+        }
+
+        stored.remove(name);
+
+        for value in headers.get_all(name) {
+            stored.append(name.clone(), value.clone());
+        }
+    }
+}
+
+/// Maximum number of URIs that a client remembers validators for.
+const MAX_ENTRIES: usize = 1024;
+
+/// Remembers response validators per request URI and makes matching requests
+/// conditional.
+///
+/// Validators are stored under the URI of the request rather than the URI of
+/// the response, so that they are found again for resources behind
+/// redirects.
+#[derive(Debug)]
+pub(crate) struct ValidatorStore {
+    entries: RwLock<Entries>,
+    max_entries: usize,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    /// Validators by request URI, along with when they were stored.
+    map: HashMap<String, (Validators, u64)>,
+    /// Counter for ordering entries by when they were stored.
+    sequence: u64,
+}
+
+impl ValidatorStore {
+    /// Create a store remembering validators for up to `max_entries` URIs.
+    /// Once full, the validators stored the longest ago are forgotten first.
+    pub(crate) fn new(max_entries: usize) -> Self {
+        Self {
+            entries: RwLock::new(Entries::default()),
+            max_entries,
+        }
+    }
+
+    fn insert(&self, uri: String, validators: Validators) {
+        let mut entries = self.entries.write().unwrap();
+
+        if entries.map.len() >= self.max_entries && !entries.map.contains_key(&uri) {
+            let oldest = entries
+                .map
+                .iter()
+                .min_by_key(|(_, (_, sequence))| *sequence)
+                .map(|(uri, _)| uri.clone());
+
+            if let Some(oldest) = oldest {
+                entries.map.remove(&oldest);
+            }
+        }
+
+        entries.sequence += 1;
+        let sequence = entries.sequence;
+        entries.map.insert(uri, (validators, sequence));
+    }
+}
+
+impl Default for ValidatorStore {
+    fn default() -> Self {
+        Self::new(MAX_ENTRIES)
+    }
+}
+
+impl Middleware for ValidatorStore {
+    fn filter_request(&self, mut request: Request<Body>) -> Request<Body> {
+        // Only safe requests can be made conditional without changing their
+        // meaning, and explicit conditions set by the user take precedence.
+        if (request.method() != http::Method::GET && request.method() != http::Method::HEAD)
+            || request.headers().contains_key(http::header::IF_NONE_MATCH)
+            || request
+                .headers()
+                .contains_key(http::header::IF_MODIFIED_SINCE)
+        {
+            return request;
+        }
+
+        let uri = request.uri().to_string();
+        let validators = match self.entries.read().unwrap().map.get(&uri) {
+            Some((validators, _)) => validators.clone(),
+            None => return request,
+        };
+
+        if let Some(etag) = validators.etag().and_then(|v| v.parse().ok()) {
+            request
+                .headers_mut()
+                .insert(http::header::IF_NONE_MATCH, etag);
+        } else if let Some(date) = validators.last_modified().and_then(|v| v.parse().ok()) {
+            request
+                .headers_mut()
+                .insert(http::header::IF_MODIFIED_SINCE, date);
+        }
+
+        request
+    }
+
+    fn filter_response(&self, mut response: Response<Body>) -> Response<Body> {
+        let uri = match response.extensions().get::<RequestUri>() {
+            Some(uri) => uri.0.to_string(),
+            None => return response,
+        };
+
+        match response.status() {
+            http::StatusCode::OK => {
+                if let Some(validators) = Validators::from_headers(response.headers()) {
+                    self.insert(uri, validators);
+                }
+            }
+            http::StatusCode::NOT_MODIFIED => {
+                let mut entries = self.entries.write().unwrap();
+
+                if let Some((validators, _)) = entries.map.get_mut(&uri) {
+                    // Servers may send updated validators along with a 304.
+                    if let Some(updated) = Validators::from_headers(response.headers()) {
+                        *validators = updated;
+                    }
+
+                    let validators = validators.clone();
+                    response.extensions_mut().insert(NotModified { validators });
+                }
+            }
+            _ => {}
+        }
+
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::{EffectiveUri, ResponseExt};
+
+    fn response(status: u16, headers: &[(&str, &str)]) -> Response<Body> {
+        let mut builder = Response::builder();
+        builder
+            .status(status)
+            .extension(RequestUri("https://example.org/feed".parse().unwrap()));
+
+        for (name, value) in headers {
+            builder.header(*name, *value);
+        }
+
+        builder.body(Body::empty()).unwrap()
+    }
+
+    fn request() -> Request<Body> {
+        Request::get("https://example.org/feed")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn etag_is_sent_on_next_request() {
+        let store = ValidatorStore::default();
+
+        assert!(store
+            .filter_request(request())
+            .headers()
+            .get("If-None-Match")
+            .is_none());
+
+        store.filter_response(response(200, &[("ETag", "\"v1\"")]));
+
+        let request = store.filter_request(request());
+        assert_eq!(request.headers()["If-None-Match"], "\"v1\"");
+        assert!(request.headers().get("If-Modified-Since").is_none());
+    }
+
+    #[test]
+    fn last_modified_is_used_without_etag() {
+        let store = ValidatorStore::default();
+
+        store.filter_response(response(
+            200,
+            &[("Last-Modified", "Wed, 21 Oct 2015 07:28:00 GMT")],
+        ));
+
+        let request = store.filter_request(request());
+        assert_eq!(
+            request.headers()["If-Modified-Since"],
+            "Wed, 21 Oct 2015 07:28:00 GMT"
+        );
+    }
+
+    #[test]
+    fn not_modified_response_is_marked() {
+        let store = ValidatorStore::default();
+
+        store.filter_response(response(200, &[("ETag", "\"v1\"")]));
+
+        let response = store.filter_response(response(304, &[]));
+        let not_modified = response.not_modified().unwrap();
+        assert_eq!(not_modified.validators().etag(), Some("\"v1\""));
+    }
+
+    #[test]
+    fn validators_are_stored_under_the_request_uri() {
+        let store = ValidatorStore::default();
+        let mut redirected = response(200, &[("ETag", "\"v1\"")]);
+        redirected
+            .extensions_mut()
+            .insert(EffectiveUri("https://example.org/feed/v2".parse().unwrap()));
+
+        store.filter_response(redirected);
+
+        let request = store.filter_request(request());
+        assert_eq!(request.headers()["If-None-Match"], "\"v1\"");
+    }
+
+    #[test]
+    fn oldest_validators_are_forgotten_first() {
+        let store = ValidatorStore::new(2);
+        let validators = |etag: &str| Validators {
+            etag: Some(etag.to_owned()),
+            last_modified: None,
+        };
+
+        store.insert("a".into(), validators("1"));
+        store.insert("b".into(), validators("2"));
+        store.insert("a".into(), validators("3"));
+        store.insert("c".into(), validators("4"));
+
+        let entries = store.entries.read().unwrap();
+        assert_eq!(entries.map.len(), 2);
+        assert_eq!(entries.map["a"].0.etag(), Some("3"));
+        assert!(!entries.map.contains_key("b"));
+        assert_eq!(entries.map["c"].0.etag(), Some("4"));
+    }
+
+    #[test]
+    fn unsafe_methods_are_not_made_conditional() {
+        let store = ValidatorStore::default();
+
+        store.filter_response(response(200, &[("ETag", "\"v1\"")]));
+
+        let request = store.filter_request(
+            Request::post("https://example.org/feed")
+                .body(Body::empty())
+                .unwrap(),
+        );
+        assert!(request.headers().get("If-None-Match").is_none());
+    }
+
+    #[test]
+    fn merge_keeps_body_and_updates_headers() {
+        let previous = Response::builder()
+            .header("ETag", "\"v1\"")
+            .header("Cache-Control", "max-age=10")
+            .header("Content-Type", "text/plain")
+            .body("hello")
+            .unwrap();
+        let not_modified = Response::builder()
+            .status(304)
+            .header("Cache-Control", "max-age=60")
+            .header("Content-Length", "0")
+            .body(())
+            .unwrap();
+
+        let merged = merge_not_modified(previous, &not_modified);
+
+        assert_eq!(merged.status(), 200);
+        assert_eq!(*merged.body(), "hello");
+        assert_eq!(merged.headers()["Cache-Control"], "max-age=60");
+        assert_eq!(merged.headers()["Content-Type"], "text/plain");
+        assert!(merged.headers().get("Content-Length").is_none());
+    }
+}
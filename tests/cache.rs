@@ -0,0 +1,306 @@
+use isahc::cache::{CacheMode, CacheStatus, ResponseCache};
+use isahc::prelude::*;
+use mockito::{mock, server_url, Matcher};
+use std::thread;
+use std::time::Duration;
+
+fn client() -> HttpClient {
+    HttpClient::builder()
+        .response_cache(1024 * 1024)
+        .build()
+        .unwrap()
+}
+
+speculate::speculate! {
+    before {
+        env_logger::try_init().ok();
+    }
+
+    test "fresh responses are served from the cache" {
+        let m = mock("GET", "/")
+            .with_header("cache-control", "max-age=60")
+            .with_body("hello world")
+            .expect(1)
+            .create();
+
+        let client = client();
+
+        let mut response = client.get(server_url()).unwrap();
+        assert_eq!(response.cache_status(), Some(CacheStatus::Miss));
+        assert_eq!(response.text().unwrap(), "hello world");
+
+        let mut response = client.get(server_url()).unwrap();
+        assert_eq!(response.cache_status(), Some(CacheStatus::Hit));
+        assert_eq!(response.text().unwrap(), "hello world");
+        assert!(response.headers().contains_key("age"));
+
+        m.assert();
+    }
+
+    test "stale responses are revalidated" {
+        let m1 = mock("GET", "/")
+            .match_header("if-none-match", Matcher::Missing)
+            .with_header("cache-control", "no-cache")
+            .with_header("etag", "\"v1\"")
+            .with_body("hello world")
+            .expect(1)
+            .create();
+        let m2 = mock("GET", "/")
+            .match_header("if-none-match", "\"v1\"")
+            .with_status(304)
+            .expect(1)
+            .create();
+
+        let client = client();
+
+        client.get(server_url()).unwrap().text().unwrap();
+
+        let mut response = client.get(server_url()).unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.cache_status(), Some(CacheStatus::Revalidated));
+        assert_eq!(response.text().unwrap(), "hello world");
+
+        m1.assert();
+        m2.assert();
+    }
+
+    test "variants of a response are cached separately" {
+        let m1 = mock("GET", "/")
+            .match_header("accept-language", "en")
+            .with_header("cache-control", "max-age=60")
+            .with_header("vary", "accept-language")
+            .with_body("hello")
+            .expect(1)
+            .create();
+        let m2 = mock("GET", "/")
+            .match_header("accept-language", "de")
+            .with_header("cache-control", "max-age=60")
+            .with_header("vary", "accept-language")
+            .with_body("hallo")
+            .expect(1)
+            .create();
+
+        let client = client();
+        let get = |language| {
+            let request = Request::get(server_url())
+                .header("accept-language", language)
+                .body(())
+                .unwrap();
+            client.send(request).unwrap()
+        };
+
+        assert_eq!(get("en").text().unwrap(), "hello");
+        assert_eq!(get("de").text().unwrap(), "hallo");
+
+        let mut response = get("en");
+        assert_eq!(response.cache_status(), Some(CacheStatus::Hit));
+        assert_eq!(response.text().unwrap(), "hello");
+
+        let mut response = get("de");
+        assert_eq!(response.cache_status(), Some(CacheStatus::Hit));
+        assert_eq!(response.text().unwrap(), "hallo");
+
+        m1.assert();
+        m2.assert();
+    }
+
+    test "ignored vary headers do not select a variant" {
+        let m = mock("GET", "/")
+            .with_header("cache-control", "max-age=60")
+            .with_header("vary", "user-agent, accept-encoding")
+            .with_body("hello world")
+            .expect(1)
+            .create();
+
+        let client = HttpClient::builder()
+            .response_cache(ResponseCache::new(1024 * 1024).ignore_vary(isahc::http::header::USER_AGENT))
+            .build()
+            .unwrap();
+        let get = |user_agent, encoding| {
+            let request = Request::get(server_url())
+                .header("user-agent", user_agent)
+                .header("accept-encoding", encoding)
+                .body(())
+                .unwrap();
+            client.send(request).unwrap()
+        };
+
+        get("a", "gzip,br").text().unwrap();
+
+        let mut response = get("b", "gzip, br");
+        assert_eq!(response.cache_status(), Some(CacheStatus::Hit));
+        assert_eq!(response.text().unwrap(), "hello world");
+
+        m.assert();
+    }
+
+    test "no-store responses are not cached" {
+        let m = mock("GET", "/")
+            .with_header("cache-control", "no-store, max-age=60")
+            .with_body("hello world")
+            .expect(2)
+            .create();
+
+        let client = client();
+
+        client.get(server_url()).unwrap().text().unwrap();
+        let response = client.get(server_url()).unwrap();
+        assert_eq!(response.cache_status(), Some(CacheStatus::Miss));
+
+        m.assert();
+    }
+
+    test "unsafe requests invalidate cached responses" {
+        let m1 = mock("GET", "/")
+            .with_header("cache-control", "max-age=60")
+            .with_body("hello world")
+            .expect(2)
+            .create();
+        let m2 = mock("POST", "/").create();
+
+        let client = client();
+
+        client.get(server_url()).unwrap().text().unwrap();
+        client.post(server_url(), "update").unwrap();
+        client.get(server_url()).unwrap().text().unwrap();
+
+        m1.assert();
+        m2.assert();
+    }
+
+    test "no-store mode bypasses the cache" {
+        let m = mock("GET", "/")
+            .with_header("cache-control", "max-age=60")
+            .with_body("hello world")
+            .expect(3)
+            .create();
+
+        let client = client();
+        let get = || {
+            let request = Request::get(server_url())
+                .cache_mode(CacheMode::NoStore)
+                .body(())
+                .unwrap();
+            client.send(request).unwrap()
+        };
+
+        client.get(server_url()).unwrap().text().unwrap();
+
+        let mut response = get();
+        assert_eq!(response.cache_status(), None);
+        assert_eq!(response.text().unwrap(), "hello world");
+
+        // The response fetched without the cache was not stored either.
+        get().text().unwrap();
+
+        m.assert();
+    }
+
+    test "force-cache mode serves stale responses" {
+        let m = mock("GET", "/")
+            .with_header("cache-control", "no-cache")
+            .with_header("etag", "\"v1\"")
+            .with_body("hello world")
+            .expect(1)
+            .create();
+
+        let client = client();
+
+        client.get(server_url()).unwrap().text().unwrap();
+
+        let request = Request::get(server_url())
+            .cache_mode(CacheMode::ForceCache)
+            .body(())
+            .unwrap();
+        let mut response = client.send(request).unwrap();
+        assert_eq!(response.cache_status(), Some(CacheStatus::Stale));
+        assert_eq!(response.text().unwrap(), "hello world");
+
+        m.assert();
+    }
+
+    test "only-if-cached mode never contacts the server" {
+        let m = mock("GET", "/")
+            .with_header("cache-control", "max-age=60")
+            .with_body("hello world")
+            .expect(0)
+            .create();
+
+        let request = Request::get(server_url())
+            .cache_mode(CacheMode::OnlyIfCached)
+            .body(())
+            .unwrap();
+        let response = client().send(request).unwrap();
+        assert_eq!(response.status(), 504);
+        assert_eq!(response.cache_status(), Some(CacheStatus::Miss));
+
+        m.assert();
+    }
+
+    test "stale responses are served while being revalidated" {
+        let m1 = mock("GET", "/")
+            .match_header("if-none-match", Matcher::Missing)
+            .with_header("cache-control", "max-age=0, stale-while-revalidate=60")
+            .with_header("etag", "\"v1\"")
+            .with_body("hello world")
+            .expect(1)
+            .create();
+        let m2 = mock("GET", "/")
+            .match_header("if-none-match", "\"v1\"")
+            .with_status(304)
+            .with_header("cache-control", "max-age=60")
+            .expect(1)
+            .create();
+
+        let client = client();
+
+        client.get(server_url()).unwrap().text().unwrap();
+
+        let mut response = client.get(server_url()).unwrap();
+        assert_eq!(response.cache_status(), Some(CacheStatus::Stale));
+        assert_eq!(response.text().unwrap(), "hello world");
+
+        // Wait for the background revalidation to refresh the stored response.
+        let mut status = None;
+        for _ in 0..100 {
+            status = client.get(server_url()).unwrap().cache_status();
+
+            if status == Some(CacheStatus::Hit) {
+                break;
+            }
+
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(status, Some(CacheStatus::Hit));
+
+        m1.assert();
+        m2.assert();
+    }
+
+    test "stale responses are served if the server fails" {
+        let m1 = mock("GET", "/")
+            .match_header("if-none-match", Matcher::Missing)
+            .with_header("cache-control", "max-age=0, stale-if-error=60")
+            .with_header("etag", "\"v1\"")
+            .with_body("hello world")
+            .expect(1)
+            .create();
+        let m2 = mock("GET", "/")
+            .match_header("if-none-match", "\"v1\"")
+            .with_status(503)
+            .expect(1)
+            .create();
+
+        let client = client();
+
+        client.get(server_url()).unwrap().text().unwrap();
+
+        let mut response = client.get(server_url()).unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.cache_status(), Some(CacheStatus::Stale));
+        assert_eq!(response.text().unwrap(), "hello world");
+
+        m1.assert();
+        m2.assert();
+    }
+}
@@ -0,0 +1,123 @@
+use isahc::prelude::*;
+use isahc::CancellationToken;
+use mockito::{mock, server_url};
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Serve a single connection that sends the start of a response and then
+/// stalls for a while.
+fn serve_stalling() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+
+        while !request.ends_with(b"\r\n\r\n") {
+            let len = stream.read(&mut buf).unwrap();
+            if len == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..len]);
+        }
+
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\nhello")
+            .unwrap();
+        thread::sleep(Duration::from_secs(5));
+    });
+
+    url
+}
+
+speculate::speculate! {
+    before {
+        env_logger::try_init().ok();
+    }
+
+    test "request with canceled token fails" {
+        let token = CancellationToken::new();
+        token.cancel();
+
+        let result = Request::get(server_url())
+            .cancellation_token(token)
+            .body(())
+            .unwrap()
+            .send();
+
+        match result {
+            Err(isahc::Error::Canceled) => {}
+            e => panic!("expected canceled error, got {:?}", e),
+        }
+    }
+
+    test "canceling aborts reading the response body" {
+        let token = CancellationToken::new();
+
+        let mut response = Request::get(serve_stalling())
+            .cancellation_token(token.clone())
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        let start = Instant::now();
+
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            token.cancel();
+        });
+
+        match response.text() {
+            Err(isahc::Error::Canceled) => {}
+            e => panic!("expected canceled error, got {:?}", e),
+        }
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    test "canceling the last request completes a pending invalidation" {
+        let m = mock("GET", "/")
+            .with_body("hello world")
+            .expect(2)
+            .create();
+
+        let client = HttpClient::new().unwrap();
+        let token = CancellationToken::new();
+
+        let mut response = client
+            .send(
+                Request::get(serve_stalling())
+                    .cancellation_token(token.clone())
+                    .body(())
+                    .unwrap(),
+            )
+            .unwrap();
+
+        // The pool can only be replaced once the active request is gone.
+        client.invalidate_connections().unwrap();
+        token.cancel();
+
+        match response.text() {
+            Err(isahc::Error::Canceled) => {}
+            e => panic!("expected canceled error, got {:?}", e),
+        }
+
+        thread::sleep(Duration::from_millis(100));
+
+        let get = || {
+            let mut response = client.get(server_url()).unwrap();
+            response.text().unwrap();
+            response.connection_info().cloned().unwrap()
+        };
+
+        assert!(!get().is_reused());
+        assert!(get().is_reused());
+
+        m.assert();
+    }
+}
@@ -0,0 +1,67 @@
+#![cfg(feature = "compression")]
+
+use flate2::read::GzEncoder;
+use isahc::config::Compression;
+use isahc::prelude::*;
+use mockito::{mock, server_url, Matcher};
+use std::io::Read;
+
+speculate::speculate! {
+    before {
+        env_logger::try_init().ok();
+    }
+
+    test "request body is compressed" {
+        let mut body_encoded = Vec::new();
+
+        GzEncoder::new("hello world".as_bytes(), flate2::Compression::default())
+            .read_to_end(&mut body_encoded)
+            .unwrap();
+
+        let m = mock("POST", "/")
+            .match_header("content-encoding", "gzip")
+            .match_header("content-length", body_encoded.len().to_string().as_str())
+            .create();
+
+        Request::post(server_url())
+            .request_compression(Compression::Gzip)
+            .body("hello world")
+            .unwrap()
+            .send()
+            .unwrap();
+
+        m.assert();
+    }
+
+    test "requests with a Content-Encoding header are sent unchanged" {
+        let m = mock("POST", "/")
+            .match_header("content-encoding", "identity")
+            .match_body("hello world")
+            .create();
+
+        Request::post(server_url())
+            .header("content-encoding", "identity")
+            .request_compression(Compression::Gzip)
+            .body("hello world")
+            .unwrap()
+            .send()
+            .unwrap();
+
+        m.assert();
+    }
+
+    test "empty request bodies are not compressed" {
+        let m = mock("POST", "/")
+            .match_header("content-encoding", Matcher::Missing)
+            .create();
+
+        Request::post(server_url())
+            .request_compression(Compression::Gzip)
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        m.assert();
+    }
+}
@@ -0,0 +1,141 @@
+use isahc::clock::MockClock;
+use isahc::prelude::*;
+use mockito::{mock, server_url};
+use std::thread::{self, sleep};
+use std::time::Duration;
+
+speculate::speculate! {
+    before {
+        env_logger::try_init().ok();
+    }
+
+    test "requests beyond the concurrency limit are queued" {
+        let m = mock("GET", "/")
+            .with_body("hello world")
+            .expect(3)
+            .create();
+
+        let client = HttpClient::builder()
+            .max_concurrent_requests(1)
+            .build()
+            .unwrap();
+
+        let responses = futures::executor::block_on(futures::future::join_all(
+            (0..3).map(|_| client.get_async(server_url())),
+        ));
+
+        let mut queued = 0;
+
+        for response in responses {
+            let mut response = response.unwrap();
+            assert_eq!(response.text().unwrap(), "hello world");
+
+            if response.queue_time().unwrap() > Duration::from_secs(0) {
+                queued += 1;
+            }
+        }
+
+        assert!(queued > 0);
+        m.assert();
+    }
+
+    test "queued request fails once the acquire timeout is reached" {
+        let m = mock("GET", "/")
+            .with_body_from_fn(|w| {
+                sleep(Duration::from_millis(500));
+                w.write_all(b"slow")
+            })
+            .create();
+
+        let client = HttpClient::builder()
+            .max_concurrent_requests(1)
+            .acquire_timeout(Duration::from_millis(100))
+            .build()
+            .unwrap();
+
+        let (first, second) = futures::executor::block_on(futures::future::join(
+            client.get_async(server_url()),
+            client.get_async(server_url()),
+        ));
+
+        assert_eq!(first.unwrap().text().unwrap(), "slow");
+
+        match second {
+            Err(isahc::Error::AcquireTimeout) => {}
+            e => panic!("expected acquire timeout error, got {:?}", e),
+        }
+
+        m.assert();
+    }
+
+    test "acquire timeout can be set per request" {
+        let m = mock("GET", "/")
+            .with_body_from_fn(|w| {
+                sleep(Duration::from_millis(500));
+                w.write_all(b"slow")
+            })
+            .create();
+
+        let client = HttpClient::builder()
+            .max_concurrent_requests(1)
+            .build()
+            .unwrap();
+
+        let second_request = Request::get(server_url())
+            .acquire_timeout(Duration::from_millis(100))
+            .body(())
+            .unwrap();
+
+        let (first, second) = futures::executor::block_on(futures::future::join(
+            client.get_async(server_url()),
+            client.send_async(second_request),
+        ));
+
+        assert_eq!(first.unwrap().text().unwrap(), "slow");
+
+        match second {
+            Err(isahc::Error::AcquireTimeout) => {}
+            e => panic!("expected acquire timeout error, got {:?}", e),
+        }
+
+        m.assert();
+    }
+
+    test "acquire timeout is measured by the client's clock" {
+        let m = mock("GET", "/")
+            .with_body_from_fn(|w| {
+                sleep(Duration::from_millis(500));
+                w.write_all(b"slow")
+            })
+            .create();
+
+        let clock = MockClock::new();
+        let client = HttpClient::builder()
+            .max_concurrent_requests(1)
+            .acquire_timeout(Duration::from_secs(60))
+            .clock(clock.clone())
+            .build()
+            .unwrap();
+
+        let advance = thread::spawn(move || {
+            sleep(Duration::from_millis(100));
+            clock.advance(Duration::from_secs(61));
+        });
+
+        let (first, second) = futures::executor::block_on(futures::future::join(
+            client.get_async(server_url()),
+            client.get_async(server_url()),
+        ));
+
+        advance.join().unwrap();
+
+        assert_eq!(first.unwrap().text().unwrap(), "slow");
+
+        match second {
+            Err(isahc::Error::AcquireTimeout) => {}
+            e => panic!("expected acquire timeout error, got {:?}", e),
+        }
+
+        m.assert();
+    }
+}
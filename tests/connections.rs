@@ -0,0 +1,468 @@
+use isahc::clock::MockClock;
+use isahc::config::{DnsOverHttps, IpVersion, ResolveMap, TlsBackend};
+use isahc::prelude::*;
+use isahc::resolver::{ResolveFuture, Resolver};
+use mockito::{mock, server_url};
+use std::io;
+use std::net::IpAddr;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Find a host name other than localhost that the system hosts file maps to
+/// 127.0.0.1, if any.
+fn loopback_host_name() -> Option<String> {
+    let hosts = std::fs::read_to_string("/etc/hosts").ok()?;
+
+    hosts
+        .lines()
+        .map(|line| line.split('#').next().unwrap())
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+
+            if fields.next() == Some("127.0.0.1") {
+                Some(fields)
+            } else {
+                None
+            }
+        })
+        .flatten()
+        .find(|name| *name != "localhost" && !name.ends_with(".localhost"))
+        .map(String::from)
+}
+
+speculate::speculate! {
+    before {
+        env_logger::try_init().ok();
+    }
+
+    test "requests succeed after pruning idle connections" {
+        let m = mock("GET", "/")
+            .with_body("hello world")
+            .expect(2)
+            .create();
+
+        let client = HttpClient::builder()
+            .connection_idle_timeout(Duration::from_secs(1))
+            .build()
+            .unwrap();
+
+        assert_eq!(client.get(server_url()).unwrap().text().unwrap(), "hello world");
+
+        client.prune_idle().unwrap();
+
+        assert_eq!(client.get(server_url()).unwrap().text().unwrap(), "hello world");
+
+        m.assert();
+    }
+
+    test "idle connections are closed once the client's clock passes the idle timeout" {
+        let m = mock("GET", "/")
+            .with_body("hello world")
+            .expect(3)
+            .create();
+
+        let clock = MockClock::new();
+        let client = HttpClient::builder()
+            .connection_idle_timeout(Duration::from_secs(60))
+            .clock(clock.clone())
+            .build()
+            .unwrap();
+        let get = || {
+            let mut response = client.get(server_url()).unwrap();
+            response.text().unwrap();
+            response.connection_info().cloned().unwrap()
+        };
+
+        assert!(!get().is_reused());
+
+        // Time passing for real does not close the connection.
+        thread::sleep(Duration::from_millis(300));
+        assert!(get().is_reused());
+
+        clock.advance(Duration::from_secs(61));
+        thread::sleep(Duration::from_millis(300));
+        assert!(!get().is_reused());
+
+        m.assert();
+    }
+
+    test "prefetching DNS does not send a request" {
+        let m = mock("GET", "/")
+            .expect(0)
+            .create();
+
+        let client = HttpClient::new().unwrap();
+
+        client.prefetch_dns(vec![server_url()]).unwrap();
+
+        m.assert();
+    }
+
+    test "prefetching DNS uses fixed addresses of the client" {
+        let port = mockito::server_address().port();
+
+        let client = HttpClient::builder()
+            .dns_resolve(ResolveMap::new().add("isahc.invalid", port, [127, 0, 0, 1]))
+            .build()
+            .unwrap();
+
+        client.prefetch_dns(vec![format!("http://isahc.invalid:{}", port)]).unwrap();
+    }
+
+    test "prefetching DNS does not bypass DNS over HTTPS" {
+        // Curl resolves localhost by itself without DoH, so look for another
+        // name that the system resolver maps to the mock server's address.
+        let host = match loopback_host_name() {
+            Some(host) => host,
+            None => {
+                log::warn!("no loopback host name in /etc/hosts, skipping test");
+                return;
+            }
+        };
+        let port = mockito::server_address().port();
+
+        // Nothing listens on this port, so every DoH lookup fails.
+        let client = HttpClient::builder()
+            .dns_over_https("https://127.0.0.1:1/dns-query")
+            .build()
+            .unwrap();
+
+        assert!(client.prefetch_dns(vec![format!("http://{}:{}", host, port)]).is_err());
+    }
+
+    test "prefetching DNS asks a custom resolver" {
+        struct Counting(Arc<AtomicUsize>);
+
+        impl Resolver for Counting {
+            fn resolve<'a>(&'a self, _host: &str, _port: u16) -> ResolveFuture<'a> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                Box::pin(futures::future::ready(Ok(vec![IpAddr::from([127, 0, 0, 1])])))
+            }
+        }
+
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let client = HttpClient::builder()
+            .resolver(Counting(lookups.clone()))
+            .build()
+            .unwrap();
+
+        // IP addresses need no resolving.
+        client
+            .prefetch_dns(vec![
+                "http://isahc.invalid",
+                "https://example.invalid",
+                "http://127.0.0.1",
+            ])
+            .unwrap();
+
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+    }
+
+    test "requests succeed after invalidating connections" {
+        let m = mock("GET", "/")
+            .with_body("hello world")
+            .expect(2)
+            .create();
+
+        let client = HttpClient::new().unwrap();
+
+        assert_eq!(client.get(server_url()).unwrap().text().unwrap(), "hello world");
+
+        client.invalidate_connections().unwrap();
+
+        assert_eq!(client.get(server_url()).unwrap().text().unwrap(), "hello world");
+
+        m.assert();
+    }
+
+    test "requests are accounted per host" {
+        let m = mock("GET", "/")
+            .with_body("hello world")
+            .expect(2)
+            .create();
+
+        let client = HttpClient::new().unwrap();
+
+        client.get(server_url()).unwrap().text().unwrap();
+        client.get(server_url()).unwrap().text().unwrap();
+
+        let stats = client.host_stats();
+        let host = server_url().trim_start_matches("http://").to_owned();
+
+        assert_eq!(stats[&host].requests(), 2);
+        assert_eq!(stats[&host].failed_requests(), 0);
+        assert!(stats[&host].bytes_sent() > 0);
+        assert!(stats[&host].bytes_received() > 2 * "hello world".len() as u64);
+
+        m.assert();
+    }
+
+    test "requests fail over to the next endpoint if connecting fails" {
+        let m = mock("POST", "/api/items?page=2")
+            .match_body("hello")
+            .with_body("from backup")
+            .create();
+
+        let client = HttpClient::builder()
+            .failover_endpoints(vec![
+                "http://127.0.0.1:1".parse().unwrap(),
+                server_url().parse().unwrap(),
+            ])
+            .build()
+            .unwrap();
+
+        let mut response = client.post("http://127.0.0.1:1/api/items?page=2", "hello").unwrap();
+
+        assert_eq!(response.text().unwrap(), "from backup");
+        m.assert();
+    }
+
+    test "host names can be resolved to fixed addresses" {
+        let port = mockito::server_address().port();
+        let m = mock("GET", "/")
+            .match_header("host", format!("isahc.invalid:{}", port).as_str())
+            .create();
+
+        let response = Request::get(format!("http://isahc.invalid:{}/", port))
+            .dns_resolve(ResolveMap::new().add("isahc.invalid", port, [127, 0, 0, 1]))
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+        m.assert();
+    }
+
+    test "failed DNS over HTTPS lookups can fall back to the system resolver" {
+        // Curl resolves localhost by itself without DoH, so look for another
+        // name that the system resolver maps to the mock server's address.
+        let host = match loopback_host_name() {
+            Some(host) => host,
+            None => {
+                log::warn!("no loopback host name in /etc/hosts, skipping test");
+                return;
+            }
+        };
+        let port = mockito::server_address().port();
+        let m = mock("GET", "/").expect(1).create();
+
+        // Nothing listens on this port, so every DoH lookup fails.
+        let doh = "https://127.0.0.1:1/dns-query";
+        let uri = format!("http://{}:{}/", host, port);
+
+        let client = HttpClient::builder()
+            .dns_over_https(doh)
+            .build()
+            .unwrap();
+
+        assert!(match client.get(&uri) {
+            Err(isahc::Error::CouldntResolveHost) => true,
+            _ => false,
+        });
+
+        let client = HttpClient::builder()
+            .dns_over_https(DnsOverHttps::new(doh).fallback_to_system(true))
+            .build()
+            .unwrap();
+
+        assert_eq!(client.get(&uri).unwrap().status(), 200);
+
+        m.assert();
+    }
+
+    test "custom resolvers are consulted before connecting" {
+        struct Loopback;
+
+        impl Resolver for Loopback {
+            fn resolve<'a>(&'a self, host: &str, _port: u16) -> ResolveFuture<'a> {
+                let result = if host == "isahc.invalid" {
+                    Ok(vec![IpAddr::from([127, 0, 0, 1])])
+                } else {
+                    Err(io::ErrorKind::NotFound.into())
+                };
+
+                Box::pin(futures::future::ready(result))
+            }
+        }
+
+        let port = mockito::server_address().port();
+        let m = mock("GET", "/")
+            .match_header("host", format!("isahc.invalid:{}", port).as_str())
+            .create();
+
+        let client = HttpClient::builder().resolver(Loopback).build().unwrap();
+
+        let response = client.get(format!("http://isahc.invalid:{}/", port)).unwrap();
+        assert_eq!(response.status(), 200);
+
+        assert!(match client.get(format!("http://unknown.invalid:{}/", port)) {
+            Err(isahc::Error::CouldntResolveHost) => true,
+            _ => false,
+        });
+
+        m.assert();
+    }
+
+    test "requests can be restricted to IPv4" {
+        let m = mock("GET", "/").create();
+
+        let response = Request::get(server_url())
+            .ip_version(IpVersion::V4)
+            .happy_eyeballs_timeout(Duration::from_millis(50))
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+        m.assert();
+    }
+
+    test "connections can be bound to a local address" {
+        let m = mock("GET", "/").create();
+
+        let response = Request::get(server_url())
+            .interface(IpAddr::from([127, 0, 0, 1]))
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+        m.assert();
+    }
+
+    test "connections can be restricted to a local port range" {
+        let m = mock("GET", "/").create();
+
+        let response = Request::get(server_url())
+            .local_ports(45000..=45100)
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+        m.assert();
+    }
+
+    test "empty local port ranges are rejected" {
+        let m = mock("GET", "/").expect(0).create();
+
+        let result = Request::get(server_url())
+            .local_ports(RangeInclusive::new(45100, 45000))
+            .body(())
+            .unwrap()
+            .send();
+
+        assert!(result.is_err());
+        m.assert();
+    }
+
+    test "blacklisted hosts bypass the proxy" {
+        let m = mock("GET", "/").create();
+
+        // Nothing listens on this port, so requests through the proxy fail.
+        let client = HttpClient::builder()
+            .proxy("http://127.0.0.1:1".parse().unwrap())
+            .build()
+            .unwrap();
+
+        assert!(client.get(server_url()).is_err());
+
+        let request = Request::get(server_url())
+            .proxy_blacklist(vec!["127.0.0.0/8"])
+            .body(())
+            .unwrap();
+        let response = client.send(request).unwrap();
+
+        assert_eq!(response.status(), 200);
+        m.assert();
+    }
+
+    test "client with shared TLS sessions sends requests" {
+        let m = mock("GET", "/").expect(2).create();
+
+        let client = HttpClient::builder()
+            .share_ssl_sessions(true)
+            .build()
+            .unwrap();
+
+        assert_eq!(client.get(server_url()).unwrap().status(), 200);
+        assert_eq!(client.get(server_url()).unwrap().status(), 200);
+
+        m.assert();
+    }
+
+    test "client with connection pool limits sends requests" {
+        let m = mock("GET", "/").with_body("hello world").expect(2).create();
+
+        let client = HttpClient::builder()
+            .max_idle_connections(1)
+            .connection_max_lifetime(Duration::from_secs(60))
+            .build()
+            .unwrap();
+
+        assert_eq!(client.get(server_url()).unwrap().text().unwrap(), "hello world");
+        assert_eq!(client.get(server_url()).unwrap().text().unwrap(), "hello world");
+
+        m.assert();
+    }
+
+    test "connections are reused for requests to the same host" {
+        let m = mock("GET", "/").with_body("hello world").expect(2).create();
+
+        let client = HttpClient::new().unwrap();
+
+        let mut first = client.get(server_url()).unwrap();
+        first.text().unwrap();
+        let first = first.connection_info().cloned().unwrap();
+
+        let mut second = client.get(server_url()).unwrap();
+        second.text().unwrap();
+        let second = second.connection_info().cloned().unwrap();
+
+        assert!(!first.is_reused());
+        assert!(second.is_reused());
+
+        if let (Some(first_id), Some(second_id)) = (first.id(), second.id()) {
+            assert_eq!(first_id, second_id);
+        }
+
+        m.assert();
+    }
+
+    test "shutdown waits for requests and then refuses new ones" {
+        let m = mock("GET", "/").with_body("hello world").create();
+
+        let client = HttpClient::new().unwrap();
+        let mut response = client.get(server_url()).unwrap();
+
+        let reader = std::thread::spawn(move || response.text().unwrap());
+
+        client.shutdown(Duration::from_secs(5)).unwrap();
+
+        assert_eq!(reader.join().unwrap(), "hello world");
+        m.assert();
+
+        match client.get(server_url()) {
+            Err(isahc::Error::ClientShutdown) => {}
+            e => panic!("expected client shutdown error, got {:?}", e),
+        }
+
+        // Shutting down again does nothing.
+        client.shutdown(Duration::from_secs(5)).unwrap();
+    }
+
+    test "the TLS backend already in use can be selected" {
+        HttpClient::new().unwrap();
+
+        let backend = TlsBackend::active().expect("libcurl has no TLS backend");
+
+        assert!(HttpClient::builder().tls_backend(backend).build().is_ok());
+    }
+}
@@ -0,0 +1,137 @@
+use isahc::prelude::*;
+use mockito::{mock, server_url, Matcher};
+use std::env;
+use std::fs;
+
+speculate::speculate! {
+    before {
+        env_logger::try_init().ok();
+    }
+
+    test "download writes the body to a new file" {
+        let path = env::temp_dir().join("isahc-download-new.txt");
+        fs::remove_file(&path).ok();
+
+        let m = mock("GET", "/")
+            .match_header("range", Matcher::Missing)
+            .with_body("hello world")
+            .create();
+
+        let length = HttpClient::new().unwrap().download(server_url(), &path).unwrap();
+
+        assert_eq!(length, 11);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello world");
+
+        fs::remove_file(&path).ok();
+        m.assert();
+    }
+
+    test "download resumes a partial file" {
+        let path = env::temp_dir().join("isahc-download-resume.txt");
+        let validator_path = env::temp_dir().join("isahc-download-resume.txt.validator");
+        fs::write(&path, "hello").unwrap();
+        fs::write(&validator_path, "\"v1\"").unwrap();
+
+        let m = mock("GET", "/")
+            .match_header("range", "bytes=5-")
+            .match_header("if-range", "\"v1\"")
+            .with_status(206)
+            .with_header("content-range", "bytes 5-10/11")
+            .with_body(" world")
+            .create();
+
+        let length = HttpClient::new().unwrap().download(server_url(), &path).unwrap();
+
+        assert_eq!(length, 11);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello world");
+        assert!(!validator_path.exists());
+
+        fs::remove_file(&path).ok();
+        m.assert();
+    }
+
+    test "download overwrites the file if the resource changed" {
+        let path = env::temp_dir().join("isahc-download-ignored.txt");
+        let validator_path = env::temp_dir().join("isahc-download-ignored.txt.validator");
+        fs::write(&path, "stale").unwrap();
+        fs::write(&validator_path, "\"v1\"").unwrap();
+
+        // The server answers with the complete resource if it does not match
+        // the If-Range validator any more.
+        let m = mock("GET", "/")
+            .match_header("range", "bytes=5-")
+            .match_header("if-range", "\"v1\"")
+            .with_header("etag", "\"v2\"")
+            .with_body("hello world")
+            .create();
+
+        let length = HttpClient::new().unwrap().download(server_url(), &path).unwrap();
+
+        assert_eq!(length, 11);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello world");
+        assert!(!validator_path.exists());
+
+        fs::remove_file(&path).ok();
+        m.assert();
+    }
+
+    test "download starts over if no validator was recorded" {
+        let path = env::temp_dir().join("isahc-download-unknown.txt");
+        fs::write(&path, "stale").unwrap();
+        fs::remove_file(env::temp_dir().join("isahc-download-unknown.txt.validator")).ok();
+
+        let m = mock("GET", "/")
+            .match_header("range", Matcher::Missing)
+            .with_body("hello world")
+            .create();
+
+        let length = HttpClient::new().unwrap().download(server_url(), &path).unwrap();
+
+        assert_eq!(length, 11);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello world");
+
+        fs::remove_file(&path).ok();
+        m.assert();
+    }
+
+    test "download of a complete file does nothing" {
+        let path = env::temp_dir().join("isahc-download-complete.txt");
+        let validator_path = env::temp_dir().join("isahc-download-complete.txt.validator");
+        fs::write(&path, "hello world").unwrap();
+        fs::write(&validator_path, "\"v1\"").unwrap();
+
+        let m = mock("GET", "/")
+            .match_header("range", "bytes=11-")
+            .match_header("if-range", "\"v1\"")
+            .with_status(416)
+            .with_header("content-range", "bytes */11")
+            .create();
+
+        let length = HttpClient::new().unwrap().download(server_url(), &path).unwrap();
+
+        assert_eq!(length, 11);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello world");
+
+        fs::remove_file(&path).ok();
+        m.assert();
+    }
+
+    test "download errors leave the file untouched" {
+        let path = env::temp_dir().join("isahc-download-error.txt");
+        fs::remove_file(&path).ok();
+
+        let m = mock("GET", "/")
+            .with_status(404)
+            .create();
+
+        let result = HttpClient::new().unwrap().download(server_url(), &path);
+
+        assert!(match result {
+            Err(isahc::Error::BadStatus(_)) => true,
+            _ => false,
+        });
+        assert!(!path.exists());
+
+        m.assert();
+    }
+}
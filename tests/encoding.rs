@@ -1,5 +1,6 @@
 use flate2::read::{DeflateEncoder, GzEncoder};
 use flate2::Compression;
+use isahc::config::ContentEncoding;
 use isahc::prelude::*;
 use mockito::{mock, server_url};
 use std::io::Read;
@@ -74,6 +75,87 @@ speculate::speculate! {
         m.assert();
     }
 
+    test "Content-Encoding header is kept after decoding" {
+        let mut body_encoded = Vec::new();
+
+        GzEncoder::new("hello world".as_bytes(), Compression::default())
+            .read_to_end(&mut body_encoded)
+            .unwrap();
+
+        let m = mock("GET", "/")
+            .with_header("Content-Encoding", "gzip")
+            .with_body(&body_encoded)
+            .create();
+
+        let mut response = isahc::get(server_url()).unwrap();
+
+        assert_eq!(response.headers()["content-encoding"], "gzip");
+        assert_eq!(response.text().unwrap(), "hello world");
+        m.assert();
+    }
+
+    test "response is not decoded if automatic decompression is disabled" {
+        let mut body_encoded = Vec::new();
+
+        GzEncoder::new("hello world".as_bytes(), Compression::default())
+            .read_to_end(&mut body_encoded)
+            .unwrap();
+
+        let m = mock("GET", "/")
+            .match_header("Accept-Encoding", mockito::Matcher::Missing)
+            .with_header("Content-Encoding", "gzip")
+            .with_body(&body_encoded)
+            .create();
+
+        let mut response = Request::get(server_url())
+            .automatic_decompression(false)
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        let mut body = Vec::new();
+        response.body_mut().read_to_end(&mut body).unwrap();
+
+        assert_eq!(body, body_encoded);
+        m.assert();
+    }
+
+    test "only the configured encodings are advertised" {
+        let m = mock("GET", "/")
+            .match_header("Accept-Encoding", "gzip")
+            .create();
+
+        Request::get(server_url())
+            .accept_encodings(vec![ContentEncoding::Gzip])
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        m.assert();
+    }
+
+    test "explicit Accept-Encoding header overrides configured encodings" {
+        let m = mock("GET", "/")
+            .match_header("Accept-Encoding", "deflate")
+            .create();
+
+        let client = HttpClient::builder()
+            .accept_encodings(vec![ContentEncoding::Gzip, ContentEncoding::Zstd])
+            .build()
+            .unwrap();
+
+        let request = Request::get(server_url())
+            .header("Accept-Encoding", "deflate")
+            .body(())
+            .unwrap();
+
+        client.send(request).unwrap();
+
+        m.assert();
+    }
+
     test "unknown Content-Encoding returns error" {
         let m = mock("GET", "/")
             .with_header("Content-Encoding", "foo")
@@ -0,0 +1,30 @@
+#![cfg(feature = "file")]
+
+use isahc::prelude::*;
+use std::env;
+use std::fs;
+
+speculate::speculate! {
+    before {
+        env_logger::try_init().ok();
+    }
+
+    test "file URLs return the file contents" {
+        let path = env::temp_dir().join("isahc-file-scheme-test.txt");
+        fs::write(&path, "hello from disk").unwrap();
+
+        let uri = format!("file://localhost{}", path.display());
+        let mut response = isahc::get(uri).unwrap();
+
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().unwrap(), "hello from disk");
+
+        fs::remove_file(&path).ok();
+    }
+
+    test "missing files are an error" {
+        let path = env::temp_dir().join("isahc-file-scheme-test-missing.txt");
+
+        assert!(isahc::get(format!("file://localhost{}", path.display())).is_err());
+    }
+}
@@ -1,4 +1,48 @@
+use isahc::prelude::*;
 use mockito::{mock, server_url, Matcher};
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Serve a single raw response on a local port, for responses too malformed
+/// to produce with mockito.
+fn serve_raw(response: &'static [u8]) -> String {
+    serve_raw_in_parts(vec![response])
+}
+
+/// Serve a single raw response on a local port, pausing between each of its
+/// parts so that they are received separately.
+fn serve_raw_in_parts(parts: Vec<&'static [u8]>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+
+        while !request.ends_with(b"\r\n\r\n") {
+            let len = stream.read(&mut buf).unwrap();
+            if len == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..len]);
+        }
+
+        for (i, part) in parts.into_iter().enumerate() {
+            if i > 0 {
+                thread::sleep(Duration::from_millis(200));
+            }
+
+            stream.write_all(part).unwrap();
+            stream.flush().unwrap();
+        }
+    });
+
+    url
+}
 
 speculate::speculate! {
     before {
@@ -25,4 +69,215 @@ speculate::speculate! {
 
         m.assert();
     }
+
+    test "automatic headers can be disabled" {
+        let m = mock("POST", "/")
+            .match_header("host", "signed.example")
+            .match_header("content-length", "5")
+            .match_header("accept-encoding", Matcher::Missing)
+            .match_body("hello")
+            .create();
+
+        Request::post(server_url())
+            .automatic_headers(false)
+            .header("host", "signed.example")
+            .header("content-length", "5")
+            .body("hello")
+            .unwrap()
+            .send()
+            .unwrap();
+
+        m.assert();
+    }
+
+    test "malformed response headers are rejected by default" {
+        let url = serve_raw(b"HTTP/1.1 200 OK\r\nX-Folded: one\r\n two\r\nContent-Length: 0\r\n\r\n");
+
+        assert!(isahc::get(url).is_err());
+    }
+
+    test "malformed response headers are tolerated when not strict" {
+        let url = serve_raw(b"HTTP/1.1 200 OK\nX-Folded: one\r\n\ttwo\r\nX-Bad\x01: yes\r\nContent-Length: 2\n\nhi");
+
+        let mut response = Request::get(url)
+            .strict(false)
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["x-folded"], "one two");
+        assert_eq!(response.text().unwrap(), "hi");
+    }
+
+    test "response with too many headers is rejected" {
+        let url = serve_raw(b"HTTP/1.1 200 OK\r\nX-One: 1\r\nX-Two: 2\r\nX-Three: 3\r\nContent-Length: 0\r\n\r\n");
+
+        let result = Request::get(url)
+            .max_response_headers(2)
+            .body(())
+            .unwrap()
+            .send();
+
+        match result {
+            Err(isahc::Error::ResponseHeadersTooLarge) => {}
+            _ => panic!("expected headers too large error, instead got {:?}", result),
+        }
+    }
+
+    test "response with too large a header is rejected" {
+        let url = serve_raw(b"HTTP/1.1 200 OK\r\nX-Padding: aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\r\nContent-Length: 0\r\n\r\n");
+
+        let client = HttpClient::builder()
+            .max_response_header_size(32)
+            .build()
+            .unwrap();
+
+        match client.get(url) {
+            Err(isahc::Error::ResponseHeadersTooLarge) => {}
+            result => panic!("expected headers too large error, instead got {:?}", result),
+        }
+    }
+
+    test "skipped malformed header lines count towards the limits" {
+        let url = serve_raw(b"HTTP/1.1 200 OK\r\njunk\r\njunk\r\njunk\r\nContent-Length: 0\r\n\r\n");
+
+        let result = Request::get(url)
+            .strict(false)
+            .max_response_headers(2)
+            .body(())
+            .unwrap()
+            .send();
+
+        match result {
+            Err(isahc::Error::ResponseHeadersTooLarge) => {}
+            _ => panic!("expected headers too large error, instead got {:?}", result),
+        }
+
+        let url = serve_raw(b"HTTP/1.1 200 OK\r\njunk junk junk junk junk junk junk\r\nContent-Length: 0\r\n\r\n");
+
+        let result = Request::get(url)
+            .strict(false)
+            .max_response_header_size(40)
+            .body(())
+            .unwrap()
+            .send();
+
+        match result {
+            Err(isahc::Error::ResponseHeadersTooLarge) => {}
+            _ => panic!("expected headers too large error, instead got {:?}", result),
+        }
+    }
+
+    test "trailer fields are received after the body" {
+        let url = serve_raw(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nTrailer: X-Checksum\r\n\r\n2\r\nhi\r\n0\r\nX-Checksum: abc\r\nX-Other: 1\r\n\r\n");
+
+        let mut response = isahc::get(url).unwrap();
+
+        assert_eq!(response.text().unwrap(), "hi");
+
+        let trailers = response.trailers().wait();
+        assert_eq!(trailers["x-checksum"], "abc");
+        assert_eq!(trailers["x-other"], "1");
+        assert!(response.headers().get("x-checksum").is_none());
+    }
+
+    test "trailer fields sent separately from the body are received" {
+        let url = serve_raw_in_parts(vec![
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nTrailer: X-Checksum\r\n\r\n2\r\nhi\r\n",
+            b"0\r\nX-Checksum: abc\r\n\r\n",
+        ]);
+
+        let mut response = isahc::get(url).unwrap();
+
+        assert_eq!(response.text().unwrap(), "hi");
+
+        let trailers = response.trailers().wait();
+        assert_eq!(trailers["x-checksum"], "abc");
+        assert!(response.headers().get("x-checksum").is_none());
+    }
+
+    test "trailer fields of an empty chunked body are not response headers" {
+        let url = serve_raw(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nTrailer: X-Checksum\r\n\r\n0\r\nX-Checksum: abc\r\n\r\n");
+
+        let mut response = isahc::get(url).unwrap();
+
+        assert!(response.headers().get("x-checksum").is_none());
+        assert_eq!(response.text().unwrap(), "");
+        assert_eq!(response.trailers().wait()["x-checksum"], "abc");
+    }
+
+    test "trailer fields count towards the header limits" {
+        let url = serve_raw(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nhi\r\n0\r\nX-One: 1\r\nX-Two: 2\r\nX-Three: 3\r\n\r\n");
+
+        let mut response = Request::get(url)
+            .max_response_headers(2)
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert!(response.text().is_err());
+    }
+
+    test "interim responses are passed to the informational callback" {
+        let url = serve_raw(b"HTTP/1.1 103 Early Hints\r\nLink: </a.css>; rel=preload\r\nLink: </b.js>; rel=preload\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi");
+
+        let links = Arc::new(Mutex::new(Vec::new()));
+
+        let mut response = Request::get(url)
+            .on_informational({
+                let links = links.clone();
+                move |status, headers| {
+                    assert_eq!(status, 103);
+                    links.lock().unwrap().extend(
+                        headers
+                            .get_all("link")
+                            .iter()
+                            .map(|value| value.to_str().unwrap().to_owned()),
+                    );
+                }
+            })
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+        assert!(response.headers().get("link").is_none());
+        assert_eq!(response.text().unwrap(), "hi");
+        assert_eq!(
+            *links.lock().unwrap(),
+            vec!["</a.css>; rel=preload", "</b.js>; rel=preload"]
+        );
+    }
+
+    test "repeated response headers are all kept" {
+        let url = serve_raw(b"HTTP/1.1 200 OK\r\nX-Repeated: one\r\nX-Repeated: two\r\nContent-Length: 0\r\n\r\n");
+
+        let response = isahc::get(url).unwrap();
+        let values: Vec<_> = response
+            .headers()
+            .get_all("x-repeated")
+            .iter()
+            .map(|value| value.to_str().unwrap())
+            .collect();
+
+        assert_eq!(values, vec!["one", "two"]);
+    }
+
+    test "response within the header limits is received" {
+        let url = serve_raw(b"HTTP/1.1 200 OK\r\nX-One: 1\r\nContent-Length: 2\r\n\r\nhi");
+
+        let mut response = Request::get(url)
+            .max_response_header_size(64)
+            .max_response_headers(2)
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert_eq!(response.text().unwrap(), "hi");
+    }
 }
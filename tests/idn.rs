@@ -0,0 +1,31 @@
+#![cfg(feature = "idna")]
+
+use isahc::config::ResolveMap;
+use isahc::prelude::*;
+use mockito::mock;
+
+speculate::speculate! {
+    before {
+        env_logger::try_init().ok();
+    }
+
+    test "unicode host names are converted to punycode" {
+        let port = mockito::server_address().port();
+        let m = mock("GET", "/katalog")
+            .match_header("host", format!("xn--bcher-kva.example:{}", port).as_str())
+            .create();
+
+        let client = HttpClient::builder()
+            .dns_resolve(ResolveMap::new().add("xn--bcher-kva.example", port, [127, 0, 0, 1]))
+            .build()
+            .unwrap();
+
+        let response = client.get(format!("http://bücher.example:{}/katalog", port)).unwrap();
+        let uri = response.effective_uri().unwrap();
+
+        assert_eq!(response.status(), 200);
+        assert_eq!(uri.host(), Some("xn--bcher-kva.example"));
+        assert_eq!(isahc::idn::unicode_host(uri).unwrap(), "bücher.example");
+        m.assert();
+    }
+}
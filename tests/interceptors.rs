@@ -0,0 +1,101 @@
+use futures::future::{self, FutureExt};
+use isahc::interceptor::{Interceptor, InterceptorFuture, Next};
+use isahc::prelude::*;
+use mockito::{mock, server_url, Matcher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+struct AddHeader(&'static str);
+
+impl Interceptor for AddHeader {
+    fn intercept<'a>(
+        &'a self,
+        mut request: Request<Body>,
+        next: Next<'a>,
+    ) -> InterceptorFuture<'a> {
+        let value = match request.headers().get("x-layer") {
+            Some(value) => format!("{}, {}", value.to_str().unwrap(), self.0),
+            None => self.0.to_owned(),
+        };
+
+        request
+            .headers_mut()
+            .insert("x-layer", value.parse().unwrap());
+
+        next.run(request)
+    }
+}
+
+struct CountResponses(Arc<AtomicUsize>);
+
+impl Interceptor for CountResponses {
+    fn intercept<'a>(&'a self, request: Request<Body>, next: Next<'a>) -> InterceptorFuture<'a> {
+        Box::pin(next.run(request).map(move |result| {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            result
+        }))
+    }
+}
+
+struct ShortCircuit;
+
+impl Interceptor for ShortCircuit {
+    fn intercept<'a>(&'a self, _: Request<Body>, _: Next<'a>) -> InterceptorFuture<'a> {
+        Box::pin(future::ready(Ok(Response::builder()
+            .status(204)
+            .body(Body::empty())
+            .unwrap())))
+    }
+}
+
+speculate::speculate! {
+    before {
+        env_logger::try_init().ok();
+    }
+
+    test "interceptors are invoked in the order they are added" {
+        let m = mock("GET", "/")
+            .match_header("x-layer", Matcher::Exact("outer, inner".into()))
+            .create();
+
+        let client = HttpClient::builder()
+            .interceptor(AddHeader("outer"))
+            .interceptor(AddHeader("inner"))
+            .build()
+            .unwrap();
+
+        client.get(server_url()).unwrap();
+
+        m.assert();
+    }
+
+    test "interceptors see the response" {
+        let m = mock("GET", "/").expect(2).create();
+        let count = Arc::new(AtomicUsize::new(0));
+
+        let client = HttpClient::builder()
+            .interceptor(CountResponses(count.clone()))
+            .build()
+            .unwrap();
+
+        client.get(server_url()).unwrap();
+        futures::executor::block_on(client.get_async(server_url())).unwrap();
+
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+        m.assert();
+    }
+
+    test "interceptors can respond without sending the request" {
+        let m = mock("GET", "/").expect(0).create();
+
+        let client = HttpClient::builder()
+            .interceptor(ShortCircuit)
+            .build()
+            .unwrap();
+
+        let response = client.get(server_url()).unwrap();
+
+        assert_eq!(response.status(), 204);
+        m.assert();
+    }
+}
@@ -1,6 +1,6 @@
-use isahc::config::RedirectPolicy;
+use isahc::config::{RedirectAction, RedirectPolicy};
 use isahc::prelude::*;
-use mockito::{mock, server_url};
+use mockito::{mock, server_url, Matcher};
 
 speculate::speculate! {
     before {
@@ -153,4 +153,403 @@ speculate::speculate! {
         m2.assert();
         m3.assert();
     }
+
+    test "redirect handler can follow redirects" {
+        let m1 = mock("GET", "/")
+            .with_status(302)
+            .with_header("Location", "/2")
+            .create();
+
+        let m2 = mock("GET", "/2")
+            .with_body("ok")
+            .create();
+
+        let mut response = Request::get(server_url())
+            .redirect_handler(|attempt| {
+                assert_eq!(attempt.status(), 302);
+                assert_eq!(attempt.uri().path(), "/2");
+                assert_eq!(attempt.hops(), 1);
+                RedirectAction::Follow
+            })
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().unwrap(), "ok");
+        assert_eq!(response.effective_uri().unwrap().path(), "/2");
+
+        m1.assert();
+        m2.assert();
+    }
+
+    test "redirect handler can stop following redirects" {
+        let m1 = mock("GET", "/")
+            .with_status(301)
+            .with_header("Location", "/2")
+            .create();
+
+        let m2 = mock("GET", "/2")
+            .with_status(301)
+            .with_header("Location", "/3")
+            .create();
+
+        let m3 = mock("GET", "/3").expect(0).create();
+
+        let response = Request::get(server_url())
+            .redirect_handler(|attempt| {
+                if attempt.hops() > 1 {
+                    RedirectAction::Stop
+                } else {
+                    RedirectAction::Follow
+                }
+            })
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), 301);
+        assert_eq!(response.headers()["Location"], "/3");
+
+        m1.assert();
+        m2.assert();
+        m3.assert();
+    }
+
+    test "redirect handler can reject cross-host redirects" {
+        let m = mock("GET", "/")
+            .with_status(302)
+            .with_header("Location", "http://example.invalid/")
+            .create();
+
+        let result = Request::get(server_url())
+            .redirect_handler(|attempt| {
+                if attempt.uri().host() == attempt.previous_uri().host() {
+                    RedirectAction::Follow
+                } else {
+                    RedirectAction::Reject("redirect to another host".into())
+                }
+            })
+            .body(())
+            .unwrap()
+            .send();
+
+        match result {
+            Err(isahc::Error::RedirectRejected(reason)) => {
+                assert_eq!(reason, "redirect to another host")
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        m.assert();
+    }
+
+    test "redirect handler resends body on 307 redirects" {
+        let m1 = mock("PUT", "/")
+            .with_status(307)
+            .with_header("Location", "/2")
+            .create();
+
+        let m2 = mock("PUT", "/2")
+            .match_body("hello")
+            .create();
+
+        let response = Request::put(server_url())
+            .redirect_handler(|_| RedirectAction::Follow)
+            .body("hello")
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+
+        m1.assert();
+        m2.assert();
+    }
+
+    test "redirect handler strips credentials on cross-origin redirects" {
+        // The same server under another host name is another origin.
+        let port = mockito::SERVER_ADDRESS.split(':').nth(1).unwrap();
+        let other_origin = format!("http://localhost:{}/2", port);
+
+        let m1 = mock("GET", "/")
+            .with_status(302)
+            .with_header("Location", &other_origin)
+            .create();
+
+        let m2 = mock("GET", "/2")
+            .match_header("authorization", Matcher::Missing)
+            .match_header("cookie", Matcher::Missing)
+            .match_header("proxy-authorization", Matcher::Missing)
+            .create();
+
+        let response = Request::get(server_url())
+            .header("Authorization", "Bearer secret")
+            .header("Cookie", "session=secret")
+            .header("Proxy-Authorization", "Basic c2VjcmV0")
+            .redirect_handler(|_| RedirectAction::Follow)
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+
+        m1.assert();
+        m2.assert();
+    }
+
+    test "redirect handler keeps credentials on same-origin redirects" {
+        let m1 = mock("GET", "/")
+            .with_status(302)
+            .with_header("Location", "/2")
+            .create();
+
+        let m2 = mock("GET", "/2")
+            .match_header("authorization", "Bearer secret")
+            .match_header("cookie", "session=secret")
+            .create();
+
+        let response = Request::get(server_url())
+            .header("Authorization", "Bearer secret")
+            .header("Cookie", "session=secret")
+            .redirect_handler(|_| RedirectAction::Follow)
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+
+        m1.assert();
+        m2.assert();
+    }
+
+    test "redirect handler keeps PUT on 302 redirects" {
+        let m1 = mock("PUT", "/")
+            .with_status(302)
+            .with_header("Location", "/2")
+            .create();
+
+        let m2 = mock("PUT", "/2")
+            .match_body("hello")
+            .create();
+
+        let response = Request::put(server_url())
+            .redirect_handler(|_| RedirectAction::Follow)
+            .body("hello")
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+
+        m1.assert();
+        m2.assert();
+    }
+
+    test "redirect handler gives up after too many redirects" {
+        let m = mock("GET", "/")
+            .with_status(302)
+            .with_header("Location", "/")
+            .expect(51)
+            .create();
+
+        let result = Request::get(server_url())
+            .redirect_handler(|_| RedirectAction::Follow)
+            .body(())
+            .unwrap()
+            .send();
+
+        match result {
+            Err(isahc::Error::TooManyRedirects) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        m.assert();
+    }
+
+    test "redirect handler resolves dot segments in relative locations" {
+        let m1 = mock("GET", "/a/b/c")
+            .with_status(302)
+            .with_header("Location", "../d/./e#f")
+            .create();
+
+        let m2 = mock("GET", "/a/d/e").create();
+
+        let response = Request::get(server_url() + "/a/b/c")
+            .redirect_handler(|attempt| {
+                assert_eq!(attempt.uri().path(), "/a/d/e");
+                RedirectAction::Follow
+            })
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.effective_uri().unwrap().path(), "/a/d/e");
+
+        m1.assert();
+        m2.assert();
+    }
+
+    test "client redirect handler is applied to requests" {
+        let m1 = mock("GET", "/")
+            .with_status(302)
+            .with_header("Location", "/2")
+            .create();
+
+        let m2 = mock("GET", "/2").create();
+
+        let client = HttpClient::builder()
+            .redirect_policy(RedirectPolicy::None)
+            .redirect_handler(|_| RedirectAction::Follow)
+            .build()
+            .unwrap();
+
+        let response = client.get(server_url()).unwrap();
+
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.effective_uri().unwrap().path(), "/2");
+
+        m1.assert();
+        m2.assert();
+    }
+
+    test "request redirect policy overrides client redirect handler" {
+        let m1 = mock("GET", "/")
+            .with_status(302)
+            .with_header("Location", "/2")
+            .create();
+
+        let m2 = mock("GET", "/2").expect(0).create();
+
+        let client = HttpClient::builder()
+            .redirect_handler(|_| RedirectAction::Follow)
+            .build()
+            .unwrap();
+
+        let request = Request::get(server_url())
+            .redirect_policy(RedirectPolicy::None)
+            .body(())
+            .unwrap();
+
+        let response = client.send(request).unwrap();
+
+        assert_eq!(response.status(), 302);
+
+        m1.assert();
+        m2.assert();
+    }
+
+    #[cfg(feature = "cookies")]
+    test "cookies set by redirects are sent to the new location" {
+        let m1 = mock("GET", "/")
+            .with_status(302)
+            .with_header("Location", "/2")
+            .with_header("Set-Cookie", "session=abc")
+            .create();
+
+        let m2 = mock("GET", "/2")
+            .match_header("cookie", "session=abc")
+            .create();
+
+        let client = HttpClient::builder()
+            .cookies()
+            .redirect_handler(|_| RedirectAction::Follow)
+            .build()
+            .unwrap();
+
+        assert_eq!(client.get(server_url()).unwrap().status(), 200);
+
+        m1.assert();
+        m2.assert();
+    }
+
+    test "redirect history lists followed redirects" {
+        let m1 = mock("GET", "/a")
+            .with_status(301)
+            .with_header("Location", "/b")
+            .expect(2)
+            .create();
+
+        let m2 = mock("GET", "/b")
+            .with_status(302)
+            .with_header("Location", "/c")
+            .expect(2)
+            .create();
+
+        let m3 = mock("GET", "/c").expect(2).create();
+
+        for &handler in &[false, true] {
+            let mut builder = Request::get(server_url() + "/a");
+
+            if handler {
+                builder.redirect_handler(|_| RedirectAction::Follow);
+            } else {
+                builder.redirect_policy(RedirectPolicy::Follow);
+            }
+
+            let response = builder.body(()).unwrap().send().unwrap();
+
+            let history = response.redirect_history();
+            assert_eq!(history.len(), 2);
+            assert_eq!(history[0].uri().path(), "/a");
+            assert_eq!(history[0].status(), 301);
+            assert_eq!(history[1].uri().path(), "/b");
+            assert_eq!(history[1].status(), 302);
+            assert_eq!(response.effective_uri().unwrap().path(), "/c");
+        }
+
+        m1.assert();
+        m2.assert();
+        m3.assert();
+    }
+
+    test "redirect history is empty without redirects" {
+        let m = mock("GET", "/").create();
+
+        let response = isahc::get(server_url()).unwrap();
+
+        assert!(response.redirect_history().is_empty());
+        m.assert();
+    }
+
+    test "conditional requests revalidate resources behind redirects" {
+        let m1 = mock("GET", "/")
+            .with_status(301)
+            .with_header("Location", "/2")
+            .expect(2)
+            .create();
+        let m2 = mock("GET", "/2")
+            .match_header("if-none-match", Matcher::Missing)
+            .with_header("etag", "\"v1\"")
+            .with_body("ok")
+            .expect(1)
+            .create();
+        let m3 = mock("GET", "/2")
+            .match_header("if-none-match", "\"v1\"")
+            .with_status(304)
+            .expect(1)
+            .create();
+
+        let client = HttpClient::builder()
+            .redirect_policy(RedirectPolicy::Follow)
+            .conditional_requests()
+            .build()
+            .unwrap();
+
+        assert_eq!(client.get(server_url()).unwrap().text().unwrap(), "ok");
+
+        let response = client.get(server_url()).unwrap();
+        assert_eq!(response.status(), 304);
+        assert!(response.not_modified().is_some());
+
+        m1.assert();
+        m2.assert();
+        m3.assert();
+    }
 }
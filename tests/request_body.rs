@@ -1,6 +1,6 @@
 use isahc::prelude::*;
-use isahc::Body;
-use mockito::{mock, server_url};
+use isahc::{Body, Form};
+use mockito::{mock, server_url, Matcher};
 
 speculate::speculate! {
     before {
@@ -29,6 +29,61 @@ speculate::speculate! {
         }
     }
 
+    test "large POST body of known size" {
+        let body = "wow so large ".repeat(10_000);
+
+        let m = mock("POST", "/")
+            .match_header("content-length", body.len().to_string().as_str())
+            .match_body(body.as_str())
+            .create();
+
+        isahc::post(server_url(), body.clone()).unwrap();
+
+        m.assert();
+    }
+
+    test "small POST body of known size" {
+        let m = mock("POST", "/")
+            .match_header("content-length", "11")
+            .match_header("content-type", "text/plain")
+            .match_header("transfer-encoding", Matcher::Missing)
+            .match_body("hello world")
+            .create();
+
+        Request::post(server_url())
+            .header("Content-Type", "text/plain")
+            .body("hello world")
+            .unwrap()
+            .send()
+            .unwrap();
+
+        m.assert();
+    }
+
+    test "small POST body describes its own content type" {
+        let m = mock("POST", "/")
+            .match_header("content-length", "9")
+            .match_header("content-type", "application/x-www-form-urlencoded")
+            .match_body("a=b&c=d+e")
+            .create();
+
+        isahc::post(server_url(), Form::new().append("a", "b").append("c", "d e")).unwrap();
+
+        m.assert();
+    }
+
+    test "empty POST body" {
+        let m = mock("POST", "/")
+            .match_header("content-length", "0")
+            .match_header("transfer-encoding", Matcher::Missing)
+            .match_body("")
+            .create();
+
+        isahc::post(server_url(), ()).unwrap();
+
+        m.assert();
+    }
+
     test "request with body of unknown size uses chunked encoding" {
         for method in &["GET", "HEAD", "POST", "PUT", "DELETE", "PATCH", "FOOBAR"] {
             let body = "foo";
@@ -52,6 +107,41 @@ speculate::speculate! {
         }
     }
 
+    test "multipart form is sent with its boundary" {
+        use isahc::multipart::FormData;
+
+        let form = FormData::with_boundary("boundary").text("hello", "world");
+
+        let m = mock("POST", "/")
+            .match_header("content-type", "multipart/form-data; boundary=boundary")
+            .match_body("--boundary\r\nContent-Disposition: form-data; name=\"hello\"\r\n\r\nworld\r\n--boundary--\r\n")
+            .create();
+
+        Request::post(server_url())
+            .header("content-type", form.content_type())
+            .body(form)
+            .unwrap()
+            .send()
+            .unwrap();
+
+        m.assert();
+    }
+
+    test "form body sets content type automatically" {
+        let m = mock("POST", "/")
+            .match_header("content-type", "application/x-www-form-urlencoded")
+            .match_body("name=Jane+Doe&color=blue+%26+green")
+            .create();
+
+        let form = isahc::Form::new()
+            .append("name", "Jane Doe")
+            .append("color", "blue & green");
+
+        isahc::post(server_url(), form).unwrap();
+
+        m.assert();
+    }
+
     // test "Content-Length header takes precedence over body object's length" {
     //     for method in &["GET", "HEAD", "POST", "PUT", "DELETE", "PATCH", "FOOBAR"] {
     //         let m = mock(method, "/")
@@ -89,4 +89,252 @@ speculate::speculate! {
             assert_eq!(body.read(&mut buf).unwrap(), 0);
         }
     }
+
+    test "text_sniffed decodes a body that is not the declared charset" {
+        use isahc::ResponseExt;
+
+        let m = mock("GET", "/")
+            .with_header("content-type", "text/plain; charset=utf-8")
+            .with_body(&b"caf\xe9"[..])
+            .create();
+
+        let mut response = isahc::get(server_url()).unwrap();
+
+        assert_eq!(response.text_sniffed().unwrap(), "caf\u{e9}");
+        m.assert();
+    }
+
+    test "metrics are only collected when enabled" {
+        use isahc::prelude::*;
+
+        let m = mock("GET", "/")
+            .with_body("hello world")
+            .expect(2)
+            .create();
+
+        let mut response = Request::get(server_url())
+            .metrics(true)
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+        response.text().unwrap();
+
+        let metrics = response.metrics().unwrap();
+        assert!(metrics.download_speed() >= 0.0);
+        assert!(metrics.peak_download_speed() >= 0.0);
+        assert!(metrics.time_to_first_byte() >= metrics.name_lookup_time());
+        assert_eq!(metrics.secure_connect_time(), std::time::Duration::default());
+
+        assert!(isahc::get(server_url()).unwrap().metrics().is_none());
+
+        m.assert();
+    }
+
+    test "progress callback is called during transfer" {
+        use isahc::prelude::*;
+        use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let m = mock("GET", "/")
+            .with_body("hello world")
+            .create();
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let downloaded = Arc::new(AtomicU64::new(0));
+
+        let mut response = Request::get(server_url())
+            .progress({
+                let calls = calls.clone();
+                let downloaded = downloaded.clone();
+                move |progress| {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    downloaded.store(progress.downloaded(), Ordering::SeqCst);
+                }
+            })
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert_eq!(response.text().unwrap(), "hello world");
+        assert!(calls.load(Ordering::SeqCst) > 0);
+        assert!(downloaded.load(Ordering::SeqCst) <= 11);
+
+        m.assert();
+    }
+
+    test "large response bodies are received in full with progress and metrics enabled" {
+        use isahc::prelude::*;
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::Arc;
+
+        // Large enough to be received in many separate writes.
+        let body = "0123456789abcdef".repeat(64 * 1024);
+
+        let m = mock("GET", "/")
+            .with_body(&body)
+            .create();
+
+        let downloaded = Arc::new(AtomicU64::new(0));
+
+        let mut response = Request::get(server_url())
+            .metrics(true)
+            .read_timeout(std::time::Duration::from_secs(10))
+            .progress({
+                let downloaded = downloaded.clone();
+                move |progress| {
+                    downloaded.store(progress.downloaded(), Ordering::SeqCst);
+                }
+            })
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert_eq!(response.text().unwrap().len(), body.len());
+        assert_eq!(downloaded.load(Ordering::SeqCst), body.len() as u64);
+        assert!(response.metrics().unwrap().download_speed() > 0.0);
+
+        m.assert();
+    }
+
+    test "response body can be copied into an async writer" {
+        use futures::io::AllowStdIo;
+        use isahc::prelude::*;
+
+        let m = mock("GET", "/")
+            .with_body("hello world")
+            .create();
+
+        let mut response = futures::executor::block_on(isahc::get_async(server_url())).unwrap();
+        let mut writer = AllowStdIo::new(Vec::new());
+        let copied = futures::executor::block_on(response.copy_to_async(&mut writer)).unwrap();
+
+        assert_eq!(copied, 11);
+        assert_eq!(writer.into_inner(), b"hello world");
+
+        m.assert();
+    }
+
+    test "response body can be copied into a writer" {
+        let m = mock("GET", "/")
+            .with_body("hello world")
+            .create();
+
+        let mut buffer = Vec::new();
+        let copied = isahc::get(server_url()).unwrap().body_mut().copy_to(&mut buffer).unwrap();
+
+        assert_eq!(copied, 11);
+        assert_eq!(buffer, b"hello world");
+
+        m.assert();
+    }
+
+    test "trailers are empty once the body is read if none were sent" {
+        use isahc::prelude::*;
+
+        let m = mock("GET", "/")
+            .with_body("hello world")
+            .create();
+
+        let mut response = isahc::get(server_url()).unwrap();
+        assert_eq!(response.text().unwrap(), "hello world");
+
+        assert!(response.trailers().wait().is_empty());
+        assert_eq!(response.trailers().try_get(), Some(Default::default()));
+
+        m.assert();
+    }
+
+    test "response larger than the maximum body size is rejected" {
+        use isahc::prelude::*;
+
+        let m = mock("GET", "/")
+            .with_body("hello world")
+            .create();
+
+        let result = Request::get(server_url())
+            .max_response_body_size(5)
+            .body(())
+            .unwrap()
+            .send();
+
+        match result {
+            Err(isahc::Error::ResponseTooLarge) => {}
+            _ => panic!("expected response too large error, instead got {:?}", result),
+        }
+
+        m.assert();
+    }
+
+    test "response within the maximum body size is received" {
+        use isahc::prelude::*;
+
+        let m = mock("GET", "/")
+            .with_body("hello world")
+            .create();
+
+        let client = HttpClient::builder()
+            .max_response_body_size(11)
+            .build()
+            .unwrap();
+        let mut response = client.get(server_url()).unwrap();
+
+        assert_eq!(response.text().unwrap(), "hello world");
+        m.assert();
+    }
+
+    test "chunked response larger than the maximum body size fails before completing" {
+        use isahc::prelude::*;
+
+        let m = mock("GET", "/")
+            .with_body_from_fn(|w| w.write_all(b"hello world"))
+            .create();
+
+        let result = Request::get(server_url())
+            .max_response_body_size(5)
+            .body(())
+            .unwrap()
+            .send();
+
+        match result {
+            Err(isahc::Error::ResponseTooLarge) => {}
+            _ => panic!("expected response too large error, instead got {:?}", result),
+        }
+
+        m.assert();
+    }
+
+    test "chunked response growing beyond the maximum body size fails while reading" {
+        use isahc::prelude::*;
+        use std::io::Read;
+
+        let m = mock("GET", "/")
+            .with_body_from_fn(|w| {
+                w.write_all(b"hello")?;
+                w.flush()?;
+                std::thread::sleep(std::time::Duration::from_millis(200));
+                w.write_all(b" world")
+            })
+            .create();
+
+        let mut response = Request::get(server_url())
+            .max_response_body_size(8)
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert!(response.headers().get("content-length").is_none());
+
+        let error = response.body_mut().read_to_end(&mut Vec::new()).unwrap_err();
+
+        match isahc::Error::from(error) {
+            isahc::Error::ResponseTooLarge => {}
+            e => panic!("expected response too large error, instead got {:?}", e),
+        }
+
+        m.assert();
+    }
 }
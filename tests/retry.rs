@@ -0,0 +1,112 @@
+use futures::executor::block_on;
+use isahc::clock::MockClock;
+use isahc::config::RetryPolicy;
+use isahc::prelude::*;
+use mockito::{mock, server_url};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn client() -> HttpClient {
+    HttpClient::builder()
+        .retry_policy(RetryPolicy::exponential(Duration::from_millis(10)).max_attempts(3))
+        .build()
+        .unwrap()
+}
+
+speculate::speculate! {
+    before {
+        env_logger::try_init().ok();
+    }
+
+    test "retryable status codes are retried up to the max attempts" {
+        let m = mock("GET", "/")
+            .with_status(503)
+            .expect(3)
+            .create();
+
+        let response = client().get(server_url()).unwrap();
+
+        assert_eq!(response.status(), 503);
+        m.assert();
+    }
+
+    test "retries also apply to async requests" {
+        let m = mock("GET", "/")
+            .with_status(429)
+            .expect(3)
+            .create();
+
+        let response = block_on(client().get_async(server_url())).unwrap();
+
+        assert_eq!(response.status(), 429);
+        m.assert();
+    }
+
+    test "successful responses are not retried" {
+        let m = mock("GET", "/")
+            .expect(1)
+            .create();
+
+        client().get(server_url()).unwrap();
+
+        m.assert();
+    }
+
+    test "non-idempotent requests are not retried after reaching the server" {
+        let m = mock("POST", "/")
+            .with_status(503)
+            .expect(1)
+            .create();
+
+        let response = client().post(server_url(), "hello").unwrap();
+
+        assert_eq!(response.status(), 503);
+        m.assert();
+    }
+
+    test "request body is sent again on each attempt" {
+        let m = mock("PUT", "/")
+            .match_body("hello world")
+            .with_status(502)
+            .expect(2)
+            .create();
+
+        Request::put(server_url())
+            .retry_policy(RetryPolicy::fixed(Duration::from_millis(10)).max_attempts(2))
+            .body("hello world")
+            .unwrap()
+            .send()
+            .unwrap();
+
+        m.assert();
+    }
+
+    test "retry delays are measured by the client's clock" {
+        let m = mock("GET", "/")
+            .with_status(503)
+            .expect(3)
+            .create();
+
+        let clock = MockClock::new();
+        let client = HttpClient::builder()
+            .retry_policy(RetryPolicy::fixed(Duration::from_secs(3600)).max_attempts(3))
+            .clock(clock.clone())
+            .build()
+            .unwrap();
+        let start = Instant::now();
+
+        let advance = thread::spawn(move || {
+            for _ in 0..2 {
+                thread::sleep(Duration::from_millis(300));
+                clock.advance(Duration::from_secs(3600));
+            }
+        });
+
+        let response = client.get(server_url()).unwrap();
+        advance.join().unwrap();
+
+        assert_eq!(response.status(), 503);
+        assert!(start.elapsed() < Duration::from_secs(60));
+        m.assert();
+    }
+}
@@ -0,0 +1,62 @@
+use isahc::prelude::*;
+use mockito::{mock, server_url, Matcher};
+
+speculate::speculate! {
+    before {
+        env_logger::try_init().ok();
+    }
+
+    test "response body can be read as events" {
+        let m = mock("GET", "/")
+            .with_header("content-type", "text/event-stream")
+            .with_body(": hello\nevent: greeting\ndata: hello\ndata: world\n\nid: 2\ndata: bye\n\n")
+            .create();
+
+        let events = isahc::get(server_url())
+            .unwrap()
+            .into_event_stream()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event(), "greeting");
+        assert_eq!(events[0].data(), "hello\nworld");
+        assert_eq!(events[0].id(), None);
+        assert_eq!(events[1].event(), "message");
+        assert_eq!(events[1].data(), "bye");
+        assert_eq!(events[1].id(), Some("2"));
+
+        m.assert();
+    }
+
+    test "event source reconnects with last event id" {
+        let m1 = mock("GET", "/")
+            .match_header("accept", "text/event-stream")
+            .match_header("last-event-id", Matcher::Missing)
+            .with_header("content-type", "text/event-stream")
+            .with_body("retry: 10\nid: 1\ndata: first\n\n")
+            .create();
+
+        let m2 = mock("GET", "/")
+            .match_header("last-event-id", "1")
+            .with_status(204)
+            .create();
+
+        let client = HttpClient::new().unwrap();
+        let mut source = client.event_source(server_url()).unwrap();
+
+        let event = source.next().unwrap().unwrap();
+        assert_eq!(event.data(), "first");
+        assert_eq!(source.last_event_id(), Some("1"));
+
+        // The server tells the client to stop reconnecting.
+        match source.next() {
+            Some(Err(isahc::Error::BadStatus(e))) => assert_eq!(e.status(), 204),
+            result => panic!("unexpected result: {:?}", result),
+        }
+        assert!(source.next().is_none());
+
+        m1.assert();
+        m2.assert();
+    }
+}
@@ -17,4 +17,36 @@ speculate::speculate! {
             m.assert();
         }
     }
+
+    test "error_for_status captures the beginning of the body" {
+        let m = mock("GET", "/")
+            .with_status(422)
+            .with_body("{\"message\":\"invalid name\"}")
+            .create();
+
+        let mut response = isahc::get(server_url()).unwrap();
+
+        match response.error_for_status(11) {
+            Err(isahc::Error::BadStatus(e)) => {
+                assert_eq!(e.status(), 422);
+                assert_eq!(e.body(), b"{\"message\":");
+            }
+            other => panic!("expected BadStatus error, got {:?}", other),
+        }
+
+        m.assert();
+    }
+
+    test "error_for_status ignores successful responses" {
+        let m = mock("GET", "/")
+            .with_body("hello")
+            .create();
+
+        let mut response = isahc::get(server_url()).unwrap();
+
+        assert!(response.error_for_status(1024).is_ok());
+        assert_eq!(response.text().unwrap(), "hello");
+
+        m.assert();
+    }
 }
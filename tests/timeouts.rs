@@ -1,7 +1,37 @@
+use isahc::clock::MockClock;
+use isahc::config::AdaptiveTimeout;
 use isahc::prelude::*;
 use mockito::{mock, server_url};
-use std::thread::sleep;
-use std::time::Duration;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
+
+/// Serve a single connection, sending the given bytes once the request has
+/// been received and then stalling for a while before closing it.
+fn serve_stalling(response: &'static [u8]) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+
+        while !request.ends_with(b"\r\n\r\n") {
+            let len = stream.read(&mut buf).unwrap();
+            if len == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..len]);
+        }
+
+        stream.write_all(response).unwrap();
+        sleep(Duration::from_secs(3));
+    });
+
+    url
+}
 
 speculate::speculate! {
     before {
@@ -27,7 +57,7 @@ speculate::speculate! {
 
         // Client should time-out.
         match result {
-            Err(isahc::Error::Timeout) => {}
+            Err(isahc::Error::Timeout(Some(isahc::Phase::Transfer))) => {}
             e => {
                 panic!("expected timeout error, got {:?}", e);
             }
@@ -35,4 +65,161 @@ speculate::speculate! {
 
         m.assert();
     }
+
+    test "request errors if the server does not respond within the first byte timeout" {
+        let url = serve_stalling(b"");
+
+        let result = Request::get(url)
+            .first_byte_timeout(Duration::from_millis(500))
+            .body(())
+            .unwrap()
+            .send();
+
+        match result {
+            Err(isahc::Error::Timeout(Some(isahc::Phase::WaitingForResponse))) => {}
+            e => {
+                panic!("expected timeout error, got {:?}", e);
+            }
+        }
+    }
+
+    test "reading the response body errors if the read timeout is reached" {
+        let url = serve_stalling(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\nhello");
+
+        let mut response = Request::get(url)
+            .read_timeout(Duration::from_millis(500))
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        match response.text() {
+            Err(isahc::Error::Timeout(_)) => {}
+            e => {
+                panic!("expected timeout error, got {:?}", e);
+            }
+        }
+    }
+
+    test "reading the response body errors if the transfer is too slow" {
+        let url = serve_stalling(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\nhello");
+
+        let mut response = Request::get(url)
+            .low_speed_limit(1000, Duration::from_secs(1))
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        match response.text() {
+            Err(isahc::Error::TransferTooSlow) => {}
+            e => {
+                panic!("expected transfer too slow error, got {:?}", e);
+            }
+        }
+    }
+
+    test "request errors if the deadline has already passed" {
+        let result = Request::get(server_url())
+            .deadline(Instant::now())
+            .body(())
+            .unwrap()
+            .send();
+
+        match result {
+            Err(isahc::Error::Timeout(_)) => {}
+            e => {
+                panic!("expected timeout error, got {:?}", e);
+            }
+        }
+    }
+
+    test "request errors if the deadline is reached" {
+        let m = mock("GET", "/")
+            .with_body_from_fn(|_| {
+                sleep(Duration::from_secs(1));
+                Ok(())
+            })
+            .create();
+
+        let result = Request::get(server_url())
+            .deadline(Instant::now() + Duration::from_millis(100))
+            .timeout(Duration::from_secs(10))
+            .body(())
+            .unwrap()
+            .send();
+
+        match result {
+            Err(isahc::Error::Timeout(_)) => {}
+            e => {
+                panic!("expected timeout error, got {:?}", e);
+            }
+        }
+
+        m.assert();
+    }
+
+    test "deadlines are measured by the client's clock" {
+        let clock = MockClock::new();
+        let client = HttpClient::builder()
+            .clock(clock.clone())
+            .build()
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(60);
+        clock.advance(Duration::from_secs(120));
+
+        let result = client.send(
+            Request::get(server_url())
+                .deadline(deadline)
+                .body(())
+                .unwrap(),
+        );
+
+        match result {
+            Err(isahc::Error::Timeout(_)) => {}
+            e => {
+                panic!("expected timeout error, got {:?}", e);
+            }
+        }
+    }
+
+    test "deadline applies even if an adaptive timeout is longer" {
+        let fast = mock("GET", "/fast")
+            .expect(20)
+            .create();
+        let slow = mock("GET", "/slow")
+            .with_body_from_fn(|_| {
+                sleep(Duration::from_secs(1));
+                Ok(())
+            })
+            .create();
+
+        let client = HttpClient::builder()
+            .adaptive_timeout(AdaptiveTimeout::new(99, 2).min(Duration::from_secs(10)))
+            .build()
+            .unwrap();
+
+        // Record enough response times for an adaptive timeout to be used.
+        for _ in 0..20 {
+            client.get(format!("{}/fast", server_url())).unwrap();
+        }
+
+        let result = client.send(
+            Request::get(format!("{}/slow", server_url()))
+                .deadline(Instant::now() + Duration::from_millis(100))
+                .body(())
+                .unwrap(),
+        );
+
+        match result {
+            Err(isahc::Error::Timeout(_)) => {}
+            e => {
+                panic!("expected timeout error, got {:?}", e);
+            }
+        }
+
+        fast.assert();
+        slow.assert();
+    }
 }
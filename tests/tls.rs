@@ -0,0 +1,261 @@
+use isahc::config::{CaCertificate, TlsVersion};
+use isahc::prelude::*;
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Hash of the public key of the test server's certificate.
+const PUBLIC_KEY_HASH: &str = "sha256//SuFgq2SLZGhU4ZE0QXtyy6BiZgwID4mGH2MafKLpIXs=";
+
+/// Get the path of a file in the directory of test certificates.
+fn cert_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("certs")
+        .join(name)
+}
+
+/// A local TLS server run by the `openssl` command line tool, which answers
+/// every request with a status page.
+///
+/// The server presents a self-signed certificate for `localhost` and
+/// `127.0.0.1`, so it is only trusted if that certificate is.
+struct TlsServer {
+    child: Child,
+    port: u16,
+}
+
+impl TlsServer {
+    /// Start a server, or return `None` if the `openssl` tool is not
+    /// available.
+    fn start() -> Option<Self> {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let child = Command::new("openssl")
+            .arg("s_server")
+            .arg("-accept")
+            .arg(format!("127.0.0.1:{}", port))
+            .arg("-cert")
+            .arg(cert_path("localhost.pem"))
+            .arg("-key")
+            .arg(cert_path("localhost.key"))
+            .arg("-www")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+
+        let server = match child {
+            Ok(child) => Self { child, port },
+            Err(e) => {
+                log::warn!("skipping test, failed to run openssl: {}", e);
+                return None;
+            }
+        };
+
+        let start = Instant::now();
+
+        while TcpStream::connect(("127.0.0.1", port)).is_err() {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "TLS server did not start"
+            );
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        Some(server)
+    }
+
+    fn url(&self) -> String {
+        format!("https://127.0.0.1:{}/", self.port)
+    }
+}
+
+impl Drop for TlsServer {
+    fn drop(&mut self) {
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+fn trusted() -> CaCertificate {
+    CaCertificate::File(cert_path("localhost.pem"))
+}
+
+speculate::speculate! {
+    before {
+        env_logger::try_init().ok();
+    }
+
+    test "untrusted server certificates are rejected" {
+        let server = match TlsServer::start() {
+            Some(server) => server,
+            None => return,
+        };
+
+        match isahc::get(server.url()) {
+            Err(isahc::Error::BadServerCertificate(_)) => {}
+            e => panic!("expected bad server certificate error, got {:?}", e),
+        }
+    }
+
+    test "CA certificate file is trusted" {
+        let server = match TlsServer::start() {
+            Some(server) => server,
+            None => return,
+        };
+
+        let response = Request::get(server.url())
+            .ssl_ca_certificate(trusted())
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+    }
+
+    test "CA certificate blob is trusted" {
+        let server = match TlsServer::start() {
+            Some(server) => server,
+            None => return,
+        };
+
+        let pem = std::fs::read(cert_path("localhost.pem")).unwrap();
+        let result = Request::get(server.url())
+            .ssl_ca_certificate(CaCertificate::Blob(pem))
+            .body(())
+            .unwrap()
+            .send();
+
+        match result {
+            Ok(response) => assert_eq!(response.status(), 200),
+            // Blobs require libcurl 7.77.
+            Err(isahc::Error::Curl(e)) => log::warn!("skipping test, {}", e),
+            Err(e) => panic!("expected success, got {:?}", e),
+        }
+    }
+
+    test "matching pinned public key is accepted" {
+        let server = match TlsServer::start() {
+            Some(server) => server,
+            None => return,
+        };
+
+        let response = Request::get(server.url())
+            .ssl_ca_certificate(trusted())
+            .pinned_public_key(PUBLIC_KEY_HASH)
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+    }
+
+    test "other pinned public key is rejected" {
+        let server = match TlsServer::start() {
+            Some(server) => server,
+            None => return,
+        };
+
+        let result = Request::get(server.url())
+            .ssl_ca_certificate(trusted())
+            .pinned_public_key("sha256//AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=")
+            .body(())
+            .unwrap()
+            .send();
+
+        match result {
+            Err(isahc::Error::BadServerCertificate(_)) => {}
+            e => panic!("expected bad server certificate error, got {:?}", e),
+        }
+    }
+
+    test "TLS versions the server does not support fail" {
+        let server = match TlsServer::start() {
+            Some(server) => server,
+            None => return,
+        };
+
+        let response = Request::get(server.url())
+            .ssl_ca_certificate(trusted())
+            .tls_min_version(TlsVersion::V1_2)
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+
+        // Current TLS engines refuse TLS 1.0 by default.
+        let result = Request::get(server.url())
+            .ssl_ca_certificate(trusted())
+            .tls_max_version(TlsVersion::V1_0)
+            .body(())
+            .unwrap()
+            .send();
+
+        assert!(result.is_err());
+    }
+
+    test "required OCSP stapling fails without a stapled response" {
+        let server = match TlsServer::start() {
+            Some(server) => server,
+            None => return,
+        };
+
+        let result = Request::get(server.url())
+            .ssl_ca_certificate(trusted())
+            .ssl_verify_status(true)
+            .body(())
+            .unwrap()
+            .send();
+
+        assert!(result.is_err());
+    }
+
+    test "HTTPS proxy is verified against its own CA certificates" {
+        let server = match TlsServer::start() {
+            Some(server) => server,
+            None => return,
+        };
+
+        let proxy: isahc::http::Uri = server.url().parse().unwrap();
+
+        // The status page is returned for any request sent to the proxy.
+        let response = Request::get("http://example.invalid/")
+            .proxy(proxy.clone())
+            .proxy_ssl_ca_certificate(trusted())
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+
+        let result = Request::get("http://example.invalid/")
+            .proxy(proxy.clone())
+            .body(())
+            .unwrap()
+            .send();
+
+        assert!(result.is_err());
+
+        let response = Request::get("http://example.invalid/")
+            .proxy(proxy)
+            .danger_accept_invalid_proxy_certs(true)
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+    }
+}
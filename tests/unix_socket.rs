@@ -0,0 +1,52 @@
+#![cfg(unix)]
+
+use isahc::prelude::*;
+use std::env;
+use std::fs;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixListener;
+use std::thread;
+
+speculate::speculate! {
+    before {
+        env_logger::try_init().ok();
+    }
+
+    test "requests can be sent over a unix socket" {
+        let path = env::temp_dir().join("isahc-unix-socket.sock");
+        fs::remove_file(&path).ok();
+
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+
+            while !request.ends_with(b"\r\n\r\n") {
+                let len = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..len]);
+            }
+
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello")
+                .unwrap();
+
+            String::from_utf8(request).unwrap()
+        });
+
+        let mut response = Request::get("http://localhost/version")
+            .unix_socket(&path)
+            .body(())
+            .unwrap()
+            .send()
+            .unwrap();
+
+        assert_eq!(response.text().unwrap(), "hello");
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("GET /version HTTP/1.1\r\n"));
+
+        fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,49 @@
+#![cfg(feature = "upgrade")]
+
+use isahc::prelude::*;
+use mockito::{mock, server_url};
+
+speculate::speculate! {
+    before {
+        env_logger::try_init().ok();
+    }
+
+    test "upgrade returns the switching protocols response" {
+        let m = mock("GET", "/chat")
+            .match_header("upgrade", "websocket")
+            .match_header("connection", "upgrade")
+            .with_status(101)
+            .with_header("upgrade", "websocket")
+            .create();
+
+        let request = Request::get(format!("{}/chat", server_url()))
+            .header("upgrade", "websocket")
+            .body(())
+            .unwrap();
+
+        let connection = HttpClient::new().unwrap().upgrade(request).unwrap();
+
+        assert_eq!(connection.response().status(), 101);
+        assert_eq!(connection.response().headers()["upgrade"], "websocket");
+
+        m.assert();
+    }
+
+    test "upgrade refused by the server is an error" {
+        let m = mock("GET", "/chat")
+            .with_status(404)
+            .create();
+
+        let request = Request::get(format!("{}/chat", server_url()))
+            .header("upgrade", "websocket")
+            .body(())
+            .unwrap();
+
+        match HttpClient::new().unwrap().upgrade(request) {
+            Err(isahc::Error::BadStatus(e)) => assert_eq!(e.status(), 404),
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        m.assert();
+    }
+}
@@ -0,0 +1,51 @@
+use isahc::config::VersionNegotiation;
+use isahc::http;
+use isahc::prelude::*;
+use mockito::{mock, server_url};
+
+speculate::speculate! {
+    before {
+        env_logger::try_init().ok();
+    }
+
+    test "cleartext requests use http/1.1 by default" {
+        let m = mock("GET", "/").create();
+
+        let response = isahc::get(server_url()).unwrap();
+
+        assert_eq!(response.version(), http::Version::HTTP_11);
+        m.assert();
+    }
+
+    test "version negotiation can be set per request" {
+        let m = mock("GET", "/").create();
+
+        let client = HttpClient::builder()
+            .version_negotiation(VersionNegotiation::http2())
+            .build()
+            .unwrap();
+
+        let request = Request::get(server_url())
+            .version_negotiation(VersionNegotiation::http11())
+            .body(())
+            .unwrap();
+        let response = client.send(request).unwrap();
+
+        assert_eq!(response.version(), http::Version::HTTP_11);
+        m.assert();
+    }
+
+    #[cfg(feature = "http2")]
+    test "http2 prior knowledge does not fall back to http/1.1" {
+        let m = mock("GET", "/").expect(0).create();
+
+        let result = Request::get(server_url())
+            .version_negotiation(VersionNegotiation::http2_prior_knowledge())
+            .body(())
+            .unwrap()
+            .send();
+
+        assert!(result.is_err());
+        m.assert();
+    }
+}